@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+
+use nannou::glam::{vec2, Vec2};
+use serde_json::{json, Value};
+
+/// A note pinned to a world position by the user, eg where a battle happened.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub position: Vec2,
+    pub name: String,
+    /// Longer text shown under the name.
+    pub note: String,
+}
+
+impl Annotation {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            position,
+            name: String::new(),
+            note: String::new(),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "position": [self.position.x, self.position.y],
+            "name": self.name,
+            "note": self.note,
+        })
+    }
+
+    pub fn from_json(json: &Value) -> Option<Self> {
+        let position = json["position"].as_array()?;
+
+        let x = position.first()?.as_f64()? as f32;
+        let y = position.get(1)?.as_f64()? as f32;
+
+        Some(Self {
+            position: vec2(x, y),
+            name: json["name"].as_str()?.to_string(),
+            note: json["note"].as_str().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+/// The annotations placed on each map, by the seed of its terrain, so that they come back with
+/// the terrain they were placed on.
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+    by_seed: BTreeMap<u64, Vec<Annotation>>,
+}
+
+impl Annotations {
+    /// The annotations on the terrain generated from [seed].
+    pub fn of(&self, seed: u64) -> &[Annotation] {
+        self.by_seed
+            .get(&seed)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn of_mut(&mut self, seed: u64) -> &mut Vec<Annotation> {
+        self.by_seed.entry(seed).or_default()
+    }
+
+    /// The index of the annotation on the terrain of [seed] nearest to [position], if there is
+    /// one within [radius].
+    pub fn nearest(&self, seed: u64, position: Vec2, radius: f32) -> Option<usize> {
+        self.of(seed)
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (i, a.position.distance(position)))
+            .filter(|(_, d)| *d <= radius)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    /// The annotations keyed by seed. Maps without annotations are left out.
+    pub fn to_json(&self) -> Value {
+        let map: serde_json::Map<String, Value> = self
+            .by_seed
+            .iter()
+            .filter(|(_, a)| !a.is_empty())
+            .map(|(seed, a)| {
+                let a: Vec<Value> = a.iter().map(|a| a.to_json()).collect();
+                (seed.to_string(), Value::from(a))
+            })
+            .collect();
+
+        Value::Object(map)
+    }
+
+    pub fn from_json(json: &Value) -> Option<Self> {
+        let mut by_seed = BTreeMap::new();
+
+        for (seed, a) in json.as_object()?.iter() {
+            let seed = seed.parse::<u64>().ok()?;
+            let a: Option<Vec<Annotation>> =
+                a.as_array()?.iter().map(Annotation::from_json).collect();
+
+            by_seed.insert(seed, a?);
+        }
+
+        Some(Self { by_seed })
+    }
+}
@@ -0,0 +1,116 @@
+use nannou::geom::*;
+use nannou::glam::Quat;
+use nannou::Draw;
+
+const ZOOM_MIN: f32 = 0.25;
+const ZOOM_MAX: f32 = 32.0;
+const ZOOM_DEFAULT: f32 = 1.0;
+
+/// The view transform used to pan and zoom around the terrain.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    /// The world position at the center of the window.
+    pub position: Vec2,
+    /// The scale from world units to screen units.
+    pub zoom: f32,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            position: Vec2::ZERO,
+            zoom: ZOOM_DEFAULT,
+        }
+    }
+
+    /// Returns a draw context that renders world coordinates through the camera.
+    pub fn transform(&self, draw: &Draw) -> Draw {
+        draw.scale(self.zoom).xy(-self.position)
+    }
+
+    /// Convert a window position to a world position.
+    pub fn screen_to_world(&self, p: Vec2) -> Vec2 {
+        p / self.zoom + self.position
+    }
+
+    /// The world rectangle visible through a [window] of the given size.
+    pub fn viewport(&self, window: Rect) -> Rect {
+        Rect::from_xy_wh(self.position, window.wh() / self.zoom)
+    }
+
+    /// Returns true if the camera is zoomed past the default scale.
+    pub fn is_zoomed(&self) -> bool {
+        self.zoom > ZOOM_DEFAULT
+    }
+
+    /// Scale the zoom by [factor], keeping the world position under the [screen] point fixed.
+    pub fn zoom_at(&mut self, screen: Vec2, factor: f32) {
+        let before = self.screen_to_world(screen);
+
+        self.zoom = (self.zoom * factor).clamp(ZOOM_MIN, ZOOM_MAX);
+
+        let after = self.screen_to_world(screen);
+
+        self.position += before - after;
+    }
+
+    /// Move the camera by a [delta] in screen units.
+    pub fn pan(&mut self, delta: Vec2) {
+        self.position += delta / self.zoom;
+    }
+}
+
+const ORBIT_SPEED: f32 = 0.01;
+const ORBIT_PITCH_MAX: f32 = 1.4;
+const ORBIT_DISTANCE_MIN: f32 = 200.0;
+const ORBIT_DISTANCE_MAX: f32 = 5000.0;
+const ORBIT_DISTANCE_DEFAULT: f32 = 1400.0;
+/// The closest depth in front of the camera that is still drawn.
+const ORBIT_NEAR: f32 = 1.0;
+
+/// A perspective camera that circles the center of the terrain, used by the 3D preview. World
+/// coordinates have x and y across the map and z up.
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitCamera {
+    /// The rotation around the vertical axis, in radians.
+    pub yaw: f32,
+    /// The tilt away from looking straight down, in radians.
+    pub pitch: f32,
+    /// The distance from the center of the terrain.
+    pub distance: f32,
+}
+
+impl OrbitCamera {
+    pub fn new() -> Self {
+        Self {
+            yaw: 0.0,
+            pitch: 0.9,
+            distance: ORBIT_DISTANCE_DEFAULT,
+        }
+    }
+
+    /// Circle around the terrain by a mouse drag [delta] in screen units.
+    pub fn orbit(&mut self, delta: Vec2) {
+        self.yaw -= delta.x * ORBIT_SPEED;
+        self.pitch = (self.pitch + delta.y * ORBIT_SPEED).clamp(0.0, ORBIT_PITCH_MAX);
+    }
+
+    /// Move towards the terrain by a zoom [factor].
+    pub fn zoom(&mut self, factor: f32) {
+        self.distance = (self.distance / factor).clamp(ORBIT_DISTANCE_MIN, ORBIT_DISTANCE_MAX);
+    }
+
+    /// Project the world point [p] to the screen, scaled by [focal] screen units at unit depth.
+    /// Returns the screen position and the depth, or None if the point is behind the camera.
+    pub fn project(&self, p: Vec3, focal: f32) -> Option<(Vec2, f32)> {
+        let view = Quat::from_rotation_x(-self.pitch) * Quat::from_rotation_z(self.yaw);
+        let p = view * p;
+        let depth = self.distance - p.z;
+
+        if depth < ORBIT_NEAR {
+            return None;
+        }
+
+        Some((p.truncate() * focal / depth, depth))
+    }
+}
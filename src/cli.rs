@@ -0,0 +1,461 @@
+use std::path::PathBuf;
+
+use crate::diff::TerrainDiff;
+use crate::export::*;
+use crate::grid::{grid_cells, GridShape, MapGrid, GRID_CELL_SIZE};
+use crate::log::{self, Level};
+use crate::regions::Regions;
+use crate::terrain::{ScoreConfig, Terrain, TerrainStats};
+
+/// The number of best seeds reported by a search.
+const SEARCH_BEST: usize = 10;
+
+const USAGE: &str = "usage:
+    terrain [-v|-q] [command]        log debug messages, or only warnings and errors
+    terrain                          open the interactive viewer
+    terrain snapshot <seed> <path>   generate a terrain and write its snapshot to <path>
+    terrain diff <a> <b>             compare two snapshots, exiting with 1 if they differ
+    terrain geojson <seed> <path> [equirectangular|mercator <west> <south> <east> <north>]
+                                     generate a terrain and write it as GeoJSON, optionally
+                                     georeferenced to a longitude and latitude box
+    terrain travel <seed> [path]     generate a terrain and print the travel costs between its
+                                     cities as CSV, or write them to <path>
+    terrain erosion <seed> [path]    generate a terrain and print the statistics of each erosion
+                                     pass as CSV, or write them to <path>
+    terrain fields <seed> <path> [fields]
+                                     generate a terrain and write the comma-separated fields of
+                                     each vertex (x,y,elevation,flux,erosion,region,habitability
+                                     by default) to <path>, as NumPy if it ends in .npy or else
+                                     as CSV
+    terrain grid <seed> <hex|square> <path> [size]
+                                     generate a terrain and write the biome, elevation band and
+                                     settlements of each cell of a play grid to <path>, as JSON
+                                     if it ends in .json or else as CSV
+    terrain search <seed> <count> [threshold]
+                                     generate up to <count> terrains from <seed> onwards,
+                                     stopping at the first scoring at least <threshold>, and print
+                                     the best seeds and their scores as CSV
+    terrain hash <seed>              generate a terrain and print the hashes of its terrain and
+                                     regions, which match on any machine for the same seed";
+
+/// Set the log verbosity from the leading -v (verbose) and -q (quiet) flags of [args], returning
+/// the remaining arguments.
+pub fn parse_verbosity(args: &[String]) -> Vec<String> {
+    let mut rest = args;
+
+    while let Some((flag, tail)) = rest.split_first() {
+        match flag.as_str() {
+            "-v" | "--verbose" => log::set_verbosity(Level::Debug),
+            "-q" | "--quiet" => log::set_verbosity(Level::Warn),
+            _ => break,
+        }
+
+        rest = tail;
+    }
+
+    rest.to_vec()
+}
+
+/// Run the command named by [args], returning the process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+
+    match args.as_slice() {
+        ["snapshot", seed, path] => snapshot(seed, path),
+        ["diff", a, b] => diff(a, b),
+        ["geojson", seed, path] => geojson(seed, path, None),
+        ["travel", seed] => travel(seed, None),
+        ["travel", seed, path] => travel(seed, Some(path)),
+        ["hash", seed] => hash(seed),
+        ["grid", seed, shape, path] => grid(seed, shape, path, None),
+        ["grid", seed, shape, path, size] => grid(seed, shape, path, Some(size)),
+        ["search", seed, count] => search(seed, count, None),
+        ["search", seed, count, threshold] => search(seed, count, Some(threshold)),
+        ["erosion", seed] => erosion(seed, None),
+        ["erosion", seed, path] => erosion(seed, Some(path)),
+        ["fields", seed, path] => fields(seed, path, None),
+        ["fields", seed, path, names] => fields(seed, path, Some(names)),
+        ["geojson", seed, path, projection, west, south, east, north] => {
+            match parse_georeference(projection, [*west, *south, *east, *north]) {
+                Some(georeference) => geojson(seed, path, Some(georeference)),
+                None => {
+                    log::error!("invalid projection or bounds");
+                    println!("{}", USAGE);
+                    2
+                }
+            }
+        }
+        _ => {
+            println!("{}", USAGE);
+            2
+        }
+    }
+}
+
+fn snapshot(seed: &str, path: &str) -> i32 {
+    let seed = match parse_seed(seed) {
+        Some(seed) => seed,
+        None => return 2,
+    };
+
+    let terrain = match generate(seed) {
+        Some(terrain) => terrain,
+        None => return 2,
+    };
+
+    let path = PathBuf::from(path);
+
+    match write_snapshot(&path, &terrain) {
+        Ok(()) => {
+            log::info!("wrote snapshot of seed {} to {:?}", seed, path);
+            0
+        }
+        Err(e) => {
+            log::error!("failed to write snapshot: {}", e);
+            2
+        }
+    }
+}
+
+fn diff(a: &str, b: &str) -> i32 {
+    let snapshots = read_snapshot(&PathBuf::from(a))
+        .and_then(|a| read_snapshot(&PathBuf::from(b)).map(|b| (a, b)));
+
+    match snapshots {
+        Ok((a, b)) => {
+            let diff = TerrainDiff::new(&a, &b);
+            println!("{}", diff);
+
+            if diff.is_identical() {
+                0
+            } else {
+                1
+            }
+        }
+        Err(e) => {
+            log::error!("failed to read snapshot: {}", e);
+            2
+        }
+    }
+}
+
+fn geojson(seed: &str, path: &str, georeference: Option<Georeference>) -> i32 {
+    let seed = match parse_seed(seed) {
+        Some(seed) => seed,
+        None => return 2,
+    };
+
+    let terrain = match generate(seed) {
+        Some(terrain) => terrain,
+        None => return 2,
+    };
+
+    let regions = Regions::new(&terrain);
+
+    let path = PathBuf::from(path);
+    let options = GeoJsonOptions {
+        georeference,
+        ..GeoJsonOptions::default()
+    };
+
+    match write_geojson(&path, &terrain, &regions, &[], options) {
+        Ok(()) => {
+            log::info!("exported geojson of seed {} to {:?}", seed, path);
+            0
+        }
+        Err(e) => {
+            log::error!("failed to export geojson: {}", e);
+            2
+        }
+    }
+}
+
+fn travel(seed: &str, path: Option<&str>) -> i32 {
+    let seed = match parse_seed(seed) {
+        Some(seed) => seed,
+        None => return 2,
+    };
+
+    let terrain = match generate(seed) {
+        Some(terrain) => terrain,
+        None => return 2,
+    };
+
+    let regions = Regions::new(&terrain);
+    let csv = regions.travel.to_csv(&regions.city_names);
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            print!("{}", csv);
+            return 0;
+        }
+    };
+
+    match std::fs::write(&path, csv) {
+        Ok(()) => {
+            log::info!("wrote travel costs of seed {} to {:?}", seed, path);
+            0
+        }
+        Err(e) => {
+            log::error!("failed to write travel costs: {}", e);
+            2
+        }
+    }
+}
+
+fn hash(seed: &str) -> i32 {
+    let seed = match parse_seed(seed) {
+        Some(seed) => seed,
+        None => return 2,
+    };
+
+    let terrain = match generate(seed) {
+        Some(terrain) => terrain,
+        None => return 2,
+    };
+
+    let regions = Regions::new(&terrain);
+
+    println!("terrain {:016x}", terrain.content_hash());
+    println!("regions {:016x}", regions.content_hash());
+
+    0
+}
+
+fn erosion(seed: &str, path: Option<&str>) -> i32 {
+    let seed = match parse_seed(seed) {
+        Some(seed) => seed,
+        None => return 2,
+    };
+
+    let terrain = match generate(seed) {
+        Some(terrain) => terrain,
+        None => return 2,
+    };
+
+    let csv = erosion_stats_to_csv(&terrain.data.erosion_stats);
+
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            print!("{}", csv);
+            return 0;
+        }
+    };
+
+    match std::fs::write(&path, csv) {
+        Ok(()) => {
+            log::info!("wrote erosion stats of seed {} to {:?}", seed, path);
+            0
+        }
+        Err(e) => {
+            log::error!("failed to write erosion stats: {}", e);
+            2
+        }
+    }
+}
+
+fn grid(seed: &str, shape: &str, path: &str, size: Option<&str>) -> i32 {
+    let shape = match GridShape::from_name(shape) {
+        Some(shape) => shape,
+        None => {
+            log::error!("invalid grid shape {:?}", shape);
+            println!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    let size = match size.map(|s| s.parse::<f32>()) {
+        None => GRID_CELL_SIZE,
+        Some(Ok(size)) if size > 0.0 => size,
+        _ => {
+            log::error!("invalid grid size {:?}", size);
+            return 2;
+        }
+    };
+
+    let seed = match parse_seed(seed) {
+        Some(seed) => seed,
+        None => return 2,
+    };
+
+    let terrain = match generate(seed) {
+        Some(terrain) => terrain,
+        None => return 2,
+    };
+
+    let regions = Regions::new(&terrain);
+
+    let grid = MapGrid::new(terrain.extent, shape, size);
+    let cells = grid_cells(&grid, &terrain, &regions);
+
+    let path = PathBuf::from(path);
+
+    match write_grid(&path, &grid, &cells, &regions) {
+        Ok(()) => {
+            log::info!("wrote {} grid of seed {} to {:?}", shape.name(), seed, path);
+            0
+        }
+        Err(e) => {
+            log::error!("failed to write grid: {}", e);
+            2
+        }
+    }
+}
+
+fn search(seed: &str, count: &str, threshold: Option<&str>) -> i32 {
+    let first = match parse_seed(seed) {
+        Some(seed) => seed,
+        None => return 2,
+    };
+
+    let count = match count.parse::<u64>() {
+        Ok(count) => count,
+        Err(e) => {
+            log::error!("invalid count {:?}: {}", count, e);
+            return 2;
+        }
+    };
+
+    let threshold = match threshold.map(|t| t.parse::<f32>()) {
+        None => f32::INFINITY,
+        Some(Ok(threshold)) => threshold,
+        Some(Err(e)) => {
+            log::error!("invalid threshold {:?}: {}", threshold, e);
+            return 2;
+        }
+    };
+
+    let config = ScoreConfig::default();
+    let mut scored = vec![];
+
+    for seed in (first..).take(count as usize) {
+        let terrain = match generate(seed) {
+            Some(terrain) => terrain,
+            None => continue,
+        };
+
+        // Only the summary numbers are scored, so a single histogram bin is enough.
+        let stats = TerrainStats::new(&terrain, 1);
+        let score = stats.score(&config);
+
+        log::debug!("seed {} scored {:.3}", seed, score);
+        scored.push((seed, score, stats));
+
+        if score >= threshold {
+            log::info!("seed {} reached the threshold with {:.3}", seed, score);
+            break;
+        }
+    }
+
+    scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    println!("seed,score,land_fraction,islands,rivers,prominence_variance");
+
+    for (seed, score, stats) in scored.iter().take(SEARCH_BEST) {
+        println!(
+            "{},{},{},{},{},{}",
+            seed,
+            score,
+            stats.land_fraction,
+            stats.num_islands,
+            stats.num_rivers,
+            stats.prominence_variance
+        );
+    }
+
+    0
+}
+
+fn fields(seed: &str, path: &str, names: Option<&str>) -> i32 {
+    let fields = match names {
+        Some(names) => match names.split(',').map(VertexField::from_name).collect() {
+            Some(fields) => fields,
+            None => {
+                log::error!("invalid fields {:?}", names);
+                println!("{}", USAGE);
+                return 2;
+            }
+        },
+        None => VertexField::ALL.to_vec(),
+    };
+
+    let seed = match parse_seed(seed) {
+        Some(seed) => seed,
+        None => return 2,
+    };
+
+    let terrain = match generate(seed) {
+        Some(terrain) => terrain,
+        None => return 2,
+    };
+
+    let regions = Regions::new(&terrain);
+
+    let path = PathBuf::from(path);
+
+    let format = if path.extension().map_or(false, |e| e == "npy") {
+        FieldFormat::Npy
+    } else {
+        FieldFormat::Csv
+    };
+
+    match write_fields(&path, &terrain, &regions, &fields, format) {
+        Ok(()) => {
+            log::info!("wrote fields of seed {} to {:?}", seed, path);
+            0
+        }
+        Err(e) => {
+            log::error!("failed to write fields: {}", e);
+            2
+        }
+    }
+}
+
+fn parse_seed(seed: &str) -> Option<u64> {
+    match seed.parse::<u64>() {
+        Ok(seed) => Some(seed),
+        Err(e) => {
+            log::error!("invalid seed {:?}: {}", seed, e);
+            None
+        }
+    }
+}
+
+fn parse_georeference(projection: &str, bounds: [&str; 4]) -> Option<Georeference> {
+    let projection = match projection {
+        "equirectangular" => Projection::Equirectangular,
+        "mercator" => Projection::Mercator,
+        _ => return None,
+    };
+
+    let mut values = [0.0; 4];
+
+    for (value, text) in values.iter_mut().zip(bounds.iter()) {
+        *value = text.parse::<f64>().ok()?;
+    }
+
+    let [west, south, east, north] = values;
+
+    Some(Georeference {
+        bounds: GeoBounds {
+            west,
+            south,
+            east,
+            north,
+        },
+        projection,
+    })
+}
+
+/// Generate the terrain for [seed] with the default config, printing the error if it fails.
+fn generate(seed: u64) -> Option<Terrain> {
+    match crate::generate_map_terrain(crate::default_config(seed), &[]) {
+        Ok(terrain) => Some(terrain),
+        Err(e) => {
+            log::error!("failed to generate terrain for seed {}: {}", seed, e);
+            None
+        }
+    }
+}
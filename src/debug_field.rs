@@ -0,0 +1,106 @@
+use colorous::Gradient;
+use nannou::prelude::*;
+
+use crate::regions::Regions;
+use crate::terrain::Terrain;
+use crate::theme::DebugPalette;
+use crate::util::{indexed_mean, map_clamp, IntoNannouColor};
+
+/// A named per-vertex data layer that the debug view can show. Adding a layer only needs a new
+/// variant here, with its values and gradient.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ScalarField {
+    Elevation,
+    Flux,
+    Erosion,
+    Hardness,
+    Moisture,
+    Temperature,
+    Habitability,
+    CoastDistance,
+}
+
+impl ScalarField {
+    /// The next field in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            ScalarField::Elevation => ScalarField::Flux,
+            ScalarField::Flux => ScalarField::Erosion,
+            ScalarField::Erosion => ScalarField::Hardness,
+            ScalarField::Hardness => ScalarField::Moisture,
+            ScalarField::Moisture => ScalarField::Temperature,
+            ScalarField::Temperature => ScalarField::Habitability,
+            ScalarField::Habitability => ScalarField::CoastDistance,
+            ScalarField::CoastDistance => ScalarField::Elevation,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScalarField::Elevation => "elevation",
+            ScalarField::Flux => "flux",
+            ScalarField::Erosion => "erosion",
+            ScalarField::Hardness => "hardness",
+            ScalarField::Moisture => "moisture",
+            ScalarField::Temperature => "temperature",
+            ScalarField::Habitability => "habitability",
+            ScalarField::CoastDistance => "coast distance",
+        }
+    }
+
+    /// The value of the field at each vertex, mapped into \[0, 1\] for the gradient.
+    pub fn values(&self, terrain: &Terrain, regions: &Regions) -> Vec<f32> {
+        let data = &terrain.data;
+
+        match self {
+            ScalarField::Elevation => data.elevation_normalized.clone(),
+            ScalarField::Flux => scaled(&data.flux, 0.0, 0.05),
+            ScalarField::Erosion => scaled(&data.erosion, 0.0, 2.0),
+            ScalarField::Hardness => {
+                let log: Vec<f32> = data.hardness.iter().map(|h| h.log2()).collect();
+                scaled(&log, -2.0, 2.0)
+            }
+            ScalarField::Moisture => data.moisture.clone(),
+            ScalarField::Temperature => scaled(&data.temperature, -16.0, 24.0),
+            ScalarField::Habitability => regions.habitability.clone(),
+            ScalarField::CoastDistance => scaled(&terrain.mesh.coast_distance, 0.0, 200.0),
+        }
+    }
+
+    pub fn gradient(&self, palette: DebugPalette) -> Gradient {
+        match self {
+            ScalarField::Elevation => palette.elevation(),
+            ScalarField::Flux => palette.water(),
+            ScalarField::Moisture => palette.wetness(),
+            ScalarField::Erosion
+            | ScalarField::Hardness
+            | ScalarField::Temperature
+            | ScalarField::Habitability
+            | ScalarField::CoastDistance => palette.intensity(),
+        }
+    }
+}
+
+/// Map [values] from the range \[min, max\] into \[0, 1\], clamping values outside it.
+pub fn scaled(values: &[f32], min: f32, max: f32) -> Vec<f32> {
+    values
+        .iter()
+        .map(|v| map_clamp(*v, min, max, 0.0, 1.0))
+        .collect()
+}
+
+/// Color each polygon by the mean of the per-vertex [values] of its cell, which are expected to
+/// be in \[0, 1\].
+pub fn debug_scalar_field(draw: &Draw, terrain: &Terrain, values: &[f32], gradient: Gradient) {
+    for (i, poly) in terrain.mesh.polygons.iter().enumerate() {
+        if let Some(poly) = poly {
+            let p = poly.points.iter().cloned();
+            let t = indexed_mean(values, terrain.graph.cell(i));
+            let c = gradient
+                .eval_continuous(t.clamp(0.0, 1.0) as f64)
+                .into_rgb();
+
+            draw.polygon().points(p).color(c);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+
+use crate::export::TerrainSnapshot;
+
+/// Per-field statistics comparing two snapshots of the same seed, for catching output drift when
+/// the generation pipeline changes.
+#[derive(Debug, Clone)]
+pub struct TerrainDiff {
+    /// The seeds of both snapshots.
+    pub seeds: (u64, u64),
+    /// The number of vertices in both snapshots.
+    pub vertex_counts: (usize, usize),
+    /// The root mean square elevation change over the shared vertices.
+    pub elevation_rms: f32,
+    /// The largest elevation change of any shared vertex.
+    pub elevation_max: f32,
+    /// The number of shared polygons that changed between land and water.
+    pub changed_surface: usize,
+    /// The number of rivers in both snapshots.
+    pub river_counts: (usize, usize),
+    /// The change in total river flux.
+    pub river_flux_delta: f32,
+}
+
+impl TerrainDiff {
+    pub fn new(a: &TerrainSnapshot, b: &TerrainSnapshot) -> Self {
+        let shared = usize::min(a.elevation.len(), b.elevation.len());
+
+        let mut sum_squares = 0.0;
+        let mut elevation_max: f32 = 0.0;
+
+        for (ea, eb) in a.elevation.iter().zip(b.elevation.iter()) {
+            let delta = (eb - ea).abs();
+            sum_squares += delta * delta;
+            elevation_max = elevation_max.max(delta);
+        }
+
+        let elevation_rms = if shared > 0 {
+            (sum_squares / shared as f32).sqrt()
+        } else {
+            0.0
+        };
+
+        let changed_surface = a
+            .land
+            .iter()
+            .zip(b.land.iter())
+            .filter(|(la, lb)| la != lb)
+            .count();
+
+        let flux_a: f32 = a.rivers.iter().sum();
+        let flux_b: f32 = b.rivers.iter().sum();
+
+        Self {
+            seeds: (a.seed, b.seed),
+            vertex_counts: (a.elevation.len(), b.elevation.len()),
+            elevation_rms,
+            elevation_max,
+            changed_surface,
+            river_counts: (a.rivers.len(), b.rivers.len()),
+            river_flux_delta: flux_b - flux_a,
+        }
+    }
+
+    /// True if the snapshots match exactly.
+    pub fn is_identical(&self) -> bool {
+        self.vertex_counts.0 == self.vertex_counts.1
+            && self.elevation_max == 0.0
+            && self.changed_surface == 0
+            && self.river_counts.0 == self.river_counts.1
+            && self.river_flux_delta == 0.0
+    }
+}
+
+impl fmt::Display for TerrainDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.seeds.0 != self.seeds.1 {
+            writeln!(
+                f,
+                "warning: comparing different seeds {} and {}",
+                self.seeds.0, self.seeds.1
+            )?;
+        }
+
+        writeln!(
+            f,
+            "vertices:        {} -> {}",
+            self.vertex_counts.0, self.vertex_counts.1
+        )?;
+        writeln!(f, "elevation rms:   {:.4}", self.elevation_rms)?;
+        writeln!(f, "elevation max:   {:.4}", self.elevation_max)?;
+        writeln!(f, "changed surface: {}", self.changed_surface)?;
+        writeln!(
+            f,
+            "rivers:          {} -> {}",
+            self.river_counts.0, self.river_counts.1
+        )?;
+        write!(f, "river flux:      {:+.6}", self.river_flux_delta)
+    }
+}
@@ -0,0 +1,20 @@
+pub mod erosion;
+pub use erosion::*;
+
+pub mod fields;
+pub use fields::*;
+
+pub mod geojson;
+pub use geojson::*;
+
+pub mod grid;
+pub use grid::*;
+
+pub mod projection;
+pub use projection::*;
+
+pub mod snapshot;
+pub use snapshot::*;
+
+pub mod svg;
+pub use svg::*;
@@ -0,0 +1,39 @@
+use crate::terrain::ErosionPassStats;
+
+/// The statistics of each erosion pass as CSV, one row per pass, with the elevation histogram
+/// bins in the last columns.
+pub fn erosion_stats_to_csv(stats: &[ErosionPassStats]) -> String {
+    let bins = stats.iter().map(|s| s.histogram.len()).max().unwrap_or(0);
+
+    let mut header = vec![
+        "pass".to_string(),
+        "removed".to_string(),
+        "max_erosion".to_string(),
+        "clamped_fraction".to_string(),
+        "min_elevation".to_string(),
+        "max_elevation".to_string(),
+    ];
+
+    header.extend((0..bins).map(|i| format!("bin_{}", i)));
+
+    let mut csv = header.join(",");
+    csv.push('\n');
+
+    for (pass, s) in stats.iter().enumerate() {
+        let mut row = vec![
+            (pass + 1).to_string(),
+            s.removed.to_string(),
+            s.max_erosion.to_string(),
+            s.clamped_fraction.to_string(),
+            s.min_elevation.to_string(),
+            s.max_elevation.to_string(),
+        ];
+
+        row.extend(s.histogram.iter().map(|count| count.to_string()));
+
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
@@ -0,0 +1,133 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use crate::regions::Regions;
+use crate::terrain::Terrain;
+
+/// A per-vertex array that can be written for analysis outside the generator.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum VertexField {
+    X,
+    Y,
+    Elevation,
+    Flux,
+    Erosion,
+    /// The index of the region containing the vertex, or -1 if it is in none.
+    Region,
+    Habitability,
+}
+
+impl VertexField {
+    /// Every field, in the order they are written by default.
+    pub const ALL: [VertexField; 7] = [
+        VertexField::X,
+        VertexField::Y,
+        VertexField::Elevation,
+        VertexField::Flux,
+        VertexField::Erosion,
+        VertexField::Region,
+        VertexField::Habitability,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VertexField::X => "x",
+            VertexField::Y => "y",
+            VertexField::Elevation => "elevation",
+            VertexField::Flux => "flux",
+            VertexField::Erosion => "erosion",
+            VertexField::Region => "region",
+            VertexField::Habitability => "habitability",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().cloned().find(|f| f.name() == name)
+    }
+
+    /// The value of the field at vertex [v].
+    fn value(&self, terrain: &Terrain, regions: &Regions, v: usize) -> f32 {
+        match self {
+            VertexField::X => terrain.graph.vertices[v].x,
+            VertexField::Y => terrain.graph.vertices[v].y,
+            VertexField::Elevation => terrain.data.elevation[v],
+            VertexField::Flux => terrain.data.flux[v],
+            VertexField::Erosion => terrain.data.erosion[v],
+            VertexField::Region => regions.region_of(v).map_or(-1.0, |r| r as f32),
+            VertexField::Habitability => regions.habitability[v],
+        }
+    }
+}
+
+/// The file format the fields are written in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FieldFormat {
+    /// A header row of field names, then one row per vertex.
+    Csv,
+    /// A NumPy array of 32-bit floats with one row per vertex and one column per field.
+    Npy,
+}
+
+/// Write the [fields] of every vertex to [path], one row per vertex in vertex order.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_fields(
+    path: &Path,
+    terrain: &Terrain,
+    regions: &Regions,
+    fields: &[VertexField],
+    format: FieldFormat,
+) -> std::io::Result<()> {
+    match format {
+        FieldFormat::Csv => std::fs::write(path, fields_to_csv(terrain, regions, fields)),
+        FieldFormat::Npy => std::fs::write(path, fields_to_npy(terrain, regions, fields)),
+    }
+}
+
+pub fn fields_to_csv(terrain: &Terrain, regions: &Regions, fields: &[VertexField]) -> String {
+    let names: Vec<&str> = fields.iter().map(|f| f.name()).collect();
+
+    let mut csv = names.join(",");
+    csv.push('\n');
+
+    for v in 0..terrain.graph.vertices.len() {
+        let row: Vec<String> = fields
+            .iter()
+            .map(|f| f.value(terrain, regions, v).to_string())
+            .collect();
+
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// The fields as a version 1.0 .npy file, which NumPy reads with `numpy.load`.
+pub fn fields_to_npy(terrain: &Terrain, regions: &Regions, fields: &[VertexField]) -> Vec<u8> {
+    let rows = terrain.graph.vertices.len();
+
+    let mut header = format!(
+        "{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}",
+        rows,
+        fields.len()
+    );
+
+    // The magic, version and header length take ten bytes, and the header is padded with spaces
+    // and ended with a newline so the data starts on a multiple of 64 bytes.
+
+    let unpadded = 10 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    let mut npy = b"\x93NUMPY\x01\x00".to_vec();
+    npy.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    npy.extend_from_slice(header.as_bytes());
+
+    for v in 0..rows {
+        for field in fields.iter() {
+            npy.extend_from_slice(&field.value(terrain, regions, v).to_le_bytes());
+        }
+    }
+
+    npy
+}
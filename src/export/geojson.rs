@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use nannou::geom::Rect;
+use nannou::glam::Vec2;
+use serde_json::{json, Value};
+
+use crate::annotations::Annotation;
+use crate::export::Georeference;
+use crate::regions::Regions;
+use crate::terrain::{Terrain, TerrainSurface};
+use crate::util::{assemble_polygons, chain_edges, is_closed_chain, simplify_path};
+
+/// The fewest positions in a GeoJSON polygon ring, including the repeated first position.
+const MIN_RING_POINTS: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+pub struct GeoJsonOptions {
+    /// The scale applied to world coordinates when they are written.
+    pub scale: f32,
+    /// If set, coordinates are written as longitude and latitude instead of world coordinates,
+    /// and [scale] is ignored.
+    pub georeference: Option<Georeference>,
+    /// The distance in world units that the coastlines and rivers may be simplified by. Zero
+    /// writes every point.
+    pub simplify: f32,
+}
+
+impl Default for GeoJsonOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            georeference: None,
+            simplify: 0.5,
+        }
+    }
+}
+
+/// Converts world points to the coordinates written to the file.
+#[derive(Debug, Clone, Copy)]
+struct CoordinateTransform {
+    extent: Rect,
+    options: GeoJsonOptions,
+}
+
+impl CoordinateTransform {
+    fn apply(&self, p: Vec2) -> [f64; 2] {
+        match self.options.georeference {
+            Some(georeference) => georeference.project(self.extent, p),
+            None => {
+                let p = p * self.options.scale;
+                [p.x as f64, p.y as f64]
+            }
+        }
+    }
+}
+
+/// Write the terrain as a GeoJSON feature collection to [path].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_geojson(
+    path: &Path,
+    terrain: &Terrain,
+    regions: &Regions,
+    annotations: &[Annotation],
+    options: GeoJsonOptions,
+) -> std::io::Result<()> {
+    let json = terrain_to_geojson(terrain, regions, annotations, options);
+    std::fs::write(path, json.to_string())
+}
+
+/// Convert the coastline, rivers, cities, regions and user [annotations] into a GeoJSON feature
+/// collection.
+pub fn terrain_to_geojson(
+    terrain: &Terrain,
+    regions: &Regions,
+    annotations: &[Annotation],
+    options: GeoJsonOptions,
+) -> Value {
+    let transform = CoordinateTransform {
+        extent: terrain.extent,
+        options,
+    };
+
+    let mut features = vec![];
+
+    features.append(&mut coastline_features(terrain, transform));
+    features.append(&mut island_features(terrain, regions, transform));
+    features.append(&mut river_features(terrain, regions, transform));
+    features.append(&mut peak_features(terrain, regions, transform));
+    features.append(&mut poi_features(regions, transform));
+    features.append(&mut city_features(terrain, regions, transform));
+    features.append(&mut region_features(terrain, regions, transform));
+    features.append(&mut annotation_features(annotations, transform));
+
+    json!({
+        "type": "FeatureCollection",
+        "features": features,
+    })
+}
+
+fn coastline_features(terrain: &Terrain, transform: CoordinateTransform) -> Vec<Value> {
+    let vertices = &terrain.graph.vertices;
+
+    let mut rings = vec![];
+    let mut features = vec![];
+
+    // Closed coastline chains are assembled into land polygons (with lakes as holes). Chains that
+    // run off the edge of the graph cannot be closed, so they are written as plain lines.
+
+    for chain in chain_edges(&terrain.mesh.contour.edges) {
+        let points: Vec<Vec2> = chain.iter().map(|v| vertices[*v]).collect();
+        let simplified = simplify_path(&points, transform.options.simplify);
+
+        if is_closed_chain(&chain) {
+            // A GeoJSON ring needs at least four positions, so a small island that simplifies
+            // down to fewer keeps all of its points, and one too small even then is dropped.
+
+            if simplified.len() >= MIN_RING_POINTS {
+                rings.push(simplified);
+            } else if points.len() >= MIN_RING_POINTS {
+                rings.push(points);
+            }
+        } else {
+            features.push(json!({
+                "type": "Feature",
+                "properties": { "kind": "coastline" },
+                "geometry": {
+                    "type": "LineString",
+                    "coordinates": coordinates(&simplified, transform),
+                },
+            }));
+        }
+    }
+
+    // The coastline separates land from water in both directions, so keep only the polygons
+    // that actually enclose land.
+
+    let polygons = assemble_polygons(rings)
+        .into_iter()
+        .filter(|polygon| encloses_land(terrain, &polygon[0]));
+
+    for polygon in polygons {
+        features.push(json!({
+            "type": "Feature",
+            "properties": { "kind": "land" },
+            "geometry": {
+                "type": "Polygon",
+                "coordinates": polygon_coordinates(&polygon, transform),
+            },
+        }));
+    }
+
+    features
+}
+
+fn island_features(
+    terrain: &Terrain,
+    regions: &Regions,
+    transform: CoordinateTransform,
+) -> Vec<Value> {
+    let mut features = vec![];
+
+    let merged: HashMap<usize, _> = terrain
+        .mesh
+        .merged_polygons(|p| terrain.mesh.island[p])
+        .into_iter()
+        .collect();
+
+    for (i, island) in terrain.mesh.islands.iter().enumerate() {
+        let polygons: Vec<Value> = match merged.get(&i) {
+            Some(polygons) => polygons
+                .iter()
+                .map(|polygon| polygon_coordinates(polygon, transform))
+                .collect(),
+            None => vec![],
+        };
+
+        let peak = island.peak.map(|p| terrain.mesh.peaks[p].vertex);
+
+        features.push(json!({
+            "type": "Feature",
+            "properties": {
+                "kind": "island",
+                "id": i,
+                "name": regions.island_names[i],
+                "area": island.area,
+                "peak": peak,
+                "cities": regions.island_cities(terrain, i),
+            },
+            "geometry": {
+                "type": "MultiPolygon",
+                "coordinates": polygons,
+            },
+        }));
+    }
+
+    features
+}
+
+fn river_features(
+    terrain: &Terrain,
+    regions: &Regions,
+    transform: CoordinateTransform,
+) -> Vec<Value> {
+    let mut features = vec![];
+
+    for (i, river) in terrain.mesh.rivers.iter().enumerate() {
+        if river.points.len() < 2 {
+            continue;
+        }
+
+        let points = simplify_path(&river.points, transform.options.simplify);
+
+        features.push(json!({
+            "type": "Feature",
+            "properties": {
+                "kind": "river",
+                "name": regions.river_names[i],
+                "flux": river.flux,
+            },
+            "geometry": {
+                "type": "LineString",
+                "coordinates": coordinates(&points, transform),
+            },
+        }));
+    }
+
+    features
+}
+
+fn peak_features(
+    terrain: &Terrain,
+    regions: &Regions,
+    transform: CoordinateTransform,
+) -> Vec<Value> {
+    let mut features = vec![];
+
+    for (i, peak) in terrain.mesh.peaks.iter().enumerate() {
+        let p = transform.apply(terrain.graph.vertices[peak.vertex]);
+
+        features.push(json!({
+            "type": "Feature",
+            "properties": {
+                "kind": "peak",
+                "name": regions.peak_names[i],
+                "vertex": peak.vertex,
+                "elevation": peak.elevation,
+                "prominence": peak.prominence,
+            },
+            "geometry": {
+                "type": "Point",
+                "coordinates": p,
+            },
+        }));
+    }
+
+    features
+}
+
+fn poi_features(regions: &Regions, transform: CoordinateTransform) -> Vec<Value> {
+    let mut features = vec![];
+
+    for poi in regions.pois.iter() {
+        let p = transform.apply(poi.position);
+
+        features.push(json!({
+            "type": "Feature",
+            "properties": {
+                "kind": "poi",
+                "poi": format!("{:?}", poi.kind),
+            },
+            "geometry": {
+                "type": "Point",
+                "coordinates": p,
+            },
+        }));
+    }
+
+    features
+}
+
+fn annotation_features(annotations: &[Annotation], transform: CoordinateTransform) -> Vec<Value> {
+    annotations
+        .iter()
+        .map(|a| {
+            json!({
+                "type": "Feature",
+                "properties": {
+                    "kind": "annotation",
+                    "name": a.name,
+                    "note": a.note,
+                },
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": transform.apply(a.position),
+                },
+            })
+        })
+        .collect()
+}
+
+fn city_features(
+    terrain: &Terrain,
+    regions: &Regions,
+    transform: CoordinateTransform,
+) -> Vec<Value> {
+    let mut features = vec![];
+
+    for (i, v) in regions.cities.iter().cloned().enumerate() {
+        let p = transform.apply(terrain.graph.vertices[v]);
+
+        features.push(json!({
+            "type": "Feature",
+            "properties": {
+                "kind": "city",
+                "id": i,
+                "name": regions.city_names[i],
+                "vertex": v,
+                "region": regions.regions[v],
+                "capital": regions.is_capital(i),
+                "economy": regions.economies[i].iter().map(|e| e.name()).collect::<Vec<_>>(),
+                "elevation": terrain.data.elevation[v],
+                "elevation_normalized": terrain.data.elevation_normalized[v],
+                "habitability": regions.habitability[v],
+            },
+            "geometry": {
+                "type": "Point",
+                "coordinates": p,
+            },
+        }));
+    }
+
+    features
+}
+
+fn region_features(
+    terrain: &Terrain,
+    regions: &Regions,
+    transform: CoordinateTransform,
+) -> Vec<Value> {
+    let mut features = vec![];
+
+    // Each region is the union of its land cells, which follows the borders as they are drawn.
+
+    let merged: HashMap<usize, _> = terrain
+        .mesh
+        .merged_polygons(|p| regions.cells[p])
+        .into_iter()
+        .collect();
+
+    for (i, city) in regions.founders().iter().cloned().enumerate() {
+        let region = regions.regions[city];
+
+        let polygons: Vec<Value> = match merged.get(&i) {
+            Some(polygons) => polygons
+                .iter()
+                .map(|polygon| polygon_coordinates(polygon, transform))
+                .collect(),
+            None => vec![],
+        };
+
+        // Neighbors are identified by their founding city vertex, like the region itself.
+
+        let neighbors: Vec<usize> = regions.adjacency[i]
+            .iter()
+            .map(|n| regions.founders()[*n])
+            .collect();
+
+        features.push(json!({
+            "type": "Feature",
+            "properties": {
+                "kind": "region",
+                "region": region,
+                "name": regions.region_names[i],
+                "culture": format!("{:?}", regions.cultures[i]),
+                "city": city,
+                "neighbors": neighbors,
+                "border_lengths": regions.border_lengths[i],
+            },
+            "geometry": {
+                "type": "MultiPolygon",
+                "coordinates": polygons,
+            },
+        }));
+    }
+
+    features
+}
+
+/// Returns true if the land side of the coastline is inside the ring.
+fn encloses_land(terrain: &Terrain, ring: &[Vec2]) -> bool {
+    // Find the cell whose point is closest to the inside of the first ring segment. Exterior rings
+    // are counter-clockwise, so the inside is to the left of each segment.
+
+    let a = ring[0];
+    let b = ring[1];
+    let probe = a.lerp(b, 0.5) + (b - a).perp() * 0.1;
+
+    let nearest = terrain
+        .graph
+        .points
+        .iter()
+        .map(|p| p.distance_squared(probe))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i);
+
+    match nearest {
+        Some(i) => terrain.mesh.surface[i] == TerrainSurface::Land,
+        None => false,
+    }
+}
+
+fn coordinates(points: &[Vec2], transform: CoordinateTransform) -> Value {
+    let coords: Vec<[f64; 2]> = points.iter().map(|p| transform.apply(*p)).collect();
+
+    json!(coords)
+}
+
+fn polygon_coordinates(polygon: &[Vec<Vec2>], transform: CoordinateTransform) -> Value {
+    let rings: Vec<Value> = polygon
+        .iter()
+        .map(|ring| coordinates(ring, transform))
+        .collect();
+
+    json!(rings)
+}
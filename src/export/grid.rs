@@ -0,0 +1,79 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::grid::{GridCell, MapGrid};
+use crate::regions::Regions;
+
+/// Write the summary of each grid cell to [path], as JSON if it ends in .json or else as CSV.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_grid(
+    path: &Path,
+    grid: &MapGrid,
+    cells: &[GridCell],
+    regions: &Regions,
+) -> std::io::Result<()> {
+    if path.extension().map_or(false, |e| e == "json") {
+        std::fs::write(path, grid_to_json(grid, cells, regions).to_string())
+    } else {
+        std::fs::write(path, grid_to_csv(cells, regions))
+    }
+}
+
+/// The grid cells as CSV, one row per cell. The names of the settlements in a cell are separated
+/// by semicolons.
+pub fn grid_to_csv(cells: &[GridCell], regions: &Regions) -> String {
+    let mut csv = "column,row,x,y,biome,elevation_band,land_fraction,settlements\n".to_string();
+
+    for cell in cells.iter() {
+        let row = [
+            cell.column.to_string(),
+            cell.row.to_string(),
+            cell.center.x.to_string(),
+            cell.center.y.to_string(),
+            cell.biome.map_or("", |b| b.name()).to_string(),
+            cell.elevation_band.to_string(),
+            cell.land_fraction.to_string(),
+            settlement_names(cell, regions).join(";"),
+        ];
+
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// The grid layout and its cells, row by row.
+pub fn grid_to_json(grid: &MapGrid, cells: &[GridCell], regions: &Regions) -> Value {
+    let cells: Vec<Value> = cells
+        .iter()
+        .map(|cell| {
+            json!({
+                "column": cell.column,
+                "row": cell.row,
+                "center": [cell.center.x, cell.center.y],
+                "biome": cell.biome.map(|b| b.name()),
+                "elevation_band": cell.elevation_band,
+                "land_fraction": cell.land_fraction,
+                "settlements": settlement_names(cell, regions),
+            })
+        })
+        .collect();
+
+    json!({
+        "shape": grid.shape.name(),
+        "size": grid.size,
+        "columns": grid.columns,
+        "rows": grid.rows,
+        "cells": cells,
+    })
+}
+
+fn settlement_names<'a>(cell: &GridCell, regions: &'a Regions) -> Vec<&'a str> {
+    cell.settlements
+        .iter()
+        .map(|i| regions.city_names[*i].as_str())
+        .collect()
+}
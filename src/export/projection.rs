@@ -0,0 +1,66 @@
+use std::f64::consts::FRAC_PI_4;
+
+use nannou::geom::Rect;
+use nannou::glam::Vec2;
+
+/// The latitude limit of the Mercator projection, beyond which it stretches towards infinity.
+const MERCATOR_MAX_LATITUDE: f64 = 85.051_128_78;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Projection {
+    /// Longitude and latitude are linear in x and y.
+    Equirectangular,
+    /// Latitude is stretched towards the poles so that shapes are preserved locally.
+    Mercator,
+}
+
+/// A bounding box of longitude and latitude in degrees.
+#[derive(Debug, Copy, Clone)]
+pub struct GeoBounds {
+    pub west: f64,
+    pub south: f64,
+    pub east: f64,
+    pub north: f64,
+}
+
+/// Places the map in real coordinates: the terrain extent covers [bounds], as drawn in
+/// [projection].
+#[derive(Debug, Copy, Clone)]
+pub struct Georeference {
+    pub bounds: GeoBounds,
+    pub projection: Projection,
+}
+
+impl Georeference {
+    /// Convert a world point within [extent] to longitude and latitude in degrees.
+    pub fn project(&self, extent: Rect, p: Vec2) -> [f64; 2] {
+        let u = ((p.x - extent.left()) / extent.w()) as f64;
+        let v = ((p.y - extent.bottom()) / extent.h()) as f64;
+
+        let bounds = &self.bounds;
+        let lon = bounds.west + u * (bounds.east - bounds.west);
+
+        let lat = match self.projection {
+            Projection::Equirectangular => bounds.south + v * (bounds.north - bounds.south),
+            Projection::Mercator => {
+                let south = mercator_y(bounds.south);
+                let north = mercator_y(bounds.north);
+
+                inverse_mercator_y(south + v * (north - south))
+            }
+        };
+
+        [lon, lat]
+    }
+}
+
+fn mercator_y(lat: f64) -> f64 {
+    let lat = lat
+        .clamp(-MERCATOR_MAX_LATITUDE, MERCATOR_MAX_LATITUDE)
+        .to_radians();
+    (FRAC_PI_4 + lat * 0.5).tan().ln()
+}
+
+fn inverse_mercator_y(y: f64) -> f64 {
+    (2.0 * y.exp().atan() - 2.0 * FRAC_PI_4).to_degrees()
+}
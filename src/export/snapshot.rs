@@ -0,0 +1,94 @@
+use std::io::{Error, ErrorKind};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::terrain::{Terrain, TerrainSurface};
+
+/// The generated fields of a terrain that are compared between code versions. Snapshots are
+/// written as JSON so that they can be kept alongside the code that produced them.
+#[derive(Debug, Clone)]
+pub struct TerrainSnapshot {
+    pub seed: u64,
+    /// The elevation of each vertex.
+    pub elevation: Vec<f32>,
+    /// True if a particular polygon is land.
+    pub land: Vec<bool>,
+    /// The mean flux of each river.
+    pub rivers: Vec<f32>,
+}
+
+impl TerrainSnapshot {
+    pub fn new(terrain: &Terrain) -> Self {
+        Self {
+            seed: terrain.config.seed,
+            elevation: terrain.data.elevation.clone(),
+            land: terrain
+                .mesh
+                .surface
+                .iter()
+                .map(|s| *s == TerrainSurface::Land)
+                .collect(),
+            rivers: terrain.mesh.rivers.iter().map(|r| r.flux).collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "seed": self.seed,
+            "elevation": self.elevation,
+            "land": self.land,
+            "rivers": self.rivers,
+        })
+    }
+
+    pub fn from_json(json: &Value) -> std::io::Result<Self> {
+        let seed = json["seed"].as_u64().ok_or_else(|| invalid("seed"))?;
+
+        let elevation = json["elevation"]
+            .as_array()
+            .and_then(|a| a.iter().map(|v| v.as_f64().map(|f| f as f32)).collect())
+            .ok_or_else(|| invalid("elevation"))?;
+
+        let land = json["land"]
+            .as_array()
+            .and_then(|a| a.iter().map(|v| v.as_bool()).collect())
+            .ok_or_else(|| invalid("land"))?;
+
+        let rivers = json["rivers"]
+            .as_array()
+            .and_then(|a| a.iter().map(|v| v.as_f64().map(|f| f as f32)).collect())
+            .ok_or_else(|| invalid("rivers"))?;
+
+        Ok(Self {
+            seed,
+            elevation,
+            land,
+            rivers,
+        })
+    }
+}
+
+/// Write a snapshot of the terrain to [path].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_snapshot(path: &Path, terrain: &Terrain) -> std::io::Result<()> {
+    let json = TerrainSnapshot::new(terrain).to_json();
+    std::fs::write(path, json.to_string())
+}
+
+/// Read a snapshot previously written by [write_snapshot].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_snapshot(path: &Path) -> std::io::Result<TerrainSnapshot> {
+    let text = std::fs::read_to_string(path)?;
+    let json: Value = serde_json::from_str(&text)?;
+
+    TerrainSnapshot::from_json(&json)
+}
+
+fn invalid(field: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("missing or invalid snapshot field {:?}", field),
+    )
+}
@@ -0,0 +1,122 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+
+use nannou::geom::Rect;
+use nannou::glam::Vec2;
+
+use crate::render::{Color, Primitive};
+use crate::util::simplify_path;
+
+/// The background color of the exported map.
+const SVG_BACKGROUND: Color = Color::new(255, 250, 250);
+/// The distance the polylines and polygons may be simplified by, in world units.
+const SVG_SIMPLIFY_TOLERANCE: f32 = 0.1;
+
+/// Write the [primitives] as an SVG document covering the [extent] to [path].
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_svg(path: &Path, extent: Rect, primitives: &[Primitive]) -> std::io::Result<()> {
+    std::fs::write(path, primitives_to_svg(extent, primitives))
+}
+
+/// Convert the [primitives] to an SVG document covering the [extent]. World coordinates point y up
+/// and SVG coordinates point y down, so every y coordinate is negated.
+pub fn primitives_to_svg(extent: Rect, primitives: &[Primitive]) -> String {
+    let mut svg = String::new();
+
+    svg.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" width=\"{}\" height=\"{}\">\n",
+        extent.left(),
+        -extent.top(),
+        extent.w(),
+        extent.h(),
+        extent.w(),
+        extent.h(),
+    ));
+
+    svg.push_str(&format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+        extent.left(),
+        -extent.top(),
+        extent.w(),
+        extent.h(),
+        rgb(SVG_BACKGROUND),
+    ));
+
+    for primitive in primitives.iter() {
+        match primitive {
+            Primitive::Line {
+                points,
+                weight,
+                color,
+            } => {
+                svg.push_str(&format!(
+                    "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\"/>\n",
+                    points.0.x,
+                    -points.0.y,
+                    points.1.x,
+                    -points.1.y,
+                    rgb(*color),
+                    weight,
+                ));
+            }
+            Primitive::Polyline {
+                points,
+                weight,
+                color,
+            } => {
+                svg.push_str(&format!(
+                    "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n",
+                    point_list(points),
+                    rgb(*color),
+                    weight,
+                ));
+            }
+            Primitive::Polygon { points, color } => {
+                svg.push_str(&format!(
+                    "<polygon points=\"{}\" fill=\"{}\"/>\n",
+                    point_list(points),
+                    rgb(*color),
+                ));
+            }
+            Primitive::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => {
+                let stroke = match stroke {
+                    Some((weight, color)) => {
+                        format!(" stroke=\"{}\" stroke-width=\"{}\"", rgb(*color), weight)
+                    }
+                    None => String::new(),
+                };
+
+                svg.push_str(&format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"{}/>\n",
+                    center.x,
+                    -center.y,
+                    radius,
+                    rgb(*fill),
+                    stroke,
+                ));
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+
+    svg
+}
+
+fn rgb(color: Color) -> String {
+    format!("rgb({},{},{})", color.r, color.g, color.b)
+}
+
+/// The points of a polyline or polygon, simplified to drop the points that make no visible
+/// difference.
+fn point_list(points: &[Vec2]) -> String {
+    let points = simplify_path(points, SVG_SIMPLIFY_TOLERANCE);
+    let coords: Vec<String> = points.iter().map(|p| format!("{},{}", p.x, -p.y)).collect();
+
+    coords.join(" ")
+}
@@ -0,0 +1,42 @@
+pub mod globe_data;
+pub mod globe_graph;
+
+pub use globe_data::GlobeData;
+pub use globe_graph::GlobeGraph;
+
+use crate::rand::{seeded_rand, RandStream};
+
+#[derive(Debug, Clone, Copy)]
+pub struct GlobeConfig {
+    pub seed: u64,
+    /// The minimum angle between points on the sphere, in radians.
+    pub spacing: f32,
+    /// The number of landmass and basin features shaping the elevation.
+    pub num_features: usize,
+}
+
+/// A planet generated on the surface of the unit sphere. Unlike [Terrain], there are no edges to
+/// the world, so the elevation, flow and erosion are computed on the spherical Delaunay graph of
+/// the points instead of the planar Voronoi graph.
+pub struct Globe {
+    pub config: GlobeConfig,
+    pub graph: GlobeGraph,
+    pub data: GlobeData,
+}
+
+pub fn generate_globe(config: GlobeConfig) -> Globe {
+    let mut rand = seeded_rand(config.seed, RandStream::Points);
+
+    let points = globe_graph::poisson_sphere(&mut rand, config.spacing);
+    let graph = GlobeGraph::new(points);
+
+    let mut rand = seeded_rand(config.seed, RandStream::Features);
+
+    let data = GlobeData::new(&mut rand, &graph, config.num_features);
+
+    Globe {
+        config,
+        graph,
+        data,
+    }
+}
@@ -0,0 +1,136 @@
+use nannou::rand::rngs::SmallRng;
+use nannou::rand::Rng;
+
+use crate::globe::globe_graph::random_unit_vector;
+use crate::globe::GlobeGraph;
+
+/// The number of erosion passes.
+const EROSION_PASSES: usize = 5;
+/// The elevation removed per pass by a unit of erosion.
+const EROSION_RATE: f32 = 0.02;
+/// The minimum flux of a point to carry a river.
+pub const GLOBE_RIVER_FLUX: f32 = 0.002;
+
+#[derive(Debug, Clone)]
+pub struct GlobeData {
+    /// The elevation of each point, relative to sea level.
+    pub elevation: Vec<f32>,
+    /// The neighbor each point drains to, if any neighbor is lower.
+    pub flow: Vec<Option<usize>>,
+    /// The fraction of all rainfall passing through each point.
+    pub flux: Vec<f32>,
+}
+
+impl GlobeData {
+    pub fn new(rand: &mut SmallRng, graph: &GlobeGraph, num_features: usize) -> Self {
+        let mut elevation = generate_elevation(rand, graph, num_features);
+
+        let mut flow = generate_flow(graph, &elevation);
+        let mut flux = generate_flux(graph, &elevation, &flow);
+
+        for _ in 0..EROSION_PASSES {
+            erode(graph, &mut elevation, &flow, &flux);
+
+            flow = generate_flow(graph, &elevation);
+            flux = generate_flux(graph, &elevation, &flow);
+        }
+
+        // Half of the planet is under water.
+
+        let mut sorted = elevation.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let sealevel = sorted.get(sorted.len() / 2).cloned().unwrap_or(0.0);
+
+        for e in elevation.iter_mut() {
+            *e -= sealevel;
+        }
+
+        Self {
+            elevation,
+            flow,
+            flux,
+        }
+    }
+
+    /// The river segments, as pairs of points, on land carrying at least [GLOBE_RIVER_FLUX].
+    pub fn rivers(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.elevation.len()).filter_map(move |i| match self.flow[i] {
+            Some(next) if self.flux[i] >= GLOBE_RIVER_FLUX && self.elevation[i] >= 0.0 => {
+                Some((i, next))
+            }
+            _ => None,
+        })
+    }
+}
+
+/// Sum raised and sunken caps of random size scattered over the sphere. Each cap falls off
+/// smoothly with the angle from its center.
+fn generate_elevation(rand: &mut SmallRng, graph: &GlobeGraph, num_features: usize) -> Vec<f32> {
+    let mut elevation = vec![0.0; graph.points.len()];
+
+    for _ in 0..num_features {
+        let center = random_unit_vector(rand);
+        let radius: f32 = rand.gen_range(0.2..0.9);
+        let height: f32 = rand.gen_range(-0.5..1.0);
+
+        for (i, p) in graph.points.iter().enumerate() {
+            let angle = p.dot(center).clamp(-1.0, 1.0).acos();
+
+            if angle < radius {
+                let t = 1.0 - (angle / radius).powi(2);
+                elevation[i] += height * t * t;
+            }
+        }
+    }
+
+    elevation
+}
+
+fn generate_flow(graph: &GlobeGraph, elevation: &[f32]) -> Vec<Option<usize>> {
+    (0..graph.points.len())
+        .map(|i| {
+            graph.neighbors[i]
+                .iter()
+                .cloned()
+                .filter(|n| elevation[*n] < elevation[i])
+                .min_by(|a, b| elevation[*a].total_cmp(&elevation[*b]))
+        })
+        .collect()
+}
+
+/// Rain falls evenly on every point and is passed downhill from the highest point to the lowest,
+/// so each point has collected all of its upstream water before passing it on.
+fn generate_flux(graph: &GlobeGraph, elevation: &[f32], flow: &[Option<usize>]) -> Vec<f32> {
+    let rainfall = 1.0 / graph.points.len().max(1) as f32;
+
+    let mut flux = vec![rainfall; graph.points.len()];
+
+    let mut order: Vec<usize> = (0..graph.points.len()).collect();
+    order.sort_by(|a, b| elevation[*b].total_cmp(&elevation[*a]));
+
+    for i in order {
+        if let Some(next) = flow[i] {
+            flux[next] += flux[i];
+        }
+    }
+
+    flux
+}
+
+/// Cut each point down in proportion to the slope to its downstream neighbor and the square root
+/// of the water passing through it, without cutting below the neighbor.
+fn erode(graph: &GlobeGraph, elevation: &mut [f32], flow: &[Option<usize>], flux: &[f32]) {
+    let before = elevation.to_vec();
+
+    for (i, next) in flow.iter().enumerate() {
+        if let Some(next) = next {
+            let drop = before[i] - before[*next];
+            let slope = drop / graph.angle(i, *next).max(f32::EPSILON);
+
+            let erosion = EROSION_RATE * slope * flux[i].sqrt();
+
+            elevation[i] -= erosion.min(drop);
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use nannou::glam::*;
+use nannou::rand::rngs::SmallRng;
+use nannou::rand::Rng;
+
+/// The number of candidates tried around each active sample before it is retired.
+const POISSON_CANDIDATES: usize = 30;
+
+#[derive(Debug, Clone)]
+pub struct GlobeGraph {
+    /// The position of each point on the unit sphere.
+    pub points: Vec<Vec3>,
+    /// The spherical Delaunay triangles, as triples of point indices.
+    pub triangles: Vec<(usize, usize, usize)>,
+    /// The points connected to each point by a Delaunay edge.
+    pub neighbors: Vec<Vec<usize>>,
+}
+
+impl GlobeGraph {
+    pub fn new(points: Vec<Vec3>) -> Self {
+        let triangles = triangulate_sphere(&points);
+
+        let mut neighbors = vec![vec![]; points.len()];
+
+        for (a, b, c) in triangles.iter().cloned() {
+            for (p, q) in [(a, b), (b, c), (c, a)] {
+                neighbors[p].push(q);
+                neighbors[q].push(p);
+            }
+        }
+
+        for n in neighbors.iter_mut() {
+            n.sort_unstable();
+            n.dedup();
+        }
+
+        Self {
+            points,
+            triangles,
+            neighbors,
+        }
+    }
+
+    /// The angle between two points, which is their distance along the surface.
+    pub fn angle(&self, a: usize, b: usize) -> f32 {
+        self.points[a].dot(self.points[b]).clamp(-1.0, 1.0).acos()
+    }
+}
+
+/// Triangulate points on the unit sphere. The sphere is rotated so the first point sits on the
+/// north pole, and the rest are stereographically projected from the pole onto the plane. The
+/// projection preserves circles, so the planar Delaunay triangulation is also Delaunay on the
+/// sphere. The convex hull of the projected points surrounds the pole, so joining each hull edge
+/// to the pole closes the triangulation.
+fn triangulate_sphere(points: &[Vec3]) -> Vec<(usize, usize, usize)> {
+    if points.len() < 4 {
+        return vec![];
+    }
+
+    let pole = 0;
+    let rotation = Quat::from_rotation_arc(points[pole], Vec3::Z);
+
+    let mut projected = vec![];
+    let mut indices = vec![];
+
+    for (i, p) in points.iter().enumerate() {
+        let q = rotation * *p;
+        let scale = 1.0 - q.z as f64;
+
+        if i == pole || scale <= f64::EPSILON {
+            continue;
+        }
+
+        projected.push(delaunator::Point {
+            x: q.x as f64 / scale,
+            y: q.y as f64 / scale,
+        });
+
+        indices.push(i);
+    }
+
+    let triangulation = delaunator::triangulate(&projected);
+
+    let mut triangles: Vec<(usize, usize, usize)> = triangulation
+        .triangles
+        .chunks(3)
+        .map(|t| (indices[t[0]], indices[t[1]], indices[t[2]]))
+        .collect();
+
+    let hull = &triangulation.hull;
+
+    for i in 0..hull.len() {
+        let a = indices[hull[i]];
+        let b = indices[hull[(i + 1) % hull.len()]];
+
+        triangles.push((b, a, pole));
+    }
+
+    triangles
+}
+
+/// Generate points on the unit sphere in a Poisson disk distribution, with a minimum [spacing]
+/// angle between them, using Bridson's algorithm on the surface.
+pub fn poisson_sphere(rand: &mut SmallRng, spacing: f32) -> Vec<Vec3> {
+    // Points are compared by chord length, and bucketed into a grid of cubes as wide as the
+    // minimum chord so that only the neighboring cubes need to be checked.
+
+    let chord = 2.0 * (spacing * 0.5).sin();
+
+    let cell = |p: Vec3| {
+        let c = (p / chord).floor();
+        (c.x as i32, c.y as i32, c.z as i32)
+    };
+
+    let mut grid: HashMap<(i32, i32, i32), Vec<usize>> = HashMap::new();
+    let mut points: Vec<Vec3> = vec![];
+    let mut active = vec![];
+
+    let first = random_unit_vector(rand);
+
+    grid.entry(cell(first)).or_default().push(0);
+    points.push(first);
+    active.push(0);
+
+    while !active.is_empty() {
+        let slot = rand.gen_range(0..active.len());
+        let center = points[active[slot]];
+
+        let mut found = false;
+
+        for _ in 0..POISSON_CANDIDATES {
+            let angle = rand.gen_range(spacing..spacing * 2.0);
+            let candidate = rotate_away(center, angle, rand.gen_range(0.0..PI * 2.0));
+
+            let (cx, cy, cz) = cell(candidate);
+
+            let crowded = (-1..=1).any(|dx| {
+                (-1..=1).any(|dy| {
+                    (-1..=1).any(|dz| {
+                        grid.get(&(cx + dx, cy + dy, cz + dz))
+                            .map_or(false, |bucket| {
+                                bucket
+                                    .iter()
+                                    .any(|i| points[*i].distance(candidate) < chord)
+                            })
+                    })
+                })
+            });
+
+            if !crowded {
+                grid.entry((cx, cy, cz)).or_default().push(points.len());
+                active.push(points.len());
+                points.push(candidate);
+                found = true;
+                break;
+            }
+        }
+
+        if !found {
+            active.swap_remove(slot);
+        }
+    }
+
+    points
+}
+
+/// A uniformly distributed random point on the unit sphere.
+pub fn random_unit_vector(rand: &mut SmallRng) -> Vec3 {
+    let z: f32 = rand.gen_range(-1.0..1.0);
+    let theta: f32 = rand.gen_range(0.0..PI * 2.0);
+    let r = (1.0 - z * z).sqrt();
+
+    vec3(r * theta.cos(), r * theta.sin(), z)
+}
+
+/// Move the unit vector [p] along the surface by [angle], in the tangent direction [heading].
+fn rotate_away(p: Vec3, angle: f32, heading: f32) -> Vec3 {
+    let axis = if p.x.abs() < 0.9 { Vec3::X } else { Vec3::Y };
+
+    let t1 = p.cross(axis).normalize();
+    let t2 = p.cross(t1);
+
+    let tangent = t1 * heading.cos() + t2 * heading.sin();
+
+    (p * angle.cos() + tangent * angle.sin()).normalize()
+}
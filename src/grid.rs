@@ -0,0 +1,285 @@
+use std::f32::consts::{FRAC_PI_3, FRAC_PI_6};
+
+use nannou::geom::Rect;
+use nannou::glam::{vec2, Vec2};
+
+use crate::regions::Regions;
+use crate::terrain::{Terrain, TerrainSurface};
+use crate::util::clip_polygon;
+
+/// The distance from the center of a grid cell to its corners for hexes, or the side of a
+/// square, in world units.
+pub const GRID_CELL_SIZE: f32 = 40.0;
+/// The fraction of the terrace bands, from the lowest, below which land is not mountainous.
+const MOUNTAIN_BAND_FRACTION: f32 = 0.625;
+
+/// The shape of the cells of a play grid laid over the map.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum GridShape {
+    /// Hexes with a corner at the top, in rows offset by half a hex.
+    Hex,
+    Square,
+}
+
+impl GridShape {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GridShape::Hex => "hex",
+            GridShape::Square => "square",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "hex" => Some(GridShape::Hex),
+            "square" => Some(GridShape::Square),
+            _ => None,
+        }
+    }
+}
+
+/// A grid of hexes or squares covering the map extent, with cell (0, 0) in the bottom left.
+#[derive(Debug, Clone, Copy)]
+pub struct MapGrid {
+    pub shape: GridShape,
+    /// The distance from the center of a cell to its corners for hexes, or the side of a square.
+    pub size: f32,
+    pub extent: Rect,
+    pub columns: usize,
+    pub rows: usize,
+}
+
+impl MapGrid {
+    pub fn new(extent: Rect, shape: GridShape, size: f32) -> Self {
+        let (width, height) = cell_spacing(shape, size);
+
+        // Hex centers start on the corner of the extent, so one more hex is needed to cover the
+        // far edges.
+
+        let extra = match shape {
+            GridShape::Hex => 1,
+            GridShape::Square => 0,
+        };
+
+        Self {
+            shape,
+            size,
+            extent,
+            columns: (extent.w() / width).ceil() as usize + extra,
+            rows: (extent.h() / height).ceil() as usize + extra,
+        }
+    }
+
+    /// Every cell of the grid, row by row.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let columns = self.columns;
+        (0..self.rows).flat_map(move |row| (0..columns).map(move |column| (column, row)))
+    }
+
+    pub fn center(&self, column: usize, row: usize) -> Vec2 {
+        let (width, height) = cell_spacing(self.shape, self.size);
+        let origin = vec2(self.extent.left(), self.extent.bottom());
+
+        match self.shape {
+            GridShape::Hex => {
+                let offset = if row % 2 == 1 { 0.5 } else { 0.0 };
+                origin + vec2(width * (column as f32 + offset), height * row as f32)
+            }
+            GridShape::Square => origin + vec2(column as f32 + 0.5, row as f32 + 0.5) * self.size,
+        }
+    }
+
+    /// The corners of the cell, counterclockwise and clipped to the extent.
+    pub fn outline(&self, column: usize, row: usize) -> Vec<Vec2> {
+        let center = self.center(column, row);
+
+        let corners: Vec<Vec2> = match self.shape {
+            GridShape::Hex => (0..6)
+                .map(|i| {
+                    let angle = FRAC_PI_6 + FRAC_PI_3 * i as f32;
+                    center + vec2(angle.cos(), angle.sin()) * self.size
+                })
+                .collect(),
+            GridShape::Square => [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)]
+                .iter()
+                .map(|(x, y)| center + vec2(*x, *y) * self.size * 0.5)
+                .collect(),
+        };
+
+        clip_polygon(&corners, self.extent)
+    }
+
+    /// The cell containing [p], if it is on the grid.
+    pub fn cell_at(&self, p: Vec2) -> Option<(usize, usize)> {
+        let p = p - vec2(self.extent.left(), self.extent.bottom());
+
+        let (column, row) = match self.shape {
+            GridShape::Hex => {
+                // Find the fractional axial coordinates of the point, round them to the nearest
+                // hex in cube coordinates, then convert them to offset rows.
+
+                let q = (3f32.sqrt() / 3.0 * p.x - p.y / 3.0) / self.size;
+                let r = (2.0 / 3.0 * p.y) / self.size;
+                let (q, r) = round_axial(q, r);
+
+                (q + (r - (r & 1)) / 2, r)
+            }
+            GridShape::Square => (
+                (p.x / self.size).floor() as i64,
+                (p.y / self.size).floor() as i64,
+            ),
+        };
+
+        if column < 0 || row < 0 || column as usize >= self.columns || row as usize >= self.rows {
+            return None;
+        }
+
+        Some((column as usize, row as usize))
+    }
+
+    fn index(&self, column: usize, row: usize) -> usize {
+        row * self.columns + column
+    }
+}
+
+/// The horizontal and vertical distances between the centers of neighboring cells.
+fn cell_spacing(shape: GridShape, size: f32) -> (f32, f32) {
+    match shape {
+        GridShape::Hex => (3f32.sqrt() * size, 1.5 * size),
+        GridShape::Square => (size, size),
+    }
+}
+
+/// Round fractional axial hex coordinates to the nearest hex.
+fn round_axial(q: f32, r: f32) -> (i64, i64) {
+    let s = -q - r;
+
+    let (mut rq, mut rr, rs) = (q.round(), r.round(), s.round());
+
+    // The rounded coordinates must still sum to zero, so recompute the one that moved the most.
+
+    let (dq, dr, ds) = ((rq - q).abs(), (rr - r).abs(), (rs - s).abs());
+
+    if dq > dr && dq > ds {
+        rq = -rr - rs;
+    } else if dr > ds {
+        rr = -rq - rs;
+    }
+
+    (rq as i64, rr as i64)
+}
+
+/// The broad kind of land in a terrain polygon, for summarizing grid cells.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Biome {
+    Water,
+    Grassland,
+    Forest,
+    Mountain,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 4] = [
+        Biome::Water,
+        Biome::Grassland,
+        Biome::Forest,
+        Biome::Mountain,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Biome::Water => "water",
+            Biome::Grassland => "grassland",
+            Biome::Forest => "forest",
+            Biome::Mountain => "mountain",
+        }
+    }
+}
+
+/// The terrain under one grid cell.
+#[derive(Debug, Clone)]
+pub struct GridCell {
+    pub column: usize,
+    pub row: usize,
+    pub center: Vec2,
+    /// The biome of the most terrain polygons centered in the cell, or None if there are none.
+    pub biome: Option<Biome>,
+    /// The most common terrace band of the polygons in the cell, from zero at the lowest.
+    pub elevation_band: usize,
+    /// The fraction of the polygons in the cell that are land.
+    pub land_fraction: f32,
+    /// The indices of the cities in the cell, into [Regions::cities].
+    pub settlements: Vec<usize>,
+}
+
+/// Summarize the terrain and cities under each cell of [grid], row by row.
+pub fn grid_cells(grid: &MapGrid, terrain: &Terrain, regions: &Regions) -> Vec<GridCell> {
+    let mesh = &terrain.mesh;
+    let terraces = &mesh.terraces;
+
+    let mountain_band = (terraces.num_bands as f32 * MOUNTAIN_BAND_FRACTION) as usize;
+
+    let num_cells = grid.columns * grid.rows;
+    let mut biomes = vec![[0; Biome::ALL.len()]; num_cells];
+    let mut bands = vec![vec![0; terraces.num_bands]; num_cells];
+    let mut settlements = vec![vec![]; num_cells];
+
+    for (i, point) in terrain.graph.points.iter().enumerate() {
+        let cell = match grid.cell_at(*point) {
+            Some((column, row)) => grid.index(column, row),
+            None => continue,
+        };
+
+        let biome = if mesh.surface[i] == TerrainSurface::Water {
+            Biome::Water
+        } else if terraces.bands[i] >= mountain_band {
+            Biome::Mountain
+        } else if mesh.forest[i] {
+            Biome::Forest
+        } else {
+            Biome::Grassland
+        };
+
+        biomes[cell][biome as usize] += 1;
+        bands[cell][terraces.bands[i]] += 1;
+    }
+
+    for (i, city) in regions.cities.iter().enumerate() {
+        if let Some((column, row)) = grid.cell_at(terrain.graph.vertices[*city]) {
+            settlements[grid.index(column, row)].push(i);
+        }
+    }
+
+    grid.cells()
+        .map(|(column, row)| {
+            let cell = grid.index(column, row);
+            let counts = &biomes[cell];
+            let total: usize = counts.iter().sum();
+
+            let biome = Biome::ALL
+                .iter()
+                .cloned()
+                .zip(counts.iter())
+                .filter(|(_, n)| **n > 0)
+                .max_by_key(|(_, n)| **n)
+                .map(|(b, _)| b);
+
+            let elevation_band = (0..terraces.num_bands)
+                .filter(|b| bands[cell][*b] > 0)
+                .max_by_key(|b| bands[cell][*b])
+                .unwrap_or(0);
+
+            let water = counts[Biome::Water as usize];
+
+            GridCell {
+                column,
+                row,
+                center: grid.center(column, row),
+                biome,
+                elevation_band,
+                land_fraction: (total - water) as f32 / total.max(1) as f32,
+                settlements: std::mem::take(&mut settlements[cell]),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,470 @@
+use nannou::geom::Rect;
+use nannou::glam::*;
+
+use crate::regions::Regions;
+use crate::render::{glyph_rects, text_rect, text_width, TextStyle};
+use crate::terrain::Terrain;
+
+const REGION_LABEL_SIZE: f32 = 20.0;
+const CAPITAL_LABEL_SIZE: f32 = 16.0;
+const CITY_LABEL_SIZE: f32 = 14.0;
+const ISLAND_LABEL_SIZE: f32 = 16.0;
+const RIVER_LABEL_SIZE: f32 = 11.0;
+const PEAK_LABEL_SIZE: f32 = 11.0;
+const SEA_LABEL_SIZE: f32 = 18.0;
+const BAY_LABEL_SIZE: f32 = 12.0;
+
+/// The radius around each city marker that labels must avoid.
+const CITY_MARKER_RADIUS: f32 = 6.0;
+/// The gaps (in multiples of the label size) between the anchor and each ring of candidates.
+/// Candidates beyond the first ring are displaced and drawn with a leader line.
+const CANDIDATE_RINGS: [f32; 3] = [0.25, 2.0, 4.0];
+/// The cost of each coastline point covered by a label.
+const COASTLINE_COST: f32 = 4.0;
+/// The cost of each ring a label is pushed away from its anchor.
+const DISPLACEMENT_COST: f32 = 10.0;
+/// The fractions along a river at which its label may be placed.
+const RIVER_CANDIDATES: [f32; 5] = [0.5, 0.35, 0.65, 0.2, 0.8];
+/// The extra space between the letters of water labels, as a fraction of the label size.
+const WATER_LABEL_TRACKING: f32 = 0.5;
+/// The radius of the arc water labels are curved along, as a multiple of the label length.
+const WATER_LABEL_ARC: f32 = 1.5;
+/// The slant of the lettering of water labels.
+const WATER_LABEL_SLANT: f32 = 0.25;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LabelKind {
+    Region,
+    City,
+    Island,
+    Peak,
+    River,
+    Sea,
+    Bay,
+}
+
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub kind: LabelKind,
+    pub text: String,
+    /// The point on the labeled feature.
+    pub anchor: Vec2,
+    /// The center of the placed label.
+    pub position: Vec2,
+    pub style: TextStyle,
+    /// True if the label was pushed away from its anchor and needs a leader line.
+    pub displaced: bool,
+}
+
+impl Label {
+    pub fn rect(&self) -> Rect {
+        text_rect(&self.text, self.position, &self.style)
+    }
+
+    /// The point on the label closest to its anchor, where a leader line should end.
+    pub fn leader_end(&self) -> Vec2 {
+        let rect = self.rect();
+
+        vec2(
+            self.anchor.x.clamp(rect.left(), rect.right()),
+            self.anchor.y.clamp(rect.bottom(), rect.top()),
+        )
+    }
+}
+
+/// A set of non-overlapping labels for the regions, cities and named islands, peaks, rivers, seas
+/// and bays.
+pub struct Labels {
+    pub labels: Vec<Label>,
+}
+
+/// A label waiting to be placed along with the positions it may be placed at.
+struct LabelRequest {
+    kind: LabelKind,
+    text: String,
+    style: TextStyle,
+    /// Candidate (anchor, position, ring) triples in order of preference.
+    candidates: Vec<(Vec2, Vec2, usize)>,
+}
+
+impl Labels {
+    /// Place labels greedily in priority order (regions, cities, islands, peaks, then rivers).
+    /// Each label takes its cheapest candidate position that doesn't overlap a placed label or a
+    /// city, or is dropped if there is none.
+    pub fn new(terrain: &Terrain, regions: &Regions) -> Self {
+        let mut requests = vec![];
+
+        requests.append(&mut region_requests(terrain, regions));
+        requests.append(&mut city_requests(terrain, regions));
+        requests.append(&mut island_requests(terrain, regions));
+        requests.append(&mut peak_requests(terrain, regions));
+        requests.append(&mut river_requests(terrain, regions));
+
+        let coastline: Vec<Vec2> = terrain.mesh.contour.segments.iter().map(|s| s.0).collect();
+
+        let markers: Vec<Rect> = regions
+            .cities
+            .iter()
+            .map(|c| {
+                let size = Vec2::splat(CITY_MARKER_RADIUS * 2.0);
+                Rect::from_xy_wh(terrain.graph.vertices[*c], size)
+            })
+            .collect();
+
+        let mut labels: Vec<Label> = vec![];
+
+        for request in requests.into_iter() {
+            let mut best: Option<(f32, Vec2, Vec2, usize)> = None;
+
+            for (anchor, position, ring) in request.candidates.iter().cloned() {
+                let rect = text_rect(&request.text, position, &request.style);
+
+                if !contains_rect(terrain.extent, rect) {
+                    continue;
+                }
+
+                if labels.iter().any(|l| rect.overlap(l.rect()).is_some()) {
+                    continue;
+                }
+
+                if markers.iter().any(|m| rect.overlap(*m).is_some()) {
+                    continue;
+                }
+
+                let covered = coastline.iter().filter(|p| rect.contains(**p)).count();
+                let cost = covered as f32 * COASTLINE_COST + ring as f32 * DISPLACEMENT_COST;
+
+                if best.map_or(true, |b| cost < b.0) {
+                    best = Some((cost, anchor, position, ring));
+                }
+            }
+
+            if let Some((_, anchor, position, ring)) = best {
+                labels.push(Label {
+                    kind: request.kind,
+                    text: request.text,
+                    anchor,
+                    position,
+                    style: request.style,
+                    displaced: ring > 0 && request.kind == LabelKind::City,
+                });
+            }
+        }
+
+        // Water labels are spread over open water, so they go in the gaps left by the others.
+
+        let mut obstacles = markers;
+
+        if let Some(compass) = &terrain.mesh.compass {
+            let size = Vec2::splat(compass.radius * 2.0);
+            obstacles.push(Rect::from_xy_wh(compass.center, size));
+        }
+
+        for request in water_requests(terrain, regions) {
+            let placed = request
+                .candidates
+                .iter()
+                .find_map(|(anchor, position, toward)| {
+                    let style = water_style(&request.text, *toward, request.size);
+
+                    let clear = glyph_rects(&request.text, *position, &style)
+                        .into_iter()
+                        .all(|rect| {
+                            contains_rect(terrain.extent, rect)
+                                && !labels.iter().any(|l| rect.overlap(l.rect()).is_some())
+                                && !obstacles.iter().any(|o| rect.overlap(*o).is_some())
+                                && !coastline.iter().any(|p| rect.contains(*p))
+                        });
+
+                    clear.then(|| (*anchor, *position, style))
+                });
+
+            if let Some((anchor, position, style)) = placed {
+                labels.push(Label {
+                    kind: request.kind,
+                    text: request.text,
+                    anchor,
+                    position,
+                    style,
+                    displaced: false,
+                });
+            }
+        }
+
+        Self { labels }
+    }
+}
+
+fn region_requests(terrain: &Terrain, regions: &Regions) -> Vec<LabelRequest> {
+    let mut requests = vec![];
+
+    for (i, city) in regions.founders().iter().cloned().enumerate() {
+        let vertices: Vec<Vec2> = (0..terrain.graph.vertices.len())
+            .filter(|v| regions.regions[*v] == city && terrain.mesh.contour.is_surface[*v])
+            .map(|v| terrain.graph.vertices[v])
+            .collect();
+
+        if vertices.is_empty() {
+            continue;
+        }
+
+        let centroid = vertices.iter().sum::<Vec2>() / vertices.len() as f32;
+        let text = regions.region_names[i].to_uppercase();
+
+        requests.push(LabelRequest {
+            kind: LabelKind::Region,
+            candidates: point_candidates(centroid, &text, REGION_LABEL_SIZE, true),
+            text,
+            style: TextStyle::new(REGION_LABEL_SIZE),
+        });
+    }
+
+    requests
+}
+
+fn city_requests(terrain: &Terrain, regions: &Regions) -> Vec<LabelRequest> {
+    let mut requests = vec![];
+
+    for (i, city) in regions.cities.iter().cloned().enumerate() {
+        let anchor = terrain.graph.vertices[city];
+        let text = regions.city_names[i].clone();
+
+        let size = if regions.is_capital(i) {
+            CAPITAL_LABEL_SIZE
+        } else {
+            CITY_LABEL_SIZE
+        };
+
+        requests.push(LabelRequest {
+            kind: LabelKind::City,
+            candidates: point_candidates(anchor, &text, size, false),
+            text,
+            style: TextStyle::new(size),
+        });
+    }
+
+    requests
+}
+
+fn island_requests(terrain: &Terrain, regions: &Regions) -> Vec<LabelRequest> {
+    let mut requests = vec![];
+
+    for (island, name) in terrain.mesh.islands.iter().zip(regions.island_names.iter()) {
+        if let Some(name) = name {
+            requests.push(LabelRequest {
+                kind: LabelKind::Island,
+                candidates: point_candidates(island.centroid, name, ISLAND_LABEL_SIZE, true),
+                text: name.clone(),
+                style: TextStyle::new(ISLAND_LABEL_SIZE),
+            });
+        }
+    }
+
+    requests
+}
+
+fn peak_requests(terrain: &Terrain, regions: &Regions) -> Vec<LabelRequest> {
+    let mut requests = vec![];
+
+    for (peak, name) in terrain.mesh.peaks.iter().zip(regions.peak_names.iter()) {
+        if let Some(name) = name {
+            let anchor = terrain.graph.vertices[peak.vertex];
+
+            requests.push(LabelRequest {
+                kind: LabelKind::Peak,
+                candidates: point_candidates(anchor, name, PEAK_LABEL_SIZE, false),
+                text: name.clone(),
+                style: TextStyle::new(PEAK_LABEL_SIZE),
+            });
+        }
+    }
+
+    requests
+}
+
+fn river_requests(terrain: &Terrain, regions: &Regions) -> Vec<LabelRequest> {
+    let mut requests = vec![];
+
+    for (river, name) in terrain.mesh.rivers.iter().zip(regions.river_names.iter()) {
+        let name = match name {
+            Some(name) if river.points.len() >= 2 => name,
+            _ => continue,
+        };
+
+        let mut candidates = vec![];
+
+        for t in RIVER_CANDIDATES.iter() {
+            let i = ((river.points.len() - 1) as f32 * t) as usize;
+            let j = usize::min(i + 1, river.points.len() - 1);
+
+            let anchor = river.points[i];
+            let normal = (river.points[j] - river.points[usize::min(i, j - 1)])
+                .normalize_or_zero()
+                .perp();
+
+            let offset = normal * RIVER_LABEL_SIZE;
+
+            candidates.push((anchor, anchor + offset, 0));
+            candidates.push((anchor, anchor - offset, 0));
+        }
+
+        requests.push(LabelRequest {
+            kind: LabelKind::River,
+            text: name.clone(),
+            style: TextStyle::new(RIVER_LABEL_SIZE),
+            candidates,
+        });
+    }
+
+    requests
+}
+
+/// A water label waiting to be placed, with the positions it may be placed at.
+struct WaterLabelRequest {
+    kind: LabelKind,
+    text: String,
+    size: f32,
+    /// Candidate (anchor, position, toward) triples in order of preference, where the label
+    /// curves towards the [toward] direction.
+    candidates: Vec<(Vec2, Vec2, Vec2)>,
+}
+
+/// The named seas, curved to follow the nearest coast, and the bays, curved around their shores.
+fn water_requests(terrain: &Terrain, regions: &Regions) -> Vec<WaterLabelRequest> {
+    let graph = &terrain.graph;
+    let contour = &terrain.mesh.contour;
+
+    let mut requests = vec![];
+
+    for (water, name) in terrain.mesh.open_water.iter().zip(regions.sea_names.iter()) {
+        let name = match name {
+            Some(name) => name,
+            None => continue,
+        };
+
+        let coast = (0..graph.vertices.len())
+            .filter(|v| contour.is_contour[*v])
+            .map(|v| graph.vertices[v])
+            .min_by(|a, b| {
+                let da = a.distance_squared(water.center);
+                let db = b.distance_squared(water.center);
+                da.total_cmp(&db)
+            });
+
+        let toward = coast.map_or(Vec2::ZERO, |c| (c - water.center).normalize_or_zero());
+        let shift = water.radius * 0.5;
+
+        let positions = [
+            water.center,
+            water.center - toward * shift,
+            water.center + vec2(0.0, shift),
+            water.center - vec2(0.0, shift),
+        ];
+
+        requests.push(WaterLabelRequest {
+            kind: LabelKind::Sea,
+            text: name.to_uppercase(),
+            size: SEA_LABEL_SIZE,
+            candidates: positions
+                .iter()
+                .map(|p| (water.center, *p, toward))
+                .collect(),
+        });
+    }
+
+    for (bay, name) in terrain.mesh.bays.iter().zip(regions.bay_names.iter()) {
+        let mouth = bay.center - bay.inland * BAY_LABEL_SIZE;
+
+        requests.push(WaterLabelRequest {
+            kind: LabelKind::Bay,
+            text: name.clone(),
+            size: BAY_LABEL_SIZE,
+            candidates: vec![
+                (bay.center, bay.center, bay.inland),
+                (bay.center, mouth, bay.inland),
+            ],
+        });
+    }
+
+    requests
+}
+
+/// The style of a water label: spaced out, slanted, and curved along an arc that bends towards
+/// [toward]. The text runs left to right and is kept near horizontal so that it stays readable.
+fn water_style(text: &str, toward: Vec2, size: f32) -> TextStyle {
+    let mut style = TextStyle {
+        tracking: WATER_LABEL_TRACKING,
+        slant: WATER_LABEL_SLANT,
+        ..TextStyle::new(size)
+    };
+
+    if toward == Vec2::ZERO {
+        return style;
+    }
+
+    let mut tangent = toward.perp();
+
+    if tangent.x < 0.0 {
+        tangent = -tangent;
+    }
+
+    if tangent.x < 0.5 {
+        tangent = vec2(1.0, 0.0);
+    }
+
+    let radius = text_width(text, &style) * WATER_LABEL_ARC;
+
+    // The center of the arc is on the side of the text facing [toward].
+    let curvature = if tangent.perp().dot(toward) >= 0.0 {
+        1.0 / radius
+    } else {
+        -1.0 / radius
+    };
+
+    style.direction = tangent;
+    style.curvature = curvature;
+
+    style
+}
+
+/// Candidate positions in eight directions around a point, in rings of increasing distance. If
+/// [centered], a candidate directly over the point is tried first.
+fn point_candidates(
+    anchor: Vec2,
+    text: &str,
+    size: f32,
+    centered: bool,
+) -> Vec<(Vec2, Vec2, usize)> {
+    let half = text_rect(text, anchor, &TextStyle::new(size)).wh() * 0.5;
+
+    let mut candidates = vec![];
+
+    if centered {
+        candidates.push((anchor, anchor, 0));
+    }
+
+    // Cartographic preference: right, left, above, below, then the diagonals.
+    let directions = [
+        vec2(1.0, 0.0),
+        vec2(-1.0, 0.0),
+        vec2(0.0, 1.0),
+        vec2(0.0, -1.0),
+        vec2(1.0, 1.0),
+        vec2(-1.0, 1.0),
+        vec2(1.0, -1.0),
+        vec2(-1.0, -1.0),
+    ];
+
+    for (ring, gap) in CANDIDATE_RINGS.iter().enumerate() {
+        for dir in directions.iter() {
+            let offset = *dir * (half + Vec2::splat(gap * size));
+            candidates.push((anchor, anchor + offset, ring));
+        }
+    }
+
+    candidates
+}
+
+fn contains_rect(outer: Rect, inner: Rect) -> bool {
+    outer.contains(inner.bottom_left()) && outer.contains(inner.top_right())
+}
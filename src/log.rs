@@ -0,0 +1,71 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The severity of a log message, from most to least severe. Messages less severe than the
+/// verbosity are dropped.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    fn label(&self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warn => "warn",
+            Level::Info => "info",
+            Level::Debug => "debug",
+        }
+    }
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Level::Info as u8);
+
+/// Set the least severe [level] of message that is written.
+pub fn set_verbosity(level: Level) {
+    VERBOSITY.store(level as u8, Ordering::Relaxed);
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// Write a message to stderr, tagged with its [level] and the module it came from. Use the
+/// [error], [warn], [info] and [debug] macros instead of calling this directly.
+pub fn write(level: Level, module: &str, args: fmt::Arguments) {
+    if enabled(level) {
+        let module = module.strip_prefix("terrain::").unwrap_or(module);
+        eprintln!("[{}] {}: {}", level.label(), module, args);
+    }
+}
+
+macro_rules! error {
+    ($($arg:tt)*) => {
+        $crate::log::write($crate::log::Level::Error, module_path!(), format_args!($($arg)*))
+    };
+}
+
+macro_rules! log_warn {
+    ($($arg:tt)*) => {
+        $crate::log::write($crate::log::Level::Warn, module_path!(), format_args!($($arg)*))
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        $crate::log::write($crate::log::Level::Info, module_path!(), format_args!($($arg)*))
+    };
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        $crate::log::write($crate::log::Level::Debug, module_path!(), format_args!($($arg)*))
+    };
+}
+
+// A macro named warn can't be re-exported by its own name, since the path would be ambiguous with
+// the built-in warn attribute.
+pub(crate) use {debug, error, info, log_warn as warn};
@@ -1,116 +1,1363 @@
-use std::time::Instant;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use nannou::glam::*;
+#[cfg(not(target_arch = "wasm32"))]
 use nannou::prelude::*;
 
+mod annotations;
+#[cfg(not(target_arch = "wasm32"))]
+mod camera;
+#[cfg(not(target_arch = "wasm32"))]
+mod cli;
+#[cfg(not(target_arch = "wasm32"))]
+mod debug_field;
+#[cfg(not(target_arch = "wasm32"))]
+mod diff;
+mod export;
+#[cfg(not(target_arch = "wasm32"))]
+mod globe;
+mod grid;
+mod labels;
+mod log;
+mod names;
+mod palette;
 mod rand;
 mod regions;
+mod render;
+mod routes;
+#[cfg(not(target_arch = "wasm32"))]
+mod settings;
 mod terrain;
+mod theme;
 mod util;
+#[cfg(target_arch = "wasm32")]
+mod web;
 
+#[cfg(not(target_arch = "wasm32"))]
+use annotations::*;
+#[cfg(not(target_arch = "wasm32"))]
+use camera::*;
+#[cfg(not(target_arch = "wasm32"))]
+use debug_field::*;
+#[cfg(not(target_arch = "wasm32"))]
+use export::*;
+#[cfg(not(target_arch = "wasm32"))]
+use globe::*;
+#[cfg(not(target_arch = "wasm32"))]
+use grid::*;
+#[cfg(not(target_arch = "wasm32"))]
+use labels::*;
+use names::*;
 use regions::*;
+#[cfg(not(target_arch = "wasm32"))]
+use render::{
+    annotation_primitives, city_primitives, debug_region_primitives, isochrone_primitives,
+    map_primitives, terrace_primitives, Primitive, PrimitiveGrid,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use routes::*;
+#[cfg(not(target_arch = "wasm32"))]
+use settings::*;
+use terrain::erosion::RainfallModel;
 use terrain::*;
+#[cfg(not(target_arch = "wasm32"))]
+use theme::*;
 use util::*;
 
 const SIZE_X: u32 = 1000;
 const SIZE_Y: u32 = 1000;
 
+#[cfg(not(target_arch = "wasm32"))]
+const CAMERA_PAN: f32 = 50.0;
+
+/// The camera zoom below which the simplified mesh is rendered.
+#[cfg(not(target_arch = "wasm32"))]
+const LOD_ZOOM: f32 = 0.75;
+
+/// The number of seconds each erosion pass is shown in the erosion animation.
+#[cfg(not(target_arch = "wasm32"))]
+const EROSION_STEP_SECONDS: f32 = 0.75;
+
+#[cfg(not(target_arch = "wasm32"))]
+const MINIMAP_SIZE: f32 = 200.0;
+#[cfg(not(target_arch = "wasm32"))]
+const MINIMAP_MARGIN: f32 = 10.0;
+#[cfg(not(target_arch = "wasm32"))]
+const MINIMAP_DECIMATION: usize = 4;
+
+/// The forest densities cycled through with the F key.
+#[cfg(not(target_arch = "wasm32"))]
+const FOREST_DENSITIES: [usize; 4] = [0, 1, 3, 6];
+
+/// The minimum angle between globe points, in radians.
+#[cfg(not(target_arch = "wasm32"))]
+const GLOBE_SPACING: f32 = 0.035;
+/// The number of landmass and basin features shaping the globe.
+#[cfg(not(target_arch = "wasm32"))]
+const GLOBE_FEATURES: usize = 24;
+/// The rotation speed of the globe, in radians per second.
+#[cfg(not(target_arch = "wasm32"))]
+const GLOBE_SPIN: f32 = 0.2;
+
+/// The number of thumbnails along each side of the seed grid.
+#[cfg(not(target_arch = "wasm32"))]
+const SEED_GRID_SIZE: usize = 3;
+/// The point radius of the reduced resolution terrains in the seed grid.
+#[cfg(not(target_arch = "wasm32"))]
+const SEED_GRID_RADIUS: f32 = 30.0;
+#[cfg(not(target_arch = "wasm32"))]
+const SEED_GRID_MARGIN: f32 = 10.0;
+
+/// The colors of the 3D preview at sea level and at this elevation.
+#[cfg(not(target_arch = "wasm32"))]
+const PREVIEW_PEAK: f32 = 500.0;
+/// The brightness of the slopes facing away from the light in the 3D preview.
+#[cfg(not(target_arch = "wasm32"))]
+const PREVIEW_AMBIENT: f32 = 0.35;
+
+/// The distance in screen units within which a click picks a city.
+#[cfg(not(target_arch = "wasm32"))]
+const CITY_PICK_RADIUS: f32 = 8.0;
+
+/// The distance in screen units within which a click picks an annotation.
+#[cfg(not(target_arch = "wasm32"))]
+const ANNOTATION_PICK_RADIUS: f32 = 12.0;
+
+/// The size of the square drawn around locked cities.
+#[cfg(not(target_arch = "wasm32"))]
+const LOCK_MARKER_SIZE: f32 = 16.0;
+
+/// The range of city counts stepped through with the [ and ] keys.
+#[cfg(not(target_arch = "wasm32"))]
+const NUM_CITIES_MIN: i32 = 1;
+#[cfg(not(target_arch = "wasm32"))]
+const NUM_CITIES_MAX: i32 = 64;
+
+/// The number of elevation bins in the statistics overlay histogram.
+#[cfg(not(target_arch = "wasm32"))]
+const STATS_BINS: usize = 20;
+#[cfg(not(target_arch = "wasm32"))]
+const STATS_WIDTH: f32 = 220.0;
+#[cfg(not(target_arch = "wasm32"))]
+const STATS_MARGIN: f32 = 10.0;
+/// The height of the sparkline of the material removed by each erosion pass.
+#[cfg(not(target_arch = "wasm32"))]
+const SPARKLINE_HEIGHT: f32 = 40.0;
+
+/// The directory that the export hotkeys write to.
+#[cfg(not(target_arch = "wasm32"))]
+const EXPORT_DIR: &str = "exports";
+/// The number of seconds an export confirmation stays on screen.
+#[cfg(not(target_arch = "wasm32"))]
+const FLASH_SECONDS: f32 = 2.0;
+
+#[cfg(not(target_arch = "wasm32"))]
 struct Model {
     terrain: Terrain,
     regions: Regions,
+    routes: Routes,
+    labels: Labels,
+    stats: TerrainStats,
+    /// Show the statistics overlay.
+    show_stats: bool,
+    mode: DrawingMode,
+    /// The data layer shown in the scalar field debug mode.
+    field: ScalarField,
+    theme: Theme,
+    camera: Camera,
+    /// The window size in points, saved with the settings on exit.
+    window_size: Vec2,
+    minimap: Minimap,
+    draw_list: DrawList,
+    erosion_animation: ErosionAnimation,
+    /// The globe for the current seed, generated the first time the globe mode is shown.
+    globe: Option<Globe>,
+    /// A message briefly shown on screen, and the app time it was shown at.
+    flash: Option<(String, f32)>,
+    /// The flash of a screenshot and the frame it was taken on. It is held back until that frame
+    /// has been drawn, so that the message isn't captured in the screenshot.
+    screenshot_flash: Option<(String, u64)>,
+    /// The terrain being generated on a worker thread, which replaces the current one when it is
+    /// finished.
+    pending: Option<PendingTerrain>,
+    /// Thumbnails of candidate seeds, shown instead of the map while open.
+    seed_grid: Option<SeedGrid>,
+    /// The 3D preview of the terrain, shown instead of the map while open.
+    preview: Option<Preview>,
+    /// The city picked with the mouse, whose region is highlighted.
+    selection: Option<Selection>,
+    /// The world positions of the cities locked in place across regeneration.
+    locked: Vec<Vec2>,
+    /// Place cities with shift-click, remove them with ctrl-click and annotate the map with
+    /// alt-click, instead of generating a new terrain on each click.
+    edit_mode: bool,
+    /// The markers and notes placed on each map, saved with the settings.
+    annotations: Annotations,
+    /// The annotation whose text is being typed, which takes the keyboard from the hotkeys.
+    editing: Option<AnnotationEdit>,
+}
+
+/// A terrain being generated on a worker thread.
+#[cfg(not(target_arch = "wasm32"))]
+struct PendingTerrain {
+    seed: u64,
+    /// Cancelled when a newer terrain is requested before this one is finished.
+    cancel: CancelToken,
+    result: Receiver<Result<Terrain, GenerationError>>,
+    timer: Timer,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct AnnotationEdit {
+    /// The index of the annotation on the current map.
+    index: usize,
+    /// Typing into the note rather than the name.
+    note: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct Selection {
+    /// The index of the picked city.
+    city: usize,
+    summary: RegionSummary,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct Preview {
+    camera: OrbitCamera,
+    /// The mouse position at the last mouse move, to orbit by the distance dragged.
+    mouse: Vec2,
+}
+
+/// Playback state for replaying the erosion passes.
+#[cfg(not(target_arch = "wasm32"))]
+struct ErosionAnimation {
+    /// The index of the erosion pass being shown.
+    step: usize,
+    /// The app time when the current step was first shown.
+    step_time: f32,
+    paused: bool,
+}
+
+/// The state that the cached draw list was built from.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct DrawListKey {
     mode: DrawingMode,
+    theme: Theme,
+    lod: bool,
+}
+
+/// Render primitives cached between frames, rebuilt only when the terrain or key changes.
+#[cfg(not(target_arch = "wasm32"))]
+struct DrawList {
+    key: Option<DrawListKey>,
+    primitives: Vec<Primitive>,
+    /// The primitives by location, for skipping those outside the window when zoomed in.
+    grid: PrimitiveGrid,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DrawList {
+    fn empty() -> Self {
+        Self {
+            key: None,
+            primitives: vec![],
+            grid: PrimitiveGrid::new(&[]),
+        }
+    }
+
+    /// Draw the primitives, skipping those outside the [window] if the camera is zoomed in.
+    fn draw(&self, draw: &Draw, camera: &Camera, window: Rect) {
+        if !camera.is_zoomed() {
+            draw_primitives(draw, self.primitives.iter());
+            return;
+        }
+
+        let visible = self.grid.visible(camera.viewport(window));
+
+        draw_primitives(draw, visible.iter().map(|i| &self.primitives[*i]));
+    }
+}
+
+/// A low-detail copy of the terrain used to draw the overview inset.
+#[cfg(not(target_arch = "wasm32"))]
+struct Minimap {
+    /// Decimated coastline chains in world coordinates.
+    coastline: Vec<Vec<Vec2>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Minimap {
+    fn new(terrain: &Terrain) -> Self {
+        let mut coastline = vec![];
+
+        for chain in chain_edges(&terrain.mesh.contour.edges) {
+            let points: Vec<Vec2> = chain.iter().map(|v| terrain.graph.vertices[*v]).collect();
+            coastline.push(decimate_path(&points, MINIMAP_DECIMATION));
+        }
+
+        Self { coastline }
+    }
+}
+
+/// A grid of reduced resolution terrains for quickly browsing seeds.
+#[cfg(not(target_arch = "wasm32"))]
+struct SeedGrid {
+    thumbnails: Vec<Thumbnail>,
+}
+
+/// The land of a reduced resolution terrain.
+#[cfg(not(target_arch = "wasm32"))]
+struct Thumbnail {
+    seed: u64,
+    extent: Rect,
+    /// Land polygons in world coordinates, with their elevation.
+    land: Vec<(Vec<Vec2>, f32)>,
+    /// Coastline chains in world coordinates.
+    coastline: Vec<Vec<Vec2>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SeedGrid {
+    fn new() -> Self {
+        let thumbnails = (0..SEED_GRID_SIZE * SEED_GRID_SIZE)
+            .filter_map(|_| Thumbnail::new(random()).ok())
+            .collect();
+
+        Self { thumbnails }
+    }
+
+    /// The screen rectangle of thumbnail [i].
+    fn cell(window: Rect, i: usize) -> Rect {
+        let w = window.w() / SEED_GRID_SIZE as f32;
+        let h = window.h() / SEED_GRID_SIZE as f32;
+
+        let col = (i % SEED_GRID_SIZE) as f32;
+        let row = (i / SEED_GRID_SIZE) as f32;
+
+        Rect::from_x_y_w_h(
+            window.left() + w * (col + 0.5),
+            window.top() - h * (row + 0.5),
+            w,
+            h,
+        )
+    }
+
+    /// The seed of the thumbnail at screen position [p], if any.
+    fn seed_at(&self, window: Rect, p: Vec2) -> Option<u64> {
+        self.thumbnails
+            .iter()
+            .enumerate()
+            .find(|(i, _)| SeedGrid::cell(window, *i).contains(p))
+            .map(|(_, t)| t.seed)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Thumbnail {
+    fn new(seed: u64) -> Result<Self, GenerationError> {
+        let mut config = default_config(seed);
+        config.radius = SEED_GRID_RADIUS;
+
+        let terrain = generate_terrain(config)?;
+
+        let mut land = vec![];
+
+        for (i, poly) in terrain.mesh.polygons.iter().enumerate() {
+            if let Some(poly) = poly {
+                if terrain.mesh.surface[i] == TerrainSurface::Land {
+                    land.push((poly.points.clone(), terrain.mesh.elevation[i]));
+                }
+            }
+        }
+
+        let coastline = chain_edges(&terrain.mesh.contour.edges)
+            .iter()
+            .map(|chain| chain.iter().map(|v| terrain.graph.vertices[*v]).collect())
+            .collect();
+
+        Ok(Self {
+            seed,
+            extent: terrain.extent,
+            land,
+            coastline,
+        })
+    }
+}
+
+fn main() {
+    // There are no command line tools or windows in the browser, so the map is drawn straight
+    // into a canvas instead.
+
+    #[cfg(target_arch = "wasm32")]
+    web::run();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let args = cli::parse_verbosity(&args);
+
+        if !args.is_empty() {
+            std::process::exit(cli::run(&args));
+        }
+
+        nannou::app(model)
+            .update(update)
+            .view(view)
+            .exit(exit)
+            .run();
+    }
+}
+
+/// Save the settings for the next run.
+#[cfg(not(target_arch = "wasm32"))]
+fn exit(_app: &App, model: Model) {
+    save_settings(&Settings {
+        seed: model.terrain.config.seed,
+        mode: format!("{:?}", model.mode),
+        theme: model.theme,
+        camera: model.camera,
+        window: model.window_size,
+        features: model.terrain.config.features,
+        annotations: model.annotations,
+    });
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn resized(_app: &App, model: &mut Model, size: Vec2) {
+    model.window_size = size;
+}
+
+/// The terrain config used by the viewer and the command line tools.
+fn default_config(seed: u64) -> TerrainConfig {
+    TerrainConfig {
+        size: Vec2::new(SIZE_X as f32, SIZE_Y as f32),
+        seed,
+        radius: 10.0,
+        vertical_scale: 1.0,
+        sampler: SamplerKind::Poisson,
+        num_cities: 8,
+        city_spacing: 100.0,
+        num_regions: 5,
+        features: FeatureGenConfig::default(),
+        rainfall: RainfallModel::CellArea,
+        light: LightConfig::default(),
+        rivers: RiverConfig::default(),
+        shallows: ShallowConfig::default(),
+        coast_noise: CoastNoiseConfig::default(),
+        bathymetry: BathymetryConfig::default(),
+        cultures: CultureMode::PerRegion,
+        pois: PoiConfig::default(),
+        habitability: HabitabilityConfig::default(),
+        borders: BorderConfig {
+            snap: true,
+            ..BorderConfig::default()
+        },
+        refine: RefineConfig::default(),
+        layers: LayerConfig::default(),
+        wrap_x: false,
+    }
+}
+
+/// Generate the terrain for [config]. If refinement is enabled, the terrain is generated again
+/// with finer cells around the cities placed on the first, including any [locked] cities.
+fn generate_map_terrain(
+    config: TerrainConfig,
+    locked: &[Vec2],
+) -> Result<Terrain, GenerationError> {
+    generate_map_terrain_cancellable(config, locked, CancelToken::new())
+}
+
+/// Generate the terrain like [generate_map_terrain], stopping early with
+/// [GenerationError::Cancelled] once [cancel] is cancelled.
+fn generate_map_terrain_cancellable(
+    config: TerrainConfig,
+    locked: &[Vec2],
+    cancel: CancelToken,
+) -> Result<Terrain, GenerationError> {
+    let terrain = generate_terrain_cancellable(config, cancel.clone())?;
+
+    if !config.refine.enabled {
+        return Ok(terrain);
+    }
+
+    let regions = if locked.is_empty() {
+        Regions::new(&terrain)
+    } else {
+        Regions::with_locked(&terrain, locked, &DefaultTravelCost::default())
+    };
+
+    let centers: Vec<Vec2> = regions
+        .cities
+        .iter()
+        .map(|c| terrain.graph.vertices[*c])
+        .collect();
+
+    generate_refined_terrain(config, &centers, cancel)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn model(app: &App) -> Model {
+    // Pick up where the last run left off, or start on a random terrain.
+
+    let settings = load_settings();
+
+    let window_size = settings
+        .as_ref()
+        .map_or(vec2(SIZE_X as f32, SIZE_Y as f32), |s| s.window);
+
+    app.new_window()
+        .size(window_size.x as u32, window_size.y as u32)
+        .view(view)
+        .mouse_moved(mouse_moved)
+        .mouse_released(mouse_released)
+        .key_pressed(key_pressed)
+        .received_character(received_character)
+        .mouse_wheel(mouse_wheel)
+        .resized(resized)
+        .build()
+        .unwrap();
+
+    let seed = settings.as_ref().map_or_else(random, |s| s.seed);
+
+    let mode = settings
+        .as_ref()
+        .and_then(|s| DrawingMode::from_name(&s.mode))
+        .unwrap_or(DrawingMode::Render);
+
+    let theme = settings.as_ref().map_or_else(Theme::default, |s| s.theme);
+    let camera = settings.as_ref().map_or_else(Camera::new, |s| s.camera);
+
+    let mut config = default_config(seed);
+
+    if let Some(settings) = &settings {
+        config.features = settings.features;
+    }
+
+    let terrain = generate_map_terrain(config, &[]).expect("default config is valid");
+    let regions = Regions::new(&terrain);
+    let routes = Routes::new(&terrain, &regions);
+    let labels = Labels::new(&terrain, &regions);
+    let minimap = Minimap::new(&terrain);
+    let stats = TerrainStats::new(&terrain, STATS_BINS);
+
+    Model {
+        terrain,
+        regions,
+        routes,
+        labels,
+        stats,
+        show_stats: false,
+        mode,
+        field: ScalarField::Elevation,
+        theme,
+        camera,
+        window_size,
+        minimap,
+        draw_list: DrawList::empty(),
+        erosion_animation: ErosionAnimation {
+            step: 0,
+            step_time: 0.0,
+            paused: false,
+        },
+        globe: None,
+        flash: None,
+        screenshot_flash: None,
+        pending: None,
+        seed_grid: None,
+        preview: None,
+        selection: None,
+        edit_mode: false,
+        locked: vec![],
+        annotations: settings.map_or_else(Annotations::default, |s| s.annotations),
+        editing: None,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn update(app: &App, model: &mut Model, _: Update) {
+    receive_terrain(model);
+
+    let animation = &mut model.erosion_animation;
+    let num_steps = model.terrain.data.erosion_history.len().max(1);
+
+    if !animation.paused && app.time - animation.step_time > EROSION_STEP_SECONDS {
+        animation.step = (animation.step + 1) % num_steps;
+        animation.step_time = app.time;
+    }
+
+    if let Some((_, frame)) = &model.screenshot_flash {
+        if app.elapsed_frames() > *frame {
+            let (message, _) = model.screenshot_flash.take().unwrap();
+            model.flash = Some((message, app.time));
+        }
+    }
+
+    let key = DrawListKey {
+        mode: model.mode,
+        theme: model.theme,
+        lod: model.camera.zoom < LOD_ZOOM,
+    };
+
+    if model.draw_list.key != Some(key) {
+        model.draw_list = build_draw_list(model, key);
+    }
+
+    if model.mode == DrawingMode::RenderGlobe && model.globe.is_none() {
+        let now = Timer::start();
+
+        let globe = generate_globe(GlobeConfig {
+            seed: model.terrain.config.seed,
+            spacing: GLOBE_SPACING,
+            num_features: GLOBE_FEATURES,
+        });
+
+        log::info!(
+            "generated globe with {:?} points in {:.4?}",
+            globe.graph.points.len(),
+            now.elapsed(),
+        );
+
+        model.globe = Some(globe);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum DrawingMode {
+    DebugMesh,
+    DebugGraphVerts,
+    DebugGraphEdges,
+    /// The per-vertex data layer chosen with [ScalarField::next].
+    DebugField,
+    DebugSlope,
+    DebugFlow,
+    DebugBasins,
+    DebugWatersheds,
+    DebugErosionHistory,
+    DebugRivers,
+    DebugSprings,
+    DebugCities,
+    DebugRegions,
+    DebugIsochrones,
+    Render,
+    RenderTerraces,
+    RenderGlobe,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DrawingMode {
+    /// The mode with the [name] it is saved in the settings under.
+    fn from_name(name: &str) -> Option<Self> {
+        let mut mode = DrawingMode::Render;
+
+        // Every mode is reached by cycling from any other.
+
+        loop {
+            if format!("{:?}", mode) == name {
+                return Some(mode);
+            }
+
+            mode = cycle_drawing_mode(mode);
+
+            if mode == DrawingMode::Render {
+                return None;
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn cycle_drawing_mode(mode: DrawingMode) -> DrawingMode {
+    match mode {
+        DrawingMode::DebugMesh => DrawingMode::DebugGraphVerts,
+        DrawingMode::DebugGraphVerts => DrawingMode::DebugGraphEdges,
+        DrawingMode::DebugGraphEdges => DrawingMode::DebugField,
+        DrawingMode::DebugField => DrawingMode::DebugSlope,
+        DrawingMode::DebugSlope => DrawingMode::DebugFlow,
+        DrawingMode::DebugFlow => DrawingMode::DebugBasins,
+        DrawingMode::DebugBasins => DrawingMode::DebugWatersheds,
+        DrawingMode::DebugWatersheds => DrawingMode::DebugErosionHistory,
+        DrawingMode::DebugErosionHistory => DrawingMode::DebugRivers,
+        DrawingMode::DebugRivers => DrawingMode::DebugSprings,
+        DrawingMode::DebugSprings => DrawingMode::DebugCities,
+        DrawingMode::DebugCities => DrawingMode::DebugRegions,
+        DrawingMode::DebugRegions => DrawingMode::DebugIsochrones,
+        DrawingMode::DebugIsochrones => DrawingMode::Render,
+        DrawingMode::Render => DrawingMode::RenderTerraces,
+        DrawingMode::RenderTerraces => DrawingMode::RenderGlobe,
+        DrawingMode::RenderGlobe => DrawingMode::DebugMesh,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn mouse_moved(app: &App, model: &mut Model, position: Point2) {
+    if let Some(preview) = &mut model.preview {
+        if app.mouse.buttons.left().is_down() {
+            preview.camera.orbit(position - preview.mouse);
+        }
+
+        preview.mouse = position;
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn mouse_released(app: &App, model: &mut Model, button: MouseButton) {
+    // The left button orbits the 3D preview instead of generating a new terrain.
+
+    if model.preview.is_some() {
+        return;
+    }
+
+    if let Some(grid) = &model.seed_grid {
+        // Clicking a thumbnail promotes its seed to a full resolution terrain.
+
+        if button == MouseButton::Left {
+            if let Some(seed) = grid.seed_at(app.window_rect(), app.mouse.position()) {
+                model.seed_grid = None;
+                regenerate(model, seed);
+            }
+        }
+
+        return;
+    }
+
+    if button == MouseButton::Left && model.edit_mode {
+        let position = model.camera.screen_to_world(app.mouse.position());
+
+        if app.keys.mods.shift() {
+            add_city(model, position);
+        } else if app.keys.mods.ctrl() {
+            remove_city(model, position);
+        } else if app.keys.mods.alt() {
+            annotate(model, position);
+        } else if let Some(city) = pick_city(model, position) {
+            select_city(model, city);
+        }
+
+        return;
+    }
+
+    if button == MouseButton::Left {
+        // Clicking a city toggles its selection; clicking anywhere else generates a new terrain.
+
+        let position = model.camera.screen_to_world(app.mouse.position());
+
+        match pick_city(model, position) {
+            Some(city) => select_city(model, city),
+            None => regenerate(model, random()),
+        }
+    }
+
+    if button == MouseButton::Right {
+        model.mode = cycle_drawing_mode(model.mode);
+    }
+}
+
+/// Start generating the terrain for [seed] on a worker thread, so the viewer stays responsive,
+/// and cancel the terrain still being generated for an earlier request.
+#[cfg(not(target_arch = "wasm32"))]
+fn regenerate(model: &mut Model, seed: u64) {
+    if let Some(pending) = model.pending.take() {
+        pending.cancel.cancel();
+    }
+
+    let mut config = model.terrain.config.clone();
+    config.seed = seed;
+
+    let locked = model.locked.clone();
+    let cancel = CancelToken::new();
+    let worker_cancel = cancel.clone();
+    let (sender, result) = channel();
+
+    std::thread::spawn(move || {
+        // The receiver is gone if the generation was cancelled, so there is no one to tell.
+        let _ = sender.send(generate_map_terrain_cancellable(
+            config,
+            &locked,
+            worker_cancel,
+        ));
+    });
+
+    model.pending = Some(PendingTerrain {
+        seed,
+        cancel,
+        result,
+        timer: Timer::start(),
+    });
+}
+
+/// Replace the terrain with the one generated on the worker thread, once it is finished.
+#[cfg(not(target_arch = "wasm32"))]
+fn receive_terrain(model: &mut Model) {
+    let result = match model.pending.as_ref().map(|p| p.result.try_recv()) {
+        Some(Ok(result)) => result,
+        Some(Err(TryRecvError::Empty)) | None => return,
+        Some(Err(TryRecvError::Disconnected)) => {
+            log::error!("the terrain generation thread stopped without a result");
+            model.pending = None;
+            return;
+        }
+    };
+
+    let PendingTerrain { seed, timer, .. } = model.pending.take().unwrap();
+
+    model.terrain = match result {
+        Ok(terrain) => terrain,
+        Err(e) => {
+            log::error!("failed to generate terrain for seed {}: {}", seed, e);
+            return;
+        }
+    };
+
+    regenerate_regions(model);
+
+    model.minimap = Minimap::new(&model.terrain);
+    model.stats = TerrainStats::new(&model.terrain, STATS_BINS);
+    model.erosion_animation.step = 0;
+    model.globe = None;
+    model.editing = None;
+
+    let npoints = model.terrain.graph.points.len();
+    let elapsed = timer.elapsed();
+
+    log::info!(
+        "generated terrain with {:?} points in {:.4?}",
+        npoints,
+        elapsed,
+    );
+}
+
+/// Start typing into the annotation at the world [position], placing a new one if there is none.
+#[cfg(not(target_arch = "wasm32"))]
+fn annotate(model: &mut Model, position: Vec2) {
+    let seed = model.terrain.config.seed;
+    let radius = ANNOTATION_PICK_RADIUS / model.camera.zoom;
+
+    let index = match model.annotations.nearest(seed, position, radius) {
+        Some(index) => index,
+        None => {
+            let annotations = model.annotations.of_mut(seed);
+            annotations.push(Annotation::new(position));
+            annotations.len() - 1
+        }
+    };
+
+    model.editing = Some(AnnotationEdit { index, note: false });
+}
+
+/// Handle the keys that finish, switch or delete the annotation being typed.
+#[cfg(not(target_arch = "wasm32"))]
+fn edit_annotation_key(model: &mut Model, key: Key) {
+    let edit = match &mut model.editing {
+        Some(edit) => edit,
+        None => return,
+    };
+
+    let annotations = model.annotations.of_mut(model.terrain.config.seed);
+
+    match key {
+        Key::Return => model.editing = None,
+        Key::Tab => edit.note = !edit.note,
+        Key::Delete => {
+            annotations.remove(edit.index);
+            model.editing = None;
+        }
+        Key::Back => {
+            let annotation = &mut annotations[edit.index];
+            let text = if edit.note {
+                &mut annotation.note
+            } else {
+                &mut annotation.name
+            };
+
+            text.pop();
+        }
+        _ => {}
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn received_character(_app: &App, model: &mut Model, c: char) {
+    let edit = match &model.editing {
+        Some(edit) => edit,
+        None => return,
+    };
+
+    // Backspace, tab and return arrive as characters too, but are handled as keys.
+
+    if c.is_control() {
+        return;
+    }
+
+    let annotation = &mut model.annotations.of_mut(model.terrain.config.seed)[edit.index];
+
+    if edit.note {
+        annotation.note.push(c);
+    } else {
+        annotation.name.push(c);
+    }
+}
+
+/// The index of the city nearest to the world [position], if it is within picking distance.
+#[cfg(not(target_arch = "wasm32"))]
+fn pick_city(model: &Model, position: Vec2) -> Option<usize> {
+    let radius = CITY_PICK_RADIUS / model.camera.zoom;
+    let vertices = &model.terrain.graph.vertices;
+
+    model
+        .regions
+        .cities
+        .iter()
+        .map(|v| vertices[*v].distance(position))
+        .enumerate()
+        .filter(|(_, d)| *d <= radius)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+}
+
+/// Select the city [city] and highlight its region, or deselect it if it is already selected.
+#[cfg(not(target_arch = "wasm32"))]
+fn select_city(model: &mut Model, city: usize) {
+    if model.selection.as_ref().map(|s| s.city) == Some(city) {
+        model.selection = None;
+        return;
+    }
+
+    let region = model.regions.city_regions[city];
+    let summary = RegionSummary::new(&model.terrain, &model.regions, region);
+
+    model.selection = Some(Selection { city, summary });
+}
+
+/// Place a city at the habitable vertex nearest to the world [position].
+#[cfg(not(target_arch = "wasm32"))]
+fn add_city(model: &mut Model, position: Vec2) {
+    let vertex = match model.regions.nearest_habitable(&model.terrain, position) {
+        Some(vertex) => vertex,
+        None => return,
+    };
+
+    if model.regions.cities.contains(&vertex) {
+        return;
+    }
+
+    let mut cities = model.regions.cities.clone();
+    cities.push(vertex);
+
+    set_cities(model, cities);
+}
+
+/// Remove the city nearest to the world [position], keeping at least one city.
+#[cfg(not(target_arch = "wasm32"))]
+fn remove_city(model: &mut Model, position: Vec2) {
+    let vertices = &model.terrain.graph.vertices;
+    let cities = &model.regions.cities;
+
+    if cities.len() <= 1 {
+        return;
+    }
+
+    let nearest = (0..cities.len()).min_by(|a, b| {
+        let da = vertices[cities[*a]].distance(position);
+        let db = vertices[cities[*b]].distance(position);
+        da.total_cmp(&db)
+    });
+
+    if let Some(nearest) = nearest {
+        let vertex = cities[nearest];
+        let mut cities = cities.clone();
+        cities.remove(nearest);
+
+        let radius = model.terrain.config.radius;
+        model
+            .locked
+            .retain(|p| !is_lock_of(*p, vertices[vertex], radius));
+
+        set_cities(model, cities);
+    }
+}
+
+/// Regrow the regions around the given [cities], keeping the terrain.
+#[cfg(not(target_arch = "wasm32"))]
+fn set_cities(model: &mut Model, cities: Vec<usize>) {
+    let now = Timer::start();
+
+    model.terrain.config.num_cities = cities.len() as u32;
+
+    let regions = Regions::with_cities(&model.terrain, &cities, &DefaultTravelCost::default());
+    set_regions(model, regions);
+
+    log::info!(
+        "generated regions for {} cities in {:.4?}",
+        cities.len(),
+        now.elapsed(),
+    );
+}
+
+/// Replace the regions and everything derived from them, keeping the terrain and any locked
+/// cities.
+#[cfg(not(target_arch = "wasm32"))]
+fn regenerate_regions(model: &mut Model) {
+    let regions = if model.locked.is_empty() {
+        Regions::new(&model.terrain)
+    } else {
+        Regions::with_locked(&model.terrain, &model.locked, &DefaultTravelCost::default())
+    };
+
+    set_regions(model, regions);
+}
+
+/// Lock the selected city in place across regeneration, or unlock it if it is locked.
+#[cfg(not(target_arch = "wasm32"))]
+fn toggle_lock(model: &mut Model) {
+    let city = match &model.selection {
+        Some(selection) => selection.city,
+        None => return,
+    };
+
+    let position = model.terrain.graph.vertices[model.regions.cities[city]];
+    let radius = model.terrain.config.radius;
+
+    if model
+        .locked
+        .iter()
+        .any(|p| is_lock_of(*p, position, radius))
+    {
+        model.locked.retain(|p| !is_lock_of(*p, position, radius));
+    } else {
+        model.locked.push(position);
+    }
+}
+
+/// True if the [locked] position snaps to the city at [position]. Locked cities snap to a nearby
+/// vertex when the terrain is regenerated, so the positions only roughly match.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_lock_of(locked: Vec2, position: Vec2, radius: f32) -> bool {
+    locked.distance(position) <= radius * 2.0
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn set_regions(model: &mut Model, regions: Regions) {
+    model.regions = regions;
+    model.routes = Routes::new(&model.terrain, &model.regions);
+    model.labels = Labels::new(&model.terrain, &model.regions);
+    model.draw_list = DrawList::empty();
+    model.selection = None;
+}
+
+/// Change the number of cities by [delta] and regrow the regions on the same terrain.
+#[cfg(not(target_arch = "wasm32"))]
+fn change_num_cities(model: &mut Model, delta: i32) {
+    let config = &mut model.terrain.config;
+    let num_cities = (config.num_cities as i32 + delta).clamp(NUM_CITIES_MIN, NUM_CITIES_MAX);
+
+    if num_cities as u32 == config.num_cities {
+        return;
+    }
+
+    config.num_cities = num_cities as u32;
+
+    let now = Timer::start();
+
+    regenerate_regions(model);
+
+    log::info!(
+        "generated regions for {} cities in {:.4?}",
+        num_cities,
+        now.elapsed(),
+    );
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn key_pressed(app: &App, model: &mut Model, key: Key) {
+    // While an annotation is being typed, the keys are text rather than hotkeys.
+
+    if model.editing.is_some() {
+        edit_annotation_key(model, key);
+        return;
+    }
+
+    // Exports write to the filesystem, which the browser doesn't have.
+
+    #[cfg(not(target_arch = "wasm32"))]
+    export_key_pressed(app, model, key);
+
+    if key == Key::B {
+        if model.seed_grid.is_some() {
+            model.seed_grid = None;
+        } else {
+            let now = Timer::start();
+
+            model.seed_grid = Some(SeedGrid::new());
+
+            log::info!("generated seed grid in {:.4?}", now.elapsed());
+        }
+    }
+
+    if key == Key::Space {
+        model.erosion_animation.paused = !model.erosion_animation.paused;
+    }
+
+    if key == Key::Period {
+        let num_steps = model.terrain.data.erosion_history.len().max(1);
+        let animation = &mut model.erosion_animation;
+
+        animation.paused = true;
+        animation.step = (animation.step + 1) % num_steps;
+    }
+
+    if key == Key::W {
+        model.theme.ocean_waves = !model.theme.ocean_waves;
+    }
+
+    if key == Key::L {
+        model.theme.labels = !model.theme.labels;
+    }
+
+    if key == Key::P {
+        model.theme.political = !model.theme.political;
+    }
+
+    if key == Key::O {
+        model.theme.frame = model.theme.frame.next();
+    }
+
+    if key == Key::R {
+        model.theme.compass = !model.theme.compass;
+    }
+
+    if key == Key::H {
+        model.theme.fill = model.theme.fill.next();
+    }
+
+    if key == Key::V {
+        if model.preview.is_some() {
+            model.preview = None;
+        } else {
+            model.preview = Some(Preview {
+                camera: OrbitCamera::new(),
+                mouse: app.mouse.position(),
+            });
+        }
+    }
+
+    if key == Key::K {
+        toggle_lock(model);
+    }
+
+    if key == Key::M {
+        model.edit_mode = !model.edit_mode;
+    }
+
+    if key == Key::LBracket {
+        change_num_cities(model, -1);
+    }
+
+    if key == Key::RBracket {
+        change_num_cities(model, 1);
+    }
+
+    if key == Key::I {
+        model.show_stats = !model.show_stats;
+    }
+
+    if key == Key::D {
+        model.field = model.field.next();
+    }
+
+    if key == Key::C {
+        model.theme.debug_palette = model.theme.debug_palette.next();
+    }
+
+    if key == Key::X {
+        model.theme.grid = match model.theme.grid {
+            None => Some(GridShape::Hex),
+            Some(GridShape::Hex) => Some(GridShape::Square),
+            Some(GridShape::Square) => None,
+        };
+    }
+
+    if key == Key::N {
+        model.theme.season = model.theme.season.next();
+    }
+
+    if key == Key::F {
+        let density = model.theme.forest_density;
+        let index = FOREST_DENSITIES.iter().position(|d| *d == density);
+        let next = index.map_or(0, |i| (i + 1) % FOREST_DENSITIES.len());
+
+        model.theme.forest_density = FOREST_DENSITIES[next];
+    }
+
+    match key {
+        Key::Left => model.camera.pan(vec2(-CAMERA_PAN, 0.0)),
+        Key::Right => model.camera.pan(vec2(CAMERA_PAN, 0.0)),
+        Key::Up => model.camera.pan(vec2(0.0, CAMERA_PAN)),
+        Key::Down => model.camera.pan(vec2(0.0, -CAMERA_PAN)),
+        Key::Home => model.camera = Camera::new(),
+        _ => {}
+    }
 }
 
-fn main() {
-    nannou::app(model).view(view).run();
-}
+/// Handle the hotkeys that export the map to files.
+#[cfg(not(target_arch = "wasm32"))]
+fn export_key_pressed(app: &App, model: &mut Model, key: Key) {
+    if key == Key::G {
+        let path = PathBuf::from(format!("terrain-{}.geojson", model.terrain.config.seed));
+        let options = GeoJsonOptions::default();
 
-fn model(app: &App) -> Model {
-    app.new_window()
-        .size(SIZE_X, SIZE_Y)
-        .view(view)
-        .mouse_released(mouse_released)
-        .build()
-        .unwrap();
+        let seed = model.terrain.config.seed;
+        let annotations = model.annotations.of(seed);
 
-    let config = TerrainConfig {
-        size: Vec2::new(SIZE_X as f32, SIZE_Y as f32),
-        seed: random(),
-        radius: 10.0,
-        num_cities: 5,
-    };
+        match write_geojson(&path, &model.terrain, &model.regions, annotations, options) {
+            Ok(()) => log::info!("exported geojson to {:?}", path),
+            Err(e) => log::error!("failed to export geojson: {}", e),
+        }
+    }
 
-    let terrain = generate_terrain(config);
-    let regions = Regions::new(&terrain);
+    if key == Key::S {
+        let result = export_path(model, "png").map(|path| {
+            app.main_window().capture_frame(&path);
+            path
+        });
 
-    Model {
-        terrain,
-        regions,
-        mode: DrawingMode::Render,
+        report_export(app, model, "screenshot", result);
+
+        if let Some((message, _)) = model.flash.take() {
+            model.screenshot_flash = Some((message, app.elapsed_frames()));
+        }
     }
-}
 
-#[derive(Debug, Copy, Clone)]
-enum DrawingMode {
-    DebugMesh,
-    DebugGraphVerts,
-    DebugGraphEdges,
-    DebugElevation,
-    DebugSlope,
-    DebugFlow,
-    DebugErosion,
-    DebugRivers,
-    DebugCities,
-    DebugRegions,
-    Render,
-}
+    if key == Key::E {
+        let result = export_path(model, "svg").and_then(|path| {
+            // Modes drawn directly to the screen have no primitives, so export the map instead.
 
-fn cycle_drawing_mode(mode: DrawingMode) -> DrawingMode {
-    match mode {
-        DrawingMode::DebugMesh => DrawingMode::DebugGraphVerts,
-        DrawingMode::DebugGraphVerts => DrawingMode::DebugGraphEdges,
-        DrawingMode::DebugGraphEdges => DrawingMode::DebugElevation,
-        DrawingMode::DebugElevation => DrawingMode::DebugSlope,
-        DrawingMode::DebugSlope => DrawingMode::DebugFlow,
-        DrawingMode::DebugFlow => DrawingMode::DebugErosion,
-        DrawingMode::DebugErosion => DrawingMode::DebugRivers,
-        DrawingMode::DebugRivers => DrawingMode::DebugCities,
-        DrawingMode::DebugCities => DrawingMode::DebugRegions,
-        DrawingMode::DebugRegions => DrawingMode::Render,
-        DrawingMode::Render => DrawingMode::DebugMesh,
+            let mut primitives = if model.draw_list.primitives.is_empty() {
+                map_primitives(
+                    &model.terrain,
+                    &model.regions,
+                    &model.routes,
+                    &model.labels,
+                    model.theme,
+                    false,
+                )
+            } else {
+                model.draw_list.primitives.clone()
+            };
+
+            let annotations = model.annotations.of(model.terrain.config.seed);
+            primitives.extend(annotation_primitives(annotations));
+
+            write_svg(&path, model.terrain.extent, &primitives).map(|_| path)
+        });
+
+        report_export(app, model, "svg", result);
+    }
+
+    if key == Key::T {
+        let shape = model.theme.grid.unwrap_or(GridShape::Hex);
+        let grid = MapGrid::new(model.terrain.extent, shape, GRID_CELL_SIZE);
+        let cells = grid_cells(&grid, &model.terrain, &model.regions);
+
+        let result = export_path(model, "json")
+            .and_then(|path| write_grid(&path, &grid, &cells, &model.regions).map(|_| path));
+
+        report_export(app, model, "grid", result);
+    }
+
+    if key == Key::J {
+        let result = export_path(model, "json")
+            .and_then(|path| write_snapshot(&path, &model.terrain).map(|_| path));
+
+        report_export(app, model, "terrain json", result);
     }
 }
 
-fn mouse_released(_: &App, model: &mut Model, button: MouseButton) {
-    if button == MouseButton::Left {
-        let now = Instant::now();
+/// A path in the export directory named after the current time and seed, creating the directory
+/// if needed.
+#[cfg(not(target_arch = "wasm32"))]
+fn export_path(model: &Model, extension: &str) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(EXPORT_DIR)?;
 
-        let mut config = model.terrain.config.clone();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
 
-        config.seed = random();
+    let name = format!("{}-{}.{}", timestamp, model.terrain.config.seed, extension);
 
-        model.terrain = generate_terrain(config);
-        model.regions = Regions::new(&model.terrain);
+    Ok(PathBuf::from(EXPORT_DIR).join(name))
+}
 
-        let npoints = model.terrain.graph.points.len();
-        let elapsed = now.elapsed();
+/// Log the result of an export and flash it on screen.
+#[cfg(not(target_arch = "wasm32"))]
+fn report_export(app: &App, model: &mut Model, what: &str, result: std::io::Result<PathBuf>) {
+    let message = match result {
+        Ok(path) => {
+            let message = format!("exported {} to {}", what, path.display());
+            log::info!("{}", message);
+            message
+        }
+        Err(e) => {
+            let message = format!("failed to export {}: {}", what, e);
+            log::error!("{}", message);
+            message
+        }
+    };
 
-        println!(
-            "generated terrain with {:?} points in {:.4?}",
-            npoints, elapsed,
-        );
-    }
+    model.flash = Some((message, app.time));
+}
 
-    if button == MouseButton::Right {
-        model.mode = cycle_drawing_mode(model.mode);
+#[cfg(not(target_arch = "wasm32"))]
+fn mouse_wheel(app: &App, model: &mut Model, delta: MouseScrollDelta, _: TouchPhase) {
+    let lines = match delta {
+        MouseScrollDelta::LineDelta(_, y) => y,
+        MouseScrollDelta::PixelDelta(p) => p.y as f32 / 20.0,
+    };
+
+    if let Some(preview) = &mut model.preview {
+        preview.camera.zoom(1.1f32.powf(lines));
+        return;
     }
+
+    model
+        .camera
+        .zoom_at(app.mouse.position(), 1.1f32.powf(lines));
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 fn view(app: &App, model: &Model, frame: Frame) {
-    let draw = app.draw();
+    let screen = app.draw();
+    let draw = model.camera.transform(&screen);
 
     draw.background().color(SNOW);
 
+    if let Some(grid) = &model.seed_grid {
+        render_seed_grid(&screen, app.window_rect(), grid);
+        screen.to_frame(app, &frame).unwrap();
+        return;
+    }
+
+    if let Some(preview) = &model.preview {
+        render_preview(&screen, app.window_rect(), &model.terrain, &preview.camera);
+        screen.to_frame(app, &frame).unwrap();
+        return;
+    }
+
+    let palette = model.theme.debug_palette;
+
+    let show_field = |field: ScalarField| {
+        let values = field.values(&model.terrain, &model.regions);
+        debug_scalar_field(&draw, &model.terrain, &values, field.gradient(palette));
+    };
+
     match model.mode {
         DrawingMode::DebugMesh => {
             debug_mesh_polygons(&draw, &model.terrain);
@@ -121,43 +1368,235 @@ fn view(app: &App, model: &Model, frame: Frame) {
         DrawingMode::DebugGraphEdges => {
             debug_graph_edges(&draw, &model.terrain);
         }
-        DrawingMode::DebugElevation => {
-            debug_elevation(&draw, &model.terrain);
+        DrawingMode::DebugField => {
+            show_field(model.field);
+
+            let window = app.window_rect();
+
+            screen
+                .text(model.field.name())
+                .x_y(window.left() + 80.0, window.top() - 20.0)
+                .color(BLACK);
         }
         DrawingMode::DebugSlope => {
-            debug_elevation(&draw, &model.terrain);
+            show_field(ScalarField::Elevation);
             debug_normal(&draw, &model.terrain);
         }
         DrawingMode::DebugFlow => {
-            debug_elevation(&draw, &model.terrain);
+            show_field(ScalarField::Elevation);
             debug_flow(&draw, &model.terrain);
         }
-        DrawingMode::DebugErosion => {
-            debug_elevation(&draw, &model.terrain);
-            debug_erosion(&draw, &model.terrain);
+        DrawingMode::DebugBasins => {
+            show_field(ScalarField::Elevation);
+            debug_basins(&draw, &model.terrain, palette);
+        }
+        DrawingMode::DebugWatersheds => {
+            debug_watersheds(&draw, &model.terrain, palette);
+        }
+        DrawingMode::DebugErosionHistory => {
+            let step = model.erosion_animation.step;
+
+            if let Some(elevation) = model.terrain.data.erosion_history.get(step) {
+                let values = scaled(elevation, -500.0, 500.0);
+                debug_scalar_field(&draw, &model.terrain, &values, palette.elevation());
+            }
+
+            let window = app.window_rect();
+
+            screen
+                .text(&format!("erosion pass {}", step))
+                .x_y(window.left() + 80.0, window.top() - 20.0)
+                .color(BLACK);
+
+            render_erosion_sparkline(&screen, window, &model.terrain.data.erosion_stats, step);
         }
         DrawingMode::DebugRivers => {
             debug_mesh_surface(&draw, &model.terrain);
-            debug_rivers(&draw, &model.terrain);
+            debug_rivers(&draw, &model.terrain, palette);
+        }
+        DrawingMode::DebugSprings => {
+            debug_mesh_surface(&draw, &model.terrain);
+            debug_groundwater(&draw, &model.terrain, palette);
+            debug_springs(&draw, &model.terrain);
         }
         DrawingMode::DebugCities => {
-            debug_habitability(&draw, &model.terrain, &model.regions);
-            render_cities(&draw, &model.terrain, &model.regions);
+            show_field(ScalarField::Habitability);
+            model
+                .draw_list
+                .draw(&draw, &model.camera, app.window_rect());
+        }
+        DrawingMode::DebugRegions
+        | DrawingMode::DebugIsochrones
+        | DrawingMode::Render
+        | DrawingMode::RenderTerraces => {
+            model
+                .draw_list
+                .draw(&draw, &model.camera, app.window_rect());
+        }
+        DrawingMode::RenderGlobe => {
+            if let Some(globe) = &model.globe {
+                render_globe(&screen, app.window_rect(), globe, app.time);
+            }
+        }
+    }
+
+    for position in model.locked.iter() {
+        draw.rect()
+            .xy(*position)
+            .w_h(LOCK_MARKER_SIZE, LOCK_MARKER_SIZE)
+            .no_fill()
+            .stroke_weight(1.5)
+            .stroke_color(STEELBLUE);
+    }
+
+    let annotations = model.annotations.of(model.terrain.config.seed);
+    draw_primitives(&draw, annotation_primitives(annotations).iter());
+
+    if let Some(edit) = &model.editing {
+        let window = app.window_rect();
+        let annotation = &annotations[edit.index];
+
+        let prompt = if edit.note {
+            format!("note: {}_", annotation.note)
+        } else {
+            format!("name: {}_", annotation.name)
+        };
+
+        screen
+            .text(&format!(
+                "{}   (tab to switch, return to finish, delete to remove)",
+                prompt
+            ))
+            .w(window.w())
+            .x_y(0.0, window.top() - 40.0)
+            .color(BLACK);
+    }
+
+    if model.edit_mode {
+        let window = app.window_rect();
+
+        screen
+            .text("edit cities: shift-click to place, ctrl-click to remove, alt-click to annotate")
+            .w(window.w())
+            .x_y(0.0, window.top() - 20.0)
+            .color(BLACK);
+    }
+
+    if let Some(selection) = &model.selection {
+        render_selection(&draw, &screen, app.window_rect(), model, selection);
+    }
+
+    if model.camera.is_zoomed() {
+        render_minimap(&screen, app.window_rect(), model);
+    }
+
+    if model.show_stats {
+        render_stats(&screen, app.window_rect(), model);
+    }
+
+    if let Some((message, time)) = &model.flash {
+        if app.time - time < FLASH_SECONDS {
+            let window = app.window_rect();
+
+            screen
+                .text(message)
+                .w(window.w())
+                .x_y(0.0, window.bottom() + 20.0)
+                .color(BLACK);
         }
+    }
+
+    screen.to_frame(app, &frame).unwrap();
+}
+
+/// Build the cached primitives for the current drawing mode. Debug modes that are not cached
+/// build an empty list and draw directly in the view instead.
+#[cfg(not(target_arch = "wasm32"))]
+fn build_draw_list(model: &Model, key: DrawListKey) -> DrawList {
+    let terrain = &model.terrain;
+    let regions = &model.regions;
+
+    let primitives = match key.mode {
+        DrawingMode::DebugCities => city_primitives(terrain, regions),
         DrawingMode::DebugRegions => {
-            render_terrain(&draw, &model.terrain);
-            debug_regions(&draw, &model.terrain, &model.regions);
-            render_cities(&draw, &model.terrain, &model.regions);
+            debug_region_primitives(terrain, regions, key.theme.debug_palette)
         }
-        DrawingMode::Render => {
-            render_terrain(&draw, &model.terrain);
-            render_cities(&draw, &model.terrain, &model.regions);
+        DrawingMode::DebugIsochrones => {
+            isochrone_primitives(terrain, regions, key.theme.debug_palette)
         }
+        DrawingMode::Render => map_primitives(
+            terrain,
+            regions,
+            &model.routes,
+            &model.labels,
+            key.theme,
+            key.lod,
+        ),
+        DrawingMode::RenderTerraces => terrace_primitives(terrain, regions),
+        _ => vec![],
+    };
+
+    DrawList {
+        key: Some(key),
+        grid: PrimitiveGrid::new(&primitives),
+        primitives,
     }
+}
 
-    draw.to_frame(app, &frame).unwrap();
+#[cfg(not(target_arch = "wasm32"))]
+fn draw_primitives<'a>(draw: &Draw, primitives: impl Iterator<Item = &'a Primitive>) {
+    for primitive in primitives {
+        match primitive {
+            Primitive::Line {
+                points,
+                weight,
+                color,
+            } => {
+                draw.line()
+                    .caps_round()
+                    .weight(*weight)
+                    .points(points.0, points.1)
+                    .color(color.into_rgb());
+            }
+            Primitive::Polyline {
+                points,
+                weight,
+                color,
+            } => {
+                draw.polyline()
+                    .join_round()
+                    .weight(*weight)
+                    .points(points.iter().cloned())
+                    .color(color.into_rgb());
+            }
+            Primitive::Polygon { points, color } => {
+                draw.polygon()
+                    .points(points.iter().cloned())
+                    .color(color.into_rgb());
+            }
+            Primitive::Circle {
+                center,
+                radius,
+                fill,
+                stroke,
+            } => {
+                let ellipse = draw
+                    .ellipse()
+                    .radius(*radius)
+                    .xy(*center)
+                    .color(fill.into_rgb());
+
+                if let Some((weight, color)) = stroke {
+                    ellipse
+                        .stroke_weight(*weight)
+                        .stroke_color(color.into_rgb());
+                }
+            }
+        }
+    }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(dead_code)]
 fn debug_points(draw: &Draw, terrain: &Terrain) {
     for p in terrain.graph.points.iter() {
@@ -165,6 +1604,7 @@ fn debug_points(draw: &Draw, terrain: &Terrain) {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(dead_code)]
 fn debug_graph_vertices(draw: &Draw, terrain: &Terrain) {
     for (i, v) in terrain.graph.vertices.iter().enumerate() {
@@ -177,6 +1617,7 @@ fn debug_graph_vertices(draw: &Draw, terrain: &Terrain) {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(dead_code)]
 fn debug_graph_edges(draw: &Draw, terrain: &Terrain) {
     for e in terrain.graph.edges.iter() {
@@ -198,6 +1639,7 @@ fn debug_graph_edges(draw: &Draw, terrain: &Terrain) {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(dead_code)]
 fn debug_mesh_polygons(draw: &Draw, terrain: &Terrain) {
     for poly in terrain.mesh.polygons.iter().flatten() {
@@ -206,17 +1648,7 @@ fn debug_mesh_polygons(draw: &Draw, terrain: &Terrain) {
     }
 }
 
-#[allow(dead_code)]
-fn debug_elevation(draw: &Draw, terrain: &Terrain) {
-    for (i, poly) in terrain.mesh.polygons.iter().flatten().enumerate() {
-        let p = poly.points.iter().cloned();
-        let t = map_clamp(terrain.mesh.elevation[i], -500.0, 500.0, 0.0, 1.0);
-        let c = colorous::COOL.eval_continuous(t as f64).into_rgb();
-
-        draw.polygon().points(p).color(c);
-    }
-}
-
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(dead_code)]
 fn debug_normal(draw: &Draw, terrain: &Terrain) {
     for (i, p) in terrain.graph.points.iter().enumerate() {
@@ -237,6 +1669,7 @@ fn debug_normal(draw: &Draw, terrain: &Terrain) {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(dead_code)]
 fn debug_flow(draw: &Draw, terrain: &Terrain) {
     for (i, v) in terrain.graph.vertices.iter().enumerate() {
@@ -255,18 +1688,57 @@ fn debug_flow(draw: &Draw, terrain: &Terrain) {
     }
 }
 
+/// Mark the vertices in closed basins, which the flow has to fill before draining away.
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(dead_code)]
-fn debug_erosion(draw: &Draw, terrain: &Terrain) {
-    for (i, e) in terrain.data.erosion.iter().enumerate() {
-        let p = terrain.graph.vertices[i];
-        let r = map_clamp(*e, 0.0, 2.0, 0.0, 10.0);
-        let t = map_clamp(*e, 0.0, 2.0, 0.0, 1.0);
-        let c = colorous::MAGMA.eval_continuous(t as f64).into_rgb();
+fn debug_basins(draw: &Draw, terrain: &Terrain, palette: DebugPalette) {
+    let c = palette.category(0, 8).into_rgb();
+
+    for (i, closed) in terrain.data.closed_basin.iter().cloned().enumerate() {
+        if closed {
+            let p = terrain.graph.vertices[i];
+            draw.ellipse().radius(2.0).xy(p).color(c);
+        }
+    }
+}
+
+/// The number of colors the drainage basins cycle through.
+#[cfg(not(target_arch = "wasm32"))]
+const WATERSHED_COLORS: usize = 12;
+
+/// Color each polygon by the drainage basin of its vertices, and draw the divides between basins.
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(dead_code)]
+fn debug_watersheds(draw: &Draw, terrain: &Terrain, palette: DebugPalette) {
+    let graph = &terrain.graph;
+    let watershed = &terrain.data.watershed;
+
+    // Outlets are vertex indices, so neighboring basins usually have nearby outlets; scatter them
+    // across the colors.
+
+    let color = |outlet: usize| {
+        let i = outlet.wrapping_mul(7919) % WATERSHED_COLORS;
+        palette.category(i, WATERSHED_COLORS).into_rgb()
+    };
+
+    for (i, poly) in terrain.mesh.polygons.iter().enumerate() {
+        if let (Some(poly), Some(v)) = (poly, graph.cell(i).first()) {
+            let p = poly.points.iter().cloned();
+            draw.polygon().points(p).color(color(watershed[*v]));
+        }
+    }
+
+    for edge in graph.edges.iter() {
+        if watershed[edge.vertices.0] != watershed[edge.vertices.1] {
+            let pa = graph.points[edge.points.0];
+            let pb = graph.points[edge.points.1];
 
-        draw.ellipse().xy(p).radius(r).color(c);
+            draw.line().weight(1.5).color(BLACK).points(pa, pb);
+        }
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(dead_code)]
 fn debug_mesh_surface(draw: &Draw, terrain: &Terrain) {
     for (i, poly) in terrain.mesh.polygons.iter().flatten().enumerate() {
@@ -280,81 +1752,440 @@ fn debug_mesh_surface(draw: &Draw, terrain: &Terrain) {
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(dead_code)]
-fn debug_rivers(draw: &Draw, terrain: &Terrain) {
+fn debug_rivers(draw: &Draw, terrain: &Terrain, palette: DebugPalette) {
     for (i, river) in terrain.mesh.rivers.iter().enumerate() {
         let p = river.points.iter().cloned();
-        let c = colorous::SINEBOW.eval_rational(i % 8, 8).into_rgb();
+        let c = palette.category(i % 8, 8).into_rgb();
         draw.polyline().join_round().weight(4.0).points(p).color(c);
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 #[allow(dead_code)]
-fn debug_habitability(draw: &Draw, terrain: &Terrain, regions: &Regions) {
-    draw.background().color(BLACK);
+fn debug_groundwater(draw: &Draw, terrain: &Terrain, palette: DebugPalette) {
+    for (i, g) in terrain.data.groundwater.iter().cloned().enumerate() {
+        if !terrain.mesh.contour.is_surface[i] {
+            continue;
+        }
 
-    for (i, h) in regions.habitability.iter().cloned().enumerate() {
         let p = terrain.graph.vertices[i];
-        let c = colorous::MAGMA.eval_continuous(h as f64).into_rgb();
+        let t = map_clamp(g, 0.0, terrain.config.rivers.flux_threshold, 0.0, 1.0);
+        let c = palette.water().eval_continuous(t as f64).into_rgb();
+
         draw.ellipse().radius(2.0).xy(p).color(c);
     }
 }
 
-fn debug_regions(draw: &Draw, terrain: &Terrain, regions: &Regions) {
-    for (i, region) in regions.regions.iter().cloned().enumerate() {
-        let p = terrain.graph.vertices[i];
-        let c = colorous::SINEBOW.eval_rational(region % 8, 8).into_rgb();
-        draw.ellipse().radius(2.0).xy(p).color(c);
+#[cfg(not(target_arch = "wasm32"))]
+#[allow(dead_code)]
+fn debug_springs(draw: &Draw, terrain: &Terrain) {
+    for river in terrain.mesh.rivers.iter() {
+        let p = river.points.iter().cloned();
+        draw.polyline()
+            .join_round()
+            .weight(3.0)
+            .points(p)
+            .color(BLACK);
+    }
+
+    for spring in terrain.mesh.springs.iter() {
+        let p = spring.stream.points.iter().cloned();
+        draw.polyline()
+            .join_round()
+            .weight(2.0)
+            .points(p)
+            .color(RED);
+
+        let c = terrain.graph.vertices[spring.vertex];
+        draw.ellipse().radius(4.0).xy(c).color(RED);
     }
 }
 
-fn render_coastline(draw: &Draw, terrain: &Terrain) {
-    for (a, b) in terrain.mesh.contour.segments.iter().cloned() {
+/// Draw the globe in orthographic projection, spinning about its axis. Only the triangles and
+/// rivers facing the viewer are drawn.
+#[cfg(not(target_arch = "wasm32"))]
+fn render_globe(draw: &Draw, window: Rect, globe: &Globe, time: f32) {
+    let radius = window.w().min(window.h()) * 0.45;
+    let rotation = Quat::from_rotation_y(time * GLOBE_SPIN);
+
+    let points: Vec<Vec3> = globe.graph.points.iter().map(|p| rotation * *p).collect();
+    let elevation = &globe.data.elevation;
+
+    let land_gradient = colorous::YELLOW_ORANGE_BROWN;
+    let water_gradient = colorous::BLUES;
+
+    for (a, b, c) in globe.graph.triangles.iter().cloned() {
+        if points[a].z <= 0.0 || points[b].z <= 0.0 || points[c].z <= 0.0 {
+            continue;
+        }
+
+        let e = (elevation[a] + elevation[b] + elevation[c]) / 3.0;
+
+        let color = if e >= 0.0 {
+            land_gradient.eval_continuous(map_clamp(e, 0.0, 1.0, 0.1, 0.7) as f64)
+        } else {
+            water_gradient.eval_continuous(map_clamp(-e, 0.0, 1.0, 0.4, 0.9) as f64)
+        };
+
+        let corners = [a, b, c].map(|i| points[i].truncate() * radius);
+
+        draw.polygon().points(corners).color(color.into_rgb());
+    }
+
+    for (a, b) in globe.data.rivers() {
+        if points[a].z <= 0.0 || points[b].z <= 0.0 {
+            continue;
+        }
+
         draw.line()
-            .caps_round()
-            .weight(3.0)
-            .points(a, b)
-            .color(BLACK);
+            .start(points[a].truncate() * radius)
+            .end(points[b].truncate() * radius)
+            .weight(1.0)
+            .color(STEELBLUE);
+    }
+
+    draw.ellipse()
+        .radius(radius)
+        .no_fill()
+        .stroke_weight(1.5)
+        .stroke(BLACK);
+}
+
+/// Draw the Delaunay triangles raised to the elevation of their points, lit by the terrain light
+/// and sorted back to front.
+#[cfg(not(target_arch = "wasm32"))]
+fn render_preview(draw: &Draw, window: Rect, terrain: &Terrain, camera: &OrbitCamera) {
+    let graph = &terrain.graph;
+    let scale = terrain.config.vertical_scale;
+    let light = terrain.config.light.direction();
+    let focal = window.w().min(window.h());
+
+    // The sea is drawn flat at sea level.
+
+    let heights: Vec<f32> = (0..graph.points.len())
+        .map(|p| indexed_mean(&terrain.data.elevation, graph.cell(p)))
+        .collect();
+
+    let positions: Vec<Vec3> = graph
+        .points
+        .iter()
+        .zip(heights.iter())
+        .map(|(p, h)| p.extend(h.max(0.0) * scale))
+        .collect();
+
+    let mut triangles = vec![];
+
+    for v in graph.interior.iter().cloned() {
+        if graph.is_seam_copy(v) {
+            continue;
+        }
+
+        let (a, b, c) = graph.vertex_points(v);
+        let corners = [a, b, c].map(|p| camera.project(positions[p], focal));
+
+        if let [Some(pa), Some(pb), Some(pc)] = corners {
+            let depth = (pa.1 + pb.1 + pc.1) / 3.0;
+            triangles.push((depth, v, [pa.0, pb.0, pc.0]));
+        }
+    }
+
+    triangles.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let land_gradient = colorous::YELLOW_ORANGE_BROWN;
+    let water_gradient = colorous::BLUES;
+
+    for (_, v, corners) in triangles {
+        let (a, b, c) = graph.vertex_points(v);
+        let e = (heights[a] + heights[b] + heights[c]) / 3.0;
+
+        let color = if e >= 0.0 {
+            land_gradient.eval_continuous(map_clamp(e, 0.0, PREVIEW_PEAK, 0.1, 0.7) as f64)
+        } else {
+            water_gradient.eval_continuous(map_clamp(-e, 0.0, PREVIEW_PEAK, 0.4, 0.9) as f64)
+        };
+
+        let normal = (positions[b] - positions[a])
+            .cross(positions[c] - positions[a])
+            .normalize_or_zero();
+
+        let normal = if normal.z < 0.0 { -normal } else { normal };
+
+        let lit = normal.dot(light).max(0.0);
+        let brightness = PREVIEW_AMBIENT + (1.0 - PREVIEW_AMBIENT) * lit;
+
+        let color = rgb(
+            color.r as f32 / 255.0 * brightness,
+            color.g as f32 / 255.0 * brightness,
+            color.b as f32 / 255.0 * brightness,
+        );
+
+        draw.polygon().points(corners).color(color);
     }
 }
 
-fn render_slopes(draw: &Draw, terrain: &Terrain) {
-    for shading in terrain.mesh.shading.iter() {
-        let w = shading.weight;
-        let a = shading.points.0;
-        let b = shading.points.1;
-        draw.line().caps_round().color(BLACK).weight(w).points(a, b);
+/// Draw each thumbnail of the seed grid scaled to fit its cell, labelled with its seed.
+#[cfg(not(target_arch = "wasm32"))]
+fn render_seed_grid(draw: &Draw, window: Rect, grid: &SeedGrid) {
+    let gradient = colorous::YELLOW_ORANGE_BROWN;
+
+    for (i, thumbnail) in grid.thumbnails.iter().enumerate() {
+        let cell = SeedGrid::cell(window, i).pad(SEED_GRID_MARGIN);
+        let extent = thumbnail.extent;
+
+        let scale = f32::min(cell.w() / extent.w(), cell.h() / extent.h());
+        let to_cell = |p: Vec2| cell.xy() + (p - extent.xy()) * scale;
+
+        draw.rect()
+            .xy(cell.xy())
+            .wh(extent.wh() * scale)
+            .color(LIGHTSTEELBLUE);
+
+        for (points, elevation) in thumbnail.land.iter() {
+            let t = map_clamp(*elevation, 0.0, 500.0, 0.1, 0.7);
+            let c = gradient.eval_continuous(t as f64).into_rgb();
+
+            draw.polygon()
+                .points(points.iter().cloned().map(to_cell))
+                .color(c);
+        }
+
+        for chain in thumbnail.coastline.iter() {
+            let points = chain.iter().cloned().map(to_cell);
+            draw.polyline().weight(1.0).points(points).color(BLACK);
+        }
+
+        draw.text(&thumbnail.seed.to_string())
+            .xy(vec2(cell.x(), cell.bottom() + 10.0))
+            .w(cell.w())
+            .color(BLACK);
     }
 }
 
-fn render_rivers(draw: &Draw, terrain: &Terrain) {
-    for river in terrain.mesh.rivers.iter() {
-        let points: Vec<Vec2> = smooth_path(&river.points).collect();
-        let weight = map_clamp(river.flux, 0.005, 0.025, 3.0, 5.0);
+/// Highlight the region of the selected city on the map, and list its statistics in a panel.
+#[cfg(not(target_arch = "wasm32"))]
+fn render_selection(
+    draw: &Draw,
+    screen: &Draw,
+    window: Rect,
+    model: &Model,
+    selection: &Selection,
+) {
+    let summary = &selection.summary;
+    let regions = &model.regions;
 
-        draw.polyline()
-            .join_round()
-            .weight(weight)
-            .points(points)
+    for p in summary.cells.iter() {
+        if let Some(poly) = &model.terrain.mesh.polygons[*p] {
+            draw.polygon()
+                .points(poly.points.iter().cloned())
+                .color(rgba(0.27, 0.51, 0.71, 0.35));
+        }
+    }
+
+    let neighbors = summary
+        .neighbors
+        .iter()
+        .map(|r| regions.region_names[*r].as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let lines = [
+        regions.city_names[selection.city].clone(),
+        regions.economies[selection.city]
+            .iter()
+            .map(|e| e.name())
+            .collect::<Vec<_>>()
+            .join(", "),
+        format!("region {}", summary.name),
+        format!("population {:.0}", summary.population),
+        format!("area {:.0}", summary.area),
+        format!("cities {}", summary.cities.len()),
+        format!("neighbors {}", neighbors),
+    ];
+
+    let line_height = 16.0;
+    let height = line_height * lines.len() as f32 + STATS_MARGIN * 2.0;
+
+    let panel = Rect::from_w_h(STATS_WIDTH, height)
+        .top_right_of(window)
+        .shift(vec2(-STATS_MARGIN, -STATS_MARGIN));
+
+    screen
+        .rect()
+        .xy(panel.xy())
+        .wh(panel.wh())
+        .color(rgba(1.0, 1.0, 1.0, 0.85))
+        .stroke_weight(1.0)
+        .stroke_color(BLACK);
+
+    let inner = panel.pad(STATS_MARGIN);
+
+    for (i, line) in lines.iter().enumerate() {
+        let y = inner.top() - line_height * (i as f32 + 0.5);
+
+        screen
+            .text(line)
+            .x_y(inner.x(), y)
+            .w(inner.w())
+            .left_justify()
             .color(BLACK);
     }
 }
 
-fn render_terrain(draw: &Draw, terrain: &Terrain) {
-    render_coastline(draw, terrain);
-    render_slopes(draw, terrain);
-    render_rivers(draw, terrain);
+/// Draw the terrain statistics and an elevation histogram in the top left corner.
+#[cfg(not(target_arch = "wasm32"))]
+fn render_stats(draw: &Draw, window: Rect, model: &Model) {
+    let stats = &model.stats;
+
+    let lines = [
+        format!("land {:.1}%", stats.land_fraction * 100.0),
+        format!("rivers {}", stats.num_rivers),
+        format!("islands {}", stats.num_islands),
+        format!("score {:.2}", stats.score(&ScoreConfig::default())),
+        format!("cities {}", model.regions.cities.len()),
+        format!("regions {}", model.regions.founders().len()),
+        format!(
+            "elevation {:.0} / {:.0} / {:.0}",
+            stats.min_elevation, stats.mean_elevation, stats.max_elevation
+        ),
+    ];
+
+    let line_height = 16.0;
+    let histogram_height = 60.0;
+    let height = line_height * lines.len() as f32 + histogram_height + STATS_MARGIN * 3.0;
+
+    let panel = Rect::from_w_h(STATS_WIDTH, height)
+        .top_left_of(window)
+        .shift(vec2(STATS_MARGIN, -STATS_MARGIN));
+
+    draw.rect()
+        .xy(panel.xy())
+        .wh(panel.wh())
+        .color(rgba(1.0, 1.0, 1.0, 0.85))
+        .stroke_weight(1.0)
+        .stroke_color(BLACK);
+
+    let inner = panel.pad(STATS_MARGIN);
+
+    for (i, line) in lines.iter().enumerate() {
+        let y = inner.top() - line_height * (i as f32 + 0.5);
+
+        draw.text(line)
+            .x_y(inner.x(), y)
+            .w(inner.w())
+            .left_justify()
+            .color(BLACK);
+    }
+
+    // Bars are scaled to the fullest bin, with sea level marked where it falls in the range.
+
+    let peak = stats.histogram.iter().cloned().max().unwrap_or(1).max(1);
+    let bar_width = inner.w() / stats.histogram.len() as f32;
+
+    for (i, count) in stats.histogram.iter().cloned().enumerate() {
+        let h = histogram_height * count as f32 / peak as f32;
+        let x = inner.left() + bar_width * (i as f32 + 0.5);
+
+        draw.rect()
+            .x_y(x, inner.bottom() + h * 0.5)
+            .w_h(bar_width * 0.8, h)
+            .color(DIMGREY);
+    }
+
+    if stats.min_elevation < 0.0 && stats.max_elevation > 0.0 {
+        let t = -stats.min_elevation / (stats.max_elevation - stats.min_elevation);
+        let x = inner.left() + inner.w() * t;
+
+        draw.line()
+            .start(vec2(x, inner.bottom()))
+            .end(vec2(x, inner.bottom() + histogram_height))
+            .weight(1.0)
+            .color(STEELBLUE);
+    }
 }
 
-fn render_cities(draw: &Draw, terrain: &Terrain, regions: &Regions) {
-    for v in regions.cities.iter() {
-        let p = terrain.graph.vertices[*v];
+/// Draw the material removed by each erosion pass as a sparkline, marking the pass that animation
+/// [step] shows the result of.
+#[cfg(not(target_arch = "wasm32"))]
+fn render_erosion_sparkline(draw: &Draw, window: Rect, stats: &[ErosionPassStats], step: usize) {
+    if stats.is_empty() {
+        return;
+    }
+
+    let panel = Rect::from_w_h(STATS_WIDTH, SPARKLINE_HEIGHT + STATS_MARGIN * 2.0)
+        .top_right_of(window)
+        .shift(vec2(-STATS_MARGIN, -STATS_MARGIN));
+
+    draw.rect()
+        .xy(panel.xy())
+        .wh(panel.wh())
+        .color(rgba(1.0, 1.0, 1.0, 0.85))
+        .stroke_weight(1.0)
+        .stroke_color(BLACK);
+
+    let inner = panel.pad(STATS_MARGIN);
 
+    let peak = stats.iter().map(|s| s.removed).fold(0.0, f32::max);
+    let peak = peak.max(f32::EPSILON);
+
+    let point = |i: usize| {
+        let t = i as f32 / (stats.len() - 1).max(1) as f32;
+        let h = stats[i].removed / peak;
+        vec2(inner.left() + inner.w() * t, inner.bottom() + inner.h() * h)
+    };
+
+    draw.polyline()
+        .weight(1.5)
+        .points((0..stats.len()).map(point))
+        .color(DIMGREY);
+
+    // Step 0 is the uneroded terrain, before the first pass.
+
+    if step > 0 && step <= stats.len() {
         draw.ellipse()
-            .radius(4.0)
-            .xy(p)
-            .color(WHITE)
-            .stroke_weight(2.0)
-            .stroke_color(BLACK);
+            .xy(point(step - 1))
+            .radius(3.0)
+            .color(STEELBLUE);
+
+        draw.text(&format!("removed {:.0}", stats[step - 1].removed))
+            .x_y(inner.x(), inner.top())
+            .w(inner.w())
+            .right_justify()
+            .color(BLACK);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn render_minimap(draw: &Draw, window: Rect, model: &Model) {
+    let extent = model.terrain.extent;
+
+    let center = vec2(
+        window.right() - MINIMAP_MARGIN - MINIMAP_SIZE * 0.5,
+        window.bottom() + MINIMAP_MARGIN + MINIMAP_SIZE * 0.5,
+    );
+
+    let scale = MINIMAP_SIZE / f32::max(extent.w(), extent.h());
+    let to_inset = |p: Vec2| center + (p - extent.xy()) * scale;
+
+    draw.rect()
+        .xy(center)
+        .wh(extent.wh() * scale)
+        .color(SNOW)
+        .stroke_weight(1.0)
+        .stroke_color(BLACK);
+
+    for chain in model.minimap.coastline.iter() {
+        let points = chain.iter().cloned().map(to_inset);
+        draw.polyline().weight(1.0).points(points).color(BLACK);
     }
+
+    let viewport = model.camera.viewport(window);
+
+    draw.rect()
+        .xy(to_inset(viewport.xy()))
+        .wh(viewport.wh() * scale)
+        .no_fill()
+        .stroke_weight(1.5)
+        .stroke_color(RED);
 }
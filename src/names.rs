@@ -0,0 +1,187 @@
+use nannou::rand::rngs::SmallRng;
+use nannou::rand::Rng;
+
+/// A linguistic flavor for generated names.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NameCulture {
+    Norse,
+    Romance,
+    Slavic,
+}
+
+/// How name cultures are assigned to regions.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CultureMode {
+    /// Every region shares a single culture chosen from the seed.
+    PerSeed,
+    /// Each region chooses its own culture.
+    PerRegion,
+}
+
+struct Syllables {
+    /// Capitalized syllables that start a name.
+    starts: &'static [&'static str],
+    /// Vowel syllables joining the start and end of a name.
+    middles: &'static [&'static str],
+    /// Syllables that optionally end a name.
+    ends: &'static [&'static str],
+    /// Suffixes added to settlement names.
+    settlements: &'static [&'static str],
+    /// Prefix and suffix pairs added to river names.
+    rivers: &'static [(&'static str, &'static str)],
+    /// Prefix and suffix pairs added to mountain names.
+    peaks: &'static [(&'static str, &'static str)],
+    /// Prefix and suffix pairs added to region names.
+    regions: &'static [(&'static str, &'static str)],
+    /// Prefix and suffix pairs added to island names.
+    islands: &'static [(&'static str, &'static str)],
+    /// Prefix and suffix pairs added to sea names.
+    seas: &'static [(&'static str, &'static str)],
+    /// Prefix and suffix pairs added to bay names.
+    bays: &'static [(&'static str, &'static str)],
+}
+
+const NORSE: Syllables = Syllables {
+    starts: &[
+        "Bj", "Sk", "Th", "Hal", "Ulf", "Grim", "Rav", "Ey", "Kj", "Sv", "Hr", "Vig",
+    ],
+    middles: &["a", "o", "i", "e", "u", "ey", "au"],
+    ends: &["rn", "nd", "lf", "rd", "k", "gg", "st", "r"],
+    settlements: &["heim", "vik", "by", "stad", "fjord", "holt", ""],
+    rivers: &[("", "elv"), ("", "a"), ("", "strom")],
+    peaks: &[("", "fjell"), ("", "tind"), ("", "berg")],
+    regions: &[("", "mark"), ("", "land"), ("Jarldom of ", "")],
+    islands: &[("", "ey"), ("", "holm"), ("", "oy")],
+    seas: &[("", "havet"), ("", " Sea"), ("Sea of ", "")],
+    bays: &[("", "fjord"), ("", "vag"), ("", " Bay")],
+};
+
+const ROMANCE: Syllables = Syllables {
+    starts: &[
+        "Val", "Mont", "Bel", "Cas", "Sar", "Lor", "Ven", "Mar", "Fel", "Ser", "Cor", "Al",
+    ],
+    middles: &["a", "e", "i", "o", "ia", "io"],
+    ends: &["ra", "na", "lle", "zo", "ne", "ta", "ro", "sa"],
+    settlements: &["", "ia", "ello", "ona", "ville", "mont"],
+    rivers: &[("Rio ", ""), ("", "e"), ("", "ano")],
+    peaks: &[("Monte ", ""), ("Pic ", ""), ("Punta ", "")],
+    regions: &[("Duchy of ", ""), ("", "ia"), ("Principality of ", "")],
+    islands: &[("Isla ", ""), ("Isola ", ""), ("", "ica")],
+    seas: &[("Mare ", ""), ("Mar ", ""), ("Sea of ", "")],
+    bays: &[("Golfo di ", ""), ("Baia ", ""), ("Bay of ", "")],
+};
+
+const SLAVIC: Syllables = Syllables {
+    starts: &[
+        "Vol", "Kras", "Mir", "Zar", "Bor", "Dob", "Grad", "Pol", "Rad", "Sv", "Vys", "Zel",
+    ],
+    middles: &["o", "a", "e", "i", "y"],
+    ends: &["sk", "v", "sl", "gor", "dn", "ch", "m", "n"],
+    settlements: &["grad", "ov", "ice", "sk", "evo", "ka"],
+    rivers: &[("", "a"), ("", "ava"), ("", "ina")],
+    peaks: &[("", "gora"), ("Vrh ", ""), ("", "ski Vrh")],
+    regions: &[("Grand Duchy of ", ""), ("", "ia"), ("Tsardom of ", "")],
+    islands: &[("Ostrov ", ""), ("", "ov Otok"), ("", "ava")],
+    seas: &[("", "sko More"), ("", "ian Sea"), ("Sea of ", "")],
+    bays: &[("", "ski Zaliv"), ("", "ska Guba"), ("Gulf of ", "")],
+};
+
+impl NameCulture {
+    pub const ALL: [NameCulture; 3] = [
+        NameCulture::Norse,
+        NameCulture::Romance,
+        NameCulture::Slavic,
+    ];
+
+    /// Choose a random culture.
+    pub fn random(rand: &mut SmallRng) -> Self {
+        Self::ALL[rand.gen_range(0..Self::ALL.len())]
+    }
+
+    fn syllables(&self) -> &'static Syllables {
+        match self {
+            NameCulture::Norse => &NORSE,
+            NameCulture::Romance => &ROMANCE,
+            NameCulture::Slavic => &SLAVIC,
+        }
+    }
+}
+
+/// Generate the name of a settlement.
+pub fn settlement_name(rand: &mut SmallRng, culture: NameCulture) -> String {
+    let syllables = culture.syllables();
+    let suffix = pick(rand, syllables.settlements);
+
+    format!("{}{}", root(rand, syllables), suffix)
+}
+
+/// Generate the name of a river.
+pub fn river_name(rand: &mut SmallRng, culture: NameCulture) -> String {
+    let syllables = culture.syllables();
+    let (prefix, suffix) = syllables.rivers[rand.gen_range(0..syllables.rivers.len())];
+
+    format!("{}{}{}", prefix, root(rand, syllables), suffix)
+}
+
+/// Generate the name of a mountain peak.
+pub fn peak_name(rand: &mut SmallRng, culture: NameCulture) -> String {
+    let syllables = culture.syllables();
+    let (prefix, suffix) = syllables.peaks[rand.gen_range(0..syllables.peaks.len())];
+
+    format!("{}{}{}", prefix, root(rand, syllables), suffix)
+}
+
+/// Generate the name of an island.
+pub fn island_name(rand: &mut SmallRng, culture: NameCulture) -> String {
+    let syllables = culture.syllables();
+    let (prefix, suffix) = syllables.islands[rand.gen_range(0..syllables.islands.len())];
+
+    format!("{}{}{}", prefix, root(rand, syllables), suffix)
+}
+
+/// Generate the name of a sea.
+pub fn sea_name(rand: &mut SmallRng, culture: NameCulture) -> String {
+    let syllables = culture.syllables();
+    let (prefix, suffix) = syllables.seas[rand.gen_range(0..syllables.seas.len())];
+
+    format!("{}{}{}", prefix, root(rand, syllables), suffix)
+}
+
+/// Generate the name of a bay.
+pub fn bay_name(rand: &mut SmallRng, culture: NameCulture) -> String {
+    let syllables = culture.syllables();
+    let (prefix, suffix) = syllables.bays[rand.gen_range(0..syllables.bays.len())];
+
+    format!("{}{}{}", prefix, root(rand, syllables), suffix)
+}
+
+/// Generate the name of a region. Regions with a titled prefix are named after their [capital],
+/// so the settlement hierarchy is visible in the names.
+pub fn region_name(rand: &mut SmallRng, culture: NameCulture, capital: &str) -> String {
+    let syllables = culture.syllables();
+    let (prefix, suffix) = syllables.regions[rand.gen_range(0..syllables.regions.len())];
+
+    if prefix.is_empty() {
+        format!("{}{}", root(rand, syllables), suffix)
+    } else {
+        format!("{}{}{}", prefix, capital, suffix)
+    }
+}
+
+/// Generate the root of a name from the start, middle and (sometimes) end syllables.
+fn root(rand: &mut SmallRng, syllables: &Syllables) -> String {
+    let mut name = String::new();
+
+    name.push_str(pick(rand, syllables.starts));
+    name.push_str(pick(rand, syllables.middles));
+
+    if rand.gen_bool(0.5) {
+        name.push_str(pick(rand, syllables.ends));
+    }
+
+    name
+}
+
+fn pick(rand: &mut SmallRng, items: &[&'static str]) -> &'static str {
+    items[rand.gen_range(0..items.len())]
+}
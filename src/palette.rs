@@ -0,0 +1,150 @@
+use colorous::Color;
+use nannou::rand::Rng;
+
+use crate::rand::{seeded_rand, RandStream};
+
+/// The rows of the matrix from CIE XYZ to linear sRGB.
+const XYZ_TO_RGB: [[f64; 3]; 3] = [
+    [3.240969941904521, -1.537383177570093, -0.498610760293],
+    [-0.96924363628087, 1.87596750150772, 0.041555057407175],
+    [0.055630079696993, -0.20397695888897, 1.056971514242878],
+];
+
+const REF_U: f64 = 0.19783000664283;
+const REF_V: f64 = 0.46831999493879;
+const KAPPA: f64 = 903.2962962;
+const EPSILON: f64 = 0.0088564516;
+
+/// Generate [n] distinct colors of the same saturation and lightness, with hues evenly spaced
+/// around the HSLuv color wheel from an offset seeded by [seed]. HSLuv keeps the perceived
+/// lightness constant across hues, so no color stands out from the rest. Consecutive colors are
+/// far apart on the wheel, so the first few colors are distinct even when [n] is large.
+pub fn distinct_colors(seed: u64, n: usize, saturation: f64, lightness: f64) -> Vec<Color> {
+    let mut rand = seeded_rand(seed, RandStream::Palette);
+    let offset: f64 = rand.gen_range(0.0..360.0);
+
+    let stride = hue_stride(n);
+
+    (0..n)
+        .map(|i| {
+            let step = (i * stride) % n;
+            let hue = (offset + 360.0 * step as f64 / n as f64) % 360.0;
+
+            hsluv(hue, saturation, lightness)
+        })
+        .collect()
+}
+
+/// The step through [n] evenly spaced hues that visits every hue, jumping roughly the golden
+/// ratio of the wheel each time.
+fn hue_stride(n: usize) -> usize {
+    if n <= 2 {
+        return 1;
+    }
+
+    let target = (n as f64 * 0.382).round() as usize;
+
+    (target.max(1)..n).find(|s| gcd(*s, n) == 1).unwrap_or(1)
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Convert a color from HSLuv, with [hue] in degrees and [saturation] and [lightness] in
+/// \[0, 100\], to sRGB.
+pub fn hsluv(hue: f64, saturation: f64, lightness: f64) -> Color {
+    // HSLuv -> LCh(uv) -> Luv -> XYZ -> linear sRGB -> sRGB, following the reference
+    // implementation at https://www.hsluv.org.
+
+    let chroma = if lightness > 99.9999999 || lightness < 1e-8 {
+        0.0
+    } else {
+        max_chroma(lightness, hue) / 100.0 * saturation
+    };
+
+    let (u, v) = (
+        chroma * hue.to_radians().cos(),
+        chroma * hue.to_radians().sin(),
+    );
+
+    let xyz = luv_to_xyz(lightness, u, v);
+
+    let channel = |row: [f64; 3]| {
+        let c = row[0] * xyz[0] + row[1] * xyz[1] + row[2] * xyz[2];
+
+        let c = if c <= 0.0031308 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+
+        (c.clamp(0.0, 1.0) * 255.0).round() as u8
+    };
+
+    Color {
+        r: channel(XYZ_TO_RGB[0]),
+        g: channel(XYZ_TO_RGB[1]),
+        b: channel(XYZ_TO_RGB[2]),
+    }
+}
+
+/// The largest chroma at [lightness] and [hue] that stays inside the sRGB gamut.
+fn max_chroma(lightness: f64, hue: f64) -> f64 {
+    let (sin, cos) = hue.to_radians().sin_cos();
+
+    gamut_bounds(lightness)
+        .iter()
+        .map(|(slope, intercept)| intercept / (sin - slope * cos))
+        .filter(|length| *length >= 0.0)
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// The lines bounding the sRGB gamut in the uv plane at [lightness], as slopes and intercepts.
+fn gamut_bounds(lightness: f64) -> Vec<(f64, f64)> {
+    let sub1 = (lightness + 16.0).powi(3) / 1560896.0;
+    let sub2 = if sub1 > EPSILON {
+        sub1
+    } else {
+        lightness / KAPPA
+    };
+
+    let mut bounds = vec![];
+
+    for [m1, m2, m3] in XYZ_TO_RGB {
+        for t in [0.0, 1.0] {
+            let top1 = (284517.0 * m1 - 94839.0 * m3) * sub2;
+            let top2 = (838422.0 * m3 + 769860.0 * m2 + 731718.0 * m1) * lightness * sub2
+                - 769860.0 * t * lightness;
+            let bottom = (632260.0 * m3 - 126452.0 * m2) * sub2 + 126452.0 * t;
+
+            bounds.push((top1 / bottom, top2 / bottom));
+        }
+    }
+
+    bounds
+}
+
+fn luv_to_xyz(l: f64, u: f64, v: f64) -> [f64; 3] {
+    if l == 0.0 {
+        return [0.0; 3];
+    }
+
+    let var_u = u / (13.0 * l) + REF_U;
+    let var_v = v / (13.0 * l) + REF_V;
+
+    let y = if l <= 8.0 {
+        l / KAPPA
+    } else {
+        ((l + 16.0) / 116.0).powi(3)
+    };
+
+    let x = -(9.0 * y * var_u) / ((var_u - 4.0) * var_v - var_u * var_v);
+    let z = (9.0 * y - 15.0 * var_v * y - var_v * x) / (3.0 * var_v);
+
+    [x, y, z]
+}
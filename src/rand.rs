@@ -4,8 +4,66 @@ use nannou::rand::prelude::*;
 
 use std::f32::consts::PI;
 
+use crate::util::StableHasher;
+
 const PI2: f32 = PI * 2.0;
 
+/// The independent random streams used by each generation stage. Each stream is derived from
+/// the master seed and its tag, so adding, removing or reordering the draws of one stage does
+/// not change the randomness of any other stage.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RandStream {
+    Points,
+    Features,
+    Shading,
+    Decoration,
+    Names,
+    Palette,
+    Vegetation,
+    PointsOfInterest,
+    Geology,
+    Coastline,
+    Refinement,
+    Depressions,
+}
+
+impl RandStream {
+    fn tag(&self) -> &'static str {
+        match self {
+            RandStream::Points => "points",
+            RandStream::Features => "features",
+            RandStream::Shading => "shading",
+            RandStream::Decoration => "decoration",
+            RandStream::Names => "names",
+            RandStream::Palette => "palette",
+            RandStream::Vegetation => "vegetation",
+            RandStream::PointsOfInterest => "points-of-interest",
+            RandStream::Geology => "geology",
+            RandStream::Coastline => "coastline",
+            RandStream::Refinement => "refinement",
+            RandStream::Depressions => "depressions",
+        }
+    }
+}
+
+/// Returns an RNG for the [stream] derived from the master [seed].
+pub fn seeded_rand(seed: u64, stream: RandStream) -> SmallRng {
+    // FNV-1a over the tag bytes, mixed with the seed through splitmix64. Both are fixed
+    // algorithms, unlike std's DefaultHasher, so streams are stable across Rust versions.
+
+    let mut hasher = StableHasher::new();
+    hasher.write_bytes(stream.tag().as_bytes());
+
+    SmallRng::seed_from_u64(splitmix64(seed ^ hasher.finish()))
+}
+
+fn splitmix64(n: u64) -> u64 {
+    let mut z = n.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
 /// Returns a random unit vector.
 pub fn random_dir(rand: &mut SmallRng) -> Vec2 {
     let t = rand.gen_range(0.0..PI2);
@@ -1,55 +1,598 @@
+pub mod borders;
+pub mod economy;
+pub mod points_of_interest;
+pub mod region_summary;
+pub mod travel_matrix;
+
+pub use borders::*;
+pub use economy::*;
+pub use points_of_interest::*;
+pub use region_summary::*;
+pub use travel_matrix::*;
+
+use std::collections::HashMap;
+
 use itertools::Itertools;
 use nannou::glam::Vec2;
+use nannou::rand::rngs::SmallRng;
 
-use ordered_float::OrderedFloat;
-
-use crate::terrain::{Terrain, VertexType};
-use crate::util::{map_clamp, normalize, PriorityQueue};
+use crate::names::*;
+use crate::rand::{seeded_rand, RandStream};
+use crate::terrain::{Terrain, TerrainSurface, VertexType};
+use crate::util::{map_clamp, DefaultTravelCost, PriorityQueue, StableHasher, TravelCost};
 
 pub struct Regions {
     /// The normalized habitability of each terrain vertex.
     pub habitability: Vec<f32>,
-    /// The vertex index of each city.
+    /// The vertex index of each city. The first [num_regions] cities are the founders that the
+    /// regions grew from; the rest are towns inside those regions.
     pub cities: Vec<usize>,
-    /// The city index of each vertex.
+    /// The rank of each city, parallel to cities. The founder of each region is its capital.
+    pub tiers: Vec<SettlementTier>,
+    /// The trades each city is known for, strongest first, parallel to cities.
+    pub economies: Vec<Vec<Economy>>,
+    /// The index of the region containing each city, parallel to cities.
+    pub city_regions: Vec<usize>,
+    /// The number of regions.
+    pub num_regions: usize,
+    /// The travel cost between each pair of cities, in the order of cities.
+    pub travel: TravelMatrix,
+    /// The founding city vertex of the region containing each vertex, or usize::MAX if it is in
+    /// none.
     pub regions: Vec<usize>,
+    /// The region of each land cell, as the region of most of its vertices, unless the border
+    /// was moved onto a natural feature nearby.
+    pub cells: Vec<Option<usize>>,
+    /// The regions bordering each region over land, in order of region index.
+    pub adjacency: Vec<Vec<usize>>,
+    /// The length of the border shared with each neighbor, parallel to adjacency.
+    pub border_lengths: Vec<Vec<f32>>,
+    /// The name culture of each region, parallel to the founders.
+    pub cultures: Vec<NameCulture>,
+    /// The name of each city, parallel to cities.
+    pub city_names: Vec<String>,
+    /// The name of each region, parallel to the founders.
+    pub region_names: Vec<String>,
+    /// The names of the major rivers, parallel to the terrain rivers. Minor rivers are unnamed.
+    pub river_names: Vec<Option<String>>,
+    /// The names of the tallest peaks, parallel to the terrain peaks. Lesser peaks are unnamed.
+    pub peak_names: Vec<Option<String>>,
+    /// The ruins, towers and caves scattered across the land.
+    pub pois: Vec<PointOfInterest>,
+    /// The names of the larger islands, parallel to the terrain islands. Small islands are
+    /// unnamed.
+    pub island_names: Vec<Option<String>>,
+    /// The names of the larger seas, parallel to the terrain open water. Small stretches of
+    /// open water are unnamed.
+    pub sea_names: Vec<Option<String>>,
+    /// The name of each bay, parallel to the terrain bays.
+    pub bay_names: Vec<String>,
+}
+
+/// The rank of a city in the settlement hierarchy.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SettlementTier {
+    /// The seat of a region, the city it grew from.
+    Capital,
+    Town,
+}
+
+/// How the habitability that cities are placed by is scored and normalized.
+#[derive(Debug, Clone, Copy)]
+pub struct HabitabilityConfig {
+    /// The low and high percentiles of the land habitability that map to 0 and 1. Each vertex
+    /// counts by the area of its triangle, so the percentiles are shares of the land area rather
+    /// than of the vertices. Scores above the high percentile are clipped, so a few outliers
+    /// don't compress the rest of the range towards zero.
+    pub clip: (f32, f32),
+    /// The power of the flatness of the ground the score is scaled by. Zero ignores the slope.
+    pub flatness: f32,
+}
+
+impl Default for HabitabilityConfig {
+    fn default() -> Self {
+        Self {
+            clip: (0.0, 0.98),
+            flatness: 1.0,
+        }
+    }
 }
 
+/// The minimum mean flux of a river to be given a name.
+const RIVER_NAME_FLUX: f32 = 0.015;
+/// The number of peaks, in order of prominence, that are given a name.
+const NAMED_PEAKS: usize = 3;
+/// The minimum area of an island to be given a name.
+const ISLAND_NAME_AREA: f32 = 5000.0;
+/// The number of open water circles, from largest to smallest, that are named as seas.
+const NAMED_SEAS: usize = 3;
+/// The minimum radius of an open water circle to be named as a sea.
+const SEA_NAME_RADIUS: f32 = 60.0;
+/// The distance from the edge of the terrain extent over which habitability fades to zero.
+const HABITABILITY_EDGE_FALLOFF: f32 = 100.0;
+
 impl Regions {
     pub fn new(terrain: &Terrain) -> Self {
+        Self::with_travel_cost(terrain, &DefaultTravelCost::default())
+    }
+
+    /// Generate the regions, growing them from their founding cities by the [cost] of travel.
+    pub fn with_travel_cost<C: TravelCost>(terrain: &Terrain, cost: &C) -> Self {
+        if !terrain.config.layers.regions {
+            return Self::empty(terrain);
+        }
+
         let habitability = generate_habitability(terrain);
+        let count = terrain.config.num_cities as usize;
+        let cities = place_cities(terrain, &habitability, vec![], count);
 
-        let mut scores = habitability.clone();
-        let mut cities = vec![];
+        Self::from_cities(terrain, habitability, cities, cost)
+    }
 
-        for _ in 0..terrain.config.num_cities {
-            let city_index = scores.iter().cloned().map(OrderedFloat).position_max();
-            let city_index = city_index.unwrap_or(0);
+    /// Generate the regions with a city at the habitable vertex nearest to each of the [locked]
+    /// positions, and place the rest of the cities by habitability around them. Locking the
+    /// positions of cities keeps them in place when the terrain is regenerated.
+    pub fn with_locked<C: TravelCost>(terrain: &Terrain, locked: &[Vec2], cost: &C) -> Self {
+        if !terrain.config.layers.regions {
+            return Self::empty(terrain);
+        }
 
-            let city_point = terrain.graph.vertices[city_index];
+        let habitability = generate_habitability(terrain);
 
-            // modify the score array based on the new city position
+        let mut fixed = vec![];
 
-            for (i, score) in scores.iter_mut().enumerate() {
-                let dist = terrain.graph.vertices[i].distance(city_point);
-                *score *= map_clamp(dist, 0.0, 100.0, 0.0, 1.0);
+        for position in locked.iter() {
+            if let Some(v) = nearest_habitable_vertex(terrain, &habitability, *position) {
+                if !fixed.contains(&v) {
+                    fixed.push(v);
+                }
             }
+        }
+
+        let count = (terrain.config.num_cities as usize).max(fixed.len());
+        let cities = place_cities(terrain, &habitability, fixed, count);
+
+        Self::from_cities(terrain, habitability, cities, cost)
+    }
+
+    /// Generate the regions around the given [cities] (as vertex indices), rather than placing
+    /// the cities by habitability. The order of the cities decides the founders, as for
+    /// [Regions::with_travel_cost].
+    pub fn with_cities<C: TravelCost>(terrain: &Terrain, cities: &[usize], cost: &C) -> Self {
+        if !terrain.config.layers.regions {
+            return Self::empty(terrain);
+        }
+
+        let habitability = generate_habitability(terrain);
+
+        Self::from_cities(terrain, habitability, cities.to_vec(), cost)
+    }
+
+    /// No cities or regions, with every name left empty, for when the regions layer is off or no
+    /// city could be placed.
+    pub fn empty(terrain: &Terrain) -> Self {
+        let mesh = &terrain.mesh;
+
+        Self {
+            habitability: vec![0.0; terrain.graph.vertices.len()],
+            cities: vec![],
+            tiers: vec![],
+            economies: vec![],
+            city_regions: vec![],
+            num_regions: 0,
+            travel: TravelMatrix {
+                cities: vec![],
+                costs: vec![],
+            },
+            regions: vec![usize::MAX; terrain.graph.vertices.len()],
+            cells: vec![None; terrain.graph.points.len()],
+            adjacency: vec![],
+            border_lengths: vec![],
+            cultures: vec![],
+            city_names: vec![],
+            region_names: vec![],
+            river_names: vec![None; mesh.rivers.len()],
+            peak_names: vec![None; mesh.peaks.len()],
+            pois: vec![],
+            island_names: vec![None; mesh.islands.len()],
+            sea_names: vec![None; mesh.open_water.len()],
+            bay_names: vec![],
+        }
+    }
 
-            cities.push(city_index);
+    fn from_cities<C: TravelCost>(
+        terrain: &Terrain,
+        habitability: Vec<f32>,
+        cities: Vec<usize>,
+        cost: &C,
+    ) -> Self {
+        // No vertex may be habitable enough for a city, eg on a map that is all sea.
+
+        if cities.is_empty() {
+            return Self {
+                habitability,
+                ..Self::empty(terrain)
+            };
         }
 
-        let regions = generate_regions(terrain, &cities);
+        let num_regions = usize::min(terrain.config.num_regions.max(1) as usize, cities.len());
+
+        // The most habitable city founds the first region, and the other founders are the cities
+        // farthest from the founders before them, so the capitals are well separated. The
+        // founders are moved to the front of the cities.
+
+        let travel = TravelMatrix::new(&terrain.graph, &terrain.data, &cities, cost);
+        let picked = travel.farthest_cities(num_regions);
+
+        let order: Vec<usize> = picked
+            .iter()
+            .cloned()
+            .chain((0..cities.len()).filter(|i| !picked.contains(i)))
+            .collect();
+
+        let travel = travel.permute(&order);
+        let cities = travel.cities.clone();
+        let founders = &cities[..num_regions];
+
+        let regions = generate_regions(terrain, founders, cost);
+
+        let mut cells = cell_regions(terrain, &regions, founders);
+
+        if terrain.config.borders.snap {
+            snap_borders(terrain, &mut cells, &terrain.config.borders);
+        }
+
+        let (adjacency, border_lengths) = region_adjacency(terrain, &cells, num_regions);
+
+        let tiers = (0..cities.len())
+            .map(|i| {
+                if i < num_regions {
+                    SettlementTier::Capital
+                } else {
+                    SettlementTier::Town
+                }
+            })
+            .collect();
+
+        let economies = generate_economies(terrain, &cities);
+
+        let city_regions = cities
+            .iter()
+            .map(|c| founders.iter().position(|f| *f == regions[*c]).unwrap_or(0))
+            .collect();
+        let pois = generate_points_of_interest(terrain, &cities);
+
+        let mut rand = seeded_rand(terrain.config.seed, RandStream::Names);
+
+        let cultures = generate_cultures(&mut rand, terrain.config.cultures, num_regions);
+
+        // The culture of the region containing a vertex.
+        let culture_of = |v: usize| {
+            let region = founders.iter().position(|c| *c == regions[v]);
+            region.map(|r| cultures[r]).unwrap_or(NameCulture::Norse)
+        };
+
+        let mut city_names = vec![];
+        let mut region_names = vec![];
+
+        for culture in cultures.iter().cloned() {
+            let city_name = settlement_name(&mut rand, culture);
+            region_names.push(region_name(&mut rand, culture, &city_name));
+            city_names.push(city_name);
+        }
+
+        for town in cities[num_regions..].iter() {
+            city_names.push(settlement_name(&mut rand, culture_of(*town)));
+        }
+
+        let river_names = generate_river_names(&mut rand, terrain, culture_of);
+
+        let peak_names = terrain
+            .mesh
+            .peaks
+            .iter()
+            .enumerate()
+            .map(|(i, peak)| {
+                if i >= NAMED_PEAKS {
+                    return None;
+                }
+
+                Some(peak_name(&mut rand, culture_of(peak.vertex)))
+            })
+            .collect();
+
+        // Islands take the culture of the region around their highest point.
+
+        let island_names = terrain
+            .mesh
+            .islands
+            .iter()
+            .map(|island| {
+                if island.area < ISLAND_NAME_AREA {
+                    return None;
+                }
+
+                let vertex = match island.peak {
+                    Some(peak) => terrain.mesh.peaks[peak].vertex,
+                    None => terrain.graph.cell(island.polygons[0])[0],
+                };
+
+                Some(island_name(&mut rand, culture_of(vertex)))
+            })
+            .collect();
+
+        // Seas and bays take the culture of the nearest city.
+
+        let nearest_culture = |p: Vec2| {
+            let nearest = cities.iter().min_by(|a, b| {
+                let da = terrain.graph.vertices[**a].distance_squared(p);
+                let db = terrain.graph.vertices[**b].distance_squared(p);
+                da.total_cmp(&db)
+            });
+
+            nearest.map_or(NameCulture::Norse, |c| culture_of(*c))
+        };
+
+        let sea_names = terrain
+            .mesh
+            .open_water
+            .iter()
+            .enumerate()
+            .map(|(i, water)| {
+                if i >= NAMED_SEAS || water.radius < SEA_NAME_RADIUS {
+                    return None;
+                }
+
+                Some(sea_name(&mut rand, nearest_culture(water.center)))
+            })
+            .collect();
+
+        let bay_names = terrain
+            .mesh
+            .bays
+            .iter()
+            .map(|bay| bay_name(&mut rand, nearest_culture(bay.center)))
+            .collect();
 
         Self {
             habitability,
             cities,
+            tiers,
+            economies,
+            city_regions,
+            num_regions,
+            travel,
             regions,
+            cells,
+            adjacency,
+            border_lengths,
+            cultures,
+            city_names,
+            region_names,
+            river_names,
+            peak_names,
+            pois,
+            island_names,
+            sea_names,
+            bay_names,
+        }
+    }
+}
+
+impl Regions {
+    /// The vertex indices of the founding city of each region.
+    pub fn founders(&self) -> &[usize] {
+        &self.cities[..self.num_regions]
+    }
+
+    /// True if city [i] is the capital of its region.
+    pub fn is_capital(&self, i: usize) -> bool {
+        self.tiers[i] == SettlementTier::Capital
+    }
+
+    /// The vertex indices of the cities on the island [island].
+    pub fn island_cities(&self, terrain: &Terrain, island: usize) -> Vec<usize> {
+        self.cities
+            .iter()
+            .cloned()
+            .filter(|c| terrain.mesh.vertex_island(&terrain.graph, *c) == Some(island))
+            .collect()
+    }
+
+    /// The land vertex nearest to [position] that is habitable at all, if there is one.
+    pub fn nearest_habitable(&self, terrain: &Terrain, position: Vec2) -> Option<usize> {
+        nearest_habitable_vertex(terrain, &self.habitability, position)
+    }
+
+    /// The index of the region containing the vertex [v].
+    pub fn region_of(&self, v: usize) -> Option<usize> {
+        self.founders().iter().position(|c| *c == self.regions[v])
+    }
+
+    /// A hash of the cities and the regions they belong to, to go with [Terrain::content_hash].
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+
+        hasher.write_usize(self.cities.len());
+
+        for (city, region) in self.cities.iter().zip(self.city_regions.iter()) {
+            hasher.write_usize(*city);
+            hasher.write_usize(*region);
+        }
+
+        hasher.write_usize(self.cells.len());
+
+        for cell in self.cells.iter() {
+            hasher.write_usize(cell.map_or(0, |r| r + 1));
+        }
+
+        hasher.finish()
+    }
+}
+
+/// The region of each land cell, as the region of most of its vertices.
+fn cell_regions(terrain: &Terrain, regions: &[usize], founders: &[usize]) -> Vec<Option<usize>> {
+    let graph = &terrain.graph;
+
+    (0..graph.points.len())
+        .map(|p| {
+            if terrain.mesh.surface[p] != TerrainSurface::Land {
+                return None;
+            }
+
+            let counts = graph
+                .cell(p)
+                .iter()
+                .filter_map(|v| founders.iter().position(|c| *c == regions[*v]))
+                .counts();
+            counts
+                .into_iter()
+                .max_by_key(|(r, n)| (*n, *r))
+                .map(|(r, _)| r)
+        })
+        .collect()
+}
+
+/// The neighbors of each region, and the length of the border shared with each, from the edges
+/// between land cells of different regions.
+fn region_adjacency(
+    terrain: &Terrain,
+    cells: &[Option<usize>],
+    num_regions: usize,
+) -> (Vec<Vec<usize>>, Vec<Vec<f32>>) {
+    let graph = &terrain.graph;
+
+    let mut lengths: HashMap<(usize, usize), f32> = HashMap::new();
+
+    for edge in graph.edges.iter() {
+        if let (Some(a), Some(b)) = (cells[edge.points.0], cells[edge.points.1]) {
+            if a != b {
+                let length = graph.distance(edge.vertices.0, edge.vertices.1);
+                *lengths.entry((a.min(b), a.max(b))).or_default() += length;
+            }
+        }
+    }
+
+    let mut adjacency = vec![vec![]; num_regions];
+    let mut border_lengths = vec![vec![]; num_regions];
+
+    // Visiting the pairs in order leaves each list of neighbors in order of region index.
+
+    let mut pairs: Vec<_> = lengths.into_iter().collect();
+    pairs.sort_by_key(|(key, _)| *key);
+
+    for ((a, b), length) in pairs {
+        adjacency[a].push(b);
+        border_lengths[a].push(length);
+        adjacency[b].push(a);
+        border_lengths[b].push(length);
+    }
+
+    (adjacency, border_lengths)
+}
+
+/// Place cities at the most habitable vertices that are at least the city spacing apart, until
+/// there are [count] cities. The [fixed] cities are kept and placed first.
+fn place_cities(
+    terrain: &Terrain,
+    habitability: &[f32],
+    fixed: Vec<usize>,
+    count: usize,
+) -> Vec<usize> {
+    let spacing = terrain.config.city_spacing;
+    let mut cities = fixed;
+
+    // Greedily take the most habitable vertices that are at least the spacing from every city
+    // placed so far. If there is no room left, there are fewer cities than asked for.
+
+    let mut candidates: Vec<usize> = (0..habitability.len())
+        .filter(|v| habitability[*v] > 0.0)
+        .collect();
+
+    candidates.sort_by(|a, b| habitability[*b].total_cmp(&habitability[*a]));
+
+    for v in candidates {
+        if cities.len() >= count {
+            break;
+        }
+
+        if cities
+            .iter()
+            .all(|c| terrain.graph.distance(*c, v) >= spacing)
+        {
+            cities.push(v);
         }
     }
+
+    cities
+}
+
+/// The land vertex nearest to [position] with any [habitability], if there is one.
+fn nearest_habitable_vertex(
+    terrain: &Terrain,
+    habitability: &[f32],
+    position: Vec2,
+) -> Option<usize> {
+    let graph = &terrain.graph;
+
+    graph
+        .interior
+        .iter()
+        .cloned()
+        .filter(|v| terrain.data.elevation[*v] >= 0.0 && habitability[*v] > 0.0)
+        .min_by(|a, b| {
+            let da = graph.vertices[*a].distance(position);
+            let db = graph.vertices[*b].distance(position);
+            da.total_cmp(&db)
+        })
+}
+
+fn generate_cultures(rand: &mut SmallRng, mode: CultureMode, count: usize) -> Vec<NameCulture> {
+    match mode {
+        CultureMode::PerSeed => vec![NameCulture::random(rand); count],
+        CultureMode::PerRegion => (0..count).map(|_| NameCulture::random(rand)).collect(),
+    }
+}
+
+/// Name the major rivers using the culture of the region containing their mouth.
+fn generate_river_names<F: Fn(usize) -> NameCulture>(
+    rand: &mut SmallRng,
+    terrain: &Terrain,
+    culture_of: F,
+) -> Vec<Option<String>> {
+    let mut names = vec![];
+
+    for river in terrain.mesh.rivers.iter() {
+        let mouth = match river.points.last() {
+            Some(p) if river.flux >= RIVER_NAME_FLUX => *p,
+            _ => {
+                names.push(None);
+                continue;
+            }
+        };
+
+        let vertex = terrain
+            .graph
+            .vertices
+            .iter()
+            .map(|v| v.distance_squared(mouth))
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+
+        names.push(Some(river_name(rand, culture_of(vertex))));
+    }
+
+    names
 }
 
 fn generate_habitability(terrain: &Terrain) -> Vec<f32> {
+    let config = &terrain.config.habitability;
+
     let mut score = vec![0.0; terrain.graph.vertices.len()];
+    // The score and area of each habitable land vertex.
+    let mut land = vec![];
 
     for (i, s) in score.iter_mut().enumerate() {
         if terrain.graph.vertex_type[i] == VertexType::Boundary {
@@ -60,35 +603,105 @@ fn generate_habitability(terrain: &Terrain) -> Vec<f32> {
             continue; // leave below-sea-level vertices at 0 city score
         }
 
+        if terrain.graph.is_seam_copy(i) {
+            continue; // leave copies across the seam of a wrapping map at 0 city score
+        }
+
         let mut score = map_clamp(terrain.data.flux[i], 0.0, 0.05, 0.0, 1.0);
 
-        // Scale the score towards zero near the edge (and outside) of the terrain extent.
+        // Steep ground is harder to build on.
 
-        let vertex = terrain.graph.vertices[i];
-        let extent = terrain.extent;
+        let flatness = terrain.data.normal[i].z.max(0.0);
+        score *= flatness.powf(config.flatness);
 
-        let dist_x_edge = f32::min(vertex.x - extent.x.start, extent.x.end - vertex.x);
-        let dist_y_edge = f32::min(vertex.y - extent.x.start, extent.y.end - vertex.y);
+        // Scale the score towards zero near the edge (and outside) of the terrain extent.
 
-        score *= map_clamp(dist_x_edge, 0.0, 100.0, 0.0, 1.0);
-        score *= map_clamp(dist_y_edge, 0.0, 100.0, 0.0, 1.0);
+        score *= terrain.graph.edge_falloff(i, HABITABILITY_EDGE_FALLOFF);
 
         *s = score;
+        land.push((score, vertex_area(terrain, i)));
     }
 
-    normalize(&mut score);
+    // Normalize between the area-weighted percentiles of the land, leaving the uninhabitable
+    // vertices at zero.
+
+    land.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let min = weighted_percentile(&land, config.clip.0);
+    let max = weighted_percentile(&land, config.clip.1);
+
+    for s in score.iter_mut().filter(|s| **s > 0.0) {
+        *s = if max > min {
+            map_clamp(*s, min, max, 0.0, 1.0).max(f32::EPSILON)
+        } else {
+            1.0
+        };
+    }
 
     score
 }
 
+/// The area of the triangle around vertex [v].
+fn vertex_area(terrain: &Terrain, v: usize) -> f32 {
+    let graph = &terrain.graph;
+    let (p, q, r) = graph.vertex_points(v);
+
+    let a = graph.offset_to_point(v, p);
+    let b = graph.offset_to_point(v, q);
+    let c = graph.offset_to_point(v, r);
+
+    (b - a).perp_dot(c - a).abs() * 0.5
+}
+
+/// The value below which the fraction [q] of the total weight of the (value, weight) pairs lies.
+/// The pairs must be sorted by value.
+fn weighted_percentile(sorted: &[(f32, f32)], q: f32) -> f32 {
+    let total: f32 = sorted.iter().map(|(_, w)| w).sum();
+    let target = total * q.clamp(0.0, 1.0);
+
+    let mut sum = 0.0;
+
+    for (value, weight) in sorted.iter() {
+        sum += weight;
+
+        if sum >= target {
+            return *value;
+        }
+    }
+
+    sorted.last().map_or(0.0, |(value, _)| *value)
+}
+
 #[derive(Eq, PartialEq)]
 struct RegionQueueValue {
     city: usize,
     vert: usize,
 }
 
-fn generate_regions(terrain: &Terrain, cities: &[usize]) -> Vec<usize> {
+fn generate_regions<C: TravelCost>(terrain: &Terrain, cities: &[usize], cost: &C) -> Vec<usize> {
+    grow_regions(terrain, cities, cost).0
+}
+
+/// The cost each vertex was claimed at as the regions grew from their founding [cities] by the
+/// [cost] of travel: the direct cost from the founder that claimed it. The regions grow into the
+/// cheapest vertices first, so bands of this cost show the order they were claimed in.
+pub fn region_growth_cost<C: TravelCost>(
+    terrain: &Terrain,
+    cities: &[usize],
+    cost: &C,
+) -> Vec<f32> {
+    grow_regions(terrain, cities, cost).1
+}
+
+/// Grow the regions from their founding [cities], returning the founder of each vertex and the
+/// cost it was claimed at.
+fn grow_regions<C: TravelCost>(
+    terrain: &Terrain,
+    cities: &[usize],
+    cost: &C,
+) -> (Vec<usize>, Vec<f32>) {
     let mut nearest_city = vec![None; terrain.graph.vertices.len()];
+    let mut claim_cost = vec![f32::INFINITY; terrain.graph.vertices.len()];
 
     let mut queue = PriorityQueue::new();
 
@@ -99,10 +712,12 @@ fn generate_regions(terrain: &Terrain, cities: &[usize]) -> Vec<usize> {
 
     for city in cities.iter().cloned() {
         nearest_city[city] = Some(city);
+        claim_cost[city] = 0.0;
+
         for vert in terrain.graph.connected_vertices(city) {
             queue.push(
                 RegionQueueValue { city, vert },
-                -calculate_travel_cost(&terrain, city, vert),
+                -cost.cost(&terrain.graph, &terrain.data, city, vert),
             );
         }
     }
@@ -113,53 +728,39 @@ fn generate_regions(terrain: &Terrain, cities: &[usize]) -> Vec<usize> {
         }
 
         nearest_city[vert] = Some(city);
+        claim_cost[vert] = cost.cost(&terrain.graph, &terrain.data, city, vert);
 
         for vert in terrain.graph.connected_vertices(vert) {
             queue.push(
                 RegionQueueValue { city, vert },
-                -calculate_travel_cost(&terrain, city, vert),
+                -cost.cost(&terrain.graph, &terrain.data, city, vert),
             );
         }
     }
 
-    let mut region = vec![0; nearest_city.len()];
+    // Vertices that no city can reach, eg with no cities at all, are in no region, as for
+    // [Regions::empty].
 
-    for (i, option_city) in nearest_city.iter().enumerate() {
-        region[i] = option_city.unwrap();
-    }
+    let region = nearest_city
+        .iter()
+        .map(|city| city.unwrap_or(usize::MAX))
+        .collect();
 
-    region
+    (region, claim_cost)
 }
 
-fn calculate_travel_cost(terrain: &Terrain, a: usize, b: usize) -> f32 {
-    let pos_a = terrain.graph.vertices[a];
-    let pos_b = terrain.graph.vertices[b];
-    let delta_pos = Vec2::distance(pos_a, pos_b);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::small_test_terrain;
 
-    let elev_a = terrain.data.elevation[a];
-    let elev_b = terrain.data.elevation[b];
-
-    // small cost for traversing water
-    if elev_a < 0.0 {
-        return delta_pos * 100.0;
-    }
+    #[test]
+    fn no_cities_gives_empty_regions() {
+        let terrain = small_test_terrain();
+        let regions = Regions::with_cities(&terrain, &[], &DefaultTravelCost::default());
 
-    // large cost for transitioning from land to water
-    if (elev_a >= 0.0) != (elev_b >= 0.0) {
-        return delta_pos * 1000.0;
+        assert!(regions.cities.is_empty());
+        assert_eq!(regions.num_regions, 0);
+        assert!(regions.cells.iter().all(|c| c.is_none()));
     }
-
-    let delta_elev = elev_b - elev_a;
-
-    // uphill is less expensive than downhill (regions end on ridges)
-    let delta_elev = if delta_elev > 0.0 {
-        delta_elev / 10.0
-    } else {
-        delta_elev
-    };
-
-    let cost_elev = 0.25 * (delta_elev / delta_pos).powf(2.0);
-    let cost_river = 100.0 * terrain.data.flux[a].sqrt();
-
-    delta_pos * (1.0 + cost_elev + cost_river)
 }
@@ -0,0 +1,224 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::terrain::{Terrain, TerrainData, TerrainGraph, TerrainSurface};
+use crate::util::{chain_edges, is_closed_chain, map_clamp, shortest_path_within, PriorityQueue};
+
+/// How the borders between regions are drawn towards natural features.
+#[derive(Debug, Clone, Copy)]
+pub struct BorderConfig {
+    /// Move the borders onto nearby rivers and ridgelines.
+    pub snap: bool,
+    /// How far a border may move from where the regions met, in world units.
+    pub tolerance: f32,
+    /// How much cheaper it is for a border to follow the largest rivers than open land.
+    pub river_weight: f32,
+    /// How much cheaper it is for a border to follow the sharpest ridges than open land.
+    pub ridge_weight: f32,
+}
+
+impl Default for BorderConfig {
+    fn default() -> Self {
+        Self {
+            snap: false,
+            tolerance: 40.0,
+            river_weight: 4.0,
+            ridge_weight: 2.0,
+        }
+    }
+}
+
+/// The flux of a river that a border is drawn to most strongly.
+const BORDER_RIVER_FLUX: f32 = 0.02;
+/// The height of a vertex above the mean of its neighbors that makes it the sharpest ridge.
+const BORDER_RIDGE_RELIEF: f32 = 20.0;
+
+/// Move each border between two regions onto the cheapest path between its ends that stays
+/// within the tolerance of the original border, where following rivers and ridgelines is cheap.
+/// The land cells between the old and new borders change hands to match. Borders that close on
+/// themselves, around an enclave, are left as they are.
+pub fn snap_borders(terrain: &Terrain, cells: &mut [Option<usize>], config: &BorderConfig) {
+    let graph = &terrain.graph;
+    let data = &terrain.data;
+
+    let attraction = feature_attraction(graph, data, config);
+
+    let cost = |graph: &TerrainGraph, _: &TerrainData, a: usize, b: usize| {
+        let pull = (attraction[a] + attraction[b]) * 0.5;
+        graph.distance(a, b) / (1.0 + pull)
+    };
+
+    for ((a, b), edges) in border_edges(graph, cells) {
+        for chain in chain_edges(&edges) {
+            if is_closed_chain(&chain) {
+                continue;
+            }
+
+            let corridor = corridor(graph, &chain, config.tolerance);
+
+            // The border may only pass between the cells of the two regions, so it can't cut
+            // through a third region or run along the coast.
+
+            let allowed = |v: usize| {
+                if !corridor.contains(&v) {
+                    return false;
+                }
+
+                let (p, q, r) = graph.vertex_points(v);
+
+                [p, q, r]
+                    .iter()
+                    .all(|c| cells[*c] == Some(a) || cells[*c] == Some(b))
+            };
+
+            let (from, to) = (chain[0], chain[chain.len() - 1]);
+
+            let path = match shortest_path_within(graph, data, cost, from, to, allowed) {
+                Some(path) => path,
+                None => continue,
+            };
+
+            reassign_cells(terrain, cells, (a, b), &corridor, &path.vertices);
+        }
+    }
+}
+
+/// How strongly each vertex draws a border towards it, from its river flux and how far it stands
+/// above its neighbors.
+fn feature_attraction(graph: &TerrainGraph, data: &TerrainData, config: &BorderConfig) -> Vec<f32> {
+    (0..graph.vertices.len())
+        .map(|v| {
+            let river = map_clamp(data.flux[v], 0.0, BORDER_RIVER_FLUX, 0.0, 1.0);
+
+            let (sum, count) = graph
+                .connected_vertices(v)
+                .fold((0.0, 0), |(sum, count), n| {
+                    (sum + data.elevation[n], count + 1)
+                });
+
+            let ridge = if count > 0 && data.elevation[v] >= 0.0 {
+                let relief = data.elevation[v] - sum / count as f32;
+                map_clamp(relief, 0.0, BORDER_RIDGE_RELIEF, 0.0, 1.0)
+            } else {
+                0.0
+            };
+
+            river * config.river_weight + ridge * config.ridge_weight
+        })
+        .collect()
+}
+
+/// The vertex edges between the land cells of each pair of regions, keyed by the pair with the
+/// lower region first.
+fn border_edges(
+    graph: &TerrainGraph,
+    cells: &[Option<usize>],
+) -> Vec<((usize, usize), Vec<(usize, usize)>)> {
+    let mut borders: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+
+    for edge in graph.edges.iter() {
+        if let (Some(a), Some(b)) = (cells[edge.points.0], cells[edge.points.1]) {
+            if a != b {
+                let key = (a.min(b), a.max(b));
+                borders.entry(key).or_default().push(edge.vertices);
+            }
+        }
+    }
+
+    let mut borders: Vec<_> = borders.into_iter().collect();
+    borders.sort_by_key(|(key, _)| *key);
+
+    borders
+}
+
+/// The vertices within [tolerance] of the [chain], measured along the graph.
+fn corridor(graph: &TerrainGraph, chain: &[usize], tolerance: f32) -> HashSet<usize> {
+    let mut distance: HashMap<usize, f32> = HashMap::new();
+    let mut open = PriorityQueue::new();
+
+    for v in chain.iter().cloned() {
+        distance.insert(v, 0.0);
+        open.push(v, 0.0);
+    }
+
+    let mut corridor = HashSet::new();
+
+    while let Some(v) = open.pop() {
+        if !corridor.insert(v) {
+            continue; // stale queue entry
+        }
+
+        for n in graph.connected_vertices(v) {
+            let d = distance[&v] + graph.distance(v, n);
+
+            if d <= tolerance && distance.get(&n).map_or(true, |e| d < *e) {
+                distance.insert(n, d);
+                open.push(n, -d);
+            }
+        }
+    }
+
+    corridor
+}
+
+/// Give the cells of the two [regions] inside the [corridor] to whichever side of the new border
+/// [path] they are on, by flooding each region from its cells outside the corridor without
+/// crossing the path.
+fn reassign_cells(
+    terrain: &Terrain,
+    cells: &mut [Option<usize>],
+    regions: (usize, usize),
+    corridor: &HashSet<usize>,
+    path: &[usize],
+) {
+    let graph = &terrain.graph;
+
+    let blocked: HashSet<(usize, usize)> = path
+        .windows(2)
+        .map(|w| (w[0].min(w[1]), w[0].max(w[1])))
+        .collect();
+
+    let in_play = |p: usize| cells[p] == Some(regions.0) || cells[p] == Some(regions.1);
+    let in_corridor = |p: usize| graph.cell(p).iter().any(|v| corridor.contains(v));
+
+    let mut neighbors: HashMap<usize, Vec<usize>> = HashMap::new();
+
+    for edge in graph.edges.iter() {
+        let (p, q) = edge.points;
+        let (u, v) = edge.vertices;
+
+        if !in_play(p) || !in_play(q) || blocked.contains(&(u.min(v), u.max(v))) {
+            continue;
+        }
+
+        neighbors.entry(p).or_default().push(q);
+        neighbors.entry(q).or_default().push(p);
+    }
+
+    // Flood breadth first from both regions at once, so that any gap in the new border is split
+    // between them rather than taken by one.
+
+    let mut assigned: HashMap<usize, usize> = HashMap::new();
+    let mut queue = VecDeque::new();
+
+    for p in 0..graph.points.len() {
+        if in_play(p) && !in_corridor(p) && terrain.mesh.surface[p] == TerrainSurface::Land {
+            assigned.insert(p, cells[p].unwrap());
+            queue.push_back(p);
+        }
+    }
+
+    while let Some(p) = queue.pop_front() {
+        let region = assigned[&p];
+
+        for q in neighbors.get(&p).into_iter().flatten().cloned() {
+            if !assigned.contains_key(&q) {
+                assigned.insert(q, region);
+                queue.push_back(q);
+            }
+        }
+    }
+
+    for (p, region) in assigned {
+        cells[p] = Some(region);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::terrain::{Terrain, TerrainSurface};
+
+/// The distance from a city within which the land and water shape its economy.
+const ECONOMY_RADIUS: f32 = 60.0;
+/// The lowest score of a trade for a city to be known for it.
+const ECONOMY_MIN_SCORE: f32 = 0.5;
+/// The fraction of the highest land elevation above which land is mountainous.
+const MINING_ELEVATION: f32 = 0.5;
+/// The multiple of the river flux threshold of a river large enough to carry trade.
+const TRADE_RIVER_FLUX: f32 = 4.0;
+/// The least moisture and flatness (the vertical component of the surface normal) of farmland,
+/// and the fraction of the highest land elevation below which it lies.
+const FARMLAND_MOISTURE: f32 = 0.4;
+const FARMLAND_FLATNESS: f32 = 0.9;
+const FARMLAND_ELEVATION: f32 = 0.3;
+
+/// A trade a city is known for, from the land and water around it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Economy {
+    /// A port on the coast with open water around it.
+    Fishing,
+    /// A town among the mountains.
+    Mining,
+    /// A hub on a river large enough for barges.
+    RiverTrade,
+    /// A market for the flat, wet lowlands around it.
+    Farming,
+}
+
+impl Economy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Economy::Fishing => "fishing port",
+            Economy::Mining => "mining town",
+            Economy::RiverTrade => "river trade hub",
+            Economy::Farming => "farming center",
+        }
+    }
+}
+
+/// The trades of each of the [cities] (as vertex indices), strongest first. Every city is known
+/// for at least its strongest trade.
+pub fn generate_economies(terrain: &Terrain, cities: &[usize]) -> Vec<Vec<Economy>> {
+    cities
+        .iter()
+        .map(|c| {
+            let mut scores = economy_scores(terrain, *c);
+            scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+            scores
+                .iter()
+                .enumerate()
+                .filter(|(i, (_, score))| *i == 0 || *score >= ECONOMY_MIN_SCORE)
+                .map(|(_, (economy, _))| *economy)
+                .collect()
+        })
+        .collect()
+}
+
+/// Score each trade in \[0, 1\] by the vertices within [ECONOMY_RADIUS] of the city at vertex
+/// [city].
+fn economy_scores(terrain: &Terrain, city: usize) -> [(Economy, f32); 4] {
+    let graph = &terrain.graph;
+    let data = &terrain.data;
+
+    let max_elevation = data.elevation_bounds.1.max(f32::EPSILON);
+    let river_flux = terrain.config.rivers.flux_threshold * TRADE_RIVER_FLUX;
+
+    let nearby: Vec<usize> = (0..graph.vertices.len())
+        .filter(|v| !graph.is_seam_copy(*v))
+        .filter(|v| graph.distance(city, *v) <= ECONOMY_RADIUS)
+        .collect();
+
+    // A vertex is water if every cell around it is, so lakes above sea level count as water too.
+
+    let is_water = |v: usize| {
+        let (a, b, c) = graph.vertex_points(v);
+        [a, b, c]
+            .iter()
+            .all(|p| terrain.mesh.surface[*p] == TerrainSurface::Water)
+    };
+
+    let land: Vec<usize> = nearby.iter().cloned().filter(|v| !is_water(*v)).collect();
+
+    let fraction = |count: usize, total: usize| count as f32 / total.max(1) as f32;
+
+    // Each share of the surroundings is scaled so that a city half surrounded by water, a third
+    // by mountains or two thirds by farmland scores fully. Fishing also needs the city itself on
+    // the coast, not just near water.
+
+    let water = fraction(nearby.len() - land.len(), nearby.len());
+    let on_coast = terrain.mesh.coast_distance[city] <= ECONOMY_RADIUS * 0.25;
+    let fishing = if on_coast {
+        (water * 2.0).min(1.0)
+    } else {
+        0.0
+    };
+
+    let mountains = land
+        .iter()
+        .filter(|v| data.elevation[**v] / max_elevation >= MINING_ELEVATION)
+        .count();
+    let mining = (fraction(mountains, land.len()) * 3.0).min(1.0);
+
+    let river_trade = if land
+        .iter()
+        .any(|v| !terrain.mesh.vertex_rivers[*v].is_empty() && data.flux[*v] >= river_flux)
+    {
+        1.0
+    } else {
+        0.0
+    };
+
+    let farmland = land
+        .iter()
+        .filter(|v| {
+            data.moisture[**v] >= FARMLAND_MOISTURE
+                && data.normal[**v].z.abs() >= FARMLAND_FLATNESS
+                && data.elevation[**v] / max_elevation <= FARMLAND_ELEVATION
+        })
+        .count();
+    let farming = (fraction(farmland, land.len()) * 1.5).min(1.0);
+
+    [
+        (Economy::Fishing, fishing),
+        (Economy::Mining, mining),
+        (Economy::RiverTrade, river_trade),
+        (Economy::Farming, farming),
+    ]
+}
@@ -0,0 +1,128 @@
+use nannou::glam::Vec2;
+use nannou::rand::seq::SliceRandom;
+
+use crate::rand::{seeded_rand, RandStream};
+use crate::terrain::Terrain;
+
+/// The minimum distance (in multiples of the point radius) between a point of interest and any
+/// city or other point of interest.
+const POI_SPACING: f32 = 5.0;
+/// The fraction of the river flux threshold above which a vertex is near an old river.
+const RUIN_RIVER_FLUX: f32 = 0.5;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PoiKind {
+    /// Ruins of old settlements, found along rivers.
+    Ruin,
+    /// Watchtowers, found on prominent hills.
+    Tower,
+    /// Cave entrances, found in cliffs.
+    Cave,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PointOfInterest {
+    pub kind: PoiKind,
+    pub position: Vec2,
+}
+
+/// The number of each kind of point of interest to scatter.
+#[derive(Debug, Clone, Copy)]
+pub struct PoiConfig {
+    pub ruins: usize,
+    pub towers: usize,
+    pub caves: usize,
+}
+
+impl Default for PoiConfig {
+    fn default() -> Self {
+        Self {
+            ruins: 3,
+            towers: 3,
+            caves: 3,
+        }
+    }
+}
+
+/// Scatter the points of interest. Each kind has its own candidate sites, and each site is
+/// rejected if it crowds a city or a point of interest placed before it.
+pub fn generate_points_of_interest(terrain: &Terrain, cities: &[usize]) -> Vec<PointOfInterest> {
+    let mut rand = seeded_rand(terrain.config.seed, RandStream::PointsOfInterest);
+
+    let config = &terrain.config.pois;
+    let graph = &terrain.graph;
+
+    let mut taken: Vec<Vec2> = cities.iter().map(|c| graph.vertices[*c]).collect();
+    let mut pois = vec![];
+
+    // Towers watch over the most prominent peaks first.
+
+    let towers: Vec<Vec2> = terrain
+        .mesh
+        .peaks
+        .iter()
+        .map(|p| graph.vertices[p.vertex])
+        .collect();
+
+    // Ruins sit on the banks of rivers, in random order.
+
+    let river_flux = terrain.config.rivers.flux_threshold * RUIN_RIVER_FLUX;
+
+    let mut ruins: Vec<Vec2> = (0..graph.vertices.len())
+        .filter(|v| terrain.mesh.contour.is_surface[*v] && !terrain.mesh.contour.is_contour[*v])
+        .filter(|v| terrain.data.flux[*v] >= river_flux)
+        .map(|v| graph.vertices[v])
+        .collect();
+
+    ruins.shuffle(&mut rand);
+
+    // Caves open from any point along a cliff, in random order.
+
+    let mut caves: Vec<Vec2> = terrain
+        .mesh
+        .cliffs
+        .iter()
+        .flat_map(|c| c.points.iter().cloned())
+        .collect();
+
+    caves.shuffle(&mut rand);
+
+    let spacing = terrain.config.radius * POI_SPACING;
+
+    let sites = [
+        (PoiKind::Tower, towers, config.towers),
+        (PoiKind::Ruin, ruins, config.ruins),
+        (PoiKind::Cave, caves, config.caves),
+    ];
+
+    for (kind, candidates, count) in sites.iter() {
+        place(&mut pois, &mut taken, *kind, candidates, *count, spacing);
+    }
+
+    pois
+}
+
+fn place(
+    pois: &mut Vec<PointOfInterest>,
+    taken: &mut Vec<Vec2>,
+    kind: PoiKind,
+    candidates: &[Vec2],
+    count: usize,
+    spacing: f32,
+) {
+    let mut placed = 0;
+
+    for position in candidates.iter().cloned() {
+        if placed >= count {
+            break;
+        }
+
+        if taken.iter().any(|p| p.distance(position) < spacing) {
+            continue;
+        }
+
+        pois.push(PointOfInterest { kind, position });
+        taken.push(position);
+        placed += 1;
+    }
+}
@@ -0,0 +1,74 @@
+use crate::regions::Regions;
+use crate::terrain::Terrain;
+use crate::util::indexed_mean;
+
+/// The number of people living on a unit of area of perfectly habitable land.
+const POPULATION_DENSITY: f32 = 5.0;
+
+/// The statistics of a single region, shown when one of its cities is picked.
+#[derive(Debug, Clone)]
+pub struct RegionSummary {
+    pub name: String,
+    /// The land cells of the region.
+    pub cells: Vec<usize>,
+    /// The land area of the region.
+    pub area: f32,
+    /// The estimated population, from the habitability of the land.
+    pub population: f32,
+    /// The indices of the cities in the region, capital first.
+    pub cities: Vec<usize>,
+    /// The indices of the regions sharing a border with the region.
+    pub neighbors: Vec<usize>,
+}
+
+impl RegionSummary {
+    pub fn new(terrain: &Terrain, regions: &Regions, region: usize) -> Self {
+        let graph = &terrain.graph;
+        let cell_regions = &regions.cells;
+
+        let cells: Vec<usize> = (0..graph.points.len())
+            .filter(|p| cell_regions[*p] == Some(region))
+            .collect();
+
+        let area = cells.iter().map(|p| graph.cell_area(*p)).sum();
+
+        let population = cells
+            .iter()
+            .map(|p| {
+                let habitability = indexed_mean(&regions.habitability, graph.cell(*p));
+                habitability * graph.cell_area(*p) * POPULATION_DENSITY
+            })
+            .sum();
+
+        let cities = (0..regions.cities.len())
+            .filter(|c| regions.city_regions[*c] == region)
+            .collect();
+
+        let mut neighbors = vec![];
+
+        for edge in graph.edges.iter() {
+            let (a, b) = (cell_regions[edge.points.0], cell_regions[edge.points.1]);
+
+            let other = match (a, b) {
+                (Some(a), Some(b)) if a == region && b != region => b,
+                (Some(a), Some(b)) if b == region && a != region => a,
+                _ => continue,
+            };
+
+            if !neighbors.contains(&other) {
+                neighbors.push(other);
+            }
+        }
+
+        neighbors.sort();
+
+        Self {
+            name: regions.region_names[region].clone(),
+            cells,
+            area,
+            population,
+            cities,
+            neighbors,
+        }
+    }
+}
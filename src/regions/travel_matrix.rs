@@ -0,0 +1,174 @@
+use crate::terrain::{TerrainData, TerrainGraph};
+use crate::util::{cost_field, TravelCost};
+
+/// The lowest travel cost between every pair of cities. Cities that can't reach each other have
+/// infinite cost.
+#[derive(Debug, Clone)]
+pub struct TravelMatrix {
+    /// The vertex index of each city, in the order of the rows and columns.
+    pub cities: Vec<usize>,
+    /// The travel cost from the city of each row to the city of each column.
+    pub costs: Vec<Vec<f32>>,
+}
+
+impl TravelMatrix {
+    pub fn new<C: TravelCost>(
+        graph: &TerrainGraph,
+        data: &TerrainData,
+        cities: &[usize],
+        cost: &C,
+    ) -> Self {
+        // One Dijkstra search from each city finds its cost to every other city.
+
+        let costs = cities
+            .iter()
+            .map(|city| {
+                let cost_fn =
+                    |g: &TerrainGraph, d: &TerrainData, a: usize, b: usize| cost.cost(g, d, a, b);
+
+                let field = cost_field(graph, data, cost_fn, &[*city]);
+
+                cities.iter().map(|c| field[*c]).collect()
+            })
+            .collect();
+
+        Self {
+            cities: cities.to_vec(),
+            costs,
+        }
+    }
+
+    /// The number of cities.
+    pub fn len(&self) -> usize {
+        self.cities.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cities.is_empty()
+    }
+
+    /// The travel cost from city [a] to city [b], as indices into [cities].
+    #[allow(dead_code)]
+    pub fn cost(&self, a: usize, b: usize) -> f32 {
+        self.costs[a][b]
+    }
+
+    /// Reorder the cities, so that city [i] of the result is city [order\[i\]] of this matrix.
+    pub fn permute(&self, order: &[usize]) -> Self {
+        Self {
+            cities: order.iter().map(|i| self.cities[*i]).collect(),
+            costs: order
+                .iter()
+                .map(|a| order.iter().map(|b| self.costs[*a][*b]).collect())
+                .collect(),
+        }
+    }
+
+    /// Pick [count] well separated cities, starting from the first city and then repeatedly
+    /// taking the city farthest from all of those already picked. Returns the city indices in the
+    /// order they were picked.
+    pub fn farthest_cities(&self, count: usize) -> Vec<usize> {
+        let mut picked = vec![];
+
+        if self.is_empty() {
+            return picked;
+        }
+
+        picked.push(0);
+
+        let mut nearest: Vec<f32> = (0..self.len()).map(|c| self.symmetric(0, c)).collect();
+
+        while picked.len() < count.min(self.len()) {
+            let next = (0..self.len())
+                .filter(|c| !picked.contains(c))
+                .max_by(|a, b| nearest[*a].total_cmp(&nearest[*b]).then(b.cmp(a)));
+
+            let next = match next {
+                Some(next) => next,
+                None => break,
+            };
+
+            picked.push(next);
+
+            for (c, n) in nearest.iter_mut().enumerate() {
+                *n = n.min(self.symmetric(next, c));
+            }
+        }
+
+        picked
+    }
+
+    /// The pairs of cities connected by the minimum spanning tree of the travel costs, as indices
+    /// into [cities]. Cities that can't reach each other are left in separate trees.
+    pub fn minimum_spanning_tree(&self) -> Vec<(usize, usize)> {
+        // Prim's algorithm, restarted from each city not yet reached by an earlier tree.
+
+        let mut in_tree = vec![false; self.len()];
+        let mut edges = vec![];
+
+        for root in 0..self.len() {
+            if in_tree[root] {
+                continue;
+            }
+
+            in_tree[root] = true;
+
+            let mut best: Vec<(f32, usize)> = (0..self.len())
+                .map(|c| (self.symmetric(root, c), root))
+                .collect();
+
+            loop {
+                let next = (0..self.len())
+                    .filter(|c| !in_tree[*c] && best[*c].0.is_finite())
+                    .min_by(|a, b| best[*a].0.total_cmp(&best[*b].0));
+
+                let next = match next {
+                    Some(next) => next,
+                    None => break,
+                };
+
+                in_tree[next] = true;
+                edges.push((best[next].1, next));
+
+                for c in 0..self.len() {
+                    let cost = self.symmetric(next, c);
+
+                    if !in_tree[c] && cost < best[c].0 {
+                        best[c] = (cost, next);
+                    }
+                }
+            }
+        }
+
+        edges
+    }
+
+    /// The cheaper of the costs between cities [a] and [b] in either direction. Travel costs are
+    /// not symmetric, since climbing is cheaper than descending.
+    pub fn symmetric(&self, a: usize, b: usize) -> f32 {
+        self.costs[a][b].min(self.costs[b][a])
+    }
+
+    /// Format the matrix as CSV, with the [names] of the cities as the header row and column.
+    pub fn to_csv(&self, names: &[String]) -> String {
+        let mut csv = String::from("city");
+
+        for name in names.iter() {
+            csv.push_str(&format!(",{}", name));
+        }
+
+        csv.push('\n');
+
+        for (name, row) in names.iter().zip(self.costs.iter()) {
+            csv.push_str(name);
+
+            for cost in row.iter() {
+                csv.push_str(&format!(",{:.1}", cost));
+            }
+
+            csv.push('\n');
+        }
+
+        csv
+    }
+}
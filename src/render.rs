@@ -0,0 +1,7 @@
+pub mod cull;
+pub mod primitives;
+pub mod text;
+
+pub use cull::*;
+pub use primitives::*;
+pub use text::*;
@@ -0,0 +1,145 @@
+use nannou::geom::Rect;
+use nannou::glam::*;
+
+use crate::render::Primitive;
+use crate::util::expand_rect;
+
+/// The width of each grid cell as a fraction of the width of the primitive bounds.
+const CULL_GRID_FRACTION: f32 = 1.0 / 32.0;
+/// The most grid cells a primitive may cover before it is drawn without culling.
+const CULL_MAX_CELLS: usize = 64;
+
+/// A uniform grid over the bounding boxes of a list of primitives, for finding the primitives
+/// that may be visible in a viewport without testing every one of them.
+pub struct PrimitiveGrid {
+    /// The bounds of the grid in world coordinates.
+    bounds: Rect,
+    /// The width and height of each cell.
+    cell: f32,
+    /// The number of columns and rows.
+    size: (usize, usize),
+    /// The indices of the primitives overlapping each cell, row by row.
+    cells: Vec<Vec<usize>>,
+    /// The indices of the primitives too large to be worth culling.
+    always: Vec<usize>,
+}
+
+impl PrimitiveGrid {
+    pub fn new(primitives: &[Primitive]) -> Self {
+        let rects: Vec<Rect> = primitives.iter().map(|p| p.bounds()).collect();
+
+        let bounds = rects
+            .iter()
+            .cloned()
+            .reduce(|a, b| a.max(b))
+            .unwrap_or_else(|| Rect::from_w_h(0.0, 0.0));
+
+        let cell = (bounds.w().max(bounds.h()) * CULL_GRID_FRACTION).max(1.0);
+        let size = (
+            (bounds.w() / cell).ceil().max(1.0) as usize,
+            (bounds.h() / cell).ceil().max(1.0) as usize,
+        );
+
+        let mut grid = Self {
+            bounds,
+            cell,
+            size,
+            cells: vec![vec![]; size.0 * size.1],
+            always: vec![],
+        };
+
+        for (i, rect) in rects.iter().enumerate() {
+            let ((x0, y0), (x1, y1)) = grid.cell_range(*rect);
+
+            if (x1 - x0 + 1) * (y1 - y0 + 1) > CULL_MAX_CELLS {
+                grid.always.push(i);
+                continue;
+            }
+
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    grid.cells[y * size.0 + x].push(i);
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// The indices of the primitives whose bounds may overlap the [viewport], in drawing order.
+    pub fn visible(&self, viewport: Rect) -> Vec<usize> {
+        let mut visible = self.always.clone();
+
+        if viewport.overlap(self.bounds).is_some() {
+            let ((x0, y0), (x1, y1)) = self.cell_range(viewport);
+
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    visible.extend_from_slice(&self.cells[y * self.size.0 + x]);
+                }
+            }
+        }
+
+        visible.sort_unstable();
+        visible.dedup();
+
+        visible
+    }
+
+    /// The first and last columns and rows covered by [rect], clamped to the grid.
+    fn cell_range(&self, rect: Rect) -> ((usize, usize), (usize, usize)) {
+        let column = |x: f32| {
+            let c = ((x - self.bounds.left()) / self.cell).floor();
+            (c.max(0.0) as usize).min(self.size.0 - 1)
+        };
+
+        let row = |y: f32| {
+            let r = ((y - self.bounds.bottom()) / self.cell).floor();
+            (r.max(0.0) as usize).min(self.size.1 - 1)
+        };
+
+        (
+            (column(rect.left()), row(rect.bottom())),
+            (column(rect.right()), row(rect.top())),
+        )
+    }
+}
+
+impl Primitive {
+    /// The bounding box of the primitive, including the width of its strokes.
+    pub fn bounds(&self) -> Rect {
+        let (rect, margin) = match self {
+            Primitive::Line { points, weight, .. } => {
+                (points_rect(&[points.0, points.1]), *weight * 0.5)
+            }
+            Primitive::Polyline { points, weight, .. } => (points_rect(points), *weight * 0.5),
+            Primitive::Polygon { points, .. } => (points_rect(points), 0.0),
+            Primitive::Circle {
+                center,
+                radius,
+                stroke,
+                ..
+            } => {
+                let rect = Rect::from_xy_wh(*center, Vec2::splat(*radius * 2.0));
+                (rect, stroke.map_or(0.0, |(weight, _)| weight * 0.5))
+            }
+        };
+
+        expand_rect(rect, margin)
+    }
+}
+
+fn points_rect(points: &[Vec2]) -> Rect {
+    let min = points
+        .iter()
+        .cloned()
+        .reduce(Vec2::min)
+        .unwrap_or(Vec2::ZERO);
+    let max = points
+        .iter()
+        .cloned()
+        .reduce(Vec2::max)
+        .unwrap_or(Vec2::ZERO);
+
+    Rect::from_corners(min, max)
+}
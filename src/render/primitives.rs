@@ -0,0 +1,1270 @@
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+use itertools::Itertools;
+use nannou::geom::Rect;
+use nannou::glam::*;
+
+use crate::annotations::Annotation;
+use crate::grid::{MapGrid, GRID_CELL_SIZE};
+use crate::labels::{LabelKind, Labels};
+use crate::palette::distinct_colors;
+use crate::rand::{random_point_in_rect, seeded_rand, RandStream};
+use crate::regions::{region_growth_cost, PoiKind, Regions};
+use crate::render::{text_strokes, TextStyle};
+use crate::routes::{RoadClass, RouteKind, Routes};
+use crate::terrain::terrain_mesh::{Compass, RiverSource, TerrainRiver, TerrainShading};
+use crate::terrain::{RiverConfig, Terrain, TerrainSurface};
+use crate::theme::{DebugPalette, FillStyle, FrameStyle, Season, Theme};
+use crate::util::{
+    bridge_holes, chain_edges, contains_point, dash_path, indexed_mean, map_clamp, resample_path,
+    smooth_path, DefaultTravelCost, Spline,
+};
+
+/// The HSLuv saturation and lightness of the muted region fill colors.
+const REGION_FILL_SATURATION: f64 = 60.0;
+const REGION_FILL_LIGHTNESS: f64 = 88.0;
+
+/// The spline that rivers, roads and the coastline are smoothed with.
+const PATH_SPLINE: Spline = Spline {
+    tension: 0.0,
+    tolerance: 0.25,
+    max_depth: 5,
+};
+/// The spline that region borders and depth contours are smoothed with. They follow the zigzag
+/// of the cell edges, so they are pulled tauter to keep them from wobbling.
+const BORDER_SPLINE: Spline = Spline {
+    tension: 0.5,
+    tolerance: 0.25,
+    max_depth: 4,
+};
+
+/// The temperature below which land is snowed over and rivers freeze, in degrees.
+const FREEZING_TEMPERATURE: f32 = 0.0;
+/// The summer temperature below which forests are evergreen, and keep their canopies in winter.
+const EVERGREEN_TEMPERATURE: f32 = 10.0;
+/// The fill of snowed over land.
+const SNOW_COLOR: Color = Color::new(232, 240, 248);
+/// The color of the channel drawn inside frozen rivers.
+const ICE_COLOR: Color = Color::new(200, 224, 240);
+/// The width of the ice channel as a fraction of the river stroke weight.
+const RIVER_ICE_WIDTH: f32 = 0.5;
+
+/// The length of the dashes and the gaps between them in the depth contours.
+const BATHYMETRY_DASH: (f32, f32) = (4.0, 3.0);
+
+/// The peak prominence mapped to the smallest and largest peak glyphs.
+const PEAK_GLYPH_PROMINENCE: (f32, f32) = (50.0, 300.0);
+/// The height of the smallest and largest peak glyphs.
+const PEAK_GLYPH_SIZE: (f32, f32) = (6.0, 16.0);
+
+/// The height of a tree symbol.
+const TREE_SIZE: f32 = 4.0;
+/// The number of attempts to place each tree symbol inside its polygon.
+const TREE_PLACEMENT_ATTEMPTS: usize = 8;
+
+/// The distance between the ticks along a cliff.
+const CLIFF_TICK_SPACING: f32 = 3.0;
+/// The length of the ticks along a cliff.
+const CLIFF_TICK_LENGTH: f32 = 4.0;
+
+/// The number of equal cost bands drawn in the isochrone map.
+const ISOCHRONE_BANDS: usize = 12;
+
+/// The radius of the smallest and largest river source glyphs.
+const RIVER_SOURCE_RADIUS: (f32, f32) = (1.5, 3.0);
+
+/// The size of the point of interest glyphs.
+const POI_SIZE: f32 = 5.0;
+
+/// The color of the lava in the caldera of an active volcano.
+const LAVA: Color = Color::new(196, 64, 32);
+/// The number of ash specks drawn around an active volcano.
+const ASH_SPECKS: usize = 24;
+
+/// The distance from the edge of the map to the outer line of the frame.
+const FRAME_INSET: f32 = 12.0;
+/// The distance between the outer and inner lines of a double frame.
+const FRAME_GAP: f32 = 5.0;
+/// The size of the flourishes in the corners of a double frame.
+const FRAME_CORNER: f32 = 14.0;
+
+/// An 8-bit sRGB color, independent of any drawing backend.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::new(0, 0, 0);
+    pub const WHITE: Color = Color::new(255, 255, 255);
+    pub const GREY: Color = Color::new(128, 128, 128);
+    pub const DIMGREY: Color = Color::new(105, 105, 105);
+    pub const STEELBLUE: Color = Color::new(70, 130, 180);
+
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl From<colorous::Color> for Color {
+    fn from(c: colorous::Color) -> Self {
+        Self::new(c.r, c.g, c.b)
+    }
+}
+
+/// A backend-agnostic drawing primitive in world coordinates.
+#[derive(Debug, Clone)]
+pub enum Primitive {
+    Line {
+        points: (Vec2, Vec2),
+        weight: f32,
+        color: Color,
+    },
+    Polyline {
+        points: Vec<Vec2>,
+        weight: f32,
+        color: Color,
+    },
+    Polygon {
+        points: Vec<Vec2>,
+        color: Color,
+    },
+    Circle {
+        center: Vec2,
+        radius: f32,
+        fill: Color,
+        stroke: Option<(f32, Color)>,
+    },
+}
+
+/// The primitives of the final ink-style map, in drawing order. If [lod], the simplified mesh is
+/// drawn in place of the full detail terrain.
+pub fn map_primitives(
+    terrain: &Terrain,
+    regions: &Regions,
+    routes: &Routes,
+    labels: &Labels,
+    theme: Theme,
+    lod: bool,
+) -> Vec<Primitive> {
+    let mut list = vec![];
+
+    if theme.fill == FillStyle::Hypsometric {
+        push_hypsometric_tints(&mut list, terrain);
+    }
+
+    if theme.political {
+        push_region_fills(&mut list, terrain, regions);
+    }
+
+    // Summer is the map as it was always drawn, so only the colder seasons add snow and ice.
+
+    if theme.season != Season::Summer {
+        push_snow(&mut list, terrain, theme.season);
+    }
+
+    if theme.ocean_waves {
+        push_strokes(&mut list, &terrain.mesh.waves, Color::GREY);
+    }
+
+    push_bathymetry(&mut list, terrain);
+    push_shallows(&mut list, terrain);
+
+    if lod {
+        push_terrain_lod(&mut list, terrain);
+    } else {
+        push_terrain(&mut list, terrain);
+    }
+
+    if theme.season != Season::Summer {
+        let rivers = if lod {
+            &terrain.mesh.lod.rivers
+        } else {
+            &terrain.mesh.rivers
+        };
+
+        push_river_ice(&mut list, terrain, rivers, theme.season);
+    }
+
+    if !lod {
+        push_forest(&mut list, terrain, theme.forest_density, theme.season);
+        push_cliffs(&mut list, terrain);
+    }
+
+    push_volcanoes(&mut list, terrain);
+    push_peaks(&mut list, terrain);
+    push_points_of_interest(&mut list, regions);
+    push_routes(&mut list, routes);
+    push_cities(&mut list, terrain, regions);
+
+    if theme.labels {
+        push_labels(&mut list, labels);
+    }
+
+    if theme.compass {
+        if let Some(compass) = &terrain.mesh.compass {
+            push_compass(&mut list, compass);
+        }
+    }
+
+    if let Some(shape) = theme.grid {
+        push_grid(
+            &mut list,
+            &MapGrid::new(terrain.extent, shape, GRID_CELL_SIZE),
+        );
+    }
+
+    push_frame(&mut list, terrain.extent, theme.frame);
+
+    list
+}
+
+/// The primitives of the terraced elevation map.
+pub fn terrace_primitives(terrain: &Terrain, regions: &Regions) -> Vec<Primitive> {
+    let mut list = vec![];
+
+    push_terraces(&mut list, terrain);
+    push_rivers(&mut list, &terrain.mesh.rivers, &terrain.config.rivers);
+    push_cities(&mut list, terrain, regions);
+
+    list
+}
+
+/// The ink terrain overlaid with the region of each vertex.
+pub fn debug_region_primitives(
+    terrain: &Terrain,
+    regions: &Regions,
+    palette: DebugPalette,
+) -> Vec<Primitive> {
+    let mut list = vec![];
+
+    push_terrain(&mut list, terrain);
+    push_debug_regions(&mut list, terrain, regions, palette);
+    push_cities(&mut list, terrain, regions);
+
+    list
+}
+
+/// Bands of the cost each vertex was claimed at as the regions grew from their founding cities,
+/// by the default cost of travel [Regions::new] grows them by.
+pub fn isochrone_primitives(
+    terrain: &Terrain,
+    regions: &Regions,
+    palette: DebugPalette,
+) -> Vec<Primitive> {
+    let mut list = vec![];
+
+    push_isochrones(&mut list, terrain, regions, palette);
+    push_coastline(&mut list, terrain);
+    push_cities(&mut list, terrain, regions);
+
+    list
+}
+
+/// The city markers alone.
+/// The height of the stem of an annotation pin.
+const PIN_HEIGHT: f32 = 14.0;
+/// The radius of the head of an annotation pin.
+const PIN_RADIUS: f32 = 4.0;
+/// The text size of annotation names. Notes are set smaller.
+const ANNOTATION_TEXT_SIZE: f32 = 10.0;
+
+/// The user's [annotations], drawn as pins labelled with their names and notes.
+pub fn annotation_primitives(annotations: &[Annotation]) -> Vec<Primitive> {
+    let mut list = vec![];
+
+    for annotation in annotations.iter() {
+        let head = annotation.position + vec2(0.0, PIN_HEIGHT);
+
+        list.push(Primitive::Line {
+            points: (annotation.position, head),
+            weight: 1.5,
+            color: Color::BLACK,
+        });
+
+        list.push(Primitive::Circle {
+            center: head,
+            radius: PIN_RADIUS,
+            fill: Color::STEELBLUE,
+            stroke: Some((1.0, Color::BLACK)),
+        });
+
+        // The name sits above the pin, and the note below its point.
+
+        let name_style = TextStyle::new(ANNOTATION_TEXT_SIZE);
+        let name_position = head + vec2(0.0, PIN_RADIUS + ANNOTATION_TEXT_SIZE);
+
+        push_text(
+            &mut list,
+            &annotation.name,
+            name_position,
+            &name_style,
+            Color::BLACK,
+        );
+
+        let note_style = TextStyle::new(ANNOTATION_TEXT_SIZE * 0.7);
+        let note_position = annotation.position - vec2(0.0, ANNOTATION_TEXT_SIZE);
+
+        push_text(
+            &mut list,
+            &annotation.note,
+            note_position,
+            &note_style,
+            Color::DIMGREY,
+        );
+    }
+
+    list
+}
+
+pub fn city_primitives(terrain: &Terrain, regions: &Regions) -> Vec<Primitive> {
+    let mut list = vec![];
+
+    push_cities(&mut list, terrain, regions);
+
+    list
+}
+
+fn push_debug_regions(
+    list: &mut Vec<Primitive>,
+    terrain: &Terrain,
+    regions: &Regions,
+    palette: DebugPalette,
+) {
+    let colors = palette.regions(terrain.config.seed, regions.num_regions);
+
+    for i in 0..regions.regions.len() {
+        let region = match regions.region_of(i) {
+            Some(region) => region,
+            None => continue,
+        };
+
+        let center = terrain.graph.vertices[i];
+        let fill = colors[region].into();
+
+        list.push(Primitive::Circle {
+            center,
+            radius: 2.0,
+            fill,
+            stroke: None,
+        });
+    }
+}
+
+/// Fill each land cell by its band of the cost the regions claimed it at and outline the
+/// boundaries between bands. The bands evenly divide the cost of the costliest land cell.
+fn push_isochrones(
+    list: &mut Vec<Primitive>,
+    terrain: &Terrain,
+    regions: &Regions,
+    palette: DebugPalette,
+) {
+    let graph = &terrain.graph;
+    let cost = region_growth_cost(terrain, regions.founders(), &DefaultTravelCost::default());
+
+    let cell_cost: Vec<Option<f32>> = (0..graph.points.len())
+        .map(|p| {
+            let c = indexed_mean(&cost, graph.cell(p));
+
+            match terrain.mesh.surface[p] {
+                TerrainSurface::Land if c.is_finite() => Some(c),
+                _ => None,
+            }
+        })
+        .collect();
+
+    let max_cost = cell_cost.iter().flatten().cloned().fold(0.0, f32::max);
+
+    if max_cost <= 0.0 {
+        return;
+    }
+
+    let band = |c: f32| ((c / max_cost * ISOCHRONE_BANDS as f32) as usize).min(ISOCHRONE_BANDS - 1);
+
+    for (p, poly) in terrain.mesh.polygons.iter().enumerate() {
+        if let (Some(poly), Some(c)) = (poly, cell_cost[p]) {
+            let t = (band(c) as f64 + 0.5) / ISOCHRONE_BANDS as f64;
+
+            list.push(Primitive::Polygon {
+                points: poly.points.clone(),
+                color: palette.wetness().eval_continuous(t).into(),
+            });
+        }
+    }
+
+    for edge in graph.edges.iter() {
+        if let (Some(a), Some(b)) = (cell_cost[edge.points.0], cell_cost[edge.points.1]) {
+            if band(a) != band(b) {
+                list.push(Primitive::Line {
+                    points: (
+                        graph.vertices[edge.vertices.0],
+                        graph.vertices[edge.vertices.1],
+                    ),
+                    weight: 1.0,
+                    color: Color::DIMGREY,
+                });
+            }
+        }
+    }
+}
+
+/// Tint the land cells of each region and outline the borders between regions. Each cell belongs
+/// to the region of most of its vertices.
+fn push_region_fills(list: &mut Vec<Primitive>, terrain: &Terrain, regions: &Regions) {
+    let graph = &terrain.graph;
+
+    let cell_regions = &regions.cells;
+
+    let mut adjacency = vec![vec![]; regions.num_regions];
+    let mut border_edges = vec![];
+
+    for edge in graph.edges.iter() {
+        if let (Some(a), Some(b)) = (cell_regions[edge.points.0], cell_regions[edge.points.1]) {
+            if a != b {
+                adjacency[a].push(b);
+                adjacency[b].push(a);
+
+                border_edges.push(edge.vertices);
+            }
+        }
+    }
+
+    let colors = region_fill_colors(terrain.config.seed, &adjacency);
+
+    // Fill each region as a few merged outlines rather than a polygon per cell, which keeps the
+    // SVG export small and leaves no seams between the cells.
+
+    for (region, polygons) in terrain.mesh.merged_polygons(|p| cell_regions[p]) {
+        for polygon in polygons.iter() {
+            list.push(Primitive::Polygon {
+                points: bridge_holes(polygon),
+                color: colors[region],
+            });
+        }
+    }
+
+    for chain in chain_edges(&border_edges) {
+        let points: Vec<Vec2> = chain.iter().map(|v| graph.vertices[*v]).collect();
+
+        list.push(Primitive::Polyline {
+            points: smooth_path(&points, &BORDER_SPLINE),
+            weight: 1.5,
+            color: Color::DIMGREY,
+        });
+    }
+}
+
+/// Muted colors for each region, from a seeded palette of distinct hues. Each region takes the
+/// first color that none of its neighbors have taken.
+fn region_fill_colors(seed: u64, adjacency: &[Vec<usize>]) -> Vec<Color> {
+    let count = adjacency.len().max(1);
+
+    let palette: Vec<Color> =
+        distinct_colors(seed, count, REGION_FILL_SATURATION, REGION_FILL_LIGHTNESS)
+            .into_iter()
+            .map(Color::from)
+            .collect();
+
+    let mut assigned: Vec<Option<usize>> = vec![None; adjacency.len()];
+
+    for region in 0..adjacency.len() {
+        let taken: Vec<usize> = adjacency[region]
+            .iter()
+            .filter_map(|n| assigned[*n])
+            .collect();
+        assigned[region] = (0..count).find(|c| !taken.contains(c));
+    }
+
+    assigned
+        .into_iter()
+        .map(|c| palette[c.unwrap_or(0)])
+        .collect()
+}
+
+fn push_coastline(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    for points in terrain.mesh.coastline.iter() {
+        list.push(Primitive::Polyline {
+            points: smooth_path(points, &PATH_SPLINE),
+            weight: 3.0,
+            color: Color::BLACK,
+        });
+    }
+}
+
+fn push_strokes(list: &mut Vec<Primitive>, strokes: &[TerrainShading], color: Color) {
+    for stroke in strokes.iter() {
+        list.push(Primitive::Line {
+            points: stroke.points,
+            weight: stroke.weight,
+            color,
+        });
+    }
+}
+
+/// Draw the depth contours as thin dashed lines, each deeper contour fainter than the last.
+fn push_bathymetry(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    let count = terrain.mesh.bathymetry.len();
+
+    for (i, contours) in terrain.mesh.bathymetry.iter().enumerate() {
+        let t = (i + 1) as f32 / (count + 1) as f32;
+        let shade = (128.0 + t * 80.0) as u8;
+        let color = Color::new(shade, shade, shade);
+
+        for contour in contours.iter() {
+            let points = smooth_path(contour, &BORDER_SPLINE);
+
+            for dash in dash_path(&points, BATHYMETRY_DASH.0, BATHYMETRY_DASH.1) {
+                list.push(Primitive::Polyline {
+                    points: dash,
+                    weight: 0.5,
+                    color,
+                });
+            }
+        }
+    }
+}
+
+fn push_shallows(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    push_strokes(list, &terrain.mesh.shallow_lines, Color::GREY);
+
+    for dot in terrain.mesh.shallow_stipple.iter() {
+        list.push(Primitive::Circle {
+            center: dot.center,
+            radius: dot.radius,
+            fill: Color::GREY,
+            stroke: None,
+        });
+    }
+}
+
+fn push_rivers(list: &mut Vec<Primitive>, rivers: &[TerrainRiver], config: &RiverConfig) {
+    for river in rivers.iter() {
+        list.push(Primitive::Polyline {
+            points: smooth_path(&river.points, &PATH_SPLINE),
+            weight: config.weight(river.flux),
+            color: Color::BLACK,
+        });
+    }
+}
+
+fn push_terraces(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    let terraces = &terrain.mesh.terraces;
+
+    for (i, poly) in terrain.mesh.polygons.iter().enumerate() {
+        if let Some(poly) = poly {
+            let color = colorous::YELLOW_ORANGE_BROWN
+                .eval_rational(terraces.bands[i], terraces.num_bands)
+                .into();
+
+            list.push(Primitive::Polygon {
+                points: poly.points.clone(),
+                color,
+            });
+        }
+    }
+
+    for outline in terraces.outlines.iter().flatten() {
+        list.push(Primitive::Polyline {
+            points: outline.clone(),
+            weight: 1.0,
+            color: Color::BLACK,
+        });
+    }
+}
+
+/// The hypsometric colors of the land, from sea level to the highest elevation.
+const HYPSOMETRIC_LAND: [(f32, Color); 4] = [
+    (0.0, Color::new(112, 168, 96)),
+    (0.3, Color::new(226, 214, 140)),
+    (0.65, Color::new(168, 126, 84)),
+    (1.0, Color::new(246, 244, 238)),
+];
+/// The bathymetric colors of the water, from sea level to the lowest elevation.
+const HYPSOMETRIC_WATER: [(f32, Color); 2] = [
+    (0.0, Color::new(198, 226, 240)),
+    (1.0, Color::new(58, 111, 168)),
+];
+
+/// Fill each polygon with the color of its elevation on the hypsometric ramps, relative to the
+/// highest and lowest points of the terrain.
+fn push_hypsometric_tints(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    let (min, max) = terrain.data.elevation_bounds;
+    let (min, max) = (min.min(-f32::EPSILON), max.max(f32::EPSILON));
+
+    for (i, poly) in terrain.mesh.polygons.iter().enumerate() {
+        if let Some(poly) = poly {
+            let elevation = terrain.mesh.elevation[i];
+
+            // Lakes can sit above sea level, so they take the shallowest water color.
+
+            let color = match terrain.mesh.surface[i] {
+                TerrainSurface::Land => {
+                    ramp(&HYPSOMETRIC_LAND, map_clamp(elevation, 0.0, max, 0.0, 1.0))
+                }
+                TerrainSurface::Water => {
+                    ramp(&HYPSOMETRIC_WATER, map_clamp(elevation, 0.0, min, 0.0, 1.0))
+                }
+            };
+
+            list.push(Primitive::Polygon {
+                points: poly.points.clone(),
+                color,
+            });
+        }
+    }
+}
+
+/// The color at [t] along a ramp of (position, color) stops in ascending order.
+fn ramp(stops: &[(f32, Color)], t: f32) -> Color {
+    let lerp = |a: u8, b: u8, s: f32| (a as f32 + (b as f32 - a as f32) * s).round() as u8;
+
+    for pair in stops.windows(2) {
+        let ((t0, c0), (t1, c1)) = (pair[0], pair[1]);
+
+        if t <= t1 {
+            let s = map_clamp(t, t0, t1, 0.0, 1.0);
+            return Color::new(
+                lerp(c0.r, c1.r, s),
+                lerp(c0.g, c1.g, s),
+                lerp(c0.b, c1.b, s),
+            );
+        }
+    }
+
+    stops[stops.len() - 1].1
+}
+
+fn push_streams(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    for spring in terrain.mesh.springs.iter() {
+        list.push(Primitive::Polyline {
+            points: smooth_path(&spring.stream.points, &PATH_SPLINE),
+            weight: 1.5,
+            color: Color::BLACK,
+        });
+    }
+}
+
+fn push_terrain(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    push_coastline(list, terrain);
+    push_strokes(list, &terrain.mesh.shading, Color::BLACK);
+    push_streams(list, terrain);
+    push_rivers(list, &terrain.mesh.rivers, &terrain.config.rivers);
+    push_river_sources(list, terrain, &terrain.mesh.river_sources);
+}
+
+/// Mark the head of each river with a small pond, or the lake pooled there, so rivers don't
+/// begin abruptly mid-slope.
+fn push_river_sources(list: &mut Vec<Primitive>, terrain: &Terrain, sources: &[RiverSource]) {
+    let config = &terrain.config.rivers;
+
+    for source in sources.iter() {
+        match &source.lake {
+            Some(lake) => {
+                list.push(Primitive::Polygon {
+                    points: lake.clone(),
+                    color: Color::WHITE,
+                });
+
+                let mut outline = lake.clone();
+                outline.push(lake[0]);
+
+                list.push(Primitive::Polyline {
+                    points: outline,
+                    weight: 1.0,
+                    color: Color::BLACK,
+                });
+            }
+            None => {
+                let (radius_min, radius_max) = RIVER_SOURCE_RADIUS;
+                let (flux_min, flux_max) = config.weight_flux;
+
+                list.push(Primitive::Circle {
+                    center: terrain.graph.vertices[source.vertex],
+                    radius: map_clamp(source.flux, flux_min, flux_max, radius_min, radius_max),
+                    fill: Color::WHITE,
+                    stroke: Some((1.0, Color::BLACK)),
+                });
+            }
+        }
+    }
+}
+
+fn push_terrain_lod(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    let lod = &terrain.mesh.lod;
+
+    for chain in lod.coastline.iter() {
+        list.push(Primitive::Polyline {
+            points: chain.clone(),
+            weight: 3.0,
+            color: Color::BLACK,
+        });
+    }
+
+    push_strokes(list, &lod.shading, Color::BLACK);
+    push_rivers(list, &lod.rivers, &terrain.config.rivers);
+}
+
+/// Draw each cliff as a line with ticks hanging from it down the cliff face.
+fn push_cliffs(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    for cliff in terrain.mesh.cliffs.iter() {
+        list.push(Primitive::Polyline {
+            points: cliff.points.clone(),
+            weight: 1.5,
+            color: Color::BLACK,
+        });
+
+        for (i, segment) in cliff.points.windows(2).enumerate() {
+            let (a, b) = (segment[0], segment[1]);
+
+            // Turn the segment normal to face the same way as the cliff face.
+            let downhill = cliff.downhill[i] + cliff.downhill[i + 1];
+            let normal = (b - a).normalize_or_zero().perp();
+            let normal = if normal.dot(downhill) < 0.0 {
+                -normal
+            } else {
+                normal
+            };
+
+            for start in resample_path(&[a, b], CLIFF_TICK_SPACING) {
+                list.push(Primitive::Line {
+                    points: (start, start + normal * CLIFF_TICK_LENGTH),
+                    weight: 1.0,
+                    color: Color::BLACK,
+                });
+            }
+        }
+    }
+}
+
+/// Scatter [density] tree symbols in each forested polygon. Trees are placed by rejection sampling
+/// inside the polygon from a seeded stream, so they are identical every time the map is drawn. In
+/// winter the broadleaf trees of the warmer forests are drawn bare, with fainter canopies, while
+/// the evergreens of the colder forests are not.
+fn push_forest(list: &mut Vec<Primitive>, terrain: &Terrain, density: usize, season: Season) {
+    let mut rand = seeded_rand(terrain.config.seed, RandStream::Vegetation);
+
+    for (i, poly) in terrain.mesh.polygons.iter().enumerate() {
+        let poly = match poly {
+            Some(poly) if terrain.mesh.forest[i] => poly,
+            _ => continue,
+        };
+
+        let temperature = indexed_mean(&terrain.data.temperature, terrain.graph.cell(i));
+        let bare = season == Season::Winter && temperature >= EVERGREEN_TEMPERATURE;
+
+        let (min, max) = poly.points.iter().fold(
+            (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+            |(min, max), p| (min.min(*p), max.max(*p)),
+        );
+
+        let bounds = Rect::from_corners(min, max);
+
+        for _ in 0..density {
+            let position = (0..TREE_PLACEMENT_ATTEMPTS)
+                .map(|_| random_point_in_rect(&mut rand, bounds))
+                .find(|p| contains_point(&poly.points, *p));
+
+            if let Some(base) = position {
+                push_tree(list, base, bare);
+            }
+        }
+    }
+}
+
+/// Draw a tree as a trunk topped by a round canopy, with its base at [base].
+fn push_tree(list: &mut Vec<Primitive>, base: Vec2, bare: bool) {
+    let canopy = base + vec2(0.0, TREE_SIZE * 0.6);
+    let (weight, color) = if bare {
+        (0.5, Color::GREY)
+    } else {
+        (1.0, Color::BLACK)
+    };
+
+    list.push(Primitive::Line {
+        points: (base, canopy),
+        weight: 1.0,
+        color: Color::BLACK,
+    });
+
+    list.push(Primitive::Circle {
+        center: canopy,
+        radius: TREE_SIZE * 0.4,
+        fill: Color::WHITE,
+        stroke: Some((weight, color)),
+    });
+}
+
+/// Cover the land polygons colder than freezing in the [season] with snow.
+fn push_snow(list: &mut Vec<Primitive>, terrain: &Terrain, season: Season) {
+    for (i, poly) in terrain.mesh.polygons.iter().enumerate() {
+        let poly = match poly {
+            Some(poly) if terrain.mesh.surface[i] == TerrainSurface::Land => poly,
+            _ => continue,
+        };
+
+        let temperature = indexed_mean(&terrain.data.temperature, terrain.graph.cell(i));
+
+        if temperature - season.cooling() < FREEZING_TEMPERATURE {
+            list.push(Primitive::Polygon {
+                points: poly.points.clone(),
+                color: SNOW_COLOR,
+            });
+        }
+    }
+}
+
+/// Draw the stretches of the [rivers] colder than freezing in the [season] as ice, a pale
+/// channel inside the river stroke.
+fn push_river_ice(
+    list: &mut Vec<Primitive>,
+    terrain: &Terrain,
+    rivers: &[TerrainRiver],
+    season: Season,
+) {
+    let frozen = |v: usize| terrain.data.temperature[v] - season.cooling() < FREEZING_TEMPERATURE;
+
+    for river in rivers.iter() {
+        let weight = terrain.config.rivers.weight(river.flux) * RIVER_ICE_WIDTH;
+
+        // Split the river into runs of frozen points.
+
+        let mut run: Vec<Vec2> = vec![];
+
+        for (p, v) in river.points.iter().zip(river.vertex_indices.iter()) {
+            if frozen(*v) {
+                run.push(*p);
+                continue;
+            }
+
+            if run.len() > 1 {
+                list.push(Primitive::Polyline {
+                    points: smooth_path(&run, &PATH_SPLINE),
+                    weight,
+                    color: ICE_COLOR,
+                });
+            }
+
+            run.clear();
+        }
+
+        if run.len() > 1 {
+            list.push(Primitive::Polyline {
+                points: smooth_path(&run, &PATH_SPLINE),
+                weight,
+                color: ICE_COLOR,
+            });
+        }
+    }
+}
+
+/// Draw a caret over each peak, sized by its prominence. The glyph is filled so that it masks the
+/// hachures underneath it.
+fn push_peaks(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    let (prominence_min, prominence_max) = PEAK_GLYPH_PROMINENCE;
+    let (size_min, size_max) = PEAK_GLYPH_SIZE;
+
+    for peak in terrain.mesh.peaks.iter() {
+        let top = terrain.graph.vertices[peak.vertex];
+        let height = map_clamp(
+            peak.prominence,
+            prominence_min,
+            prominence_max,
+            size_min,
+            size_max,
+        );
+
+        let left = top + vec2(-height * 0.6, -height);
+        let right = top + vec2(height * 0.6, -height);
+
+        list.push(Primitive::Polygon {
+            points: vec![left, top, right],
+            color: Color::WHITE,
+        });
+
+        list.push(Primitive::Polyline {
+            points: vec![left, top, right],
+            weight: 1.5,
+            color: Color::BLACK,
+        });
+    }
+}
+
+/// Fill the caldera of each active volcano with lava and scatter ash around its summit. Dormant
+/// volcanoes are left to the terrain shading.
+fn push_volcanoes(list: &mut Vec<Primitive>, terrain: &Terrain) {
+    for volcano in terrain.features.volcanoes.iter().filter(|v| v.active) {
+        let caldera = volcano.radius * volcano.caldera_radius;
+
+        for i in 0..ASH_SPECKS {
+            // A golden angle spiral spreads the specks evenly without any randomness.
+            let angle = i as f32 * 2.399;
+            let distance = caldera * (1.2 + 1.5 * (i as f32 / ASH_SPECKS as f32).sqrt());
+
+            list.push(Primitive::Circle {
+                center: volcano.center + vec2(angle.cos(), angle.sin()) * distance,
+                radius: 1.0,
+                fill: Color::DIMGREY,
+                stroke: None,
+            });
+        }
+
+        list.push(Primitive::Circle {
+            center: volcano.center,
+            radius: caldera * 0.6,
+            fill: LAVA,
+            stroke: Some((1.0, Color::BLACK)),
+        });
+    }
+}
+
+fn push_points_of_interest(list: &mut Vec<Primitive>, regions: &Regions) {
+    let s = POI_SIZE * 0.5;
+
+    for poi in regions.pois.iter() {
+        let p = poi.position;
+
+        match poi.kind {
+            PoiKind::Ruin => {
+                // A broken wall: a square missing its top and part of one side.
+                list.push(Primitive::Polyline {
+                    points: vec![
+                        p + vec2(-s, s * 0.2),
+                        p + vec2(-s, -s),
+                        p + vec2(s, -s),
+                        p + vec2(s, s),
+                    ],
+                    weight: 1.0,
+                    color: Color::BLACK,
+                });
+            }
+            PoiKind::Tower => {
+                // A solid turret with a pointed roof.
+                list.push(Primitive::Polygon {
+                    points: vec![
+                        p + vec2(-s * 0.5, -s),
+                        p + vec2(s * 0.5, -s),
+                        p + vec2(s * 0.5, s * 0.5),
+                        p + vec2(0.0, s * 1.2),
+                        p + vec2(-s * 0.5, s * 0.5),
+                    ],
+                    color: Color::BLACK,
+                });
+            }
+            PoiKind::Cave => {
+                // A dark arch opening onto the ground line.
+                let arch: Vec<Vec2> = (0..=8)
+                    .map(|i| {
+                        let angle = std::f32::consts::PI * i as f32 / 8.0;
+                        p + vec2(angle.cos(), angle.sin()) * s
+                    })
+                    .collect();
+
+                list.push(Primitive::Polygon {
+                    points: arch,
+                    color: Color::BLACK,
+                });
+
+                list.push(Primitive::Line {
+                    points: (p + vec2(-s * 1.5, 0.0), p + vec2(s * 1.5, 0.0)),
+                    weight: 1.0,
+                    color: Color::BLACK,
+                });
+            }
+        }
+    }
+}
+
+fn push_routes(list: &mut Vec<Primitive>, routes: &Routes) {
+    for route in routes.routes.iter() {
+        if route.kind != RouteKind::SeaLane {
+            continue;
+        }
+
+        let points: Vec<Vec2> = smooth_path(&route.points, &PATH_SPLINE);
+
+        for center in resample_path(&points, 6.0) {
+            list.push(Primitive::Circle {
+                center,
+                radius: 1.0,
+                fill: Color::BLACK,
+                stroke: None,
+            });
+        }
+    }
+
+    // Caravan routes are drawn as the road network, styled by how much traffic each road carries.
+
+    for road in routes.roads.iter() {
+        let points: Vec<Vec2> = smooth_path(&road.points, &PATH_SPLINE);
+
+        match road.class {
+            RoadClass::Highway => {
+                list.push(Primitive::Polyline {
+                    points,
+                    weight: 2.5,
+                    color: Color::BLACK,
+                });
+            }
+            RoadClass::Road => {
+                for points in resample_path(&points, 4.0).into_iter().tuples() {
+                    list.push(Primitive::Line {
+                        points,
+                        weight: 1.5,
+                        color: Color::BLACK,
+                    });
+                }
+            }
+            RoadClass::Trail => {
+                for center in resample_path(&points, 3.0) {
+                    list.push(Primitive::Circle {
+                        center,
+                        radius: 0.6,
+                        fill: Color::BLACK,
+                        stroke: None,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Draw towns as open circles, and capitals as a larger circle around a dot.
+fn push_cities(list: &mut Vec<Primitive>, terrain: &Terrain, regions: &Regions) {
+    for (i, v) in regions.cities.iter().enumerate() {
+        let center = terrain.graph.vertices[*v];
+
+        if !regions.is_capital(i) {
+            list.push(Primitive::Circle {
+                center,
+                radius: 4.0,
+                fill: Color::WHITE,
+                stroke: Some((2.0, Color::BLACK)),
+            });
+
+            continue;
+        }
+
+        list.push(Primitive::Circle {
+            center,
+            radius: 6.0,
+            fill: Color::WHITE,
+            stroke: Some((2.0, Color::BLACK)),
+        });
+
+        list.push(Primitive::Circle {
+            center,
+            radius: 2.5,
+            fill: Color::BLACK,
+            stroke: None,
+        });
+    }
+}
+
+fn push_labels(list: &mut Vec<Primitive>, labels: &Labels) {
+    for label in labels.labels.iter() {
+        if label.displaced {
+            list.push(Primitive::Line {
+                points: (label.anchor, label.leader_end()),
+                weight: 1.0,
+                color: Color::GREY,
+            });
+        }
+
+        let color = match label.kind {
+            LabelKind::Region | LabelKind::Island => Color::DIMGREY,
+            LabelKind::City | LabelKind::Peak => Color::BLACK,
+            LabelKind::River | LabelKind::Sea | LabelKind::Bay => Color::STEELBLUE,
+        };
+
+        push_text(list, &label.text, label.position, &label.style, color);
+    }
+}
+
+/// Draw a compass rose: four long cardinal points and four short diagonal points inside a ring,
+/// each point shaded black on one side, with an N over the north point.
+fn push_compass(list: &mut Vec<Primitive>, compass: &Compass) {
+    let center = compass.center;
+    let radius = compass.radius;
+
+    list.push(Primitive::Circle {
+        center,
+        radius: radius * 0.6,
+        fill: Color::WHITE,
+        stroke: Some((1.0, Color::BLACK)),
+    });
+
+    // The diagonal points are drawn first so the cardinal points overlap them.
+
+    for k in [1, 3, 5, 7, 0, 2, 4, 6] {
+        let angle = k as f32 * FRAC_PI_4 + FRAC_PI_2;
+        let direction = vec2(angle.cos(), angle.sin());
+        let (length, width) = if k % 2 == 0 {
+            (radius, radius * 0.16)
+        } else {
+            (radius * 0.55, radius * 0.1)
+        };
+
+        let tip = center + direction * length;
+        let side = direction.perp() * width;
+
+        list.push(Primitive::Polygon {
+            points: vec![center + side, tip, center - side],
+            color: Color::WHITE,
+        });
+
+        list.push(Primitive::Polygon {
+            points: vec![center, tip, center + side],
+            color: Color::BLACK,
+        });
+
+        list.push(Primitive::Polyline {
+            points: vec![center + side, tip, center - side, center + side],
+            weight: 1.0,
+            color: Color::BLACK,
+        });
+    }
+
+    let style = TextStyle::new(14.0);
+    push_text(
+        list,
+        "N",
+        center + vec2(0.0, radius + 10.0),
+        &style,
+        Color::BLACK,
+    );
+}
+
+/// Draw [text] centered on [position] as strokes of the built-in font.
+fn push_text(
+    list: &mut Vec<Primitive>,
+    text: &str,
+    position: Vec2,
+    style: &TextStyle,
+    color: Color,
+) {
+    for points in text_strokes(text, position, style) {
+        list.push(Primitive::Polyline {
+            points,
+            weight: style.weight(),
+            color,
+        });
+    }
+}
+
+/// Outline each cell of the play [grid].
+fn push_grid(list: &mut Vec<Primitive>, grid: &MapGrid) {
+    for (column, row) in grid.cells() {
+        let mut points = grid.outline(column, row);
+
+        if points.len() < 3 {
+            continue;
+        }
+
+        points.push(points[0]);
+
+        list.push(Primitive::Polyline {
+            points,
+            weight: 0.5,
+            color: Color::DIMGREY,
+        });
+    }
+}
+
+/// Draw a border inset from the edge of the [extent], blanking the margin outside it.
+fn push_frame(list: &mut Vec<Primitive>, extent: Rect, style: FrameStyle) {
+    if style == FrameStyle::None {
+        return;
+    }
+
+    let outer = Rect::from_xy_wh(extent.xy(), extent.wh() - 2.0 * FRAME_INSET);
+
+    // Four strips cover the margin between the frame and the edge of the map.
+
+    let margins = [
+        Rect::from_corners(extent.bottom_left(), vec2(extent.right(), outer.bottom())),
+        Rect::from_corners(vec2(extent.left(), outer.top()), extent.top_right()),
+        Rect::from_corners(extent.bottom_left(), vec2(outer.left(), extent.top())),
+        Rect::from_corners(vec2(outer.right(), extent.bottom()), extent.top_right()),
+    ];
+
+    for margin in margins {
+        list.push(Primitive::Polygon {
+            points: rect_points(margin),
+            color: Color::WHITE,
+        });
+    }
+
+    let outline = |rect: Rect| {
+        let mut points = rect_points(rect);
+        points.push(points[0]);
+        points
+    };
+
+    if style == FrameStyle::Single {
+        list.push(Primitive::Polyline {
+            points: outline(outer),
+            weight: 2.0,
+            color: Color::BLACK,
+        });
+
+        return;
+    }
+
+    let inner = Rect::from_xy_wh(outer.xy(), outer.wh() - 2.0 * FRAME_GAP);
+
+    list.push(Primitive::Polyline {
+        points: outline(outer),
+        weight: 3.0,
+        color: Color::BLACK,
+    });
+
+    list.push(Primitive::Polyline {
+        points: outline(inner),
+        weight: 1.0,
+        color: Color::BLACK,
+    });
+
+    // Each corner gets a small diamond on the inner corner, with a dot at its center.
+
+    for corner in rect_points(inner) {
+        let toward = (outer.xy() - corner).signum() * FRAME_CORNER * 0.5;
+        let center = corner + toward;
+        let (dx, dy) = (vec2(toward.x, 0.0), vec2(0.0, toward.y));
+
+        list.push(Primitive::Polygon {
+            points: vec![center - dx, center - dy, center + dx, center + dy],
+            color: Color::WHITE,
+        });
+
+        list.push(Primitive::Polyline {
+            points: vec![
+                center - dx,
+                center - dy,
+                center + dx,
+                center + dy,
+                center - dx,
+            ],
+            weight: 1.0,
+            color: Color::BLACK,
+        });
+
+        list.push(Primitive::Circle {
+            center,
+            radius: 1.5,
+            fill: Color::BLACK,
+            stroke: None,
+        });
+    }
+}
+
+/// The corners of [rect], counterclockwise from the bottom left.
+fn rect_points(rect: Rect) -> Vec<Vec2> {
+    vec![
+        rect.bottom_left(),
+        rect.bottom_right(),
+        rect.top_right(),
+        rect.top_left(),
+    ]
+}
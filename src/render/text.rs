@@ -0,0 +1,368 @@
+use nannou::geom::Rect;
+use nannou::glam::*;
+
+/// The height of the capital letters as a fraction of the text size.
+const CAP_HEIGHT: f32 = 0.7;
+/// The number of font units in the height of a capital letter.
+const FONT_UNITS: f32 = 6.0;
+/// The advance of each character in font units, including the gap to the next.
+const ADVANCE: f32 = 5.0;
+/// The advance of a space in font units.
+const SPACE_ADVANCE: f32 = 3.0;
+/// The scale of lowercase letters, which are drawn as small capitals.
+const SMALL_CAPS: f32 = 0.75;
+/// The stroke weight as a fraction of the text size.
+const STROKE_WEIGHT: f32 = 0.08;
+
+/// How a line of text is set. Text is drawn with a built-in stroke font, so it looks the same on
+/// screen and in every export.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TextStyle {
+    /// The font size. Capital letters are a little shorter than this.
+    pub size: f32,
+    /// The extra space between characters, as a fraction of the size.
+    pub tracking: f32,
+    /// The curvature of the baseline (one over the radius of the arc). Positive curvature bends
+    /// the ends of the text upwards, towards the left of the [direction].
+    pub curvature: f32,
+    /// The slant of the letters, as the horizontal shift per unit of height, eg for the
+    /// italic-style lettering of water labels.
+    pub slant: f32,
+    /// The unit direction of the baseline at the center of the text.
+    pub direction: Vec2,
+}
+
+impl TextStyle {
+    /// Straight, upright, horizontal text of the given [size].
+    pub fn new(size: f32) -> Self {
+        Self {
+            size,
+            tracking: 0.0,
+            curvature: 0.0,
+            slant: 0.0,
+            direction: Vec2::X,
+        }
+    }
+
+    /// The stroke weight of the letters.
+    pub fn weight(&self) -> f32 {
+        self.size * STROKE_WEIGHT
+    }
+}
+
+/// A character placed along the baseline.
+#[derive(Debug, Copy, Clone)]
+pub struct PlacedGlyph {
+    pub c: char,
+    /// The point on the baseline under the middle of the character.
+    pub origin: Vec2,
+    /// The unit direction of the baseline under the character.
+    pub direction: Vec2,
+    /// The scale from font units to world units.
+    pub scale: f32,
+}
+
+/// The length of [text] along its baseline.
+pub fn text_width(text: &str, style: &TextStyle) -> f32 {
+    let unit = style.size * CAP_HEIGHT / FONT_UNITS;
+    let n = text.chars().count();
+
+    let advances: f32 = text.chars().map(|c| advance(c) * unit).sum();
+
+    advances + n.saturating_sub(1) as f32 * style.tracking * style.size
+}
+
+/// Place each character of [text] along the baseline, centered on [center].
+pub fn layout_text(text: &str, center: Vec2, style: &TextStyle) -> Vec<PlacedGlyph> {
+    let unit = style.size * CAP_HEIGHT / FONT_UNITS;
+    let width = text_width(text, style);
+
+    let tangent = style.direction.normalize_or_zero();
+    let normal = tangent.perp();
+
+    // The distance along the baseline is bent onto an arc with its center on the left of the
+    // direction for positive curvature.
+
+    let point_at = |s: f32| -> (Vec2, Vec2) {
+        if style.curvature.abs() < f32::EPSILON {
+            return (center + tangent * s, tangent);
+        }
+
+        let radius = 1.0 / style.curvature;
+        let angle = s * style.curvature;
+        let arc_center = center + normal * radius;
+
+        let origin = arc_center - normal * radius * angle.cos() + tangent * radius * angle.sin();
+        let direction = tangent * angle.cos() + normal * angle.sin();
+
+        (origin, direction)
+    };
+
+    let mut glyphs = vec![];
+    let mut s = -width * 0.5;
+
+    for c in text.chars() {
+        let half = advance(c) * unit * 0.5;
+        let (origin, direction) = point_at(s + half);
+
+        let scale = if c.is_lowercase() {
+            unit * SMALL_CAPS
+        } else {
+            unit
+        };
+
+        glyphs.push(PlacedGlyph {
+            c,
+            origin,
+            direction,
+            scale,
+        });
+
+        s += half * 2.0 + style.tracking * style.size;
+    }
+
+    glyphs
+}
+
+/// The strokes of [text] centered on [center], as polylines in world coordinates. The text is
+/// vertically centered on the middle of the capital letters.
+pub fn text_strokes(text: &str, center: Vec2, style: &TextStyle) -> Vec<Vec<Vec2>> {
+    let baseline = center - style.direction.normalize_or_zero().perp() * style.size * 0.35;
+
+    let mut strokes = vec![];
+
+    for glyph in layout_text(text, baseline, style) {
+        let across = glyph.direction;
+        let up = across.perp();
+
+        for stroke in glyph_strokes(glyph.c) {
+            let points = stroke
+                .iter()
+                .map(|(x, y)| {
+                    let (x, y) = (*x as f32, *y as f32);
+                    let x = x - 2.0 + y * style.slant;
+
+                    glyph.origin + (across * x + up * y) * glyph.scale
+                })
+                .collect();
+
+            strokes.push(points);
+        }
+    }
+
+    strokes
+}
+
+/// The bounds of each character of [text] centered on [center], for checking overlaps.
+pub fn glyph_rects(text: &str, center: Vec2, style: &TextStyle) -> Vec<Rect> {
+    let unit = style.size * CAP_HEIGHT / FONT_UNITS;
+
+    layout_text(text, center, style)
+        .iter()
+        .map(|g| {
+            let middle = g.origin;
+            Rect::from_xy_wh(middle, vec2(advance(g.c) * unit, style.size))
+        })
+        .collect()
+}
+
+/// The bounds of the whole of [text] centered on [center].
+pub fn text_rect(text: &str, center: Vec2, style: &TextStyle) -> Rect {
+    glyph_rects(text, center, style)
+        .into_iter()
+        .reduce(|a, b| a.max(b))
+        .unwrap_or_else(|| Rect::from_xy_wh(center, vec2(0.0, style.size)))
+}
+
+fn advance(c: char) -> f32 {
+    let scale = if c.is_lowercase() { SMALL_CAPS } else { 1.0 };
+
+    if c.is_whitespace() {
+        SPACE_ADVANCE
+    } else {
+        ADVANCE * scale
+    }
+}
+
+type Stroke = &'static [(i8, i8)];
+
+/// The strokes of a character in font units, four wide and six tall with the baseline at zero.
+/// Lowercase letters use the capitals, and characters without a glyph are drawn as a box.
+fn glyph_strokes(c: char) -> &'static [Stroke] {
+    match c.to_ascii_uppercase() {
+        'A' => &[&[(0, 0), (2, 6), (4, 0)], &[(1, 3), (3, 3)]],
+        'B' => &[
+            &[(0, 0), (0, 6), (3, 6), (4, 5), (4, 4), (3, 3), (0, 3)],
+            &[(3, 3), (4, 2), (4, 1), (3, 0), (0, 0)],
+        ],
+        'C' => &[&[
+            (4, 5),
+            (3, 6),
+            (1, 6),
+            (0, 5),
+            (0, 1),
+            (1, 0),
+            (3, 0),
+            (4, 1),
+        ]],
+        'D' => &[&[(0, 0), (0, 6), (2, 6), (4, 4), (4, 2), (2, 0), (0, 0)]],
+        'E' => &[&[(4, 6), (0, 6), (0, 0), (4, 0)], &[(0, 3), (3, 3)]],
+        'F' => &[&[(4, 6), (0, 6), (0, 0)], &[(0, 3), (3, 3)]],
+        'G' => &[&[
+            (4, 5),
+            (3, 6),
+            (1, 6),
+            (0, 5),
+            (0, 1),
+            (1, 0),
+            (3, 0),
+            (4, 1),
+            (4, 3),
+            (2, 3),
+        ]],
+        'H' => &[&[(0, 0), (0, 6)], &[(4, 0), (4, 6)], &[(0, 3), (4, 3)]],
+        'I' => &[&[(1, 6), (3, 6)], &[(2, 6), (2, 0)], &[(1, 0), (3, 0)]],
+        'J' => &[&[(4, 6), (4, 1), (3, 0), (1, 0), (0, 1)]],
+        'K' => &[&[(0, 0), (0, 6)], &[(4, 6), (0, 2)], &[(1, 3), (4, 0)]],
+        'L' => &[&[(0, 6), (0, 0), (4, 0)]],
+        'M' => &[&[(0, 0), (0, 6), (2, 3), (4, 6), (4, 0)]],
+        'N' => &[&[(0, 0), (0, 6), (4, 0), (4, 6)]],
+        'O' => &[&[
+            (1, 0),
+            (0, 1),
+            (0, 5),
+            (1, 6),
+            (3, 6),
+            (4, 5),
+            (4, 1),
+            (3, 0),
+            (1, 0),
+        ]],
+        'P' => &[&[(0, 0), (0, 6), (3, 6), (4, 5), (4, 4), (3, 3), (0, 3)]],
+        'Q' => &[
+            &[
+                (1, 0),
+                (0, 1),
+                (0, 5),
+                (1, 6),
+                (3, 6),
+                (4, 5),
+                (4, 1),
+                (3, 0),
+                (1, 0),
+            ],
+            &[(2, 2), (4, 0)],
+        ],
+        'R' => &[
+            &[(0, 0), (0, 6), (3, 6), (4, 5), (4, 4), (3, 3), (0, 3)],
+            &[(2, 3), (4, 0)],
+        ],
+        'S' => &[&[
+            (4, 5),
+            (3, 6),
+            (1, 6),
+            (0, 5),
+            (0, 4),
+            (1, 3),
+            (3, 3),
+            (4, 2),
+            (4, 1),
+            (3, 0),
+            (1, 0),
+            (0, 1),
+        ]],
+        'T' => &[&[(0, 6), (4, 6)], &[(2, 6), (2, 0)]],
+        'U' => &[&[(0, 6), (0, 1), (1, 0), (3, 0), (4, 1), (4, 6)]],
+        'V' => &[&[(0, 6), (2, 0), (4, 6)]],
+        'W' => &[&[(0, 6), (1, 0), (2, 4), (3, 0), (4, 6)]],
+        'X' => &[&[(0, 6), (4, 0)], &[(0, 0), (4, 6)]],
+        'Y' => &[&[(0, 6), (2, 3), (4, 6)], &[(2, 3), (2, 0)]],
+        'Z' => &[&[(0, 6), (4, 6), (0, 0), (4, 0)]],
+        '0' => &[
+            &[
+                (1, 0),
+                (0, 1),
+                (0, 5),
+                (1, 6),
+                (3, 6),
+                (4, 5),
+                (4, 1),
+                (3, 0),
+                (1, 0),
+            ],
+            &[(1, 1), (3, 5)],
+        ],
+        '1' => &[&[(1, 5), (2, 6), (2, 0)], &[(1, 0), (3, 0)]],
+        '2' => &[&[(0, 5), (1, 6), (3, 6), (4, 5), (4, 4), (0, 0), (4, 0)]],
+        '3' => &[
+            &[(0, 5), (1, 6), (3, 6), (4, 5), (4, 4), (3, 3), (1, 3)],
+            &[(3, 3), (4, 2), (4, 1), (3, 0), (1, 0), (0, 1)],
+        ],
+        '4' => &[&[(3, 0), (3, 6), (0, 2), (4, 2)]],
+        '5' => &[&[
+            (4, 6),
+            (0, 6),
+            (0, 3),
+            (3, 3),
+            (4, 2),
+            (4, 1),
+            (3, 0),
+            (0, 0),
+        ]],
+        '6' => &[&[
+            (4, 5),
+            (3, 6),
+            (1, 6),
+            (0, 5),
+            (0, 1),
+            (1, 0),
+            (3, 0),
+            (4, 1),
+            (4, 2),
+            (3, 3),
+            (0, 3),
+        ]],
+        '7' => &[&[(0, 6), (4, 6), (1, 0)]],
+        '8' => &[
+            &[
+                (1, 3),
+                (0, 4),
+                (0, 5),
+                (1, 6),
+                (3, 6),
+                (4, 5),
+                (4, 4),
+                (3, 3),
+            ],
+            &[
+                (1, 3),
+                (0, 2),
+                (0, 1),
+                (1, 0),
+                (3, 0),
+                (4, 1),
+                (4, 2),
+                (3, 3),
+                (1, 3),
+            ],
+        ],
+        '9' => &[&[
+            (4, 3),
+            (1, 3),
+            (0, 4),
+            (0, 5),
+            (1, 6),
+            (3, 6),
+            (4, 5),
+            (4, 1),
+            (3, 0),
+            (1, 0),
+        ]],
+        '-' => &[&[(1, 3), (3, 3)]],
+        '.' => &[&[(2, 0), (2, 1)]],
+        ',' => &[&[(2, 1), (1, -1)]],
+        '\'' => &[&[(2, 6), (2, 4)]],
+        ' ' => &[],
+        _ => &[&[(0, 0), (0, 6), (4, 6), (4, 0), (0, 0)]],
+    }
+}
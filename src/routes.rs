@@ -0,0 +1,304 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use nannou::glam::Vec2;
+
+use crate::regions::Regions;
+use crate::terrain::{Terrain, TerrainData, TerrainGraph};
+use crate::util::{shortest_path, travel_cost, GraphPath};
+
+/// The number of nearest cities each port tries to connect to.
+const ROUTE_NEIGHBORS: usize = 2;
+/// A pair of cities is connected by a shortcut if traveling between them along the spanning tree
+/// costs this many times more than the direct route.
+const ROUTE_SHORTCUT_RATIO: f32 = 2.0;
+/// The maximum coast distance (in multiples of the point radius) of a port city.
+const PORT_COAST_DISTANCE: f32 = 3.0;
+/// The coast distance over which the cost of sailing doubles.
+const SEA_LANE_COAST_FALLOFF: f32 = 50.0;
+/// The cost multiplier for sea lanes crossing land.
+const SEA_LANE_LAND_COST: f32 = 50.0;
+/// The number of caravan routes sharing a road segment to make it a road instead of a trail.
+const ROAD_TRAFFIC: usize = 2;
+/// The number of caravan routes sharing a road segment to make it a highway.
+const HIGHWAY_TRAFFIC: usize = 4;
+
+pub struct Routes {
+    pub routes: Vec<Route>,
+    /// The caravan routes split into stretches of the same road class, with each segment shared
+    /// by several routes included only once.
+    pub roads: Vec<Road>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub kind: RouteKind,
+    /// The vertex indices of the cities connected by the route.
+    pub cities: (usize, usize),
+    /// The vertex indices along the route.
+    pub vertices: Vec<usize>,
+    /// The points along the route.
+    pub points: Vec<Vec2>,
+    /// The total travel cost of the route.
+    pub cost: f32,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RouteKind {
+    /// A shipping lane between two port cities.
+    SeaLane,
+    /// An overland route between two cities on the same landmass.
+    Caravan,
+}
+
+/// A stretch of the overland road network where every segment has the same class.
+#[derive(Debug, Clone)]
+pub struct Road {
+    pub class: RoadClass,
+    /// The largest number of caravan routes using a segment of the road.
+    pub traffic: usize,
+    /// The vertex indices along the road.
+    pub vertices: Vec<usize>,
+    /// The points along the road.
+    pub points: Vec<Vec2>,
+}
+
+/// The class of a road segment, from the number of caravan routes using it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum RoadClass {
+    Trail,
+    Road,
+    Highway,
+}
+
+impl RoadClass {
+    pub fn from_traffic(traffic: usize) -> Self {
+        if traffic >= HIGHWAY_TRAFFIC {
+            RoadClass::Highway
+        } else if traffic >= ROAD_TRAFFIC {
+            RoadClass::Road
+        } else {
+            RoadClass::Trail
+        }
+    }
+}
+
+impl Routes {
+    pub fn new(terrain: &Terrain, regions: &Regions) -> Self {
+        let graph = &terrain.graph;
+        let data = &terrain.data;
+        let coast_distance = &terrain.mesh.coast_distance;
+
+        let port_distance = terrain.config.radius * PORT_COAST_DISTANCE;
+
+        let ports: Vec<usize> = regions
+            .cities
+            .iter()
+            .cloned()
+            .filter(|c| coast_distance[*c] <= port_distance)
+            .collect();
+
+        let sea_cost = |g: &TerrainGraph, d: &TerrainData, a: usize, b: usize| {
+            let delta_pos = g.distance(a, b);
+
+            // Ships prefer to stay close to the coast and avoid cutting across land.
+
+            if d.elevation[b] >= 0.0 {
+                return delta_pos * SEA_LANE_LAND_COST;
+            }
+
+            delta_pos * (1.0 + coast_distance[b] / SEA_LANE_COAST_FALLOFF)
+        };
+
+        let mut routes = vec![];
+
+        for (a, b) in nearest_pairs(graph, &ports) {
+            if let Some(path) = shortest_path(graph, data, sea_cost, a, b) {
+                let crosses_water = path.vertices.iter().any(|v| data.elevation[*v] < 0.0);
+
+                if crosses_water {
+                    routes.push(Route::new(graph, RouteKind::SeaLane, (a, b), path));
+                }
+            }
+        }
+
+        for (a, b) in caravan_pairs(regions) {
+            if let Some(path) = shortest_path(graph, data, travel_cost, a, b) {
+                let over_land = path.vertices.iter().all(|v| data.elevation[*v] >= 0.0);
+
+                if over_land {
+                    routes.push(Route::new(graph, RouteKind::Caravan, (a, b), path));
+                }
+            }
+        }
+
+        let roads = generate_roads(graph, &routes);
+
+        Self { routes, roads }
+    }
+}
+
+impl Route {
+    fn new(graph: &TerrainGraph, kind: RouteKind, cities: (usize, usize), path: GraphPath) -> Self {
+        let points = graph.path_points(&path.vertices);
+
+        Self {
+            kind,
+            cities,
+            vertices: path.vertices,
+            points,
+            cost: path.cost,
+        }
+    }
+}
+
+/// Split the caravan routes into roads of a single class, counting the traffic on each segment as
+/// the number of routes that use it.
+fn generate_roads(graph: &TerrainGraph, routes: &[Route]) -> Vec<Road> {
+    let caravans: Vec<&Route> = routes
+        .iter()
+        .filter(|r| r.kind == RouteKind::Caravan)
+        .collect();
+
+    let segment = |a: usize, b: usize| (a.min(b), a.max(b));
+
+    let mut traffic: HashMap<(usize, usize), usize> = HashMap::new();
+
+    for route in caravans.iter() {
+        let segments: HashSet<(usize, usize)> = route
+            .vertices
+            .windows(2)
+            .map(|w| segment(w[0], w[1]))
+            .collect();
+
+        for s in segments {
+            *traffic.entry(s).or_insert(0) += 1;
+        }
+    }
+
+    // Walk each route, starting a new road whenever the class changes or the route joins a
+    // segment already covered by an earlier road.
+
+    let mut covered = HashSet::new();
+    let mut roads = vec![];
+
+    let mut finish = |vertices: &mut Vec<usize>, class: RoadClass, max_traffic: usize| {
+        if vertices.len() >= 2 {
+            roads.push(Road {
+                class,
+                traffic: max_traffic,
+                points: graph.path_points(vertices),
+                vertices: std::mem::take(vertices),
+            });
+        }
+
+        vertices.clear();
+    };
+
+    for route in caravans.iter() {
+        let mut vertices = vec![];
+        let mut class = RoadClass::Trail;
+        let mut max_traffic = 0;
+
+        for w in route.vertices.windows(2) {
+            let s = segment(w[0], w[1]);
+
+            if !covered.insert(s) {
+                finish(&mut vertices, class, max_traffic);
+                continue;
+            }
+
+            let t = traffic[&s];
+
+            if !vertices.is_empty() && RoadClass::from_traffic(t) != class {
+                finish(&mut vertices, class, max_traffic);
+            }
+
+            if vertices.is_empty() {
+                vertices.push(w[0]);
+                class = RoadClass::from_traffic(t);
+                max_traffic = 0;
+            }
+
+            vertices.push(w[1]);
+            max_traffic = max_traffic.max(t);
+        }
+
+        finish(&mut vertices, class, max_traffic);
+    }
+
+    roads
+}
+
+/// Pair the cities along the minimum spanning tree of their travel costs, plus shortcuts between
+/// cities that the tree connects by a long detour.
+fn caravan_pairs(regions: &Regions) -> BTreeSet<(usize, usize)> {
+    let travel = &regions.travel;
+    let tree = travel.minimum_spanning_tree();
+
+    let mut pairs = BTreeSet::new();
+
+    for (a, b) in tree.iter().cloned() {
+        pairs.insert((a.min(b), a.max(b)));
+    }
+
+    // The cost of traveling between each pair of cities along the tree, found by walking the
+    // tree from each city.
+
+    let mut neighbors = vec![vec![]; travel.len()];
+
+    for (a, b) in tree.iter().cloned() {
+        let cost = travel.symmetric(a, b);
+        neighbors[a].push((b, cost));
+        neighbors[b].push((a, cost));
+    }
+
+    for a in 0..travel.len() {
+        let mut tree_cost = vec![f32::INFINITY; travel.len()];
+        let mut stack = vec![a];
+
+        tree_cost[a] = 0.0;
+
+        while let Some(c) = stack.pop() {
+            for (n, cost) in neighbors[c].iter().cloned() {
+                if tree_cost[n].is_infinite() {
+                    tree_cost[n] = tree_cost[c] + cost;
+                    stack.push(n);
+                }
+            }
+        }
+
+        for b in (a + 1)..travel.len() {
+            let direct = travel.symmetric(a, b);
+
+            if tree_cost[b].is_finite() && direct * ROUTE_SHORTCUT_RATIO < tree_cost[b] {
+                pairs.insert((a, b));
+            }
+        }
+    }
+
+    pairs
+        .into_iter()
+        .map(|(a, b)| (travel.cities[a], travel.cities[b]))
+        .collect()
+}
+
+/// Pair each city with its nearest neighbors by straight line distance, without duplicates.
+fn nearest_pairs(graph: &TerrainGraph, cities: &[usize]) -> BTreeSet<(usize, usize)> {
+    let mut pairs = BTreeSet::new();
+
+    for a in cities.iter().cloned() {
+        let mut others: Vec<usize> = cities.iter().cloned().filter(|b| *b != a).collect();
+
+        others.sort_by(|b, c| {
+            let db = graph.distance(a, *b);
+            let dc = graph.distance(a, *c);
+            db.total_cmp(&dc)
+        });
+
+        for b in others.into_iter().take(ROUTE_NEIGHBORS) {
+            pairs.insert((a.min(b), a.max(b)));
+        }
+    }
+
+    pairs
+}
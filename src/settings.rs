@@ -0,0 +1,297 @@
+use std::io::{Error, ErrorKind};
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::{Path, PathBuf};
+
+use nannou::glam::Vec2;
+use serde_json::{json, Value};
+
+use crate::annotations::Annotations;
+use crate::camera::Camera;
+use crate::grid::GridShape;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::log;
+use crate::terrain::FeatureGenConfig;
+use crate::theme::{DebugPalette, FillStyle, FrameStyle, Season, Theme};
+
+/// The viewer state saved on exit and restored on the next launch.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    /// The seed of the terrain shown on exit.
+    pub seed: u64,
+    /// The name of the drawing mode.
+    pub mode: String,
+    pub theme: Theme,
+    pub camera: Camera,
+    /// The window size in points.
+    pub window: Vec2,
+    /// The random features the terrain is built from. Edit the settings file to change the
+    /// character of the maps.
+    pub features: FeatureGenConfig,
+    /// The annotations placed on each map.
+    pub annotations: Annotations,
+}
+
+impl Settings {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "seed": self.seed,
+            "mode": self.mode,
+            "theme": {
+                "ocean_waves": self.theme.ocean_waves,
+                "labels": self.theme.labels,
+                "political": self.theme.political,
+                "forest_density": self.theme.forest_density,
+                "debug_palette": palette_name(self.theme.debug_palette),
+                "frame": frame_name(self.theme.frame),
+                "compass": self.theme.compass,
+                "fill": fill_name(self.theme.fill),
+                "grid": self.theme.grid.map(|g| g.name()),
+                "season": season_name(self.theme.season),
+            },
+            "camera": {
+                "position": [self.camera.position.x, self.camera.position.y],
+                "zoom": self.camera.zoom,
+            },
+            "window": [self.window.x, self.window.y],
+            "features": self.features.to_json(),
+            "annotations": self.annotations.to_json(),
+        })
+    }
+
+    pub fn from_json(json: &Value) -> std::io::Result<Self> {
+        let seed = json["seed"].as_u64().ok_or_else(|| invalid("seed"))?;
+        let mode = json["mode"].as_str().ok_or_else(|| invalid("mode"))?;
+
+        let theme = &json["theme"];
+
+        let theme = Theme {
+            ocean_waves: bool_field(theme, "ocean_waves")?,
+            labels: bool_field(theme, "labels")?,
+            political: bool_field(theme, "political")?,
+            forest_density: theme["forest_density"]
+                .as_u64()
+                .ok_or_else(|| invalid("forest_density"))? as usize,
+            debug_palette: theme["debug_palette"]
+                .as_str()
+                .and_then(parse_palette)
+                .ok_or_else(|| invalid("debug_palette"))?,
+            // Settings saved before the frame and compass were added have neither.
+            frame: match theme.get("frame") {
+                Some(frame) => frame
+                    .as_str()
+                    .and_then(parse_frame)
+                    .ok_or_else(|| invalid("frame"))?,
+                None => FrameStyle::Double,
+            },
+            compass: match theme.get("compass") {
+                Some(_) => bool_field(theme, "compass")?,
+                None => true,
+            },
+            // Settings saved before the fill style was added have none.
+            fill: match theme.get("fill") {
+                Some(fill) => fill
+                    .as_str()
+                    .and_then(parse_fill)
+                    .ok_or_else(|| invalid("fill"))?,
+                None => FillStyle::Ink,
+            },
+            // Settings saved before the grid was added have none, and a null grid is hidden.
+            grid: match theme.get("grid") {
+                Some(Value::Null) | None => None,
+                Some(grid) => Some(
+                    grid.as_str()
+                        .and_then(GridShape::from_name)
+                        .ok_or_else(|| invalid("grid"))?,
+                ),
+            },
+            // Settings saved before seasons were added are drawn in summer.
+            season: match theme.get("season") {
+                Some(season) => season
+                    .as_str()
+                    .and_then(parse_season)
+                    .ok_or_else(|| invalid("season"))?,
+                None => Season::Summer,
+            },
+        };
+
+        let camera = Camera {
+            position: vec2_field(&json["camera"]["position"]).ok_or_else(|| invalid("position"))?,
+            zoom: json["camera"]["zoom"]
+                .as_f64()
+                .ok_or_else(|| invalid("zoom"))? as f32,
+        };
+
+        let window = vec2_field(&json["window"]).ok_or_else(|| invalid("window"))?;
+
+        // Settings saved before the features were configurable have none.
+
+        let features = match json.get("features") {
+            Some(features) => FeatureGenConfig::from_json(features)?,
+            None => FeatureGenConfig::default(),
+        };
+
+        // Settings saved before annotations were added have none.
+
+        let annotations = match json.get("annotations") {
+            Some(annotations) => {
+                Annotations::from_json(annotations).ok_or_else(|| invalid("annotations"))?
+            }
+            None => Annotations::default(),
+        };
+
+        Ok(Self {
+            seed,
+            mode: mode.to_string(),
+            theme,
+            camera,
+            window,
+            features,
+            annotations,
+        })
+    }
+}
+
+/// Read the settings saved by the last run, if there are any. Settings that can't be read are
+/// logged and ignored.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_settings() -> Option<Settings> {
+    let path = settings_path()?;
+
+    match read_settings(&path) {
+        Ok(settings) => Some(settings),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => {
+            log::warn!("ignoring settings {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// The browser has nowhere to keep settings between visits.
+#[cfg(target_arch = "wasm32")]
+pub fn load_settings() -> Option<Settings> {
+    None
+}
+
+/// Write the [settings] for the next run, logging any failure.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_settings(settings: &Settings) {
+    let path = match settings_path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let result = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&path, settings.to_json().to_string()));
+
+    match result {
+        Ok(()) => log::debug!("saved settings to {}", path.display()),
+        Err(e) => log::warn!("failed to save settings to {}: {}", path.display(), e),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_settings(_settings: &Settings) {}
+
+/// The settings file in the user's config directory, if there is one.
+#[cfg(not(target_arch = "wasm32"))]
+fn settings_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))?;
+
+    Some(config.join("terrain").join("settings.json"))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_settings(path: &Path) -> std::io::Result<Settings> {
+    let text = std::fs::read_to_string(path)?;
+    let json: Value = serde_json::from_str(&text)?;
+
+    Settings::from_json(&json)
+}
+
+fn palette_name(palette: DebugPalette) -> &'static str {
+    match palette {
+        DebugPalette::Classic => "classic",
+        DebugPalette::ColorBlind => "color-blind",
+        DebugPalette::Print => "print",
+    }
+}
+
+fn parse_palette(name: &str) -> Option<DebugPalette> {
+    match name {
+        "classic" => Some(DebugPalette::Classic),
+        "color-blind" => Some(DebugPalette::ColorBlind),
+        "print" => Some(DebugPalette::Print),
+        _ => None,
+    }
+}
+
+fn frame_name(frame: FrameStyle) -> &'static str {
+    match frame {
+        FrameStyle::None => "none",
+        FrameStyle::Single => "single",
+        FrameStyle::Double => "double",
+    }
+}
+
+fn parse_frame(name: &str) -> Option<FrameStyle> {
+    match name {
+        "none" => Some(FrameStyle::None),
+        "single" => Some(FrameStyle::Single),
+        "double" => Some(FrameStyle::Double),
+        _ => None,
+    }
+}
+
+fn fill_name(fill: FillStyle) -> &'static str {
+    match fill {
+        FillStyle::Ink => "ink",
+        FillStyle::Hypsometric => "hypsometric",
+    }
+}
+
+fn parse_fill(name: &str) -> Option<FillStyle> {
+    match name {
+        "ink" => Some(FillStyle::Ink),
+        "hypsometric" => Some(FillStyle::Hypsometric),
+        _ => None,
+    }
+}
+
+fn season_name(season: Season) -> &'static str {
+    match season {
+        Season::Summer => "summer",
+        Season::Winter => "winter",
+    }
+}
+
+fn parse_season(name: &str) -> Option<Season> {
+    match name {
+        "summer" => Some(Season::Summer),
+        "winter" => Some(Season::Winter),
+        _ => None,
+    }
+}
+
+fn bool_field(json: &Value, field: &str) -> std::io::Result<bool> {
+    json[field].as_bool().ok_or_else(|| invalid(field))
+}
+
+fn vec2_field(json: &Value) -> Option<Vec2> {
+    let x = json.get(0)?.as_f64()?;
+    let y = json.get(1)?.as_f64()?;
+
+    Some(Vec2::new(x as f32, y as f32))
+}
+
+fn invalid(field: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("missing or invalid settings field {:?}", field),
+    )
+}
@@ -1,38 +1,158 @@
 use nannou::geom::*;
 use nannou::math::map_range;
 use nannou::rand::rngs::SmallRng;
-use nannou::rand::SeedableRng;
 
 pub mod erosion;
+pub mod open_water;
+pub mod pipeline;
 pub mod terrain_data;
+pub mod terrain_error;
 pub mod terrain_features;
 pub mod terrain_graph;
+pub mod terrain_lod;
 pub mod terrain_mesh;
-
+pub mod terrain_stats;
+
+pub use open_water::Bay;
+pub use open_water::OpenWater;
+pub use pipeline::CancelToken;
+pub use pipeline::EditFeatures;
+pub use pipeline::Pipeline;
+pub use pipeline::PipelineStage;
+pub use pipeline::PipelineState;
+pub use pipeline::RefinePoints;
 pub use terrain_data::TerrainData;
+pub use terrain_error::GenerationError;
+pub use terrain_features::FeatureGenConfig;
 pub use terrain_features::TerrainFeatures;
 pub use terrain_graph::TerrainGraph;
 pub use terrain_graph::VertexType;
+pub use terrain_lod::TerrainLod;
+pub use terrain_mesh::BathymetryConfig;
+pub use terrain_mesh::CoastNoiseConfig;
+pub use terrain_mesh::Compass;
+pub use terrain_mesh::LightConfig;
+pub use terrain_mesh::Peak;
+pub use terrain_mesh::RiverConfig;
+pub use terrain_mesh::ShallowConfig;
+pub use terrain_mesh::ShallowStyle;
 pub use terrain_mesh::TerrainMesh;
 pub use terrain_mesh::TerrainSurface;
+pub use terrain_stats::ErosionPassStats;
+pub use terrain_stats::ScoreConfig;
+pub use terrain_stats::TerrainStats;
 
-use crate::util::expand_rect;
+use crate::names::CultureMode;
+use crate::rand::{seeded_rand, RandStream};
+use crate::regions::{BorderConfig, HabitabilityConfig, PoiConfig};
+use crate::terrain::erosion::RainfallModel;
+use crate::util::{expand_rect, PointSampler, SamplerKind, StableHasher};
 
 #[derive(Debug, Clone, Copy)]
 pub struct TerrainConfig {
     pub size: Vec2,
     pub seed: u64,
     pub radius: f32,
+    /// The exaggeration of the elevation relative to the XY plane in the surface normals, and so
+    /// in the shading, erosion and 3D views. 1 treats elevation and XY as the same world units.
+    pub vertical_scale: f32,
+    /// How the points that the terrain cells are built around are sampled.
+    pub sampler: SamplerKind,
     pub num_cities: u32,
+    /// The minimum distance between cities. Fewer cities are placed if there isn't room for them.
+    pub city_spacing: f32,
+    /// The number of regions, grown from the most habitable cities. The remaining cities become
+    /// towns inside those regions.
+    pub num_regions: u32,
+    /// The counts, chances and sizes of the random features the elevation is built from.
+    pub features: FeatureGenConfig,
+    pub rainfall: RainfallModel,
+    pub light: LightConfig,
+    pub rivers: RiverConfig,
+    /// How the shallow water along the coast is decorated.
+    pub shallows: ShallowConfig,
+    /// The fractal detail added to the coastline.
+    pub coast_noise: CoastNoiseConfig,
+    /// The depths of the contour lines drawn offshore.
+    pub bathymetry: BathymetryConfig,
+    pub cultures: CultureMode,
+    pub pois: PoiConfig,
+    /// How the habitability that the cities are placed by is scored.
+    pub habitability: HabitabilityConfig,
+    /// How the region borders follow rivers and ridgelines.
+    pub borders: BorderConfig,
+    /// The finer cells around the cities.
+    pub refine: RefineConfig,
+    /// Which of the cartographic layers are generated.
+    pub layers: LayerConfig,
+    /// Wrap the map horizontally, so the west edge continues from the east edge.
+    pub wrap_x: bool,
+}
+
+/// Finer terrain cells around points of interest, so the coastline and rivers near the cities
+/// have more detail than the wilderness between them.
+#[derive(Debug, Clone, Copy)]
+pub struct RefineConfig {
+    /// Generate the terrain a second time with finer cells around the cities of the first.
+    pub enabled: bool,
+    /// The radius of the refined area around each city.
+    pub radius: f32,
+    /// The point radius in the refined areas, as a fraction of the terrain point radius.
+    pub scale: f32,
+}
+
+impl Default for RefineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius: 80.0,
+            scale: 0.5,
+        }
+    }
+}
+
+/// Switches for the cartographic layers built on top of the heightmap. Layers that are off are
+/// left empty, so a caller that only needs the elevation, or draws the map its own way, doesn't
+/// pay for them.
+#[derive(Debug, Clone, Copy)]
+pub struct LayerConfig {
+    /// The slope shading lines.
+    pub shading: bool,
+    /// The rivers, their sources and the springs.
+    pub rivers: bool,
+    /// The cities, regions and names. When off, [crate::regions::Regions] are generated empty.
+    pub regions: bool,
+    /// The terrace outlines and the bathymetric contours.
+    pub contours: bool,
+}
+
+impl Default for LayerConfig {
+    fn default() -> Self {
+        Self {
+            shading: true,
+            rivers: true,
+            regions: true,
+            contours: true,
+        }
+    }
 }
 
 /// General-purpose state used for terrain generation that is derived from the config.
 #[derive(Debug, Clone)]
 pub struct TerrainContext {
+    /// The config the terrain is generated from.
+    pub config: TerrainConfig,
     /// The terrain extents in world coordinates.
     pub extent: Rect,
-    /// The explicitly seeded RNG used for terrain generation.
-    pub rand: SmallRng,
+    /// The master seed that each stage derives its random stream from.
+    pub seed: u64,
+}
+
+impl TerrainContext {
+    /// Returns a fresh RNG for the [stream], seeded from the master seed.
+    pub fn rand(&self, stream: RandStream) -> SmallRng {
+        seeded_rand(self.seed, stream)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -51,48 +171,221 @@ pub struct Terrain {
     pub mesh: TerrainMesh,
 }
 
-pub fn generate_terrain(config: TerrainConfig) -> Terrain {
-    let mut rand = SmallRng::seed_from_u64(config.seed);
+/// The steps that elevation and flux are rounded to before hashing.
+const HASH_ELEVATION_STEP: f32 = 0.01;
+const HASH_FLUX_STEP: f32 = 1e-6;
+
+impl Terrain {
+    /// A hash of the elevation, flux, surface and rivers, with the floating point values rounded
+    /// so that the same seed and config give the same hash on any machine. Two terrains with the
+    /// same hash are the same map, for golden tests and checking that a seed reproduces.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+
+        hasher.write_quantized_slice(&self.data.elevation, HASH_ELEVATION_STEP);
+        hasher.write_quantized_slice(&self.data.flux, HASH_FLUX_STEP);
 
-    let extent = Rect::from_wh(config.size);
-    let points = generate_points(&mut rand, extent, config.radius);
+        hasher.write_usize(self.mesh.surface.len());
 
-    let mut context = TerrainContext { extent, rand };
+        for surface in self.mesh.surface.iter() {
+            hasher.write_bytes(&[*surface as u8]);
+        }
 
-    let features = TerrainFeatures::generate(&mut context);
+        hasher.write_usize(self.mesh.rivers.len());
 
-    let graph = TerrainGraph::new(&points);
+        for river in self.mesh.rivers.iter() {
+            hasher.write_usize(river.vertex_indices.len());
 
-    let data = TerrainData::new(&graph, &features);
+            for v in river.vertex_indices.iter() {
+                hasher.write_usize(*v);
+            }
 
-    let mesh = TerrainMesh::new(&graph, &data);
+            hasher.write_quantized(river.flux, HASH_FLUX_STEP);
+        }
 
-    Terrain {
-        config,
-        extent,
-        graph,
-        data,
-        mesh,
-        features,
+        hasher.finish()
     }
 }
 
+pub fn generate_terrain(config: TerrainConfig) -> Result<Terrain, GenerationError> {
+    generate_terrain_with(config, |features| features)
+}
+
+/// Generate terrain like [generate_terrain], eg on a background thread, stopping early with
+/// [GenerationError::Cancelled] once [cancel] is cancelled.
+pub fn generate_terrain_cancellable(
+    config: TerrainConfig,
+    cancel: CancelToken,
+) -> Result<Terrain, GenerationError> {
+    let mut pipeline = Pipeline::default();
+    pipeline.cancel_with(cancel);
+    pipeline.run(config)
+}
+
+/// Generate terrain, passing the randomly generated features through [edit] before the elevation
+/// is built from them, eg to place a volcano with [TerrainFeatures::with_volcano].
+pub fn generate_terrain_with<F>(config: TerrainConfig, edit: F) -> Result<Terrain, GenerationError>
+where
+    F: FnOnce(TerrainFeatures) -> TerrainFeatures + 'static,
+{
+    let mut pipeline = Pipeline::default();
+    pipeline.insert_after("features", EditFeatures(Some(edit)));
+    pipeline.run(config)
+}
+
+/// Generate terrain with finer cells within the refine radius of each of the [centers], stopping
+/// early with [GenerationError::Cancelled] once [cancel] is cancelled.
+pub fn generate_refined_terrain(
+    config: TerrainConfig,
+    centers: &[Vec2],
+    cancel: CancelToken,
+) -> Result<Terrain, GenerationError> {
+    let mut pipeline = Pipeline::default();
+    pipeline.cancel_with(cancel);
+    pipeline.insert_before(
+        "features",
+        RefinePoints {
+            centers: centers.to_vec(),
+        },
+    );
+    pipeline.run(config)
+}
+
+/// Check that the config describes a terrain that can be generated.
+fn validate_config(config: &TerrainConfig) -> Result<(), GenerationError> {
+    let fields = [
+        ("size.x", config.size.x),
+        ("size.y", config.size.y),
+        ("radius", config.radius),
+        ("vertical_scale", config.vertical_scale),
+    ];
+
+    for (field, value) in fields {
+        if !value.is_finite() || value <= 0.0 {
+            return Err(GenerationError::InvalidConfig { field, value });
+        }
+    }
+
+    Ok(())
+}
+
 /// Fill the extent with randomly sampled points, roughly separated by [radius] distance.
-fn generate_points(rand: &mut SmallRng, extent: Rect, radius: f32) -> Vec<Vec2> {
-    let mut points = crate::util::poisson(rand, extent, radius);
+fn generate_points<S: PointSampler>(
+    rand: &mut SmallRng,
+    sampler: &S,
+    extent: Rect,
+    radius: f32,
+) -> Vec<Vec2> {
+    // No boundary points are needed; the open cells on the hull are clipped to the extent
+    // instead (see [TerrainGraph::clip_hull_cells]).
+
+    sampler.sample(rand, extent, radius)
+}
+
+/// The width (in multiples of the point radius) of the strip of points copied across the seam of a
+/// wrapping map.
+const SEAM_MARGIN: f32 = 3.0;
+
+/// Fill the extent with randomly sampled points for a map that wraps horizontally. The points near
+/// each side are copied across to the other side, so the triangulation on either side of the seam
+/// matches, and only the top and bottom boundary points are kept.
+fn generate_wrapped_points<S: PointSampler>(
+    rand: &mut SmallRng,
+    sampler: &S,
+    extent: Rect,
+    radius: f32,
+) -> Vec<Vec2> {
+    let mut points = sampler.sample(rand, extent, radius);
+
+    let margin = radius * SEAM_MARGIN;
+    let shift = Vec2::new(extent.w(), 0.0);
+
+    let mut copies = vec![];
+
+    for p in points.iter().cloned() {
+        if p.x < extent.left() + margin {
+            copies.push(p + shift);
+        }
+
+        if p.x > extent.right() - margin {
+            copies.push(p - shift);
+        }
+    }
+
+    points.append(&mut copies);
 
-    // Generate boundary points to improve Voronoi cell generation at the edges using techniques
-    // in [0]. It would be nice to skip the boundary points by clipping the boundary cells as
-    // described in [1] if I can ever figure out the math.
+    // Generate boundary points above and below the extent to improve the Voronoi cells at the
+    // top and bottom edges using techniques in [0].
 
     // [0] https://www.redblobgames.com/x/2314-poisson-with-boundary/
-    // [1] https://www.microsoft.com/en-us/research/wp-content/uploads/2016/12/Efficient-Computation-of-Clipped-Voronoi-Diagram-and-Applications.pdf
 
-    points.append(&mut generate_boundary_points(extent, radius));
+    let boundary = generate_boundary_points(extent, radius);
+
+    points.extend(
+        boundary
+            .into_iter()
+            .filter(|p| p.y < extent.bottom() || p.y > extent.top()),
+    );
 
     points
 }
 
+/// Replace the [points] within [config.radius] of each of the [centers] with points sampled
+/// [config.scale] times closer together. Points outside the extent are kept.
+fn refine_points<S: PointSampler>(
+    rand: &mut SmallRng,
+    sampler: &S,
+    points: &mut Vec<Vec2>,
+    extent: Rect,
+    centers: &[Vec2],
+    radius: f32,
+    config: &RefineConfig,
+) {
+    let fine_radius = radius * config.scale;
+
+    let refined =
+        |p: Vec2| extent.contains(p) && centers.iter().any(|c| c.distance(p) < config.radius);
+
+    points.retain(|p| !refined(*p));
+
+    let mut fine: Vec<Vec2> = vec![];
+
+    for center in centers.iter().cloned() {
+        let area = Rect::from_xy_wh(center, Vec2::splat(config.radius * 2.0));
+
+        let area = match area.overlap(extent) {
+            Some(area) => area,
+            None => continue,
+        };
+
+        // The points that a new point could crowd: the coarse points just outside the refined
+        // area, and the fine points already placed around nearby centers.
+
+        let reach = config.radius + radius;
+
+        let nearby: Vec<Vec2> = points
+            .iter()
+            .chain(fine.iter())
+            .cloned()
+            .filter(|p| p.distance(center) < reach)
+            .collect();
+
+        for p in sampler.sample(rand, area, fine_radius) {
+            if p.distance(center) >= config.radius {
+                continue;
+            }
+
+            if nearby.iter().any(|n| n.distance(p) < fine_radius) {
+                continue;
+            }
+
+            fine.push(p);
+        }
+    }
+
+    points.append(&mut fine);
+}
+
 fn generate_boundary_points(extent: Rect, distance: f32) -> Vec<Vec2> {
     let inner_extent = expand_rect(extent, distance * 1.0);
     let outer_extent = expand_rect(extent, distance * 2.0);
@@ -155,3 +448,55 @@ fn generate_boundary_points(extent: Rect, distance: f32) -> Vec<Vec2> {
 
     points
 }
+
+/// A small map that is quick to generate, for the unit tests.
+#[cfg(test)]
+pub fn small_test_terrain() -> Terrain {
+    let config = TerrainConfig {
+        size: Vec2::new(200.0, 200.0),
+        ..crate::default_config(1)
+    };
+
+    generate_terrain(config).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regions::Regions;
+
+    /// The hashes printed by `terrain hash 1`. Record them again whenever a change to the
+    /// generation is meant to change the maps. The f64 offsets change the normals and so the
+    /// erosion, which gives a different terrain.
+    #[cfg(not(feature = "f64"))]
+    const SEED_1_TERRAIN_HASH: u64 = 0x8a276256d3b6bbf0;
+    #[cfg(feature = "f64")]
+    const SEED_1_TERRAIN_HASH: u64 = 0x7992f018b62b2575;
+    const SEED_1_REGIONS_HASH: u64 = 0x9a38ab8396fdd92b;
+
+    fn hashes(seed: u64) -> (u64, u64) {
+        let terrain = crate::generate_map_terrain(crate::default_config(seed), &[]).unwrap();
+        let regions = Regions::new(&terrain);
+
+        (terrain.content_hash(), regions.content_hash())
+    }
+
+    #[test]
+    fn same_seed_gives_same_hashes() {
+        assert_eq!(hashes(1), hashes(1));
+    }
+
+    #[test]
+    fn different_seeds_give_different_hashes() {
+        let (terrain_1, regions_1) = hashes(1);
+        let (terrain_2, regions_2) = hashes(2);
+
+        assert_ne!(terrain_1, terrain_2);
+        assert_ne!(regions_1, regions_2);
+    }
+
+    #[test]
+    fn seed_1_gives_recorded_hashes() {
+        assert_eq!(hashes(1), (SEED_1_TERRAIN_HASH, SEED_1_REGIONS_HASH));
+    }
+}
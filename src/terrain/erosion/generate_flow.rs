@@ -1,8 +1,10 @@
 use crate::terrain::TerrainGraph;
 use crate::util::PriorityQueue;
 
-/// Generate the flow graph of the terrain vertices.
-pub fn generate_flow(graph: &TerrainGraph, elevation: &[f32]) -> Vec<Flow> {
+/// Generate the flow graph of the terrain vertices, and whether each vertex is in a closed basin:
+/// a depression with no downhill route to the boundary, which water has to fill before spilling
+/// over its rim.
+pub fn generate_flow(graph: &TerrainGraph, elevation: &[f32]) -> (Vec<Flow>, Vec<bool>) {
     // Implements algorithm 4 from Barnes, Lehman, Mulla [0]. Compared to the original terrain
     // generator implementation (which used Planchon-Darboux to fill depressions) this algorithm
     // does not need to modify the original elevation map. Flow nodes are generated for local
@@ -11,7 +13,12 @@ pub fn generate_flow(graph: &TerrainGraph, elevation: &[f32]) -> Vec<Flow> {
     //
     // [0] https://arxiv.org/abs/1511.04463
 
+    // The flood also tracks the water level of each vertex: the highest elevation along its flow
+    // path to the boundary. A vertex below its water level sits in a depression that the flood
+    // had to fill.
+
     let mut flow = vec![None; graph.vertices.len()];
+    let mut level = elevation.to_owned();
 
     let mut open = PriorityQueue::new();
     let mut seen = vec![false; flow.len()];
@@ -29,12 +36,19 @@ pub fn generate_flow(graph: &TerrainGraph, elevation: &[f32]) -> Vec<Flow> {
 
             flow[neighbor] = Some(next);
             seen[neighbor] = true;
+            level[neighbor] = level[neighbor].max(level[next]);
 
             open.push(neighbor, -elevation[neighbor]);
         }
     }
 
-    flow
+    let closed_basin = level
+        .iter()
+        .zip(elevation.iter())
+        .map(|(l, e)| l > e)
+        .collect();
+
+    (flow, closed_basin)
 }
 
 pub type Flow = Option<usize>;
@@ -66,3 +80,55 @@ impl Iterator for FlowGraphIterator<'_> {
         curr
     }
 }
+
+/// Invert the flow graph: the vertices that flow directly into each vertex.
+pub fn generate_upstream(flow: &[Flow]) -> Vec<Vec<usize>> {
+    let mut upstream = vec![vec![]; flow.len()];
+
+    for (v, next) in flow.iter().enumerate() {
+        if let Some(next) = next {
+            upstream[*next].push(v);
+        }
+    }
+
+    upstream
+}
+
+/// Label each vertex with the outlet it drains to: the vertex at the end of its flow path, on
+/// the boundary of the graph. The vertices with the same outlet form a drainage basin.
+pub fn generate_watershed(flow: &[Flow], upstream: &[Vec<usize>]) -> Vec<usize> {
+    let mut watershed: Vec<usize> = (0..flow.len()).collect();
+
+    for outlet in (0..flow.len()).filter(|v| flow[*v].is_none()) {
+        for v in traverse_upstream(upstream, outlet) {
+            watershed[v] = outlet;
+        }
+    }
+
+    watershed
+}
+
+/// Iterate through every vertex that flows into [start], directly or through other vertices,
+/// starting with [start] itself. Each vertex is visited before the vertices upstream of it.
+pub fn traverse_upstream(upstream: &[Vec<usize>], start: usize) -> UpstreamIterator {
+    UpstreamIterator {
+        upstream,
+        stack: vec![start],
+    }
+}
+
+pub struct UpstreamIterator<'a> {
+    upstream: &'a [Vec<usize>],
+    stack: Vec<usize>,
+}
+
+impl Iterator for UpstreamIterator<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let curr = self.stack.pop()?;
+        self.stack.extend_from_slice(&self.upstream[curr]);
+
+        Some(curr)
+    }
+}
@@ -1,17 +1,93 @@
-use crate::terrain::erosion::{traverse_flow_graph, Flow};
+use crate::terrain::erosion::Flow;
 use crate::terrain::TerrainGraph;
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RainfallModel {
+    /// Every vertex receives the same amount of rainfall.
+    Uniform,
+    /// Each vertex receives rainfall proportional to the area of the cells around it.
+    CellArea,
+}
+
+/// Generate the rainfall falling on each vertex. The total rainfall always sums to one. On a
+/// wrapping map, no rain falls on the copies of vertices across the seam.
+pub fn generate_rainfall(graph: &TerrainGraph, model: RainfallModel) -> Vec<f32> {
+    let count = (0..graph.vertices.len())
+        .filter(|v| !graph.is_seam_copy(*v))
+        .count();
+
+    let uniform = 1.0 / count.max(1) as f32;
+
+    let uniform: Vec<f32> = (0..graph.vertices.len())
+        .map(|v| if graph.is_seam_copy(v) { 0.0 } else { uniform })
+        .collect();
+
+    match model {
+        RainfallModel::Uniform => uniform,
+        RainfallModel::CellArea => {
+            // Each cell shares its area evenly between the vertices around it, so the area
+            // represented by a vertex is a third of each of the three cells it touches.
+
+            let mut area = vec![0.0; graph.vertices.len()];
+
+            for (v, a) in area.iter_mut().enumerate() {
+                if graph.is_seam_copy(v) {
+                    continue;
+                }
+
+                let (pa, pb, pc) = graph.vertex_points(v);
+
+                for p in [pa, pb, pc] {
+                    *a += graph.cell_area(p) / graph.cell(p).len() as f32;
+                }
+            }
+
+            let total: f32 = area.iter().sum();
+
+            if total <= 0.0 {
+                return uniform;
+            }
+
+            area.iter().map(|a| a / total).collect()
+        }
+    }
+}
+
 /// Generate the flux data for each vertex.
-pub fn generate_flux(graph: &TerrainGraph, flow: &[Flow]) -> Vec<f32> {
-    let rainfall = 1.0 / graph.vertices.len() as f32;
+pub fn generate_flux(graph: &TerrainGraph, flow: &[Flow], rainfall: &[f32]) -> Vec<f32> {
+    // The rainfall on each interior vertex is carried down its whole flow path, starting with
+    // the vertex itself. Rather than walking the path from every vertex, visit the vertices in
+    // topological order of the flow forest, so each vertex passes on its accumulated water once
+    // all of the vertices flowing into it have been visited.
 
-    let mut flux = vec![rainfall; graph.vertices.len()];
+    let mut accumulated = vec![0.0; flow.len()];
 
     for v in graph.interior.iter().cloned() {
-        for n in traverse_flow_graph(flow, v) {
-            flux[n] += rainfall;
+        accumulated[v] = rainfall[v];
+    }
+
+    let mut upstream = vec![0usize; flow.len()];
+
+    for down in flow.iter().flatten() {
+        upstream[*down] += 1;
+    }
+
+    let mut ready: Vec<usize> = (0..flow.len()).filter(|v| upstream[*v] == 0).collect();
+
+    while let Some(v) = ready.pop() {
+        if let Some(down) = flow[v] {
+            accumulated[down] += accumulated[v];
+            upstream[down] -= 1;
+
+            if upstream[down] == 0 {
+                ready.push(down);
+            }
         }
     }
 
-    flux
+    rainfall
+        .iter()
+        .zip(accumulated)
+        .map(|(r, a)| r + a)
+        .collect()
 }
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+use nannou::glam::{vec2, Vec2};
+
+use crate::terrain::terrain_mesh::TerrainContour;
+use crate::terrain::TerrainGraph;
+
+/// A circle of open water, clear of land and the edges of the map.
+#[derive(Debug, Clone, Copy)]
+pub struct OpenWater {
+    /// The water vertex at the center of the circle.
+    pub vertex: usize,
+    pub center: Vec2,
+    /// The distance from the center to the nearest coast or edge of the map.
+    pub radius: f32,
+}
+
+/// A stretch of water mostly enclosed by land, open to the sea on one side.
+#[derive(Debug, Clone, Copy)]
+pub struct Bay {
+    /// The water vertex at the middle of the bay.
+    pub vertex: usize,
+    pub center: Vec2,
+    /// The unit direction from the center towards the land around the bay, away from its mouth.
+    pub inland: Vec2,
+}
+
+/// The number of rays cast from each candidate bay center.
+const BAY_RAYS: usize = 16;
+/// The distance the rays reach, in multiples of the point radius.
+const BAY_REACH: f32 = 15.0;
+/// The fraction of rays that must hit land for the water to be a bay. Water enclosed on every
+/// side is a lake, not a bay.
+const BAY_ENCLOSURE: f32 = 0.6;
+/// The coast distance (in multiples of the point radius) of a bay center.
+const BAY_CLEARANCE: (f32, f32) = (1.5, 6.0);
+/// The minimum distance between bays, in multiples of the point radius.
+const BAY_SPACING: f32 = 15.0;
+
+/// Find up to [count] of the largest circles of open water that don't overlap, from largest to
+/// smallest, for placing decorations and labels away from the land.
+pub fn find_open_water(
+    graph: &TerrainGraph,
+    contour: &TerrainContour,
+    coast_distance: &[f32],
+    count: usize,
+) -> Vec<OpenWater> {
+    // The coast distance is a distance transform over the water, so the largest circle that fits
+    // around each water vertex reaches to the nearer of the coast and the edge of the map.
+
+    let mut candidates: Vec<OpenWater> = graph
+        .interior
+        .iter()
+        .cloned()
+        .filter(|v| !contour.is_surface[*v])
+        .map(|v| OpenWater {
+            vertex: v,
+            center: graph.vertices[v],
+            radius: coast_distance[v].min(graph.edge_distance[v]),
+        })
+        .filter(|c| c.radius.is_finite() && c.radius > 0.0)
+        .collect();
+
+    candidates.sort_by(|a, b| b.radius.total_cmp(&a.radius).then(a.vertex.cmp(&b.vertex)));
+
+    // Take the largest circles first, skipping any that overlap a circle already taken.
+
+    let mut circles: Vec<OpenWater> = vec![];
+
+    for candidate in candidates {
+        if circles.len() >= count {
+            break;
+        }
+
+        let overlaps = circles.iter().any(|c| {
+            let d = graph.wrap_delta(candidate.center - c.center).length();
+            d < candidate.radius + c.radius
+        });
+
+        if !overlaps {
+            circles.push(candidate);
+        }
+    }
+
+    circles
+}
+
+/// Find up to [count] bays, from the widest to the narrowest. A bay center is a water
+/// vertex along the middle of a channel that sees land in most directions, but not all of them.
+pub fn find_bays(
+    graph: &TerrainGraph,
+    contour: &TerrainContour,
+    coast_distance: &[f32],
+    radius: f32,
+    count: usize,
+) -> Vec<Bay> {
+    // Bucket the vertices so the rays can look up the surface under each of their steps.
+
+    let key = |p: Vec2| ((p.x / radius).floor() as i32, (p.y / radius).floor() as i32);
+
+    let mut buckets: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+
+    for v in graph.interior.iter().cloned() {
+        buckets.entry(key(graph.vertices[v])).or_default().push(v);
+    }
+
+    let is_land = |p: Vec2| {
+        let (kx, ky) = key(p);
+
+        let nearest = (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dy| (kx + dx, ky + dy)))
+            .filter_map(|k| buckets.get(&k))
+            .flatten()
+            .cloned()
+            .min_by(|a, b| {
+                let da = graph.vertices[*a].distance_squared(p);
+                let db = graph.vertices[*b].distance_squared(p);
+                da.total_cmp(&db)
+            });
+
+        nearest.map_or(false, |v| contour.is_surface[v])
+    };
+
+    // Candidates lie along the middle of the water, where the coast distance is a local maximum.
+
+    let (min_clearance, max_clearance) = (BAY_CLEARANCE.0 * radius, BAY_CLEARANCE.1 * radius);
+
+    let mut candidates: Vec<usize> = graph
+        .interior
+        .iter()
+        .cloned()
+        .filter(|v| !contour.is_surface[*v])
+        .filter(|v| (min_clearance..=max_clearance).contains(&coast_distance[*v]))
+        .filter(|v| {
+            graph
+                .connected_vertices(*v)
+                .all(|n| coast_distance[n] <= coast_distance[*v])
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| {
+        coast_distance[*b]
+            .total_cmp(&coast_distance[*a])
+            .then(a.cmp(b))
+    });
+
+    let mut bays: Vec<Bay> = vec![];
+
+    for v in candidates {
+        if bays.len() >= count {
+            break;
+        }
+
+        let center = graph.vertices[v];
+
+        if bays
+            .iter()
+            .any(|b| b.center.distance(center) < BAY_SPACING * radius)
+        {
+            continue;
+        }
+
+        let mut hits = 0;
+        let mut inland = Vec2::ZERO;
+
+        for k in 0..BAY_RAYS {
+            let angle = k as f32 / BAY_RAYS as f32 * TAU;
+            let direction = vec2(angle.cos(), angle.sin());
+
+            let steps = BAY_REACH as usize;
+            let hit = (1..=steps).any(|i| is_land(center + direction * i as f32 * radius));
+
+            if hit {
+                hits += 1;
+                inland += direction;
+            }
+        }
+
+        let enclosure = hits as f32 / BAY_RAYS as f32;
+
+        if enclosure >= BAY_ENCLOSURE && hits < BAY_RAYS {
+            bays.push(Bay {
+                vertex: v,
+                center,
+                inland: inland.normalize_or_zero(),
+            });
+        }
+    }
+
+    bays
+}
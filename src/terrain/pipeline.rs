@@ -0,0 +1,437 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use nannou::geom::*;
+
+use crate::log;
+use crate::rand::RandStream;
+use crate::terrain::terrain_data::EROSION_PASSES;
+use crate::terrain::*;
+use crate::util::Timer;
+
+/// The terrain generated so far, passed through the pipeline stages in order. Each stage reads
+/// the parts generated by the stages before it and fills in its own.
+pub struct PipelineState {
+    pub context: TerrainContext,
+    /// The sampled points, until the graph stage moves them into [TerrainGraph::points].
+    pub points: Vec<Vec2>,
+    pub features: Option<TerrainFeatures>,
+    pub graph: Option<TerrainGraph>,
+    pub data: Option<TerrainData>,
+    pub mesh: Option<TerrainMesh>,
+    /// Checked between stages, and by long running stages as they go.
+    pub cancel: CancelToken,
+}
+
+impl PipelineState {
+    pub fn features(&self) -> Result<&TerrainFeatures, GenerationError> {
+        self.features
+            .as_ref()
+            .ok_or(GenerationError::MissingStage { needs: "features" })
+    }
+
+    pub fn graph(&self) -> Result<&TerrainGraph, GenerationError> {
+        self.graph
+            .as_ref()
+            .ok_or(GenerationError::MissingStage { needs: "graph" })
+    }
+
+    pub fn data(&self) -> Result<&TerrainData, GenerationError> {
+        self.data
+            .as_ref()
+            .ok_or(GenerationError::MissingStage { needs: "elevation" })
+    }
+}
+
+/// A flag shared with a generation running on another thread, which stops it at the next check
+/// with [GenerationError::Cancelled], eg when the user changes a parameter before it finishes.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask every generation holding a clone of this token to stop.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fail with [GenerationError::Cancelled] if the token has been cancelled.
+    pub fn check(&self) -> Result<(), GenerationError> {
+        if self.is_cancelled() {
+            Err(GenerationError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A step of terrain generation. Stages are run in order by a [Pipeline], and can be inserted
+/// between the built-in stages, eg to run a custom climate model after erosion.
+pub trait PipelineStage {
+    /// The name the stage is found by when inserting stages.
+    fn name(&self) -> &str;
+
+    fn run(&mut self, state: &mut PipelineState) -> Result<(), GenerationError>;
+}
+
+/// An ordered list of generation stages. Regions are not a stage; they are generated from the
+/// finished terrain with [crate::regions::Regions::new], since the viewer regrows them around
+/// locked or moved cities without generating the terrain again, and refinement places the cities
+/// on a first, coarser terrain to decide where the second one is refined.
+pub struct Pipeline {
+    stages: Vec<Box<dyn PipelineStage>>,
+    cancel: CancelToken,
+}
+
+impl Default for Pipeline {
+    /// The built-in stages: points, features, graph, elevation, erosion and mesh.
+    fn default() -> Self {
+        Self {
+            stages: vec![
+                Box::new(PointGen),
+                Box::new(FeatureGen),
+                Box::new(GraphBuild),
+                Box::new(Elevation),
+                Box::new(Erosion {
+                    passes: EROSION_PASSES,
+                }),
+                Box::new(Mesh),
+            ],
+            cancel: CancelToken::new(),
+        }
+    }
+}
+
+impl Pipeline {
+    /// Insert a [stage] after the stage named [after], or at the end if there is none.
+    pub fn insert_after<S: PipelineStage + 'static>(&mut self, after: &str, stage: S) {
+        let index = self.position(after).map_or(self.stages.len(), |i| i + 1);
+        self.stages.insert(index, Box::new(stage));
+    }
+
+    /// Insert a [stage] before the stage named [before], or at the start if there is none.
+    pub fn insert_before<S: PipelineStage + 'static>(&mut self, before: &str, stage: S) {
+        let index = self.position(before).unwrap_or(0);
+        self.stages.insert(index, Box::new(stage));
+    }
+
+    /// Stop the run when [cancel] is cancelled, checking before each stage and between erosion
+    /// passes.
+    pub fn cancel_with(&mut self, cancel: CancelToken) {
+        self.cancel = cancel;
+    }
+
+    /// Run each stage in order and assemble the terrain.
+    pub fn run(&mut self, config: TerrainConfig) -> Result<Terrain, GenerationError> {
+        validate_config(&config)?;
+
+        let extent = Rect::from_wh(config.size);
+
+        let mut state = PipelineState {
+            context: TerrainContext {
+                config,
+                extent,
+                seed: config.seed,
+            },
+            points: vec![],
+            features: None,
+            graph: None,
+            data: None,
+            mesh: None,
+            cancel: self.cancel.clone(),
+        };
+
+        for stage in self.stages.iter_mut() {
+            let timer = Timer::start();
+
+            if let Err(e) = state.cancel.check().and_then(|_| stage.run(&mut state)) {
+                if e == GenerationError::Cancelled {
+                    log::debug!(
+                        "generation cancelled before stage {} finished",
+                        stage.name()
+                    );
+                } else {
+                    log::error!("stage {} failed: {}", stage.name(), e);
+                }
+                return Err(e);
+            }
+
+            log::debug!("stage {} took {:.4?}", stage.name(), timer.elapsed());
+        }
+
+        Ok(Terrain {
+            config,
+            extent,
+            features: state
+                .features
+                .ok_or(GenerationError::MissingStage { needs: "features" })?,
+            graph: state
+                .graph
+                .ok_or(GenerationError::MissingStage { needs: "graph" })?,
+            data: state
+                .data
+                .ok_or(GenerationError::MissingStage { needs: "elevation" })?,
+            mesh: state
+                .mesh
+                .ok_or(GenerationError::MissingStage { needs: "mesh" })?,
+        })
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.stages.iter().position(|s| s.name() == name)
+    }
+}
+
+/// Samples the points that the terrain cells are built around.
+pub struct PointGen;
+
+impl PipelineStage for PointGen {
+    fn name(&self) -> &str {
+        "points"
+    }
+
+    fn run(&mut self, state: &mut PipelineState) -> Result<(), GenerationError> {
+        let config = &state.context.config;
+        let extent = state.context.extent;
+
+        let mut rand = state.context.rand(RandStream::Points);
+
+        state.points = if config.wrap_x {
+            generate_wrapped_points(&mut rand, &config.sampler, extent, config.radius)
+        } else {
+            generate_points(&mut rand, &config.sampler, extent, config.radius)
+        };
+
+        Ok(())
+    }
+}
+
+/// Replaces the points around each of the [centers] with finer points, eg around the cities of a
+/// terrain generated from the same config. Maps that wrap are left as they are, since the points
+/// across the seam would no longer match.
+pub struct RefinePoints {
+    pub centers: Vec<Vec2>,
+}
+
+impl PipelineStage for RefinePoints {
+    fn name(&self) -> &str {
+        "refine"
+    }
+
+    fn run(&mut self, state: &mut PipelineState) -> Result<(), GenerationError> {
+        let config = &state.context.config;
+
+        if config.wrap_x {
+            log::debug!("not refining the points of a wrapping map");
+            return Ok(());
+        }
+
+        let mut rand = state.context.rand(RandStream::Refinement);
+
+        refine_points(
+            &mut rand,
+            &config.sampler,
+            &mut state.points,
+            state.context.extent,
+            &self.centers,
+            config.radius,
+            &config.refine,
+        );
+
+        Ok(())
+    }
+}
+
+/// Places the random landmass, crater and volcano features.
+pub struct FeatureGen;
+
+impl PipelineStage for FeatureGen {
+    fn name(&self) -> &str {
+        "features"
+    }
+
+    fn run(&mut self, state: &mut PipelineState) -> Result<(), GenerationError> {
+        state.features = Some(TerrainFeatures::generate(&state.context));
+        Ok(())
+    }
+}
+
+/// Passes the generated features through a function, eg to add a volcano.
+pub struct EditFeatures<F>(pub Option<F>);
+
+impl<F> PipelineStage for EditFeatures<F>
+where
+    F: FnOnce(TerrainFeatures) -> TerrainFeatures,
+{
+    fn name(&self) -> &str {
+        "edit_features"
+    }
+
+    fn run(&mut self, state: &mut PipelineState) -> Result<(), GenerationError> {
+        let features = state.features.take();
+        let features = features.ok_or(GenerationError::MissingStage { needs: "features" })?;
+
+        // The edit can only run once, after which the features pass through unchanged.
+
+        state.features = Some(match self.0.take() {
+            Some(edit) => edit(features),
+            None => features,
+        });
+
+        Ok(())
+    }
+}
+
+/// Triangulates the points, and wraps or clips the graph to the extent.
+pub struct GraphBuild;
+
+impl PipelineStage for GraphBuild {
+    fn name(&self) -> &str {
+        "graph"
+    }
+
+    fn run(&mut self, state: &mut PipelineState) -> Result<(), GenerationError> {
+        let config = &state.context.config;
+        let extent = state.context.extent;
+
+        // The graph takes the points rather than copying them.
+
+        let mut graph = TerrainGraph::new(std::mem::take(&mut state.points))?;
+
+        if config.wrap_x {
+            graph.wrap_x(extent, config.radius);
+        } else {
+            graph.clip_hull_cells(extent);
+        }
+
+        if graph.interior.is_empty() {
+            return Err(GenerationError::NoInteriorVertices {
+                vertices: graph.vertices.len(),
+            });
+        }
+
+        log::debug!("graph memory: {}", graph.memory_usage());
+
+        state.graph = Some(graph);
+
+        Ok(())
+    }
+}
+
+/// Builds the elevation from the features, with its flow and flux.
+pub struct Elevation;
+
+impl PipelineStage for Elevation {
+    fn name(&self) -> &str {
+        "elevation"
+    }
+
+    fn run(&mut self, state: &mut PipelineState) -> Result<(), GenerationError> {
+        let data = TerrainData::uneroded(&state.context, state.graph()?, state.features()?);
+        state.data = Some(data);
+        Ok(())
+    }
+}
+
+/// Erodes the elevation and sets the sea level.
+pub struct Erosion {
+    pub passes: usize,
+}
+
+impl PipelineStage for Erosion {
+    fn name(&self) -> &str {
+        "erosion"
+    }
+
+    fn run(&mut self, state: &mut PipelineState) -> Result<(), GenerationError> {
+        let graph = state.graph.as_ref();
+        let graph = graph.ok_or(GenerationError::MissingStage { needs: "graph" })?;
+        let data = state.data.as_mut();
+        let data = data.ok_or(GenerationError::MissingStage { needs: "elevation" })?;
+
+        data.erode(graph, self.passes, &state.cancel)
+    }
+}
+
+/// Builds the polygons, coastline, rivers and decorations used to render the terrain.
+pub struct Mesh;
+
+impl PipelineStage for Mesh {
+    fn name(&self) -> &str {
+        "mesh"
+    }
+
+    fn run(&mut self, state: &mut PipelineState) -> Result<(), GenerationError> {
+        let mesh = TerrainMesh::new(&state.context, state.graph()?, state.data()?);
+        state.mesh = Some(mesh);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the stages that had run by the time it ran.
+    struct Probe {
+        name: &'static str,
+        seen: Arc<std::sync::Mutex<Vec<&'static str>>>,
+    }
+
+    impl PipelineStage for Probe {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn run(&mut self, state: &mut PipelineState) -> Result<(), GenerationError> {
+            let mut seen = self.seen.lock().unwrap();
+
+            seen.push(self.name);
+            seen.push(match (&state.features, &state.data) {
+                (None, _) => "no features",
+                (Some(_), None) => "features",
+                (Some(_), Some(_)) => "elevation",
+            });
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn cancelled_run_stops_with_cancelled() {
+        let cancel = CancelToken::new();
+        cancel.cancel();
+
+        let mut pipeline = Pipeline::default();
+        pipeline.cancel_with(cancel);
+
+        let result = pipeline.run(crate::default_config(1));
+
+        assert_eq!(result.err(), Some(GenerationError::Cancelled));
+    }
+
+    #[test]
+    fn inserted_stages_run_in_place() {
+        let seen = Arc::new(std::sync::Mutex::new(vec![]));
+        let probe = |name| Probe {
+            name,
+            seen: seen.clone(),
+        };
+
+        let mut pipeline = Pipeline::default();
+        pipeline.insert_before("features", probe("before"));
+        pipeline.insert_after("elevation", probe("after"));
+
+        pipeline.run(crate::default_config(1)).unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(*seen, ["before", "no features", "after", "elevation"]);
+    }
+}
@@ -1,30 +1,94 @@
 use nannou::geom::*;
 
+use crate::log;
 use crate::terrain::erosion::*;
 use crate::terrain::terrain_features::*;
-use crate::terrain::TerrainGraph;
+use crate::terrain::{
+    CancelToken, ErosionPassStats, GenerationError, TerrainContext, TerrainGraph,
+};
 use crate::util::*;
 
 #[derive(Debug, Clone)]
 pub struct TerrainData {
     /// The elevation of each terrain vertex.
     pub elevation: Vec<f32>,
+    /// The elevation of each terrain vertex mapped from [elevation_bounds] into \[0, 1\], for the
+    /// consumers that want relative height rather than world units.
+    pub elevation_normalized: Vec<f32>,
+    /// The lowest and highest elevation, which map to 0 and 1 in [elevation_normalized].
+    pub elevation_bounds: (f32, f32),
+    /// The rainfall falling on each terrain vertex.
+    pub rainfall: Vec<f32>,
     /// The surface normal of each terrain vertex.
     pub normal: Vec<Vec3>,
     /// The flow of water in each terrain vertex, expressed as the index of a downhill vertex.
     pub flow: Vec<Flow>,
+    /// The vertices that flow directly into each terrain vertex, the inverse of [flow].
+    pub upstream: Vec<Vec<usize>>,
+    /// The outlet on the boundary that each terrain vertex ultimately drains to. The vertices
+    /// sharing an outlet form its drainage basin, and the edges between basins are the divides.
+    pub watershed: Vec<usize>,
+    /// True if a terrain vertex is in a closed basin, a depression that drains nowhere until it
+    /// fills to the lowest point of its rim.
+    pub closed_basin: Vec<bool>,
     /// The flux of water in each terrain vertex.
     pub flux: Vec<f32>,
+    /// The groundwater accumulated below each terrain vertex, as its flux averaged with the flux
+    /// of its neighbors (water seeps sideways underground).
+    pub groundwater: Vec<f32>,
+    /// The wetness of each terrain vertex in \[0, 1\], from the water flowing through and under it,
+    /// the rain falling on it and its distance from the sea. Water vertices are fully wet.
+    pub moisture: Vec<f32>,
+    /// The mean summer temperature of each terrain vertex in degrees, falling from the bottom of
+    /// the map to the top and with height above sea level.
+    pub temperature: Vec<f32>,
+    /// The resistance of the rock to erosion at each terrain vertex. Erosion is divided by the
+    /// hardness, so hard rock is left standing as ridges and soft rock wears down into basins.
+    pub hardness: Vec<f32>,
     /// The erosion scalar at each terrain vertex.
     pub erosion: Vec<f32>,
+    /// The elevation of each terrain vertex after each erosion pass, starting with the uneroded
+    /// elevation. Used to replay the erosion as an animation.
+    pub erosion_history: Vec<Vec<f32>>,
+    /// What each erosion pass did, for tuning the erosion constants.
+    pub erosion_stats: Vec<ErosionPassStats>,
+    /// The exaggeration of the elevation in the surface normals, from [TerrainConfig::vertical_scale].
+    pub vertical_scale: f32,
 }
 
+/// The number of erosion passes run on the feature elevation.
+pub const EROSION_PASSES: usize = 5;
+/// The elevation removed by each unit of the erosion scalar in each pass.
+pub const EROSION_SCALAR: f32 = 500.0;
+
 impl TerrainData {
-    pub fn new(graph: &TerrainGraph, features: &TerrainFeatures) -> Self {
+    /// Build the elevation from the [features] alone, and derive the flow, flux and the rest from
+    /// it, without any erosion.
+    pub fn uneroded(
+        context: &TerrainContext,
+        graph: &TerrainGraph,
+        features: &TerrainFeatures,
+    ) -> Self {
         let mut elevation = vec![0f32; graph.vertices.len()];
 
         for feature in features.cones.iter() {
-            add_elevation_cone(&mut elevation, &graph.vertices, feature);
+            add_elevation_cone(&mut elevation, graph, feature);
+        }
+
+        for feature in features.craters.iter() {
+            add_elevation_crater(&mut elevation, graph, feature);
+        }
+
+        for feature in features.volcanoes.iter() {
+            add_elevation_volcano(&mut elevation, graph, feature);
+        }
+
+        for feature in features.trenches.iter() {
+            add_elevation_trench(&mut elevation, graph, feature);
+        }
+
+        for feature in features.rifts.iter() {
+            add_elevation_rift(&mut elevation, graph, feature);
         }
 
         for feature in features.slopes.iter() {
@@ -39,6 +103,8 @@ impl TerrainData {
             relax(graph, &mut elevation);
         }
 
+        sanitize_field("elevation", &mut elevation);
+
         // Original implementation normalizes the elevation data here. I skip this step because it
         // introduces some oddities with mixing normalized and non-normalized data. Namely the
         // slope calculations combine the world space XY coordinates of the vertices with the
@@ -48,34 +114,133 @@ impl TerrainData {
 
         // Instead we can preserve the original elevation and work in world coordinates during the
         // the slope and erosion computations. The political features (cities, towns, regions)
-        // still benefit from normalized elevation data, so they calculate it there.
+        // still benefit from normalized elevation data, so it is kept alongside the original in
+        // [elevation_normalized].
 
-        let mut flow = generate_flow(graph, &elevation);
-        let mut flux = generate_flux(graph, &flow);
-        let mut normal = generate_normal(graph, &elevation);
-        let mut erosion = generate_erosion(graph, &flux, &normal);
+        let rainfall = generate_rainfall(graph, context.config.rainfall);
+        let hardness = generate_hardness(graph, &features.rocks);
+
+        let mut data = Self {
+            elevation,
+            elevation_normalized: vec![],
+            elevation_bounds: (0.0, 0.0),
+            rainfall,
+            hardness,
+            normal: vec![],
+            flow: vec![],
+            upstream: vec![],
+            watershed: vec![],
+            closed_basin: vec![],
+            flux: vec![],
+            groundwater: vec![],
+            moisture: vec![],
+            temperature: vec![],
+            erosion: vec![],
+            erosion_history: vec![],
+            erosion_stats: vec![],
+            vertical_scale: context.config.vertical_scale,
+        };
+
+        data.recompute_from_elevation(graph);
+        data.erosion_history.push(data.elevation.clone());
+
+        data
+    }
 
-        for _ in 0..5 {
-            erode(&mut elevation, &erosion, 500.0);
+    /// Run [passes] erosion passes, then shift the elevation so that half of the terrain is
+    /// below sea level. The [cancel] token is checked before each pass. If it is cancelled the
+    /// elevation is left part way through erosion, and the terrain should be discarded.
+    pub fn erode(
+        &mut self,
+        graph: &TerrainGraph,
+        passes: usize,
+        cancel: &CancelToken,
+    ) -> Result<(), GenerationError> {
+        for _ in 0..passes {
+            cancel.check()?;
+
+            erode(&mut self.elevation, &self.erosion, EROSION_SCALAR);
+
+            self.erosion_stats.push(ErosionPassStats::new(
+                graph,
+                &self.erosion,
+                EROSION_SCALAR,
+                &self.elevation,
+            ));
 
             // recalculate flow/flux/slope/erosion on each iteration
-            flow = generate_flow(graph, &elevation);
-            flux = generate_flux(graph, &flow);
-            normal = generate_normal(graph, &elevation);
-            erosion = generate_erosion(graph, &flux, &normal);
+            self.recompute_from_elevation(graph);
+            self.erosion_history.push(self.elevation.clone());
         }
 
-        set_median_sealevel(&mut elevation);
+        // Shift the history by the same sealevel so the snapshots are comparable.
 
-        // TODO smooth coastline
+        let sealevel = median(&self.elevation);
 
-        Self {
-            elevation,
-            normal,
-            flow,
-            flux,
-            erosion,
+        set_sealevel(&mut self.elevation, sealevel);
+
+        for snapshot in self.erosion_history.iter_mut() {
+            set_sealevel(snapshot, sealevel);
         }
+
+        for stats in self.erosion_stats.iter_mut() {
+            stats.min_elevation -= sealevel;
+            stats.max_elevation -= sealevel;
+        }
+
+        self.normalize_elevation();
+
+        // The temperature falls with height above the sea, so it moves with the sea level.
+        self.temperature = generate_temperature(graph, &self.elevation, self.elevation_bounds);
+
+        // TODO smooth coastline
+
+        Ok(())
+    }
+
+    /// Recalculate the flow, watersheds, flux, normal and erosion of each vertex from the current elevation.
+    /// Call this after editing the elevation in place.
+    pub fn recompute_from_elevation(&mut self, graph: &TerrainGraph) {
+        sanitize_field("elevation", &mut self.elevation);
+        self.normalize_elevation();
+
+        (self.flow, self.closed_basin) = generate_flow(graph, &self.elevation);
+        self.flux = generate_flux(graph, &self.flow, &self.rainfall);
+        self.groundwater = generate_groundwater(graph, &self.flux);
+        self.normal = generate_normal(graph, &self.elevation, self.vertical_scale);
+        self.erosion = generate_erosion(graph, &self.flux, &self.normal, &self.hardness);
+        self.moisture =
+            generate_moisture(graph, &self.elevation, &self.rainfall, &self.groundwater);
+        self.temperature = generate_temperature(graph, &self.elevation, self.elevation_bounds);
+
+        // Degenerate geometry can still produce the odd NaN, which would otherwise spread through
+        // every later pass.
+
+        sanitize_field("flux", &mut self.flux);
+        sanitize_field("groundwater", &mut self.groundwater);
+        sanitize_field("erosion", &mut self.erosion);
+        sanitize_field("moisture", &mut self.moisture);
+        sanitize_field("temperature", &mut self.temperature);
+        sanitize_normals(&mut self.normal);
+
+        graph.sync_seam(&mut self.flow);
+        graph.sync_seam(&mut self.closed_basin);
+        graph.sync_seam(&mut self.flux);
+        graph.sync_seam(&mut self.groundwater);
+        graph.sync_seam(&mut self.normal);
+        graph.sync_seam(&mut self.erosion);
+        graph.sync_seam(&mut self.moisture);
+        graph.sync_seam(&mut self.temperature);
+
+        self.upstream = generate_upstream(&self.flow);
+        self.watershed = generate_watershed(&self.flow, &self.upstream);
+    }
+
+    /// Update the normalized elevation and its bounds from the elevation.
+    fn normalize_elevation(&mut self) {
+        self.elevation_bounds = minmax(&self.elevation).unwrap_or((0.0, 0.0));
+        self.elevation_normalized = self.elevation.clone();
+        normalize(&mut self.elevation_normalized);
     }
 
     // /// Find the mean elevation of a list of vertices.
@@ -101,14 +266,17 @@ impl TerrainData {
     // }
 }
 
-fn add_elevation_cone(elevation: &mut [f32], points: &[Vec2], feature: &Cone) {
+fn add_elevation_cone(elevation: &mut [f32], graph: &TerrainGraph, feature: &Cone) {
     // Deviation from the original work here. Instead of distinguishing between hills and cones
     // as two feature types, cones are generalized with a steepness parameter that introduces an
     // exponential falloff. A steepness of 1 creates a linear falloff, increasing steepness
     // produces a falloff with exponential in-out easing.
 
-    for (i, p) in points.iter().cloned().enumerate() {
-        let d = p - feature.center;
+    // On a wrapping map the distance is measured the short way around, so cones near one side
+    // also rise on the other.
+
+    for (i, p) in graph.vertices.iter().cloned().enumerate() {
+        let d = graph.wrap_delta(p - feature.center);
         let t = saturate(1.0 - d.length() / feature.radius);
         let t = ease_with_power(t, feature.steepness);
 
@@ -116,6 +284,82 @@ fn add_elevation_cone(elevation: &mut [f32], points: &[Vec2], feature: &Cone) {
     }
 }
 
+fn add_elevation_crater(elevation: &mut [f32], graph: &TerrainGraph, feature: &Crater) {
+    // The bowl rises from its floor to the top of the rim, steepening towards the rim, and the
+    // outer wall falls off over one more radius.
+
+    for (i, p) in graph.vertices.iter().cloned().enumerate() {
+        let r = graph.wrap_delta(p - feature.center).length() / feature.radius;
+
+        if r < 1.0 {
+            elevation[i] += feature.rim_height * r.powi(4) - feature.depth * (1.0 - r * r);
+        } else if r < 2.0 {
+            elevation[i] += feature.rim_height * (2.0 - r).powi(2);
+        }
+    }
+}
+
+fn add_elevation_volcano(elevation: &mut [f32], graph: &TerrainGraph, feature: &Volcano) {
+    // A steep cone, with a rounded caldera cut out of the summit.
+
+    let caldera_radius = feature.radius * feature.caldera_radius;
+
+    for (i, p) in graph.vertices.iter().cloned().enumerate() {
+        let d = graph.wrap_delta(p - feature.center).length();
+        let t = saturate(1.0 - d / feature.radius);
+
+        elevation[i] += feature.height * ease_with_power(t, 1.5);
+
+        if d < caldera_radius {
+            elevation[i] -= feature.caldera_depth * (1.0 - (d / caldera_radius).powi(2));
+        }
+    }
+}
+
+/// The distance from [p] to the segment of [length] along [direction] from [origin].
+fn distance_to_line(
+    graph: &TerrainGraph,
+    p: Vec2,
+    origin: Vec2,
+    direction: Vec2,
+    length: f32,
+) -> f32 {
+    let d = graph.wrap_delta(p - origin);
+    let t = d.dot(direction).clamp(0.0, length);
+
+    d.distance(direction * t)
+}
+
+fn add_elevation_trench(elevation: &mut [f32], graph: &TerrainGraph, feature: &Trench) {
+    // The cross section is a rounded channel, deepest along the line.
+
+    for (i, p) in graph.vertices.iter().cloned().enumerate() {
+        let d = distance_to_line(graph, p, feature.origin, feature.direction, feature.length);
+        let r = d / feature.width;
+
+        if r < 1.0 {
+            elevation[i] -= feature.depth * (1.0 - r * r);
+        }
+    }
+}
+
+fn add_elevation_rift(elevation: &mut [f32], graph: &TerrainGraph, feature: &Rift) {
+    // Like a crater stretched along a line: the floor rises to the top of the shoulders at the
+    // width, and the shoulders fall off outside it.
+
+    for (i, p) in graph.vertices.iter().cloned().enumerate() {
+        let d = distance_to_line(graph, p, feature.origin, feature.direction, feature.length);
+
+        if d < feature.width {
+            let r = d / feature.width;
+            elevation[i] += feature.shoulder_height * r.powi(4) - feature.depth * (1.0 - r * r);
+        } else if d < feature.width + feature.shoulder_width {
+            let r = (d - feature.width) / feature.shoulder_width;
+            elevation[i] += feature.shoulder_height * (1.0 - r).powi(2);
+        }
+    }
+}
+
 fn add_elevation_slope(elevation: &mut [f32], points: &[Vec2], feature: &Slope) {
     // I believe mewo generated all slopes as bisecting the center the of extents, and rlguy used
     // something closer to the implementation below, which generates slopes with random origin.
@@ -131,6 +375,135 @@ fn add_elevation_slope(elevation: &mut [f32], points: &[Vec2], feature: &Slope)
     }
 }
 
+/// The range of the combined hardness of overlapping rock bodies.
+const HARDNESS_MIN: f32 = 0.25;
+const HARDNESS_MAX: f32 = 4.0;
+
+/// Find the rock hardness at each vertex. Each rock body scales the hardness towards its own,
+/// easing out to ordinary rock at its edge.
+fn generate_hardness(graph: &TerrainGraph, rocks: &[RockBody]) -> Vec<f32> {
+    let mut hardness = vec![1.0; graph.vertices.len()];
+
+    for rock in rocks.iter() {
+        for (i, p) in graph.vertices.iter().cloned().enumerate() {
+            let d = graph.wrap_delta(p - rock.center);
+            let t = saturate(1.0 - d.length() / rock.radius);
+            let t = ease_with_power(t, 2.0);
+
+            hardness[i] *= rock.hardness.powf(t);
+        }
+    }
+
+    for h in hardness.iter_mut() {
+        *h = h.clamp(HARDNESS_MIN, HARDNESS_MAX);
+    }
+
+    hardness
+}
+
+/// Average the flux of each vertex with the flux of its neighbors.
+fn generate_groundwater(graph: &TerrainGraph, flux: &[f32]) -> Vec<f32> {
+    let mut groundwater = vec![0.0; flux.len()];
+
+    for (i, g) in groundwater.iter_mut().enumerate() {
+        let mut sum = flux[i];
+        let mut div = 1.0;
+
+        for n in graph.connected_vertices(i) {
+            sum += flux[n];
+            div += 1.0;
+        }
+
+        *g = sum / div;
+    }
+
+    groundwater
+}
+
+/// The groundwater at which a vertex is saturated.
+const MOISTURE_GROUNDWATER: f32 = 0.005;
+/// The distance from the sea over which its moisture falls off.
+const MOISTURE_COAST_FALLOFF: f32 = 150.0;
+/// The weights of the groundwater, sea and rainfall in the moisture.
+const MOISTURE_WEIGHTS: (f32, f32, f32) = (0.5, 0.3, 0.2);
+
+fn generate_moisture(
+    graph: &TerrainGraph,
+    elevation: &[f32],
+    rainfall: &[f32],
+    groundwater: &[f32],
+) -> Vec<f32> {
+    let sea_distance = generate_sea_distance(graph, elevation);
+
+    // Rainfall sums to one, so scale it by the vertex count to compare it to an even share.
+    let rain_scale = graph.interior.len().max(1) as f32 * 0.5;
+
+    let (w_ground, w_sea, w_rain) = MOISTURE_WEIGHTS;
+
+    (0..elevation.len())
+        .map(|v| {
+            if elevation[v] < 0.0 {
+                return 1.0;
+            }
+
+            let ground = saturate(groundwater[v] / MOISTURE_GROUNDWATER);
+            let sea = (-sea_distance[v] / MOISTURE_COAST_FALLOFF).exp();
+            let rain = saturate(rainfall[v] * rain_scale);
+
+            saturate(ground * w_ground + sea * w_sea + rain * w_rain)
+        })
+        .collect()
+}
+
+/// The temperature at sea level at the bottom and top of the map, in degrees.
+const TEMPERATURE_SEA_LEVEL: (f32, f32) = (24.0, 8.0);
+/// The fall in temperature from sea level to the highest land, in degrees.
+const TEMPERATURE_LAPSE: f32 = 24.0;
+
+fn generate_temperature(graph: &TerrainGraph, elevation: &[f32], bounds: (f32, f32)) -> Vec<f32> {
+    let (bottom, top) = TEMPERATURE_SEA_LEVEL;
+
+    let ys: Vec<f32> = graph.vertices.iter().map(|v| v.y).collect();
+    let (min_y, max_y) = minmax(&ys).unwrap_or((0.0, 0.0));
+
+    let max_elevation = bounds.1.max(f32::EPSILON);
+
+    (0..elevation.len())
+        .map(|v| {
+            let latitude = unlerp(ys[v], min_y, max_y.max(min_y + f32::EPSILON));
+            let height = saturate(elevation[v] / max_elevation);
+
+            lerp(latitude, bottom, top) - height * TEMPERATURE_LAPSE
+        })
+        .collect()
+}
+
+/// Find the distance along the graph from each vertex to the nearest vertex below sea level.
+fn generate_sea_distance(graph: &TerrainGraph, elevation: &[f32]) -> Vec<f32> {
+    let mut distance = vec![f32::INFINITY; elevation.len()];
+    let mut open = PriorityQueue::new();
+
+    for (v, e) in elevation.iter().enumerate() {
+        if *e < 0.0 {
+            distance[v] = 0.0;
+            open.push(v, 0.0);
+        }
+    }
+
+    while let Some(v) = open.pop() {
+        for n in graph.connected_vertices(v) {
+            let d = distance[v] + graph.distance(v, n);
+
+            if d < distance[n] {
+                distance[n] = d;
+                open.push(n, -d);
+            }
+        }
+    }
+
+    distance
+}
+
 /// Take the square root of each elevation.
 fn smooth(elevation: &mut [f32]) {
     for e in elevation.iter_mut() {
@@ -168,36 +541,63 @@ fn set_sealevel(elevation: &mut [f32], sealevel: f32) {
     }
 }
 
-fn set_median_sealevel(elevation: &mut [f32]) {
-    let median = median(elevation);
-    set_sealevel(elevation, median);
-}
-
 fn median(elevation: &[f32]) -> f32 {
     let mut sorted = elevation.to_owned();
 
-    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted.sort_by(|a, b| a.total_cmp(b));
 
     let sorted_len = sorted.len();
     let sorted_mid = sorted_len / 2;
 
+    if sorted_len == 0 {
+        return 0.0;
+    }
+
     if sorted_len % 2 == 0 {
-        return (sorted[sorted_mid] + sorted[sorted_mid + 1]) * 0.5;
+        return (sorted[sorted_mid - 1] + sorted[sorted_mid]) * 0.5;
     }
 
     sorted[sorted_mid]
 }
 
-/// Find the surface normal of each terrain vertex.
-fn generate_normal(graph: &TerrainGraph, elevation: &[f32]) -> Vec<Vec3> {
+/// Replace the non-finite values of a field with zero, logging how many there were.
+fn sanitize_field(name: &str, values: &mut [f32]) {
+    let count = sanitize(values, 0.0);
+
+    if count > 0 {
+        log::warn!("replaced {} non-finite {} values with zero", count, name);
+    }
+}
+
+/// Replace the non-finite normals with flat ground.
+fn sanitize_normals(normals: &mut [Vec3]) {
+    let mut count = 0;
+
+    for n in normals.iter_mut() {
+        if !n.is_finite() {
+            *n = Vec3::Z;
+            count += 1;
+        }
+    }
+
+    if count > 0 {
+        log::warn!("replaced {} non-finite normals with flat ground", count);
+    }
+}
+
+/// Find the surface normal of each terrain vertex, with the elevation exaggerated by
+/// [vertical_scale].
+fn generate_normal(graph: &TerrainGraph, elevation: &[f32], vertical_scale: f32) -> Vec<Vec3> {
     let mut normals = vec![Vec3::ZERO; elevation.len()];
 
+    let z = |v: usize| elevation[v] * vertical_scale;
+
     for v in graph.interior.iter() {
         let (na, nb, nc) = graph.interior_connected_vertices(*v).unwrap();
 
-        let pa = Vec3::from((graph.vertices[na], elevation[na]));
-        let pb = Vec3::from((graph.vertices[nb], elevation[nb]));
-        let pc = Vec3::from((graph.vertices[nc], elevation[nc]));
+        let pa = Vec3::from((graph.offset(*v, na), z(na)));
+        let pb = Vec3::from((graph.offset(*v, nb), z(nb)));
+        let pc = Vec3::from((graph.offset(*v, nc), z(nc)));
 
         let normal = Vec3::cross(pb - pa, pc - pa).normalize_or_zero();
 
@@ -216,3 +616,48 @@ fn ease_with_power(t: f32, p: f32) -> f32 {
         1.0 - (2.0 - t * 2.0).powf(p) * 0.5
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::small_test_terrain;
+
+    #[test]
+    fn recompute_from_elevation_replaces_non_finite_values() {
+        let mut terrain = small_test_terrain();
+        let data = &mut terrain.data;
+
+        data.elevation[0] = f32::NAN;
+        data.elevation[1] = f32::INFINITY;
+        data.normal.iter_mut().for_each(|n| *n = Vec3::ZERO);
+
+        data.recompute_from_elevation(&terrain.graph);
+
+        assert!(data.elevation.iter().all(|e| e.is_finite()));
+        assert!(data.flux.iter().all(|f| f.is_finite()));
+        assert!(data.erosion.iter().all(|e| e.is_finite()));
+        assert!(data.moisture.iter().all(|m| m.is_finite()));
+        assert!(data.temperature.iter().all(|t| t.is_finite()));
+        assert!(data.normal.iter().all(|n| n.is_finite()));
+    }
+
+    #[test]
+    fn erosion_is_finite_with_zero_length_normals() {
+        let terrain = small_test_terrain();
+        let data = &terrain.data;
+        let normals = vec![Vec3::ZERO; data.normal.len()];
+
+        let erosion = generate_erosion(&terrain.graph, &data.flux, &normals, &data.hardness);
+
+        assert!(erosion.iter().all(|e| e.is_finite()));
+    }
+
+    #[test]
+    fn median_does_not_panic_on_nan() {
+        assert_eq!(median(&[3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&[]), 0.0);
+
+        // NaN sorts above every number rather than panicking the sort.
+        assert_eq!(median(&[f32::NAN, 1.0, 2.0]), 2.0);
+    }
+}
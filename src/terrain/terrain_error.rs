@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// The reasons terrain generation can fail.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenerationError {
+    /// A config field is out of range, eg a zero size or a negative radius.
+    InvalidConfig { field: &'static str, value: f32 },
+    /// A sampled point is NaN or infinite.
+    NonFinitePoint { index: usize },
+    /// Too few points were sampled to triangulate.
+    TooFewPoints { count: usize },
+    /// The triangulation of the points has no triangles, eg because the points are collinear.
+    EmptyTriangulation { points: usize },
+    /// Every vertex of the graph is on the boundary, so there is nowhere to put the terrain.
+    NoInteriorVertices { vertices: usize },
+    /// A pipeline stage ran before the stage that generates what it [needs].
+    MissingStage { needs: &'static str },
+    /// Generation was stopped part way through by its [crate::terrain::CancelToken].
+    Cancelled,
+}
+
+impl fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenerationError::InvalidConfig { field, value } => {
+                write!(f, "invalid config: {} is {}", field, value)
+            }
+            GenerationError::NonFinitePoint { index } => {
+                write!(f, "point {} is not finite", index)
+            }
+            GenerationError::TooFewPoints { count } => {
+                write!(f, "too few points to triangulate ({}, need 3)", count)
+            }
+            GenerationError::EmptyTriangulation { points } => {
+                write!(f, "triangulating {} points produced no triangles", points)
+            }
+            GenerationError::NoInteriorVertices { vertices } => {
+                write!(f, "all {} graph vertices are on the boundary", vertices)
+            }
+            GenerationError::MissingStage { needs } => {
+                write!(f, "no pipeline stage generated the {}", needs)
+            }
+            GenerationError::Cancelled => write!(f, "generation was cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for GenerationError {}
@@ -1,13 +1,177 @@
+use std::io::{Error, ErrorKind};
+
 use nannou::geom::*;
+use nannou::rand::rngs::SmallRng;
 use nannou::rand::Rng;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 
 use crate::rand::*;
 use crate::terrain::TerrainContext;
 
+/// The counts, chances and sizes of the randomly generated features, which decide most of the
+/// character of a map. Ranges are (min, max) pairs, and chances are in \[0, 1\].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeatureGenConfig {
+    /// The number of ordinary cones.
+    pub cones: (u32, u32),
+    pub cone_radius: (f32, f32),
+    pub cone_height: (f32, f32),
+    /// The chance that a cone is steep rather than gentle.
+    #[serde(deserialize_with = "chance")]
+    pub steep_cone_chance: f64,
+    pub steep_cone_steepness: (f32, f32),
+    pub gentle_cone_steepness: (f32, f32),
+    /// The chance of one huge cone.
+    #[serde(deserialize_with = "chance")]
+    pub huge_cone_chance: f64,
+    pub huge_cone_radius: (f32, f32),
+    pub huge_cone_height: (f32, f32),
+    pub huge_cone_steepness: (f32, f32),
+    /// The chance of one huge slope, which tilts the whole map.
+    #[serde(deserialize_with = "chance")]
+    pub slope_chance: f64,
+    pub slope_length: (f32, f32),
+    pub slope_height: (f32, f32),
+    /// The chance that the elevation is relaxed.
+    #[serde(deserialize_with = "chance")]
+    pub relax_chance: f64,
+    #[serde(deserialize_with = "chance")]
+    pub crater_chance: f64,
+    pub crater_radius: (f32, f32),
+    pub crater_depth: (f32, f32),
+    pub crater_rim_height: (f32, f32),
+    #[serde(deserialize_with = "chance")]
+    pub volcano_chance: f64,
+    pub volcano_radius: (f32, f32),
+    pub volcano_height: (f32, f32),
+    /// The radius of the caldera as a fraction of the radius of the volcano.
+    pub caldera_radius: (f32, f32),
+    pub caldera_depth: (f32, f32),
+    /// The chance that a volcano is active.
+    #[serde(deserialize_with = "chance")]
+    pub active_chance: f64,
+    /// The number of rock bodies.
+    pub rocks: (u32, u32),
+    pub rock_radius: (f32, f32),
+    /// The chance that a rock body is harder than the surrounding rock rather than softer.
+    #[serde(deserialize_with = "chance")]
+    pub hard_rock_chance: f64,
+    pub hard_rock_hardness: (f32, f32),
+    pub soft_rock_hardness: (f32, f32),
+    /// The number of basins, cones sunk below the surrounding terrain.
+    pub basins: (u32, u32),
+    pub basin_radius: (f32, f32),
+    pub basin_depth: (f32, f32),
+    pub basin_steepness: (f32, f32),
+    /// The chance of one trench, a long narrow depression.
+    #[serde(deserialize_with = "chance")]
+    pub trench_chance: f64,
+    pub trench_length: (f32, f32),
+    pub trench_width: (f32, f32),
+    pub trench_depth: (f32, f32),
+    /// The chance of one rift valley, a trench between raised shoulders.
+    #[serde(deserialize_with = "chance")]
+    pub rift_chance: f64,
+    pub rift_length: (f32, f32),
+    pub rift_width: (f32, f32),
+    pub rift_depth: (f32, f32),
+    pub rift_shoulder_height: (f32, f32),
+    pub rift_shoulder_width: (f32, f32),
+}
+
+impl Default for FeatureGenConfig {
+    fn default() -> Self {
+        Self {
+            cones: (100, 250),
+            cone_radius: (50.0, 400.0),
+            cone_height: (25.0, 75.0),
+            steep_cone_chance: 0.2,
+            steep_cone_steepness: (2.0, 6.0),
+            gentle_cone_steepness: (1.0, 1.5),
+            huge_cone_chance: 0.5,
+            huge_cone_radius: (300.0, 600.0),
+            huge_cone_height: (50.0, 150.0),
+            huge_cone_steepness: (0.9, 1.1),
+            slope_chance: 0.1,
+            slope_length: (100.0, 300.0),
+            slope_height: (100.0, 300.0),
+            relax_chance: 0.5,
+            crater_chance: 0.15,
+            crater_radius: (40.0, 120.0),
+            crater_depth: (30.0, 80.0),
+            crater_rim_height: (20.0, 60.0),
+            volcano_chance: 0.1,
+            volcano_radius: (100.0, 250.0),
+            volcano_height: (150.0, 300.0),
+            caldera_radius: (0.1, 0.25),
+            caldera_depth: (20.0, 60.0),
+            active_chance: 0.5,
+            rocks: (10, 30),
+            rock_radius: (50.0, 250.0),
+            hard_rock_chance: 0.5,
+            hard_rock_hardness: (1.5, 3.0),
+            soft_rock_hardness: (0.3, 0.7),
+            basins: (0, 3),
+            basin_radius: (100.0, 300.0),
+            basin_depth: (40.0, 120.0),
+            basin_steepness: (1.0, 2.0),
+            trench_chance: 0.25,
+            trench_length: (200.0, 600.0),
+            trench_width: (30.0, 80.0),
+            trench_depth: (40.0, 120.0),
+            rift_chance: 0.1,
+            rift_length: (300.0, 800.0),
+            rift_width: (40.0, 100.0),
+            rift_depth: (40.0, 100.0),
+            rift_shoulder_height: (20.0, 60.0),
+            rift_shoulder_width: (40.0, 120.0),
+        }
+    }
+}
+
+impl FeatureGenConfig {
+    pub fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap()
+    }
+
+    /// Read a config from [json]. Fields that are missing keep their default values, so a config
+    /// only needs the fields it changes.
+    pub fn from_json(json: &Value) -> std::io::Result<Self> {
+        Self::deserialize(json).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid feature config: {}", e),
+            )
+        })
+    }
+}
+
+/// Deserialize a chance, which must be in \[0, 1\].
+fn chance<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let chance = f64::deserialize(deserializer)?;
+
+    if !(0.0..=1.0).contains(&chance) {
+        return Err(D::Error::custom(format!(
+            "chance {} is not between 0 and 1",
+            chance
+        )));
+    }
+
+    Ok(chance)
+}
+
 #[derive(Debug, Clone)]
 pub struct TerrainFeatures {
     pub slopes: Vec<Slope>,
     pub cones: Vec<Cone>,
+    pub craters: Vec<Crater>,
+    pub volcanoes: Vec<Volcano>,
+    pub trenches: Vec<Trench>,
+    pub rifts: Vec<Rift>,
+    pub rocks: Vec<RockBody>,
     pub smooth: bool,
     pub relax: bool,
     pub erode: bool,
@@ -29,72 +193,276 @@ pub struct Cone {
     pub steepness: f32,
 }
 
+/// An impact crater: a bowl sunk below the surrounding terrain inside a raised rim.
+#[derive(Debug, Clone)]
+pub struct Crater {
+    pub center: Vec2,
+    /// The radius of the rim. The rim slopes back down to the surrounding terrain over the same
+    /// distance again outside it.
+    pub radius: f32,
+    /// The depth of the bowl below the surrounding terrain.
+    pub depth: f32,
+    pub rim_height: f32,
+}
+
+/// A volcanic cone with a caldera sunk into its summit.
+#[derive(Debug, Clone)]
+pub struct Volcano {
+    pub center: Vec2,
+    pub radius: f32,
+    pub height: f32,
+    /// The radius of the caldera as a fraction of the radius of the cone.
+    pub caldera_radius: f32,
+    pub caldera_depth: f32,
+    /// True if the volcano is active, and drawn with lava and ash.
+    pub active: bool,
+}
+
+/// A long narrow depression along a straight line, rounded at its ends.
+#[derive(Debug, Clone)]
+pub struct Trench {
+    pub origin: Vec2,
+    /// The unit direction from the origin to the far end.
+    pub direction: Vec2,
+    pub length: f32,
+    /// The distance from the line to the edge of the trench.
+    pub width: f32,
+    pub depth: f32,
+}
+
+/// A rift valley: a trench whose edges rise into raised shoulders either side of it.
+#[derive(Debug, Clone)]
+pub struct Rift {
+    pub origin: Vec2,
+    /// The unit direction from the origin to the far end.
+    pub direction: Vec2,
+    pub length: f32,
+    /// The distance from the line to the top of each shoulder.
+    pub width: f32,
+    pub depth: f32,
+    pub shoulder_height: f32,
+    /// The distance over which each shoulder slopes back down to the surrounding terrain.
+    pub shoulder_width: f32,
+}
+
+/// A body of rock that is harder or softer than the surrounding rock.
+#[derive(Debug, Clone)]
+pub struct RockBody {
+    pub center: Vec2,
+    pub radius: f32,
+    /// The hardness at the center, relative to a hardness of one for ordinary rock.
+    pub hardness: f32,
+}
+
 impl TerrainFeatures {
     /// Generate random terrain features.
-    pub fn generate(context: &mut TerrainContext) -> Self {
+    pub fn generate(context: &TerrainContext) -> Self {
         let expanded_extent = Rect::from_wh(context.extent.wh() * 1.2);
         let smaller_extent = Rect::from_wh(context.extent.wh() * 0.5);
 
+        let config = &context.config.features;
+
         let mut slopes = vec![];
         let mut cones = vec![];
 
-        let rand = &mut context.rand;
+        let rand = &mut context.rand(RandStream::Features);
 
         // add lots of average cones
 
-        for _ in 0..rand.gen_range(100..250) {
-            let steepness = if rand.gen_bool(0.2) {
-                rand.gen_range(2.0..6.0)
+        for _ in 0..count(rand, config.cones) {
+            let steepness = if rand.gen_bool(config.steep_cone_chance) {
+                range(rand, config.steep_cone_steepness)
             } else {
-                rand.gen_range(1.0..1.5)
+                range(rand, config.gentle_cone_steepness)
             };
 
             cones.push(Cone {
                 center: random_point_in_rect(rand, expanded_extent),
-                radius: rand.gen_range(50.0..400.0),
-                height: rand.gen_range(25.0..75.0),
+                radius: range(rand, config.cone_radius),
+                height: range(rand, config.cone_height),
                 steepness,
             });
         }
 
         // maybe add a huge cone
 
-        if rand.gen_bool(0.5) {
+        if rand.gen_bool(config.huge_cone_chance) {
             cones.push(Cone {
                 center: random_point_in_rect(rand, expanded_extent),
-                radius: rand.gen_range(300.0..600.0),
-                height: rand.gen_range(50.0..150.0),
-                steepness: rand.gen_range(0.9..1.1),
+                radius: range(rand, config.huge_cone_radius),
+                height: range(rand, config.huge_cone_height),
+                steepness: range(rand, config.huge_cone_steepness),
             });
         }
 
         // maybe add a huge slope
 
-        if rand.gen_bool(0.1) {
+        if rand.gen_bool(config.slope_chance) {
             let origin = random_point_in_rect(rand, smaller_extent);
             let direction = random_dir(rand);
 
-            let length = rand.gen_range(100.0..300.0);
-            let height = rand.gen_range(100.0..300.0);
+            let length = range(rand, config.slope_length);
+            let height = range(rand, config.slope_height);
 
-            slopes.push(Slope {
-                origin,
-                direction,
-                length,
-                height,
-            });
+            // A slope can't continue across the seam of a wrapping map without a cliff.
+
+            if !context.config.wrap_x {
+                slopes.push(Slope {
+                    origin,
+                    direction,
+                    length,
+                    height,
+                });
+            }
         }
 
         let smooth = false;
-        let relax = rand.gen_bool(0.5);
+        let relax = rand.gen_bool(config.relax_chance);
         let erode = true;
 
+        // rarely add a crater or a volcano
+
+        let mut craters = vec![];
+
+        if rand.gen_bool(config.crater_chance) {
+            craters.push(Crater {
+                center: random_point_in_rect(rand, smaller_extent),
+                radius: range(rand, config.crater_radius),
+                depth: range(rand, config.crater_depth),
+                rim_height: range(rand, config.crater_rim_height),
+            });
+        }
+
+        let mut volcanoes = vec![];
+
+        if rand.gen_bool(config.volcano_chance) {
+            volcanoes.push(Volcano {
+                center: random_point_in_rect(rand, smaller_extent),
+                radius: range(rand, config.volcano_radius),
+                height: range(rand, config.volcano_height),
+                caldera_radius: range(rand, config.caldera_radius),
+                caldera_depth: range(rand, config.caldera_depth),
+                active: rand.gen_bool(config.active_chance),
+            });
+        }
+
+        // The depressions use their own stream so they don't disturb the other features. Basins
+        // are cones with negative height.
+
+        let rand = &mut context.rand(RandStream::Depressions);
+
+        for _ in 0..count(rand, config.basins) {
+            cones.push(Cone {
+                center: random_point_in_rect(rand, expanded_extent),
+                radius: range(rand, config.basin_radius),
+                height: -range(rand, config.basin_depth),
+                steepness: range(rand, config.basin_steepness),
+            });
+        }
+
+        let mut trenches = vec![];
+
+        if rand.gen_bool(config.trench_chance) {
+            trenches.push(Trench {
+                origin: random_point_in_rect(rand, expanded_extent),
+                direction: random_dir(rand),
+                length: range(rand, config.trench_length),
+                width: range(rand, config.trench_width),
+                depth: range(rand, config.trench_depth),
+            });
+        }
+
+        let mut rifts = vec![];
+
+        if rand.gen_bool(config.rift_chance) {
+            rifts.push(Rift {
+                origin: random_point_in_rect(rand, smaller_extent),
+                direction: random_dir(rand),
+                length: range(rand, config.rift_length),
+                width: range(rand, config.rift_width),
+                depth: range(rand, config.rift_depth),
+                shoulder_height: range(rand, config.rift_shoulder_height),
+                shoulder_width: range(rand, config.rift_shoulder_width),
+            });
+        }
+
+        // The rock bodies use their own stream so they don't disturb the elevation features.
+
+        let rand = &mut context.rand(RandStream::Geology);
+
+        let mut rocks = vec![];
+
+        for _ in 0..count(rand, config.rocks) {
+            let hardness = if rand.gen_bool(config.hard_rock_chance) {
+                range(rand, config.hard_rock_hardness)
+            } else {
+                range(rand, config.soft_rock_hardness)
+            };
+
+            rocks.push(RockBody {
+                center: random_point_in_rect(rand, expanded_extent),
+                radius: range(rand, config.rock_radius),
+                hardness,
+            });
+        }
+
         Self {
             slopes,
             cones,
+            craters,
+            volcanoes,
+            trenches,
+            rifts,
+            rocks,
             smooth,
             relax,
             erode,
         }
     }
+
+    /// Add a crater to the features.
+    #[allow(dead_code)]
+    pub fn with_crater(mut self, crater: Crater) -> Self {
+        self.craters.push(crater);
+        self
+    }
+
+    /// Add a volcano to the features.
+    #[allow(dead_code)]
+    pub fn with_volcano(mut self, volcano: Volcano) -> Self {
+        self.volcanoes.push(volcano);
+        self
+    }
+
+    /// Add a trench to the features.
+    #[allow(dead_code)]
+    pub fn with_trench(mut self, trench: Trench) -> Self {
+        self.trenches.push(trench);
+        self
+    }
+
+    /// Add a rift valley to the features.
+    #[allow(dead_code)]
+    pub fn with_rift(mut self, rift: Rift) -> Self {
+        self.rifts.push(rift);
+        self
+    }
+}
+
+/// A random count in the (min, max) range, excluding max, or min if the range is empty.
+fn count(rand: &mut SmallRng, (min, max): (u32, u32)) -> u32 {
+    if max > min {
+        rand.gen_range(min..max)
+    } else {
+        min
+    }
+}
+
+/// A random value in the (min, max) range, or min if the range is empty.
+fn range(rand: &mut SmallRng, (min, max): (f32, f32)) -> f32 {
+    if max > min {
+        rand.gen_range(min..max)
+    } else {
+        min
+    }
 }
@@ -1,7 +1,20 @@
+use std::cell::OnceCell;
+use std::collections::HashMap;
+
 use nannou::geom::*;
+use nannou::glam::vec2;
+
+use crate::log;
+use crate::terrain::GenerationError;
+use crate::util::voronoi::{Voronoi, VoronoiCells};
+use crate::util::{
+    clip_polygon, from_real, heap_size, map_clamp, polygon_centroid, signed_area, to_real, FaceId,
+    HalfEdgeMesh, MemoryUsage, RealVec2,
+};
 
-use crate::util::voronoi;
-use crate::util::voronoi::Voronoi;
+/// The maximum distance (in multiples of the point radius) between a vertex copied across the
+/// seam of a wrapping map and the vertex it duplicates.
+const SEAM_TOLERANCE: f32 = 0.1;
 
 #[derive(Debug, Clone)]
 pub struct TerrainGraph {
@@ -9,16 +22,41 @@ pub struct TerrainGraph {
     pub points: Vec<Vec2>,
     /// The terrain vertices.
     pub vertices: Vec<Vec2>,
+    /// The terrain vertices in f64, which the offsets between vertices are measured from.
+    #[cfg(feature = "f64")]
+    positions: Vec<RealVec2>,
     /// The indices of the boundary vertices.
     pub boundary: Vec<usize>,
     /// The indices of the interior (non-boundary) vertices.
     pub interior: Vec<usize>,
     /// The type of each vertex.
     pub vertex_type: Vec<VertexType>,
+    /// The distance from each vertex to the nearest edge of the extent, negative outside it. The
+    /// sides of a wrapping map are not edges. Infinite until the graph is clipped or wrapped.
+    pub edge_distance: Vec<f32>,
     /// The terrain edges.
     pub edges: Vec<TerrainGraphEdge>,
-    /// The Voronoi tesselation backing the terrain graph.
-    voronoi: Voronoi,
+    /// The Delaunay edges on the convex hull, which have no opposite vertex.
+    pub hull_edges: Vec<TerrainGraphHullEdge>,
+    /// The horizontal range that wraps around, if the map wraps.
+    wrap: Option<Range>,
+    /// The vertex inside the wrapped range that each vertex duplicates. Vertices that are not
+    /// copies across the seam map to themselves.
+    seam: Vec<usize>,
+    /// The polygon of each hull cell closed against the extent, if the hull cells were clipped.
+    hull_polygons: HashMap<usize, Vec<Vec2>>,
+    /// The Voronoi cells around the points.
+    cells: VoronoiCells,
+    /// The Delaunay triangulation of the points, whose faces are the vertices.
+    mesh: HalfEdgeMesh,
+    /// The area and centroid of each Voronoi cell, computed on first use.
+    cell_geometry: OnceCell<Vec<CellGeometry>>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct CellGeometry {
+    area: f32,
+    centroid: Vec2,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -35,22 +73,64 @@ pub struct TerrainGraphEdge {
     pub points: (usize, usize),
 }
 
+#[derive(Debug, Copy, Clone)]
+pub struct TerrainGraphHullEdge {
+    /// The index of the vertex inside the hull edge.
+    pub vertex: usize,
+    /// The indices of the input points forming the hull edge.
+    pub points: (usize, usize),
+}
+
 impl TerrainGraph {
-    pub fn new(points: &Vec<Vec2>) -> Self {
+    /// Build the graph from the [points], taking ownership of them rather than keeping a copy.
+    pub fn new(points: Vec<Vec2>) -> Result<Self, GenerationError> {
+        // Check the points can be triangulated before relying on the triangulation.
+
+        if let Some(index) = points.iter().position(|p| !p.is_finite()) {
+            return Err(GenerationError::NonFinitePoint { index });
+        }
+
+        if points.len() < 3 {
+            return Err(GenerationError::TooFewPoints {
+                count: points.len(),
+            });
+        }
+
         // Generate the Voronoi tesselation for the input points.
 
-        let voronoi = Voronoi::new(points);
+        let Voronoi {
+            cells,
+            vertices,
+            mesh,
+        } = Voronoi::new(&points);
+
+        if mesh.num_faces() == 0 {
+            return Err(GenerationError::EmptyTriangulation {
+                points: points.len(),
+            });
+        }
+
+        // Nearly collinear points make slivers with no area, which are kept but can throw off
+        // the normals and flow around them.
 
-        // Copy the voronoi vertices,
+        let degenerate = mesh
+            .faces()
+            .filter(|f| {
+                let [a, b, c] = mesh.face_points(*f).map(|p| points[p.0]);
+                (b - a).perp_dot(c - a).abs() < f32::EPSILON
+            })
+            .count();
 
-        let vertices = voronoi.vertices.clone();
+        if degenerate > 0 {
+            log::warn!("{} degenerate triangles in the triangulation", degenerate);
+        }
 
         // Construct a lookup from vertex index to vertex type.
 
         let mut vertex_type = vec![VertexType::Interior; vertices.len()];
 
-        for i in voronoi.triangulation.hull.iter() {
-            for v in voronoi.cells[*i].vertices.iter() {
+        for p in mesh.hull().iter() {
+            for v in cells.get(p.0).iter() {
                 vertex_type[*v] = VertexType::Boundary;
             }
         }
@@ -72,117 +152,420 @@ impl TerrainGraph {
         // graph entries for interior vertices; the connections of the boundary vertices are
         // never used.
 
-        let mut edges = Vec::with_capacity(voronoi.triangulation.triangles.len());
+        let mut edges = Vec::with_capacity(mesh.num_half_edges() / 2);
 
-        let mut halfedge_seen = vec![false; voronoi.triangulation.triangles.len()];
+        let mut hull_edges = vec![];
 
-        for i in 0..voronoi.triangulation.triangles.len() {
-            let inc_halfedge = i;
-            let out_halfedge = voronoi.triangulation.halfedges[i];
+        for inc in mesh.half_edges() {
+            let out = match mesh.twin(inc) {
+                Some(out) => out,
+                None => {
+                    let vertex = mesh.face(inc).0;
+                    let points = (mesh.origin(inc).0, mesh.destination(inc).0);
 
-            if halfedge_seen[inc_halfedge] {
-                continue;
-            }
+                    hull_edges.push(TerrainGraphHullEdge { vertex, points });
 
-            if out_halfedge == delaunator::EMPTY {
-                continue;
-            }
+                    continue;
+                }
+            };
 
-            halfedge_seen[inc_halfedge] = true;
-            halfedge_seen[out_halfedge] = true;
+            // Each pair of half-edges makes one edge, taken from the first of the pair.
 
-            let va = voronoi::triangle_of_edge(out_halfedge);
-            let vb = voronoi::triangle_of_edge(inc_halfedge);
-            let vertices = (va, vb);
+            if out < inc {
+                continue;
+            }
 
-            let pa = voronoi.triangulation.triangles[out_halfedge];
-            let pb = voronoi.triangulation.triangles[inc_halfedge];
-            let points = (pa, pb);
+            let vertices = (mesh.face(out).0, mesh.face(inc).0);
+            let points = (mesh.origin(out).0, mesh.origin(inc).0);
 
             edges.push(TerrainGraphEdge { vertices, points });
         }
 
-        Self {
-            points: points.clone(),
+        let vertices_len = vertices.len();
+
+        #[cfg(feature = "f64")]
+        let positions = crate::util::vertex_positions(&points, &mesh);
+
+        Ok(Self {
+            points,
             vertices,
+            #[cfg(feature = "f64")]
+            positions,
             boundary,
             interior,
             vertex_type,
+            edge_distance: vec![f32::INFINITY; vertices_len],
             edges,
-            voronoi,
+            hull_edges,
+            wrap: None,
+            seam: (0..vertices_len).collect(),
+            hull_polygons: HashMap::new(),
+            cells,
+            mesh,
+            cell_geometry: OnceCell::new(),
+        })
+    }
+
+    /// Wrap the graph horizontally around the [extent]. The points must include copies of the
+    /// points near each side shifted across to the other side, so the vertices just beyond each
+    /// side duplicate the vertices just inside the other side. The duplicates are merged into the
+    /// vertices they copy, which connects the graph across the seam.
+    pub fn wrap_x(&mut self, extent: Rect, radius: f32) {
+        self.wrap = Some(extent.x);
+
+        let width = extent.w();
+
+        // Bucket the vertices inside the extent so each copy only searches the vertices nearby.
+
+        let key = |p: Vec2| ((p.x / radius).floor() as i32, (p.y / radius).floor() as i32);
+
+        let mut buckets: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+
+        for v in 0..self.vertices.len() {
+            if !self.is_seam_copy(v) {
+                buckets.entry(key(self.vertices[v])).or_default().push(v);
+            }
         }
+
+        let mut seam: Vec<usize> = (0..self.vertices.len()).collect();
+
+        for (v, s) in seam.iter_mut().enumerate() {
+            if !self.is_seam_copy(v) {
+                continue;
+            }
+
+            let p = self.vertices[v];
+
+            let target = if p.x < extent.left() {
+                p + vec2(width, 0.0)
+            } else {
+                p - vec2(width, 0.0)
+            };
+
+            let (kx, ky) = key(target);
+
+            let nearest = (-1..=1)
+                .flat_map(|dx| (-1..=1).map(move |dy| (kx + dx, ky + dy)))
+                .filter_map(|k| buckets.get(&k))
+                .flatten()
+                .cloned()
+                .min_by(|a, b| {
+                    let da = self.vertices[*a].distance_squared(target);
+                    let db = self.vertices[*b].distance_squared(target);
+                    da.total_cmp(&db)
+                });
+
+            // Copies near the outer edge of the duplicated points are missing some of their
+            // neighbors, so their triangles don't match anything inside the extent. They stay
+            // unmerged, but are left out of the boundary and interior so nothing uses them.
+
+            if let Some(n) = nearest {
+                if self.vertices[n].distance(target) < radius * SEAM_TOLERANCE {
+                    *s = n;
+                }
+            }
+        }
+
+        self.seam = seam;
+
+        let boundary = self.boundary.iter().cloned();
+        let interior = self.interior.iter().cloned();
+
+        self.boundary = boundary.filter(|v| !self.is_seam_copy(*v)).collect();
+        self.interior = interior.filter(|v| !self.is_seam_copy(*v)).collect();
+
+        self.measure_edge_distance(extent);
     }
 
-    /// Get the vertices forming the Voronoi cell around input point [p].
-    pub fn cell(&self, p: usize) -> &[usize] {
-        self.voronoi.cells[p].vertices.as_slice()
+    /// Close the open hull cells so that together with the other cells they cover the [extent].
+    /// Each hull cell is closed through the midpoints of its two hull edges and extended outwards
+    /// from them, then clipped to the extent.
+    pub fn clip_hull_cells(&mut self, extent: Rect) {
+        let hull = self.mesh.hull();
+        let reach = extent.w() + extent.h();
+
+        let mut hull_polygons = HashMap::new();
+
+        for (k, p) in hull.iter().map(|p| p.0).enumerate() {
+            let cell = self.cell(p);
+
+            if cell.is_empty() {
+                continue;
+            }
+
+            let prev = hull[(k + hull.len() - 1) % hull.len()].0;
+            let next = hull[(k + 1) % hull.len()].0;
+
+            // The chain of cell vertices runs between the triangles on the two hull edges. Find
+            // which neighbor the chain ends next to; if the cell has a single vertex both orders
+            // give the same polygon, and the orientation is fixed below.
+
+            let last = cell[cell.len() - 1];
+            let (la, lb, lc) = self.vertex_points(last);
+
+            let (start_nb, end_nb) = if [la, lb, lc].contains(&prev) {
+                (next, prev)
+            } else {
+                (prev, next)
+            };
+
+            let point = self.points[p];
+            let inside = self.vertices[cell[0]];
+
+            let outward = |nb: usize| {
+                let edge = self.points[nb] - point;
+                let normal = edge.perp().normalize_or_zero();
+
+                if normal.dot(inside - point) > 0.0 {
+                    -normal
+                } else {
+                    normal
+                }
+            };
+
+            let start_mid = point.lerp(self.points[start_nb], 0.5);
+            let end_mid = point.lerp(self.points[end_nb], 0.5);
+
+            let start_out = outward(start_nb);
+            let end_out = outward(end_nb);
+            let corner_out = (start_out + end_out).normalize_or_zero();
+
+            let mut polygon: Vec<Vec2> = cell.iter().map(|v| self.vertices[*v]).collect();
+
+            polygon.push(end_mid);
+            polygon.push(end_mid + end_out * reach);
+            polygon.push(point + corner_out * reach);
+            polygon.push(start_mid + start_out * reach);
+            polygon.push(start_mid);
+
+            if signed_area(&polygon) < 0.0 {
+                polygon.reverse();
+            }
+
+            let polygon = clip_polygon(&polygon, extent);
+
+            if polygon.len() >= 3 {
+                hull_polygons.insert(p, polygon);
+            }
+        }
+
+        self.hull_polygons = hull_polygons;
+        self.cell_geometry = OnceCell::new();
+
+        self.measure_edge_distance(extent);
     }
 
-    pub fn is_hull_cell(&self, p: usize) -> bool {
-        self.voronoi.cells[p].hull
+    fn measure_edge_distance(&mut self, extent: Rect) {
+        let wraps = self.wrap.is_some();
+
+        for (d, p) in self.edge_distance.iter_mut().zip(self.vertices.iter()) {
+            let y = f32::min(p.y - extent.bottom(), extent.top() - p.y);
+
+            *d = if wraps {
+                y
+            } else {
+                y.min(f32::min(p.x - extent.left(), extent.right() - p.x))
+            };
+        }
     }
 
-    /// Iterate over the vertex indices connected to vertex [v].
-    pub fn connected_vertices(&self, v: usize) -> ConnectedVerticesIterator {
-        ConnectedVerticesIterator {
-            voronoi: &self.voronoi,
-            vertex: v,
-            offset: 0,
+    /// A factor rising linearly from 0 at the edge of the extent to 1 at [width] inside it, for
+    /// fading values out towards the edges of the map.
+    pub fn edge_falloff(&self, v: usize, width: f32) -> f32 {
+        map_clamp(self.edge_distance[v], 0.0, width, 0.0, 1.0)
+    }
+
+    /// True if the vertex [v] lies beyond the seam of a wrapping map, duplicating a vertex on the
+    /// other side.
+    pub fn is_seam_copy(&self, v: usize) -> bool {
+        match self.wrap {
+            Some(range) => {
+                let x = self.vertices[v].x;
+                x < range.start || x >= range.end
+            }
+            None => false,
         }
     }
 
-    /// Get a triplet tuple of connected vertex indices for an interior vertex. Returns None if the vertex is a boundary vertex.
-    pub fn interior_connected_vertices(&self, v: usize) -> Option<(usize, usize, usize)> {
-        // We can find the vertex neighbors by finding the three half-edges that compose the
-        // corresponding triangle (each Voronoi vertex is the center of a Delaunay triangle). For
-        // each half-edge, we find its opposite half-edge in the triangulation. If the opposite
-        // half-edge is empty, no neighboring vertex exists.
+    /// Copy the value of each vertex inside a wrapping map to its duplicates across the seam.
+    pub fn sync_seam<T: Clone>(&self, values: &mut [T]) {
+        if self.wrap.is_none() {
+            return;
+        }
 
-        if self.vertex_type[v] == VertexType::Boundary {
-            return None;
+        for (v, s) in self.seam.iter().cloned().enumerate() {
+            if s != v {
+                values[v] = values[s].clone();
+            }
         }
+    }
 
-        let (ea, eb, ec) = voronoi::edge_tuple_of_triangle(v);
+    /// Shorten the displacement [d] to the nearest copy across the seam of a wrapping map.
+    pub fn wrap_delta(&self, d: Vec2) -> Vec2 {
+        match self.wrap {
+            Some(range) => {
+                let width = range.end - range.start;
+                vec2(d.x - width * (d.x / width).round(), d.y)
+            }
+            None => d,
+        }
+    }
 
-        let ha = self.voronoi.triangulation.halfedges[ea];
-        let hb = self.voronoi.triangulation.halfedges[eb];
-        let hc = self.voronoi.triangulation.halfedges[ec];
+    /// The displacement from vertex [a] to vertex [b], the short way around a wrapping map.
+    pub fn offset(&self, a: usize, b: usize) -> Vec2 {
+        self.wrap_delta(from_real(self.position(b) - self.position(a)))
+    }
 
-        assert!(ha != delaunator::EMPTY && hb != delaunator::EMPTY && hc != delaunator::EMPTY);
+    /// The displacement from vertex [v] to input point [p], the short way around a wrapping map.
+    pub fn offset_to_point(&self, v: usize, p: usize) -> Vec2 {
+        self.wrap_delta(from_real(to_real(self.points[p]) - self.position(v)))
+    }
 
-        let ta = voronoi::triangle_of_edge(ha);
-        let tb = voronoi::triangle_of_edge(hb);
-        let tc = voronoi::triangle_of_edge(hc);
+    /// The position of vertex [v] at the precision of [crate::util::Real].
+    #[cfg(feature = "f64")]
+    fn position(&self, v: usize) -> RealVec2 {
+        self.positions[v]
+    }
 
-        Some((ta, tb, tc))
+    /// The position of vertex [v] at the precision of [crate::util::Real].
+    #[cfg(not(feature = "f64"))]
+    fn position(&self, v: usize) -> RealVec2 {
+        self.vertices[v]
     }
-}
 
-pub struct ConnectedVerticesIterator<'a> {
-    voronoi: &'a Voronoi,
-    /// The vertex to iterate around.
-    vertex: usize,
-    /// The current half-edge index (0/1/2).
-    offset: usize,
-}
+    /// The distance between vertices [a] and [b], the short way around a wrapping map.
+    pub fn distance(&self, a: usize, b: usize) -> f32 {
+        self.offset(a, b).length()
+    }
 
-impl Iterator for ConnectedVerticesIterator<'_> {
-    type Item = usize;
+    /// The positions along a path of connected vertices. On a wrapping map the path carries on
+    /// past the seam instead of jumping back across the map.
+    pub fn path_points(&self, path: &[usize]) -> Vec<Vec2> {
+        let mut points: Vec<Vec2> = Vec::with_capacity(path.len());
 
-    fn next(&mut self) -> Option<Self::Item> {
-        while self.offset < 3 {
-            let incoming = self.vertex * 3 + self.offset;
-            let outgoing = self.voronoi.triangulation.halfedges[incoming];
+        for (i, v) in path.iter().cloned().enumerate() {
+            match i {
+                0 => points.push(self.vertices[v]),
+                _ => points.push(points[i - 1] + self.offset(path[i - 1], v)),
+            }
+        }
 
-            self.offset += 1;
+        points
+    }
 
-            if outgoing == delaunator::EMPTY {
-                continue;
+    /// Get the vertices forming the Voronoi cell around input point [p].
+    pub fn cell(&self, p: usize) -> &[usize] {
+        self.cells.get(p)
+    }
+
+    pub fn is_hull_cell(&self, p: usize) -> bool {
+        self.cells.is_hull(p)
+    }
+
+    /// Get the closed polygon of the Voronoi cell around input point [p]. Hull cells only have a
+    /// polygon once they have been clipped with [clip_hull_cells].
+    pub fn cell_polygon(&self, p: usize) -> Option<Vec<Vec2>> {
+        if self.is_hull_cell(p) {
+            return self.hull_polygons.get(&p).cloned();
+        }
+
+        Some(self.cell(p).iter().map(|v| self.vertices[*v]).collect())
+    }
+
+    /// Get the area of the Voronoi cell around input point [p]. Unclipped hull cells are
+    /// unbounded, so their area is zero.
+    pub fn cell_area(&self, p: usize) -> f32 {
+        self.cell_geometry()[p].area
+    }
+
+    /// Get the centroid of the Voronoi cell around input point [p]. Unclipped hull cells are
+    /// unbounded, so their centroid is the input point itself.
+    pub fn cell_centroid(&self, p: usize) -> Vec2 {
+        self.cell_geometry()[p].centroid
+    }
+
+    fn cell_geometry(&self) -> &[CellGeometry] {
+        self.cell_geometry.get_or_init(|| {
+            let mut geometry = Vec::with_capacity(self.points.len());
+
+            for (i, point) in self.points.iter().cloned().enumerate() {
+                let polygon = match self.cell_polygon(i) {
+                    Some(polygon) => polygon,
+                    None => {
+                        geometry.push(CellGeometry {
+                            area: 0.0,
+                            centroid: point,
+                        });
+
+                        continue;
+                    }
+                };
+
+                geometry.push(CellGeometry {
+                    area: signed_area(&polygon).abs(),
+                    centroid: polygon_centroid(&polygon),
+                });
             }
 
-            return Some(voronoi::triangle_of_edge(outgoing));
+            geometry
+        })
+    }
+
+    /// Iterate over the vertex indices connected to vertex [v].
+    pub fn connected_vertices(&self, v: usize) -> impl Iterator<Item = usize> + '_ {
+        self.mesh.face_neighbors(FaceId(v)).map(|f| self.seam[f.0])
+    }
+
+    /// Get the indices of the three input points around vertex [v], ie the corners of the
+    /// Delaunay triangle whose center is the vertex.
+    pub fn vertex_points(&self, v: usize) -> (usize, usize, usize) {
+        let [a, b, c] = self.mesh.face_points(FaceId(v));
+
+        (a.0, b.0, c.0)
+    }
+
+    /// Get a triplet tuple of connected vertex indices for an interior vertex. Returns None if the vertex is a boundary vertex.
+    pub fn interior_connected_vertices(&self, v: usize) -> Option<(usize, usize, usize)> {
+        // Each Voronoi vertex is the center of a Delaunay triangle, and its neighbors are the
+        // centers of the triangles across each of its sides. A boundary vertex is missing some.
+
+        if self.vertex_type[v] == VertexType::Boundary {
+            return None;
         }
 
-        None
+        let mut neighbors = self.connected_vertices(v);
+
+        Some((neighbors.next()?, neighbors.next()?, neighbors.next()?))
+    }
+
+    /// The bytes allocated for each part of the graph, for seeing where the memory of a large map
+    /// goes.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let mut usage = MemoryUsage::default();
+
+        usage.add("points", heap_size(&self.points));
+        usage.add("vertices", heap_size(&self.vertices));
+        #[cfg(feature = "f64")]
+        usage.add("positions", heap_size(&self.positions));
+        usage.add("boundary", heap_size(&self.boundary));
+        usage.add("interior", heap_size(&self.interior));
+        usage.add("vertex_type", heap_size(&self.vertex_type));
+        usage.add("edge_distance", heap_size(&self.edge_distance));
+        usage.add("edges", heap_size(&self.edges));
+        usage.add("hull_edges", heap_size(&self.hull_edges));
+        usage.add("seam", heap_size(&self.seam));
+        usage.add(
+            "hull_polygons",
+            self.hull_polygons.values().map(heap_size).sum::<usize>()
+                + self.hull_polygons.capacity() * std::mem::size_of::<(usize, Vec<Vec2>)>(),
+        );
+        usage.add("cells", self.cells.heap_size());
+        usage.add("mesh", self.mesh.heap_size());
+        usage.add(
+            "cell_geometry",
+            self.cell_geometry.get().map_or(0, heap_size),
+        );
+
+        usage
     }
 }
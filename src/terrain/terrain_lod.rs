@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use nannou::glam::*;
+
+use crate::terrain::terrain_mesh::{TerrainContour, TerrainRiver, TerrainShading};
+use crate::terrain::TerrainGraph;
+use crate::util::{chain_edges, simplify_path};
+
+/// The distance the simplified coastline may stray from the full coastline.
+const LOD_COASTLINE_TOLERANCE: f32 = 2.0;
+/// The size of the grid cells that shading strokes are merged into.
+const LOD_SHADING_CELL: f32 = 15.0;
+/// The minimum mean flux of a river kept in the simplified mesh.
+const LOD_RIVER_FLUX: f32 = 0.01;
+
+/// A simplified version of the terrain mesh for rendering while zoomed out.
+#[derive(Debug, Clone)]
+pub struct TerrainLod {
+    /// The coastline as simplified chains of points.
+    pub coastline: Vec<Vec<Vec2>>,
+    /// The shading strokes, merged so there is at most one stroke per grid cell.
+    pub shading: Vec<TerrainShading>,
+    /// The major rivers.
+    pub rivers: Vec<TerrainRiver>,
+}
+
+impl TerrainLod {
+    pub fn new(
+        graph: &TerrainGraph,
+        contour: &TerrainContour,
+        shading: &[TerrainShading],
+        rivers: &[TerrainRiver],
+    ) -> Self {
+        let mut coastline = vec![];
+
+        for chain in chain_edges(&contour.edges) {
+            let points: Vec<Vec2> = chain.iter().map(|v| graph.vertices[*v]).collect();
+            coastline.push(simplify_path(&points, LOD_COASTLINE_TOLERANCE));
+        }
+
+        let shading = merge_shading(shading, LOD_SHADING_CELL);
+
+        let rivers = rivers
+            .iter()
+            .filter(|r| r.flux >= LOD_RIVER_FLUX)
+            .cloned()
+            .collect();
+
+        Self {
+            coastline,
+            shading,
+            rivers,
+        }
+    }
+}
+
+/// Merge the strokes in each grid cell into a single stroke with the mean position, direction
+/// and weight of the strokes.
+fn merge_shading(shading: &[TerrainShading], cell_size: f32) -> Vec<TerrainShading> {
+    // (sum of midpoints, sum of directions, sum of weights, count)
+    let mut cells: HashMap<(i32, i32), (Vec2, Vec2, f32, f32)> = HashMap::new();
+
+    for stroke in shading.iter() {
+        let (a, b) = stroke.points;
+        let middle = a.lerp(b, 0.5);
+
+        let key = (
+            (middle.x / cell_size).floor() as i32,
+            (middle.y / cell_size).floor() as i32,
+        );
+
+        let cell = cells
+            .entry(key)
+            .or_insert((Vec2::ZERO, Vec2::ZERO, 0.0, 0.0));
+
+        cell.0 += middle;
+        cell.1 += b - a;
+        cell.2 += stroke.weight;
+        cell.3 += 1.0;
+    }
+
+    let mut keys: Vec<(i32, i32)> = cells.keys().cloned().collect();
+    keys.sort(); // keep the output order deterministic
+
+    let mut merged = vec![];
+
+    for key in keys {
+        let (middle, direction, weight, count) = cells[&key];
+
+        let middle = middle / count;
+        let direction = direction / count * 0.5;
+
+        merged.push(TerrainShading {
+            points: (middle - direction, middle + direction),
+            weight: weight / count,
+        });
+    }
+
+    merged
+}
@@ -1,26 +1,74 @@
 use nannou::glam::*;
 use nannou::math::*;
-use nannou::rand::random;
+use nannou::rand::rngs::SmallRng;
+use nannou::rand::Rng;
 
+use std::collections::BTreeMap;
+use std::f32::consts::FRAC_PI_2;
+
+use crate::log;
+use crate::rand::RandStream;
 use crate::terrain::erosion::traverse_flow_graph;
-use crate::terrain::{TerrainData, TerrainGraph};
-use crate::util::{indexed_mean, map_clamp};
+use crate::terrain::open_water::{find_bays, find_open_water, Bay, OpenWater};
+use crate::terrain::{TerrainContext, TerrainData, TerrainGraph, TerrainLod, VertexType};
+use crate::util::{
+    chain_edges, indexed_mean, lerp, map_clamp, merge_polygons, unlerp, PriorityQueue,
+};
 
 #[derive(Debug, Clone)]
 pub struct TerrainMesh {
-    /// The polygon of each terrain cell. No polygons are generated for cells of hull points.
+    /// The polygon of each terrain cell. No polygons are generated for unclipped hull cells.
     pub polygons: Vec<Option<TerrainPolygon>>,
     /// The contour of the terrain coastline.
     pub contour: TerrainContour,
+    /// The contour edges chained into polylines, with fractal detail if coast noise is enabled.
+    pub coastline: Vec<Vec<Vec2>>,
+    /// The distance along the graph from each vertex to the nearest contour vertex.
+    pub coast_distance: Vec<f32>,
     /// Line segments to shade slopes.
     pub shading: Vec<TerrainShading>,
+    /// Line segments to decorate the ocean with waves.
+    pub waves: Vec<TerrainShading>,
+    /// Line segments following the coast at fixed distances offshore, if the shallows are drawn
+    /// as lines.
+    pub shallow_lines: Vec<TerrainShading>,
+    /// Dots scattered offshore, thinning out away from the coast, if the shallows are stippled.
+    pub shallow_stipple: Vec<Stipple>,
+    /// The largest circles of open water, from largest to smallest.
+    pub open_water: Vec<OpenWater>,
+    /// The stretches of water mostly enclosed by land, from widest to narrowest.
+    pub bays: Vec<Bay>,
+    /// The compass rose, placed in the most open water, if there is room for it.
+    pub compass: Option<Compass>,
 
     pub rivers: Vec<TerrainRiver>,
+    /// The indices of the rivers passing through each vertex.
+    pub vertex_rivers: Vec<Vec<usize>>,
+    /// The heads of the rivers, where they begin on the slopes.
+    pub river_sources: Vec<RiverSource>,
+    /// The springs where groundwater seeps out of a slope, feeding small streams.
+    pub springs: Vec<TerrainSpring>,
+    /// The summits of the terrain, ordered from most to least prominent.
+    pub peaks: Vec<Peak>,
+    /// True if a particular terrain polygon is forested.
+    pub forest: Vec<bool>,
+    /// The lines of sharp scarps on the land.
+    pub cliffs: Vec<TerrainCliff>,
+    /// The separate landmasses, ordered from largest to smallest.
+    pub islands: Vec<Island>,
+    /// The index of the island containing each terrain polygon, if it is land.
+    pub island: Vec<Option<usize>>,
 
     /// The elevation of each terrain polygon, as the mean of its vertices.
     pub elevation: Vec<f32>,
     /// The surface type of each terrain polygon.
     pub surface: Vec<TerrainSurface>,
+    /// The quantized elevation bands used for stepped terrace rendering.
+    pub terraces: TerrainTerraces,
+    /// The depth contours offshore, as chains of points, parallel to the configured depths.
+    pub bathymetry: Vec<Vec<Vec<Vec2>>>,
+    /// The simplified mesh used for rendering while zoomed out.
+    pub lod: TerrainLod,
 }
 
 #[derive(Debug, Clone)]
@@ -35,9 +83,24 @@ pub struct TerrainShading {
     pub weight: f32,
 }
 
+#[derive(Debug, Clone, Copy)]
+pub struct Stipple {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Compass {
+    pub center: Vec2,
+    /// The length of the cardinal points.
+    pub radius: f32,
+}
+
 #[derive(Debug, Clone)]
 pub struct TerrainContour {
     pub segments: Vec<(Vec2, Vec2)>,
+    /// The vertex indices of each contour segment.
+    pub edges: Vec<(usize, usize)>,
     /// True if a particular vertex is on the contour.
     pub is_contour: Vec<bool>,
     /// True if a particular vertex is on or inside the contour.
@@ -48,10 +111,168 @@ pub struct TerrainContour {
 pub struct TerrainRiver {
     /// A sequential list of points comprising the river segment.
     pub points: Vec<Vec2>,
+    /// The vertex index of each point.
+    pub vertex_indices: Vec<usize>,
     /// The mean flux across the river segment.
     pub flux: f32,
 }
 
+#[derive(Debug, Clone)]
+pub struct RiverSource {
+    /// The vertex index where the river begins.
+    pub vertex: usize,
+    /// The flux of the river at its source.
+    pub flux: f32,
+    /// A small lake pooled in the triangle around the source, if source lakes are enabled.
+    pub lake: Option<Vec<Vec2>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TerrainTerraces {
+    /// The number of elevation bands.
+    pub num_bands: usize,
+    /// The elevation band of each terrain polygon.
+    pub bands: Vec<usize>,
+    /// The merged outline of each band, as chains of points. Each band outline encloses all of
+    /// the polygons in that band or higher; band zero has no outline.
+    pub outlines: Vec<Vec<Vec<Vec2>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RiverConfig {
+    /// The minimum flux of a vertex to be part of a river.
+    pub flux_threshold: f32,
+    /// The river flux mapped to the minimum and maximum stroke weights.
+    pub weight_flux: (f32, f32),
+    /// The stroke weight of the smallest and largest rivers.
+    pub weight: (f32, f32),
+    /// If set, only keep this many of the highest flux river segments.
+    pub max_rivers: Option<usize>,
+    /// Pool a small lake at the source of each river.
+    pub source_lakes: bool,
+    /// Fill the floors of closed basins on land with water, as lakes without an outlet.
+    pub basin_lakes: bool,
+}
+
+impl Default for RiverConfig {
+    fn default() -> Self {
+        Self {
+            flux_threshold: 0.005,
+            weight_flux: (0.005, 0.025),
+            weight: (3.0, 5.0),
+            max_rivers: None,
+            source_lakes: false,
+            basin_lakes: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShallowConfig {
+    pub style: ShallowStyle,
+    /// The distance offshore covered by the shallows.
+    pub band: f32,
+    /// The distance between the lines, or the mean distance between the dots.
+    pub spacing: f32,
+}
+
+/// How the shallow water along the coast is decorated.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ShallowStyle {
+    None,
+    Stipple,
+    Lines,
+}
+
+impl Default for ShallowConfig {
+    fn default() -> Self {
+        Self {
+            style: ShallowStyle::Lines,
+            band: 25.0,
+            spacing: 6.0,
+        }
+    }
+}
+
+/// The depth contours drawn offshore.
+#[derive(Debug, Clone, Copy)]
+pub struct BathymetryConfig {
+    /// The elevations of the contours, from shallowest to deepest. Empty to draw no contours.
+    pub depths: &'static [f32],
+}
+
+impl Default for BathymetryConfig {
+    fn default() -> Self {
+        Self {
+            depths: &[-50.0, -150.0, -300.0],
+        }
+    }
+}
+
+/// Fractal detail added to the drawn coastline by midpoint displacement.
+#[derive(Debug, Clone, Copy)]
+pub struct CoastNoiseConfig {
+    /// The number of times each coastline segment is halved. 0 leaves the coastline as is.
+    pub iterations: u32,
+    /// The largest displacement of a midpoint, as a fraction of the length of its segment.
+    pub amplitude: f32,
+}
+
+impl Default for CoastNoiseConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 0,
+            amplitude: 0.25,
+        }
+    }
+}
+
+impl RiverConfig {
+    /// The stroke weight of a river with the given mean [flux].
+    pub fn weight(&self, flux: f32) -> f32 {
+        let (flux_min, flux_max) = self.weight_flux;
+        let (weight_min, weight_max) = self.weight;
+
+        map_clamp(flux, flux_min, flux_max, weight_min, weight_max)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TerrainCliff {
+    /// A sequential list of points along the cliff.
+    pub points: Vec<Vec2>,
+    /// The downhill direction at each point, in which the cliff face drops.
+    pub downhill: Vec<Vec2>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Peak {
+    pub vertex: usize,
+    pub elevation: f32,
+    /// The height of the peak above the highest saddle connecting it to higher terrain.
+    pub prominence: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Island {
+    /// The indices of the land polygons forming the island.
+    pub polygons: Vec<usize>,
+    /// The total area of the island's polygons.
+    pub area: f32,
+    /// The area-weighted center of the island's polygons.
+    pub centroid: Vec2,
+    /// The index of the highest peak on the island, if it has one.
+    pub peak: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TerrainSpring {
+    /// The vertex index where the spring emerges.
+    pub vertex: usize,
+    /// The stream flowing from the spring until it joins a river or reaches the coast.
+    pub stream: TerrainRiver,
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum TerrainSurface {
     Water,
@@ -59,7 +280,7 @@ pub enum TerrainSurface {
 }
 
 impl TerrainMesh {
-    pub fn new(graph: &TerrainGraph, data: &TerrainData) -> Self {
+    pub fn new(context: &TerrainContext, graph: &TerrainGraph, data: &TerrainData) -> Self {
         let polygons = generate_polygons(graph);
 
         // Compute the mean elevation of each terrain polygon.
@@ -94,37 +315,158 @@ impl TerrainMesh {
             }
         }
 
-        let shading = generate_shading(graph, &surface, &normals);
+        // Land cells entirely inside a closed basin are the floor of an endorheic lake.
+
+        if context.config.rivers.basin_lakes {
+            for (i, s) in surface.iter_mut().enumerate() {
+                let cell = graph.cell(i);
+
+                if !cell.is_empty() && cell.iter().all(|v| data.closed_basin[*v]) {
+                    *s = TerrainSurface::Water;
+                }
+            }
+        }
+
+        let mut rand = context.rand(RandStream::Shading);
+
+        let light = &context.config.light;
+        let layers = &context.config.layers;
+
+        let shading = if layers.shading {
+            generate_shading(&mut rand, graph, &surface, &normals, light)
+        } else {
+            vec![]
+        };
         let contour = generate_contour(graph, &surface);
+        let coast_distance = generate_coast_distance(graph, &contour);
+
+        let mut rand = context.rand(RandStream::Coastline);
+
+        let coastline = generate_coastline(&mut rand, graph, &contour, &context.config.coast_noise);
+
+        let mut rand = context.rand(RandStream::Decoration);
+
+        let waves = generate_waves(&mut rand, graph, &surface, &coast_distance);
+
+        let shallows = &context.config.shallows;
+
+        let shallow_lines = match shallows.style {
+            ShallowStyle::Lines => {
+                generate_shallow_lines(graph, &surface, &coast_distance, shallows)
+            }
+            _ => vec![],
+        };
+
+        let shallow_stipple = match shallows.style {
+            ShallowStyle::Stipple => {
+                generate_shallow_stipple(&mut rand, graph, &surface, &coast_distance, shallows)
+            }
+            _ => vec![],
+        };
+
+        let open_water = find_open_water(graph, &contour, &coast_distance, OPEN_WATER_CIRCLES);
+        let compass = place_compass(&open_water);
+
+        let radius = context.config.radius;
+        let bays = find_bays(graph, &contour, &coast_distance, radius, MAX_BAYS);
+
+        let river_config = &context.config.rivers;
+
+        let (rivers, river_sources, springs) = if layers.rivers {
+            let rivers = generate_rivers(graph, data, &contour, river_config);
+            let sources = generate_river_sources(graph, data, &contour, &rivers, river_config);
+            let springs = generate_springs(graph, data, &contour, river_config);
+
+            (rivers, sources, springs)
+        } else {
+            (vec![], vec![], vec![])
+        };
+
+        let vertex_rivers = index_rivers(graph, &rivers);
+
+        let peaks = generate_peaks(graph, &data.elevation, PEAK_PROMINENCE);
+        let forest = generate_forest(graph, data, &elevation, &surface, &normals);
+        let cliffs = generate_cliffs(graph, data, &surface);
+        let (islands, island) = generate_islands(graph, &surface, &peaks);
 
-        let rivers = generate_rivers(graph, data, &contour);
+        let terraces = generate_terraces(graph, data, TERRACE_BANDS, layers.contours);
+
+        let bathymetry = if layers.contours {
+            let depths = context.config.bathymetry.depths;
+            generate_bathymetry(graph, &elevation, &surface, depths)
+        } else {
+            vec![]
+        };
+
+        let lod = TerrainLod::new(graph, &contour, &shading, &rivers);
 
         Self {
             polygons,
             elevation,
             surface,
             contour,
+            coastline,
+            coast_distance,
             shading,
+            waves,
+            shallow_lines,
+            shallow_stipple,
+            open_water,
+            bays,
+            compass,
             rivers,
+            vertex_rivers,
+            river_sources,
+            springs,
+            peaks,
+            forest,
+            cliffs,
+            islands,
+            island,
+            terraces,
+            bathymetry,
+            lod,
         }
     }
 }
 
-fn generate_polygons(graph: &TerrainGraph) -> Vec<Option<TerrainPolygon>> {
-    let mut polygons = vec![None; graph.points.len()];
+impl TerrainMesh {
+    /// The index of the island touching vertex [v], if any.
+    pub fn vertex_island(&self, graph: &TerrainGraph, v: usize) -> Option<usize> {
+        let (pa, pb, pc) = graph.vertex_points(v);
 
-    for (i, poly) in polygons.iter_mut().enumerate() {
-        if graph.is_hull_cell(i) {
-            continue;
+        [pa, pb, pc].iter().find_map(|p| self.island[*p])
+    }
+
+    /// Merge the polygons of the cells in each class into outlines with holes, eg the land cells
+    /// of each region. Cells without a class are left out. The classes are in ascending order.
+    pub fn merged_polygons<K, F>(&self, class: F) -> Vec<(K, Vec<Vec<Vec<Vec2>>>)>
+    where
+        K: Ord,
+        F: Fn(usize) -> Option<K>,
+    {
+        let mut classes: BTreeMap<K, Vec<Vec<Vec2>>> = BTreeMap::new();
+
+        for (i, polygon) in self.polygons.iter().enumerate() {
+            if let (Some(polygon), Some(k)) = (polygon, class(i)) {
+                classes.entry(k).or_default().push(polygon.points.clone());
+            }
         }
 
-        let mut points = vec![];
+        classes
+            .into_iter()
+            .map(|(k, polygons)| (k, merge_polygons(&polygons)))
+            .collect()
+    }
+}
 
-        for v in graph.cell(i) {
-            points.push(graph.vertices[*v])
-        }
+fn generate_polygons(graph: &TerrainGraph) -> Vec<Option<TerrainPolygon>> {
+    let mut polygons = vec![None; graph.points.len()];
 
-        *poly = Some(TerrainPolygon { points });
+    for (i, poly) in polygons.iter_mut().enumerate() {
+        *poly = graph
+            .cell_polygon(i)
+            .map(|points| TerrainPolygon { points });
     }
 
     polygons
@@ -132,6 +474,7 @@ fn generate_polygons(graph: &TerrainGraph) -> Vec<Option<TerrainPolygon>> {
 
 fn generate_contour(graph: &TerrainGraph, surface: &[TerrainSurface]) -> TerrainContour {
     let mut segments = vec![];
+    let mut edges = vec![];
     let mut is_contour = vec![false; graph.vertices.len()];
 
     for edge in graph.edges.iter() {
@@ -143,6 +486,7 @@ fn generate_contour(graph: &TerrainGraph, surface: &[TerrainSurface]) -> Terrain
             let vb = graph.vertices[edge.vertices.1];
 
             segments.push((va, vb));
+            edges.push(edge.vertices);
         }
     }
 
@@ -158,72 +502,1004 @@ fn generate_contour(graph: &TerrainGraph, surface: &[TerrainSurface]) -> Terrain
 
     TerrainContour {
         segments,
+        edges,
         is_contour,
         is_surface,
     }
 }
 
+/// Chain the contour edges into polylines, and roughen them by repeatedly displacing the midpoint
+/// of each segment sideways by a random fraction of its length.
+fn generate_coastline(
+    rand: &mut SmallRng,
+    graph: &TerrainGraph,
+    contour: &TerrainContour,
+    config: &CoastNoiseConfig,
+) -> Vec<Vec<Vec2>> {
+    let mut coastline = vec![];
+
+    for chain in chain_edges(&contour.edges) {
+        let mut points: Vec<Vec2> = chain.iter().map(|v| graph.vertices[*v]).collect();
+
+        // The displacement is proportional to the segment length, so each iteration adds detail
+        // at half the scale of the one before. The ends of each segment never move, so closed
+        // chains stay closed.
+
+        for _ in 0..config.iterations {
+            let mut displaced = Vec::with_capacity(points.len() * 2);
+
+            for segment in points.windows(2) {
+                let (a, b) = (segment[0], segment[1]);
+                let offset = (b - a).perp() * config.amplitude * rand.gen_range(-1.0..=1.0);
+
+                displaced.push(a);
+                displaced.push(a.lerp(b, 0.5) + offset);
+            }
+
+            displaced.extend(points.last());
+            points = displaced;
+        }
+
+        coastline.push(points);
+    }
+
+    coastline
+}
+
+/// The minimum prominence of a detected peak.
+const PEAK_PROMINENCE: f32 = 50.0;
+
+fn generate_peaks(graph: &TerrainGraph, elevation: &[f32], min_prominence: f32) -> Vec<Peak> {
+    // Flood the land from the highest vertex downwards. Each vertex either rises out of the flood
+    // as a new island (a peak), extends the island of its neighbors, or joins several islands at a
+    // saddle. When islands join, the lower peak's prominence is its height above the saddle. Each
+    // island is a disjoint set whose root is its peak.
+
+    let mut order: Vec<usize> = (0..graph.vertices.len())
+        .filter(|v| elevation[*v] >= 0.0 && !graph.is_seam_copy(*v))
+        .collect();
+
+    order.sort_by(|a, b| elevation[*b].total_cmp(&elevation[*a]));
+
+    let mut parent: Vec<usize> = (0..graph.vertices.len()).collect();
+    let mut flooded = vec![false; graph.vertices.len()];
+    let mut prominence: Vec<Option<f32>> = vec![None; graph.vertices.len()];
+    let mut summits = vec![];
+
+    for v in order {
+        flooded[v] = true;
+
+        let mut roots: Vec<usize> = graph
+            .connected_vertices(v)
+            .filter(|n| flooded[*n])
+            .map(|n| find_root(&mut parent, n))
+            .collect();
+
+        roots.sort_unstable();
+        roots.dedup();
+
+        let highest = roots
+            .iter()
+            .cloned()
+            .max_by(|a, b| elevation[*a].total_cmp(&elevation[*b]));
+
+        match highest {
+            None => summits.push(v),
+            Some(highest) => {
+                for root in roots {
+                    if root != highest {
+                        prominence[root] = Some(elevation[root] - elevation[v]);
+                        parent[root] = highest;
+                    }
+                }
+
+                parent[v] = highest;
+            }
+        }
+    }
+
+    // Peaks that were never joined to higher terrain rise straight out of the sea.
+
+    let mut peaks: Vec<Peak> = summits
+        .into_iter()
+        .map(|v| Peak {
+            vertex: v,
+            elevation: elevation[v],
+            prominence: prominence[v].unwrap_or(elevation[v]),
+        })
+        .filter(|p| p.prominence >= min_prominence)
+        .collect();
+
+    peaks.sort_by(|a, b| b.prominence.total_cmp(&a.prominence));
+
+    peaks
+}
+
+fn find_root(parent: &mut [usize], v: usize) -> usize {
+    let mut root = v;
+
+    while parent[root] != root {
+        root = parent[root];
+    }
+
+    // Compress the path so later lookups are fast.
+
+    let mut node = v;
+
+    while parent[node] != root {
+        let next = parent[node];
+        parent[node] = root;
+        node = next;
+    }
+
+    root
+}
+
+/// The highest elevation of forest, as a fraction of the highest land elevation.
+const FOREST_MAX_ELEVATION: f32 = 0.6;
+/// The minimum flatness (the vertical component of the surface normal) of forest.
+const FOREST_MIN_FLATNESS: f32 = 0.8;
+/// The fraction of the driest land polygons that are never forested.
+const FOREST_MOISTURE_QUANTILE: f32 = 0.5;
+
+/// Select the forested polygons: land that is low, gently sloped and wetter than most of the
+/// land.
+fn generate_forest(
+    graph: &TerrainGraph,
+    data: &TerrainData,
+    elevation: &[f32],
+    surface: &[TerrainSurface],
+    normals: &[Vec3],
+) -> Vec<bool> {
+    let land: Vec<usize> = (0..surface.len())
+        .filter(|i| surface[*i] == TerrainSurface::Land)
+        .collect();
+
+    let moisture: Vec<f32> = (0..surface.len())
+        .map(|i| indexed_mean(&data.moisture, graph.cell(i)))
+        .collect();
+
+    let mut land_moisture: Vec<f32> = land.iter().map(|i| moisture[*i]).collect();
+    land_moisture.sort_by(|a, b| a.total_cmp(b));
+
+    let quantile = (land_moisture.len() as f32 * FOREST_MOISTURE_QUANTILE) as usize;
+    let min_moisture = land_moisture.get(quantile).cloned().unwrap_or(0.0);
+
+    let max_elevation = land.iter().map(|i| elevation[*i]).fold(0.0, f32::max);
+
+    let mut forest = vec![false; surface.len()];
+
+    for i in land {
+        forest[i] = elevation[i] <= max_elevation * FOREST_MAX_ELEVATION
+            && normals[i].z.abs() >= FOREST_MIN_FLATNESS
+            && moisture[i] >= min_moisture;
+    }
+
+    forest
+}
+
+/// The fraction of land edges that are too gentle to be cliffs. Cliffs are the steepest edges
+/// relative to the rest of the terrain, since the elevation scale varies with the features.
+const CLIFF_SLOPE_QUANTILE: f32 = 0.97;
+/// The minimum number of edges in a cliff line. Shorter chains are dropped as noise.
+const CLIFF_MIN_EDGES: usize = 3;
+
+fn generate_cliffs(
+    graph: &TerrainGraph,
+    data: &TerrainData,
+    surface: &[TerrainSurface],
+) -> Vec<TerrainCliff> {
+    // Elevation is stored on the vertices, so a steep edge runs down the face of the cliff. The
+    // cliff line itself follows the dual edges between the polygons on either side, which cross
+    // each steep edge at a right angle.
+
+    let slope = |a: usize, b: usize| {
+        let run = graph.vertices[a].distance(graph.vertices[b]);
+        let rise = (data.elevation[a] - data.elevation[b]).abs() * data.vertical_scale;
+
+        if run > 0.0 {
+            rise / run
+        } else {
+            0.0
+        }
+    };
+
+    let is_land = |p: usize| surface[p] == TerrainSurface::Land && !graph.is_hull_cell(p);
+
+    let land_edges: Vec<_> = graph
+        .edges
+        .iter()
+        .filter(|e| is_land(e.points.0) && is_land(e.points.1))
+        .collect();
+
+    let mut slopes: Vec<f32> = land_edges
+        .iter()
+        .map(|e| slope(e.vertices.0, e.vertices.1))
+        .collect();
+
+    slopes.sort_by(|a, b| a.total_cmp(b));
+
+    let quantile = (slopes.len() as f32 * CLIFF_SLOPE_QUANTILE) as usize;
+
+    let min_slope = match slopes.get(quantile) {
+        Some(s) => *s,
+        None => return vec![],
+    };
+
+    let cliff_edges: Vec<(usize, usize)> = land_edges
+        .into_iter()
+        .filter(|e| slope(e.vertices.0, e.vertices.1) > min_slope)
+        .map(|e| e.points)
+        .collect();
+
+    let mut cliffs = vec![];
+
+    for chain in chain_edges(&cliff_edges) {
+        if chain.len() <= CLIFF_MIN_EDGES {
+            continue;
+        }
+
+        let points = chain.iter().map(|p| graph.points[*p]).collect();
+
+        let downhill = chain
+            .iter()
+            .map(|p| {
+                // The horizontal part of an upward facing normal points downhill.
+                let normal: Vec3 = graph.cell(*p).iter().map(|v| &data.normal[*v]).sum();
+                let normal = if normal.z < 0.0 { -normal } else { normal };
+
+                normal.xy().normalize_or_zero()
+            })
+            .collect();
+
+        cliffs.push(TerrainCliff { points, downhill });
+    }
+
+    cliffs
+}
+
+/// Group the land polygons into islands by flooding across the edges between them. Returns the
+/// islands, largest first, and the island of each polygon.
+fn generate_islands(
+    graph: &TerrainGraph,
+    surface: &[TerrainSurface],
+    peaks: &[Peak],
+) -> (Vec<Island>, Vec<Option<usize>>) {
+    let is_land = |p: usize| surface[p] == TerrainSurface::Land && !graph.is_hull_cell(p);
+
+    let mut neighbors = vec![vec![]; surface.len()];
+
+    for edge in graph.edges.iter() {
+        let (a, b) = edge.points;
+
+        if is_land(a) && is_land(b) {
+            neighbors[a].push(b);
+            neighbors[b].push(a);
+        }
+    }
+
+    let mut seen = vec![false; surface.len()];
+    let mut components = vec![];
+
+    for start in 0..surface.len() {
+        if !is_land(start) || seen[start] {
+            continue;
+        }
+
+        let mut polygons = vec![];
+        let mut stack = vec![start];
+
+        seen[start] = true;
+
+        while let Some(p) = stack.pop() {
+            polygons.push(p);
+
+            for n in neighbors[p].iter().cloned() {
+                if !seen[n] {
+                    seen[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+
+        components.push(polygons);
+    }
+
+    let mut islands: Vec<Island> = components
+        .into_iter()
+        .map(|polygons| {
+            let area: f32 = polygons.iter().map(|p| graph.cell_area(*p)).sum();
+
+            let centroid = if area > 0.0 {
+                let weighted = polygons.iter().fold(Vec2::ZERO, |sum, p| {
+                    sum + graph.cell_centroid(*p) * graph.cell_area(*p)
+                });
+
+                weighted / area
+            } else {
+                polygons.iter().map(|p| &graph.points[*p]).sum::<Vec2>() / polygons.len() as f32
+            };
+
+            Island {
+                polygons,
+                area,
+                centroid,
+                peak: None,
+            }
+        })
+        .collect();
+
+    islands.sort_by(|a, b| b.area.total_cmp(&a.area));
+
+    let mut island = vec![None; surface.len()];
+
+    for (i, isle) in islands.iter().enumerate() {
+        for p in isle.polygons.iter() {
+            island[*p] = Some(i);
+        }
+    }
+
+    for (i, peak) in peaks.iter().enumerate() {
+        let (pa, pb, pc) = graph.vertex_points(peak.vertex);
+
+        if let Some(k) = [pa, pb, pc].iter().find_map(|p| island[*p]) {
+            let higher = match islands[k].peak {
+                Some(j) => peak.elevation > peaks[j].elevation,
+                None => true,
+            };
+
+            if higher {
+                islands[k].peak = Some(i);
+            }
+        }
+    }
+
+    (islands, island)
+}
+
+const TERRACE_BANDS: usize = 8;
+
+/// Quantize the normalized elevation of each polygon into bands, and outline each band if
+/// [outline] is set.
+fn generate_terraces(
+    graph: &TerrainGraph,
+    data: &TerrainData,
+    num_bands: usize,
+    outline: bool,
+) -> TerrainTerraces {
+    let mut bands = vec![0; graph.points.len()];
+
+    for (i, band) in bands.iter_mut().enumerate() {
+        let t = indexed_mean(&data.elevation_normalized, graph.cell(i));
+        *band = ((t * num_bands as f32) as usize).min(num_bands - 1);
+    }
+
+    // Like stacked paper cutouts, each band layer covers every polygon at or above the band. The
+    // layer outline is made of the edges between a polygon inside the layer and one outside it,
+    // which merges all of the adjacent polygons into a handful of chains.
+
+    let mut outlines = vec![vec![]; num_bands];
+
+    if outline {
+        for (k, outline) in outlines.iter_mut().enumerate().skip(1) {
+            *outline = isolines(graph, |p| bands[p] >= k, |_| true);
+        }
+    }
+
+    TerrainTerraces {
+        num_bands,
+        bands,
+        outlines,
+    }
+}
+
+/// The bathymetric contours at each of the [depths], as chains of points. Each contour separates
+/// the water polygons deeper than its depth from the shallower water polygons, and stops at the
+/// coast.
+fn generate_bathymetry(
+    graph: &TerrainGraph,
+    elevation: &[f32],
+    surface: &[TerrainSurface],
+    depths: &[f32],
+) -> Vec<Vec<Vec<Vec2>>> {
+    let is_water = |p: usize| surface[p] == TerrainSurface::Water;
+
+    depths
+        .iter()
+        .map(|depth| isolines(graph, |p| elevation[p] < *depth, is_water))
+        .collect()
+}
+
+/// The outlines of the polygons that are [inside], as chains of points. Only the edges between
+/// two polygons that are both [included] are part of an outline.
+fn isolines<I, F>(graph: &TerrainGraph, inside: I, included: F) -> Vec<Vec<Vec2>>
+where
+    I: Fn(usize) -> bool,
+    F: Fn(usize) -> bool,
+{
+    let mut edges = vec![];
+
+    for edge in graph.edges.iter() {
+        let (a, b) = edge.points;
+
+        if inside(a) != inside(b) && included(a) && included(b) {
+            edges.push(edge.vertices);
+        }
+    }
+
+    chain_edges(&edges)
+        .iter()
+        .map(|chain| chain.iter().map(|v| graph.vertices[*v]).collect())
+        .collect()
+}
+
+fn generate_coast_distance(graph: &TerrainGraph, contour: &TerrainContour) -> Vec<f32> {
+    let mut distance = vec![f32::INFINITY; graph.vertices.len()];
+    let mut open = PriorityQueue::new();
+
+    for (v, is_contour) in contour.is_contour.iter().enumerate() {
+        if *is_contour {
+            distance[v] = 0.0;
+            open.push(v, 0.0);
+        }
+    }
+
+    // Dijkstra from every contour vertex at once; the queue uses negative distance so the
+    // nearest vertices are processed first.
+
+    while let Some(v) = open.pop() {
+        for n in graph.connected_vertices(v) {
+            let d = distance[v] + graph.distance(v, n);
+
+            if d < distance[n] {
+                distance[n] = d;
+                open.push(n, -d);
+            }
+        }
+    }
+
+    distance
+}
+
+/// The number of circles of open water found for placing decorations and labels.
+const OPEN_WATER_CIRCLES: usize = 8;
+/// The most bays found for naming.
+const MAX_BAYS: usize = 4;
+/// The smallest and largest radius of the compass rose.
+const COMPASS_RADIUS: (f32, f32) = (30.0, 60.0);
+/// The open water needed around the compass rose, in multiples of its radius.
+const COMPASS_CLEARANCE: f32 = 1.5;
+
+/// Place the compass rose at the center of the largest circle of open water.
+fn place_compass(open_water: &[OpenWater]) -> Option<Compass> {
+    let water = open_water.first()?;
+    let radius = (water.radius / COMPASS_CLEARANCE).min(COMPASS_RADIUS.1);
+
+    if radius < COMPASS_RADIUS.0 {
+        return None;
+    }
+
+    Some(Compass {
+        center: water.center,
+        radius,
+    })
+}
+
 fn generate_rivers(
     graph: &TerrainGraph,
     data: &TerrainData,
     contour: &TerrainContour,
+    config: &RiverConfig,
 ) -> Vec<TerrainRiver> {
-    // Construct a list of vertex indices which will compose the rivers. These vertices are on the
-    // surface (on or inside the contour) and have sufficient water flux. I sort the vertices by
-    // their flux from lowest to highest; as we construct the river segments, this means each
-    // segment starts at its most inland nod, which prevents discontinuities.
+    // The river vertices are on the surface (on or inside the contour) and have sufficient water
+    // flux. Each river vertex drains into the next one downstream, so the rivers form a forest.
+
+    let is_river = |v: usize| {
+        graph.vertex_type[v] == VertexType::Interior
+            && contour.is_surface[v]
+            && data.flux[v] >= config.flux_threshold
+    };
+
+    // At each confluence, the upstream branch carrying the most water continues as the main stem
+    // and the others end there.
+
+    let mut has_upstream = vec![false; graph.vertices.len()];
+    let mut main_upstream: Vec<Option<usize>> = vec![None; graph.vertices.len()];
+
+    for v in graph.interior.iter().cloned() {
+        if let (true, Some(down)) = (is_river(v), data.flow[v]) {
+            has_upstream[down] = true;
+
+            let is_main = match main_upstream[down] {
+                Some(m) => data.flux[v]
+                    .total_cmp(&data.flux[m])
+                    .then(m.cmp(&v))
+                    .is_gt(),
+                None => true,
+            };
+
+            if is_main {
+                main_upstream[down] = Some(v);
+            }
+        }
+    }
+
+    // Walk down from each source. The path takes every vertex it is the main stem of, so each flow
+    // edge belongs to exactly one river, and a tributary ends on the confluence vertex. Sources
+    // are ordered from the lowest to highest flux, so the smaller rivers come first.
+
+    let mut sources: Vec<usize> = graph
+        .interior
+        .iter()
+        .cloned()
+        .filter(|v| is_river(*v) && !has_upstream[*v])
+        .collect();
+
+    sources.sort_by(|a, b| data.flux[*a].total_cmp(&data.flux[*b]).then(a.cmp(b)));
+
+    let mut rivers = vec![];
+
+    for source in sources {
+        let mut path = vec![source];
+        let mut v = source;
+
+        while !contour.is_contour[v] {
+            let down = match data.flow[v] {
+                Some(down) => down,
+                None => break,
+            };
+
+            path.push(down);
+
+            if main_upstream[down] != Some(v) || !is_river(down) {
+                break; // a tributary ends where it joins a larger river
+            }
+
+            v = down;
+        }
+
+        if path.len() < 2 {
+            continue;
+        }
+
+        let flux = path.iter().map(|v| data.flux[*v]).sum::<f32>() / path.len() as f32;
+        let points = graph.path_points(&path);
+
+        rivers.push(TerrainRiver {
+            points,
+            vertex_indices: path,
+            flux,
+        });
+    }
+
+    if let Some(max_rivers) = config.max_rivers {
+        // Find the flux of the nth largest river and drop the rivers below it, preserving the
+        // upstream-to-downstream order of the remaining rivers.
+
+        if rivers.len() > max_rivers {
+            let mut fluxes: Vec<f32> = rivers.iter().map(|r| r.flux).collect();
+            fluxes.sort_by(|a, b| b.total_cmp(a));
 
-    let mut indices = vec![];
+            let min_flux = fluxes.get(max_rivers.max(1) - 1).cloned().unwrap_or(0.0);
 
-    for v in graph.interior.iter() {
-        if contour.is_surface[*v] && data.flux[*v] >= 0.005 {
-            indices.push(*v);
+            let count = rivers.len();
+
+            rivers.retain(|r| r.flux >= min_flux);
+            rivers.truncate(max_rivers);
+
+            log::info!("kept the {} largest of {} rivers", rivers.len(), count);
+        }
+    }
+
+    rivers
+}
+
+/// Find the rivers passing through each vertex.
+fn index_rivers(graph: &TerrainGraph, rivers: &[TerrainRiver]) -> Vec<Vec<usize>> {
+    let mut index = vec![vec![]; graph.vertices.len()];
+
+    for (i, river) in rivers.iter().enumerate() {
+        for v in river.vertex_indices.iter() {
+            if !index[*v].contains(&i) {
+                index[*v].push(i);
+            }
+        }
+    }
+
+    index
+}
+
+/// The size of a river source lake, as a fraction of the triangle around the source.
+const SOURCE_LAKE_SIZE: f32 = 0.35;
+
+/// Find the vertices where the [rivers] begin: river vertices that no other river vertex flows
+/// into.
+fn generate_river_sources(
+    graph: &TerrainGraph,
+    data: &TerrainData,
+    contour: &TerrainContour,
+    rivers: &[TerrainRiver],
+    config: &RiverConfig,
+) -> Vec<RiverSource> {
+    let is_river = |v: usize| contour.is_surface[v] && data.flux[v] >= config.flux_threshold;
+
+    let mut has_upstream = vec![false; graph.vertices.len()];
+
+    for v in graph.interior.iter().cloned() {
+        if let (true, Some(down)) = (is_river(v), data.flow[v]) {
+            has_upstream[down] = true;
+        }
+    }
+
+    let mut sources = vec![];
+
+    for v in graph.interior.iter().cloned() {
+        if !is_river(v) || has_upstream[v] || contour.is_contour[v] {
+            continue;
+        }
+
+        // Rivers may have been dropped by the max river count, so only keep sources that still
+        // start a river.
+
+        let position = graph.vertices[v];
+
+        if !rivers.iter().any(|r| r.vertex_indices.first() == Some(&v)) {
+            continue;
         }
+
+        let lake = if config.source_lakes {
+            let (pa, pb, pc) = graph.vertex_points(v);
+            let corners = [pa, pb, pc].map(|p| graph.offset_to_point(v, p));
+
+            Some(
+                corners
+                    .iter()
+                    .map(|c| position + *c * SOURCE_LAKE_SIZE)
+                    .collect(),
+            )
+        } else {
+            None
+        };
+
+        sources.push(RiverSource {
+            vertex: v,
+            flux: data.flux[v],
+            lake,
+        });
     }
 
-    indices.sort_by(|a, b| f32::partial_cmp(&data.flux[*a], &data.flux[*b]).unwrap());
+    sources
+}
+
+const SPRING_MIN_GROUNDWATER: f32 = 0.001;
+const SPRING_MIN_SLOPE: f32 = 0.3;
+const SPRING_SPACING: f32 = 40.0;
+const SPRING_MAX_COUNT: usize = 40;
 
+fn generate_springs(
+    graph: &TerrainGraph,
+    data: &TerrainData,
+    contour: &TerrainContour,
+    config: &RiverConfig,
+) -> Vec<TerrainSpring> {
+    // Springs appear where a lot of groundwater has accumulated under a steep slope, but not
+    // enough surface water to already form a river. Rank the candidates by both and take the
+    // best ones that are not too close to each other.
+
+    let mut candidates = vec![];
+
+    for v in graph.interior.iter().cloned() {
+        if !contour.is_surface[v] || contour.is_contour[v] {
+            continue;
+        }
+
+        let slope = data.normal[v].xy().length();
+        let groundwater = data.groundwater[v];
+
+        if data.flux[v] >= config.flux_threshold {
+            continue;
+        }
+
+        if groundwater >= SPRING_MIN_GROUNDWATER && slope >= SPRING_MIN_SLOPE {
+            candidates.push((v, groundwater * slope));
+        }
+    }
+
+    candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    let mut springs: Vec<TerrainSpring> = vec![];
     let mut seen = vec![false; graph.vertices.len()];
-    let mut rivers = vec![];
 
-    for v in indices {
-        let mut points = vec![];
+    for (v, _) in candidates {
+        if springs.len() >= SPRING_MAX_COUNT {
+            break;
+        }
+
+        let crowded = springs
+            .iter()
+            .any(|s| graph.distance(s.vertex, v) < SPRING_SPACING);
+
+        if crowded || seen[v] {
+            continue;
+        }
+
+        // Follow the flow downhill until the stream joins a river, another stream or the coast.
+
+        let mut path = vec![];
         let mut flux = 0.0;
 
         for n in traverse_flow_graph(&data.flow, v) {
-            points.push(graph.vertices[n]);
+            path.push(n);
             flux += data.flux[n];
 
-            if contour.is_contour[n] {
-                break; // terminate after we reach the contour
+            if data.flux[n] >= config.flux_threshold || contour.is_contour[n] || seen[n] {
+                break;
             }
 
-            if seen[n] {
-                break; // terminate after we reach a vertex we have seen
+            seen[n] = true;
+        }
+
+        flux /= path.len() as f32;
+
+        let points = graph.path_points(&path);
+
+        springs.push(TerrainSpring {
+            vertex: v,
+            stream: TerrainRiver {
+                points,
+                vertex_indices: path,
+                flux,
+            },
+        });
+    }
+
+    springs
+}
+
+const WAVE_COAST_FALLOFF: f32 = 150.0;
+const WAVE_MIN_DENSITY: f32 = 0.05;
+const WAVE_LENGTH: f32 = 6.0;
+
+fn generate_waves(
+    rand: &mut SmallRng,
+    graph: &TerrainGraph,
+    surface: &[TerrainSurface],
+    coast_distance: &[f32],
+) -> Vec<TerrainShading> {
+    // The wave direction is a smooth field built from a few randomly oriented sine waves, which
+    // gives the look of a swirling current without needing a noise library.
+
+    let base = rand.gen_range(0.0..std::f32::consts::TAU);
+
+    let terms: Vec<(Vec2, f32, f32)> = (0..3)
+        .map(|_| {
+            let angle = rand.gen_range(0.0..std::f32::consts::TAU);
+            let frequency = rand.gen_range(0.002..0.008);
+            let phase = rand.gen_range(0.0..std::f32::consts::TAU);
+            let amplitude = rand.gen_range(0.2..0.6);
+
+            (vec2(angle.cos(), angle.sin()) * frequency, phase, amplitude)
+        })
+        .collect();
+
+    let mut waves = vec![];
+
+    for (i, point) in graph.points.iter().cloned().enumerate() {
+        if surface[i] == TerrainSurface::Land || graph.is_hull_cell(i) {
+            continue;
+        }
+
+        // Waves are dense near the coast and become sparse in the open ocean.
+
+        let distance = indexed_mean(coast_distance, graph.cell(i));
+        let t = map_clamp(distance, 0.0, WAVE_COAST_FALLOFF, 0.0, 1.0);
+        let density = lerp(t, 1.0, WAVE_MIN_DENSITY);
+
+        if !rand.gen_bool(density as f64) {
+            continue;
+        }
+
+        let mut angle = base;
+
+        for (frequency, phase, amplitude) in terms.iter() {
+            angle += amplitude * (frequency.dot(point) + phase).sin();
+        }
+
+        let stroke = vec2(angle.cos(), angle.sin()) * WAVE_LENGTH * 0.5;
+
+        waves.push(TerrainShading {
+            points: (point - stroke, point + stroke),
+            weight: 1.0,
+        });
+    }
+
+    waves
+}
+
+/// The stroke weight of the shallow water lines nearest to and farthest from the coast.
+const SHALLOW_LINE_WEIGHT: (f32, f32) = (1.0, 0.3);
+const SHALLOW_STIPPLE_RADIUS: f32 = 0.6;
+
+/// The signed coast distance of each terrain cell, positive offshore and negative inland.
+fn signed_coast_distance(
+    graph: &TerrainGraph,
+    surface: &[TerrainSurface],
+    coast_distance: &[f32],
+) -> Vec<f32> {
+    (0..graph.points.len())
+        .map(|i| {
+            let distance = indexed_mean(coast_distance, graph.cell(i));
+
+            match surface[i] {
+                TerrainSurface::Water => distance,
+                TerrainSurface::Land => -distance,
             }
+        })
+        .collect()
+}
 
-            seen[n] = true;
+fn generate_shallow_lines(
+    graph: &TerrainGraph,
+    surface: &[TerrainSurface],
+    coast_distance: &[f32],
+    config: &ShallowConfig,
+) -> Vec<TerrainShading> {
+    let distance = signed_coast_distance(graph, surface, coast_distance);
+
+    let num_lines = (config.band / config.spacing) as usize;
+
+    let mut lines = vec![];
+
+    // Offset curves of the coastline are the level sets of the coast distance, traced through
+    // each Delaunay triangle with marching triangles.
+
+    for v in graph.interior.iter().cloned() {
+        if graph.is_seam_copy(v) {
+            continue;
         }
 
-        flux /= points.len() as f32;
+        let (a, b, c) = graph.vertex_points(v);
+        let corners = [a, b, c];
 
-        rivers.push(TerrainRiver { points, flux });
+        for k in 1..=num_lines {
+            let level = config.spacing * k as f32;
+            let mut crossings = vec![];
+
+            for (p, q) in [(a, b), (b, c), (c, a)] {
+                let (dp, dq) = (distance[p], distance[q]);
+
+                if (dp < level) != (dq < level) {
+                    let t = unlerp(level, dp, dq);
+                    let pp = graph.vertices[v] + graph.offset_to_point(v, p);
+                    let pq = graph.vertices[v] + graph.offset_to_point(v, q);
+
+                    crossings.push(pp.lerp(pq, t));
+                }
+            }
+
+            if crossings.len() == 2 && !corners.iter().any(|p| graph.is_hull_cell(*p)) {
+                let t = (k - 1) as f32 / num_lines.max(2).saturating_sub(1) as f32;
+
+                lines.push(TerrainShading {
+                    points: (crossings[0], crossings[1]),
+                    weight: lerp(t, SHALLOW_LINE_WEIGHT.0, SHALLOW_LINE_WEIGHT.1),
+                });
+            }
+        }
     }
 
-    rivers
+    lines
+}
+
+fn generate_shallow_stipple(
+    rand: &mut SmallRng,
+    graph: &TerrainGraph,
+    surface: &[TerrainSurface],
+    coast_distance: &[f32],
+    config: &ShallowConfig,
+) -> Vec<Stipple> {
+    let mut stipple = vec![];
+
+    for v in graph.interior.iter().cloned() {
+        if graph.is_seam_copy(v) || coast_distance[v] > config.band {
+            continue;
+        }
+
+        let (a, b, c) = graph.vertex_points(v);
+
+        if [a, b, c]
+            .iter()
+            .any(|p| surface[*p] == TerrainSurface::Land)
+        {
+            continue;
+        }
+
+        // The dots thin out with distance from the coast.
+
+        let corners = [a, b, c].map(|p| graph.vertices[v] + graph.offset_to_point(v, p));
+        let area = (corners[1] - corners[0])
+            .perp_dot(corners[2] - corners[0])
+            .abs()
+            * 0.5;
+
+        let t = coast_distance[v] / config.band;
+        let expected = area / (config.spacing * config.spacing) * (1.0 - t);
+        let count = expected.floor() as usize + rand.gen_bool(expected.fract() as f64) as usize;
+
+        for _ in 0..count {
+            let (mut u, mut w) = (rand.gen::<f32>(), rand.gen::<f32>());
+
+            if u + w > 1.0 {
+                u = 1.0 - u;
+                w = 1.0 - w;
+            }
+
+            stipple.push(Stipple {
+                center: corners[0] + (corners[1] - corners[0]) * u + (corners[2] - corners[0]) * w,
+                radius: SHALLOW_STIPPLE_RADIUS,
+            });
+        }
+    }
+
+    stipple
 }
 
 const SHADING_LIGHT_THRESHOLD: f32 = 0.25;
+const CROSS_HATCH_LIGHT_THRESHOLD: f32 = 0.6;
 const SLOPE_SHADING_STEEPNESS: f32 = 1.0;
 
+#[derive(Debug, Clone, Copy)]
+pub struct LightConfig {
+    /// The direction the light comes from, in degrees clockwise from north.
+    pub azimuth: f32,
+    /// The angle of the light above the horizon, in degrees.
+    pub altitude: f32,
+    /// The azimuth of an optional second light, which cross-hatches the steepest slopes.
+    pub cross_hatch_azimuth: Option<f32>,
+}
+
+impl Default for LightConfig {
+    fn default() -> Self {
+        // Light from the north-west, following the cartographic convention.
+        Self {
+            azimuth: 315.0,
+            altitude: 35.26,
+            cross_hatch_azimuth: None,
+        }
+    }
+}
+
+impl LightConfig {
+    /// The unit vector pointing towards the light.
+    pub fn direction(&self) -> Vec3 {
+        light_direction(self.azimuth, self.altitude)
+    }
+}
+
+fn light_direction(azimuth: f32, altitude: f32) -> Vec3 {
+    let azimuth = azimuth.to_radians();
+    let altitude = altitude.to_radians();
+
+    vec3(
+        azimuth.sin() * altitude.cos(),
+        azimuth.cos() * altitude.cos(),
+        altitude.sin(),
+    )
+}
+
 fn generate_shading(
+    rand: &mut SmallRng,
     graph: &TerrainGraph,
     surface: &[TerrainSurface],
     normals: &[Vec3],
+    light: &LightConfig,
 ) -> Vec<TerrainShading> {
     let mut shading = vec![];
 
-    let light = vec3(1.0, -1.0, -1.0).normalize();
+    let primary = light.direction();
+    let secondary = light
+        .cross_hatch_azimuth
+        .map(|azimuth| light_direction(azimuth, light.altitude));
 
     // This section is significantly different than the original implementation...I couldnt
     // grok the code. But it arrives at a similar style. First do a standard lighting pass by
@@ -232,6 +1508,9 @@ fn generate_shading(
     // weight and length in a straightforward way. Orient the strokes with the elevation
     // gradient as in the Hachure style [0].
     //
+    // The optional second light only shades above a higher threshold, with strokes turned a
+    // quarter turn, so that the steepest slopes are cross-hatched.
+    //
     // [0] https://en.wikipedia.org/wiki/Hachure_map
 
     for (i, point) in graph.points.iter().enumerate() {
@@ -240,37 +1519,72 @@ fn generate_shading(
         }
 
         let normal = normals[i];
-        let shadow = normal.dot(-light) * 0.5 + 0.5;
 
-        if shadow < SHADING_LIGHT_THRESHOLD {
-            continue;
+        let shadow = normal.dot(primary) * 0.5 + 0.5;
+        push_hachure(
+            &mut shading,
+            rand,
+            *point,
+            normal,
+            shadow,
+            SHADING_LIGHT_THRESHOLD,
+            0.0,
+        );
+
+        if let Some(secondary) = secondary {
+            let shadow = normal.dot(secondary) * 0.5 + 0.5;
+            let threshold = CROSS_HATCH_LIGHT_THRESHOLD;
+            push_hachure(
+                &mut shading,
+                rand,
+                *point,
+                normal,
+                shadow,
+                threshold,
+                FRAC_PI_2,
+            );
         }
+    }
 
-        let t = map_range(shadow, SHADING_LIGHT_THRESHOLD, 1.0, 0.0, 1.0);
+    shading
+}
 
-        let angle = normal.x * SLOPE_SHADING_STEEPNESS;
-        let angle = angle + map_range(random(), 0.0, 1.0, -0.1, 0.1);
+/// Add a pair of hachure strokes at [point] if the [shadow] value is above the [threshold].
+fn push_hachure(
+    shading: &mut Vec<TerrainShading>,
+    rand: &mut SmallRng,
+    point: Vec2,
+    normal: Vec3,
+    shadow: f32,
+    threshold: f32,
+    rotation: f32,
+) {
+    if shadow < threshold {
+        return;
+    }
 
-        let stroke = vec2(angle.cos(), angle.sin());
+    let t = map_range(shadow, threshold, 1.0, 0.0, 1.0);
 
-        let length = map_clamp(t, 0.0, 1.0, 2.0, 6.0);
-        let weight = map_clamp(t, 0.0, 1.0, 1.0, 3.0);
+    let angle = normal.x * SLOPE_SHADING_STEEPNESS + rotation;
+    let angle = angle + rand.gen_range(-0.1..0.1);
 
-        let offset = vec2(stroke.y, -stroke.x) * map_range(t, 0.0, 1.0, 1.0, 2.0);
+    let stroke = vec2(angle.cos(), angle.sin());
 
-        let pa = *point;
-        let pb = *point + stroke * length;
+    let length = map_clamp(t, 0.0, 1.0, 2.0, 6.0);
+    let weight = map_clamp(t, 0.0, 1.0, 1.0, 3.0);
 
-        shading.push(TerrainShading {
-            points: (pa - offset, pb - offset),
-            weight,
-        });
+    let offset = vec2(stroke.y, -stroke.x) * map_range(t, 0.0, 1.0, 1.0, 2.0);
 
-        shading.push(TerrainShading {
-            points: (pa + offset, pb + offset),
-            weight,
-        });
-    }
+    let pa = point;
+    let pb = point + stroke * length;
 
-    shading
+    shading.push(TerrainShading {
+        points: (pa - offset, pb - offset),
+        weight,
+    });
+
+    shading.push(TerrainShading {
+        points: (pa + offset, pb + offset),
+        weight,
+    });
 }
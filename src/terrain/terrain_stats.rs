@@ -0,0 +1,202 @@
+use crate::terrain::erosion::generate_erosion::EROSION_MAX;
+use crate::terrain::{Terrain, TerrainGraph, TerrainSurface};
+
+/// The number of elevation bins in the histogram of each erosion pass.
+pub const EROSION_STATS_BINS: usize = 16;
+/// The smallest island counted by [TerrainStats::num_islands].
+pub const SCORE_ISLAND_AREA: f32 = 5000.0;
+
+/// Summary numbers of a generated terrain, for judging the effect of parameter changes.
+#[derive(Debug, Clone)]
+pub struct TerrainStats {
+    /// The fraction of polygons that are land.
+    pub land_fraction: f32,
+    pub num_rivers: usize,
+    /// The number of islands at least [SCORE_ISLAND_AREA] in area, so that rocks off the coast
+    /// are not counted.
+    pub num_islands: usize,
+    /// The variance of the prominence of the peaks. Maps with a few large mountain ranges among
+    /// smaller hills vary more than maps of evenly sized hills.
+    pub prominence_variance: f32,
+    pub min_elevation: f32,
+    pub max_elevation: f32,
+    pub mean_elevation: f32,
+    /// The number of vertices in each of the evenly sized elevation bins between the minimum and
+    /// maximum elevation.
+    pub histogram: Vec<usize>,
+}
+
+impl TerrainStats {
+    pub fn new(terrain: &Terrain, num_bins: usize) -> Self {
+        let graph = &terrain.graph;
+        let surface = &terrain.mesh.surface;
+
+        let num_land = surface
+            .iter()
+            .filter(|s| **s == TerrainSurface::Land)
+            .count();
+
+        let land_fraction = num_land as f32 / surface.len().max(1) as f32;
+
+        // Seam copies duplicate vertices on the other side of a wrapped map, so skip them.
+
+        let elevation: Vec<f32> = terrain
+            .data
+            .elevation
+            .iter()
+            .cloned()
+            .enumerate()
+            .filter(|(v, _)| !graph.is_seam_copy(*v))
+            .map(|(_, e)| e)
+            .collect();
+
+        let (min_elevation, max_elevation) = terrain.data.elevation_bounds;
+        let mean_elevation = elevation.iter().sum::<f32>() / elevation.len().max(1) as f32;
+
+        let histogram = histogram(&elevation, min_elevation, max_elevation, num_bins);
+
+        let num_islands = terrain
+            .mesh
+            .islands
+            .iter()
+            .filter(|i| i.area >= SCORE_ISLAND_AREA)
+            .count();
+
+        let peaks = &terrain.mesh.peaks;
+        let num_peaks = peaks.len().max(1) as f32;
+        let mean_prominence = peaks.iter().map(|p| p.prominence).sum::<f32>() / num_peaks;
+        let prominence_variance = peaks
+            .iter()
+            .map(|p| (p.prominence - mean_prominence).powi(2))
+            .sum::<f32>()
+            / num_peaks;
+
+        Self {
+            land_fraction,
+            num_rivers: terrain.mesh.rivers.len(),
+            num_islands,
+            prominence_variance,
+            min_elevation,
+            max_elevation,
+            mean_elevation,
+            histogram,
+        }
+    }
+}
+
+impl TerrainStats {
+    /// How interesting the map is under [config], between 0 and 1.
+    pub fn score(&self, config: &ScoreConfig) -> f32 {
+        // Each measure is mapped to 0..1, where more islands, rivers and varied mountains are
+        // better but with diminishing returns past the half points.
+
+        let saturate = |value: f32, half: f32| value / (value + half.max(f32::EPSILON));
+
+        let land_error = (self.land_fraction - config.land_fraction).abs();
+        let land_range = config.land_fraction.max(1.0 - config.land_fraction);
+
+        let land = 1.0 - (land_error / land_range).min(1.0);
+        let islands = saturate(self.num_islands as f32, config.islands_half);
+        let rivers = saturate(self.num_rivers as f32, config.rivers_half);
+        let prominence = saturate(self.prominence_variance.sqrt(), config.prominence_half);
+
+        let weights = config.weights;
+        let total = weights.iter().sum::<f32>().max(f32::EPSILON);
+
+        (land * weights[0] + islands * weights[1] + rivers * weights[2] + prominence * weights[3])
+            / total
+    }
+}
+
+/// What makes a map interesting, for searching seeds with [TerrainStats::score].
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreConfig {
+    /// The fraction of land scored highest.
+    pub land_fraction: f32,
+    /// The number of islands that scores half of the island measure.
+    pub islands_half: f32,
+    /// The number of rivers that scores half of the river measure.
+    pub rivers_half: f32,
+    /// The standard deviation of peak prominence that scores half of the mountain measure.
+    pub prominence_half: f32,
+    /// The weights of the land, island, river and mountain measures.
+    pub weights: [f32; 4],
+}
+
+impl Default for ScoreConfig {
+    fn default() -> Self {
+        Self {
+            land_fraction: 0.4,
+            islands_half: 3.0,
+            rivers_half: 20.0,
+            prominence_half: 100.0,
+            weights: [2.0, 1.0, 1.0, 1.0],
+        }
+    }
+}
+
+/// What one erosion pass did to the terrain, for tuning the erosion constants.
+#[derive(Debug, Clone)]
+pub struct ErosionPassStats {
+    /// The total elevation removed from every vertex.
+    pub removed: f32,
+    /// The most elevation removed from a single vertex.
+    pub max_erosion: f32,
+    /// The fraction of vertices whose erosion was clamped to the maximum.
+    pub clamped_fraction: f32,
+    pub min_elevation: f32,
+    pub max_elevation: f32,
+    /// The number of vertices in each of the evenly sized elevation bins between the minimum and
+    /// maximum elevation after the pass.
+    pub histogram: Vec<usize>,
+}
+
+impl ErosionPassStats {
+    /// Measure a pass that removed [erosion] times [scalar] from each vertex, leaving [elevation].
+    pub fn new(graph: &TerrainGraph, erosion: &[f32], scalar: f32, elevation: &[f32]) -> Self {
+        // Seam copies duplicate vertices on the other side of a wrapped map, so skip them.
+
+        let vertices: Vec<usize> = (0..graph.vertices.len())
+            .filter(|v| !graph.is_seam_copy(*v))
+            .collect();
+
+        let removed = vertices.iter().map(|v| erosion[*v] * scalar).sum();
+
+        let max_erosion = vertices
+            .iter()
+            .map(|v| erosion[*v] * scalar)
+            .fold(0.0, f32::max);
+
+        let clamped = vertices
+            .iter()
+            .filter(|v| erosion[**v] >= EROSION_MAX)
+            .count();
+
+        let elevation: Vec<f32> = vertices.iter().map(|v| elevation[*v]).collect();
+
+        let min_elevation = elevation.iter().cloned().fold(f32::INFINITY, f32::min);
+        let max_elevation = elevation.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+
+        Self {
+            removed,
+            max_erosion,
+            clamped_fraction: clamped as f32 / vertices.len().max(1) as f32,
+            min_elevation,
+            max_elevation,
+            histogram: histogram(&elevation, min_elevation, max_elevation, EROSION_STATS_BINS),
+        }
+    }
+}
+
+/// Count the [values] in each of [num_bins] evenly sized bins between [min] and [max].
+fn histogram(values: &[f32], min: f32, max: f32, num_bins: usize) -> Vec<usize> {
+    let mut histogram = vec![0; num_bins];
+    let range = (max - min).max(f32::EPSILON);
+
+    for e in values.iter() {
+        let bin = ((e - min) / range * num_bins as f32) as usize;
+        histogram[bin.min(num_bins - 1)] += 1;
+    }
+
+    histogram
+}
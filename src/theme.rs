@@ -0,0 +1,188 @@
+use colorous::Gradient;
+
+use crate::grid::GridShape;
+use crate::palette::distinct_colors;
+
+/// Presentation options for the final rendered map.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Theme {
+    /// Decorate the ocean with wave strokes.
+    pub ocean_waves: bool,
+    /// Label regions, cities and major rivers.
+    pub labels: bool,
+    /// Tint the land of each region with a muted color and outline the region borders.
+    pub political: bool,
+    /// The number of tree symbols scattered in each forested polygon.
+    pub forest_density: usize,
+    /// The gradients used by the debug modes.
+    pub debug_palette: DebugPalette,
+    /// The border drawn around the map.
+    pub frame: FrameStyle,
+    /// Draw a compass rose in open water.
+    pub compass: bool,
+    /// How the land and water are filled under the ink.
+    pub fill: FillStyle,
+    /// The play grid laid over the map, if any.
+    pub grid: Option<GridShape>,
+    /// The time of year the map is drawn in.
+    pub season: Season,
+}
+
+/// How the land and water are filled under the ink.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FillStyle {
+    /// Plain paper, as a sketched map.
+    Ink,
+    /// Atlas-style hypsometric tints: green lowlands rising through yellow and brown to white
+    /// peaks, and blue water darkening with depth.
+    Hypsometric,
+}
+
+impl FillStyle {
+    /// The next style in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            FillStyle::Ink => FillStyle::Hypsometric,
+            FillStyle::Hypsometric => FillStyle::Ink,
+        }
+    }
+}
+
+/// The lines of the border drawn around the map.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FrameStyle {
+    None,
+    Single,
+    /// A heavy outer line and a thin inner line, with flourishes in the corners.
+    Double,
+}
+
+impl FrameStyle {
+    /// The next style in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            FrameStyle::None => FrameStyle::Single,
+            FrameStyle::Single => FrameStyle::Double,
+            FrameStyle::Double => FrameStyle::None,
+        }
+    }
+}
+
+/// The time of year the map is drawn in. Colder seasons lay snow, freeze the rivers and bare the
+/// trees where it is cold enough.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Season {
+    /// The terrain as generated, without snow.
+    Summer,
+    Winter,
+}
+
+impl Season {
+    /// The next season in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            Season::Summer => Season::Winter,
+            Season::Winter => Season::Summer,
+        }
+    }
+
+    /// How much colder the season is than the summer temperature of the terrain, in degrees.
+    pub fn cooling(&self) -> f32 {
+        match self {
+            Season::Summer => 0.0,
+            Season::Winter => 16.0,
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            ocean_waves: true,
+            labels: true,
+            political: false,
+            forest_density: 3,
+            debug_palette: DebugPalette::Classic,
+            frame: FrameStyle::Double,
+            compass: true,
+            fill: FillStyle::Ink,
+            grid: None,
+            season: Season::Summer,
+        }
+    }
+}
+
+/// A set of gradients for the debug modes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum DebugPalette {
+    /// The original gradients.
+    Classic,
+    /// Perceptually uniform gradients that stay distinguishable with color vision deficiencies.
+    ColorBlind,
+    /// High contrast grayscale for printing.
+    Print,
+}
+
+impl DebugPalette {
+    /// The next palette in the cycle.
+    pub fn next(self) -> Self {
+        match self {
+            DebugPalette::Classic => DebugPalette::ColorBlind,
+            DebugPalette::ColorBlind => DebugPalette::Print,
+            DebugPalette::Print => DebugPalette::Classic,
+        }
+    }
+
+    /// The gradient for elevation, from the deep sea to the highest land.
+    pub fn elevation(&self) -> Gradient {
+        match self {
+            DebugPalette::Classic => colorous::COOL,
+            DebugPalette::ColorBlind => colorous::VIRIDIS,
+            DebugPalette::Print => colorous::GREYS,
+        }
+    }
+
+    /// The gradient for magnitudes such as erosion, hardness and habitability.
+    pub fn intensity(&self) -> Gradient {
+        match self {
+            DebugPalette::Classic => colorous::MAGMA,
+            DebugPalette::ColorBlind => colorous::CIVIDIS,
+            DebugPalette::Print => colorous::GREYS,
+        }
+    }
+
+    /// The gradient for wetness, from dry to wet.
+    pub fn wetness(&self) -> Gradient {
+        match self {
+            DebugPalette::Classic => colorous::YELLOW_GREEN_BLUE,
+            DebugPalette::ColorBlind => colorous::VIRIDIS,
+            DebugPalette::Print => colorous::GREYS,
+        }
+    }
+
+    /// The gradient for amounts of water.
+    pub fn water(&self) -> Gradient {
+        match self {
+            DebugPalette::Classic => colorous::BLUES,
+            DebugPalette::ColorBlind => colorous::CIVIDIS,
+            DebugPalette::Print => colorous::GREYS,
+        }
+    }
+
+    /// The color of category [i] of [n], for telling apart rivers or regions.
+    pub fn category(&self, i: usize, n: usize) -> colorous::Color {
+        match self {
+            DebugPalette::Classic => colorous::SINEBOW.eval_rational(i, n),
+            DebugPalette::ColorBlind => colorous::VIRIDIS.eval_rational(i, n),
+            DebugPalette::Print => colorous::GREYS.eval_continuous(0.3 + 0.7 * i as f64 / n as f64),
+        }
+    }
+
+    /// A distinct color for each of [n] regions. The classic palette is seeded by the map [seed].
+    pub fn regions(&self, seed: u64, n: usize) -> Vec<colorous::Color> {
+        match self {
+            DebugPalette::Classic => distinct_colors(seed, n, 90.0, 60.0),
+            _ => (0..n).map(|i| self.category(i, n)).collect(),
+        }
+    }
+}
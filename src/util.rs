@@ -1,18 +1,45 @@
 use nannou::geom::*;
 use nannou::math::*;
 
+pub mod chain;
+pub use chain::*;
+
 pub mod ext;
 pub use ext::*;
 
+pub mod half_edge;
+pub use half_edge::*;
+
+pub mod hash;
+pub use hash::*;
+
+pub mod memory;
+pub use memory::*;
+
 pub mod path;
 pub use path::*;
 
+pub mod pathfind;
+pub use pathfind::*;
+
 pub mod poisson;
 pub use poisson::*;
 
+pub mod polygon;
+pub use polygon::*;
+
+pub mod precision;
+pub use precision::*;
+
+pub mod sampler;
+pub use sampler::*;
+
 pub mod priority_index;
 pub use priority_index::*;
 
+pub mod timer;
+pub use timer::*;
+
 pub mod voronoi;
 pub use voronoi::*;
 
@@ -63,15 +90,36 @@ pub fn minmax(arr: &[f32]) -> Option<(f32, f32)> {
     Some((min, max))
 }
 
-/// Normalize a slice of f32 into the range \[0.0, 1.0\] using the min and max elements.
+/// Normalize a slice of f32 into the range \[0.0, 1.0\] using the min and max elements. A slice
+/// where every element is equal normalizes to zero.
 pub fn normalize(arr: &mut [f32]) {
     if let Some((min, max)) = minmax(arr) {
+        if max - min <= 0.0 {
+            arr.fill(0.0);
+            return;
+        }
+
         for e in arr.iter_mut() {
             *e = map_range(*e, min, max, 0.0, 1.0);
         }
     }
 }
 
+/// Replace the NaN and infinite elements of a slice with [fallback], returning how many were
+/// replaced.
+pub fn sanitize(arr: &mut [f32], fallback: f32) -> usize {
+    let mut count = 0;
+
+    for e in arr.iter_mut() {
+        if !e.is_finite() {
+            *e = fallback;
+            count += 1;
+        }
+    }
+
+    count
+}
+
 #[allow(dead_code)]
 pub fn map_clamp(val: f32, in_min: f32, in_max: f32, out_min: f32, out_max: f32) -> f32 {
     map_range(val, in_min, in_max, out_min, out_max).clamp(out_min, out_max)
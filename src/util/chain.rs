@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+
+/// Join undirected edges into chains of node indices. Closed chains repeat their first index at
+/// the end; open chains start and end at nodes with an odd number of edges.
+pub fn chain_edges(edges: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let mut adjacency: HashMap<usize, Vec<usize>> = HashMap::new();
+
+    for (i, (a, b)) in edges.iter().cloned().enumerate() {
+        adjacency.entry(a).or_default().push(i);
+        adjacency.entry(b).or_default().push(i);
+    }
+
+    // Start with the odd-degree nodes so open chains are walked from one of their ends instead
+    // of being split somewhere in the middle. The remaining edges all belong to closed loops.
+
+    let mut starts: Vec<usize> = adjacency
+        .iter()
+        .filter(|(_, e)| e.len() % 2 == 1)
+        .map(|(n, _)| *n)
+        .collect();
+
+    starts.sort();
+    starts.extend(edges.iter().map(|e| e.0));
+
+    let mut used = vec![false; edges.len()];
+    let mut chains = vec![];
+
+    for start in starts {
+        loop {
+            let mut chain = vec![start];
+            let mut curr = start;
+
+            while let Some(e) = adjacency[&curr].iter().cloned().find(|e| !used[*e]) {
+                used[e] = true;
+
+                curr = if edges[e].0 == curr {
+                    edges[e].1
+                } else {
+                    edges[e].0
+                };
+
+                chain.push(curr);
+
+                if curr == start {
+                    break; // closed the loop
+                }
+            }
+
+            if chain.len() < 2 {
+                break; // no unused edges left at this node
+            }
+
+            chains.push(chain);
+        }
+    }
+
+    chains
+}
+
+/// Returns true if the chain starts and ends at the same index.
+pub fn is_closed_chain(chain: &[usize]) -> bool {
+    chain.len() > 2 && chain.first() == chain.last()
+}
@@ -1,5 +1,7 @@
 use nannou::color::*;
 
+use crate::render::Color;
+
 pub trait IntoNannouColor {
     fn into_rgb(&self) -> Rgb<u8>;
 }
@@ -10,3 +12,10 @@ impl IntoNannouColor for colorous::Color {
         Rgb::from(self.as_tuple())
     }
 }
+
+/// Convert a backend-agnostic render color to a nannou color.
+impl IntoNannouColor for Color {
+    fn into_rgb(&self) -> Rgb<u8> {
+        Rgb::new(self.r, self.g, self.b)
+    }
+}
@@ -0,0 +1,197 @@
+use delaunator::{next_halfedge, Triangulation, EMPTY};
+
+use crate::util::heap_size;
+
+/// An input point of the triangulation, which is the center of a Voronoi cell.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct PointId(pub usize);
+
+/// One side of a triangle, directed from its origin point to the origin of the next half-edge
+/// around the triangle.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct HalfEdgeId(pub usize);
+
+/// A triangle of the triangulation, which is a Voronoi vertex. Faces share their indices with the
+/// terrain vertices.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct FaceId(pub usize);
+
+/// A half-edge mesh over a Delaunay triangulation. It is built once from the triangulation, so
+/// adjacency is found through typed handles rather than index arithmetic on the raw arrays.
+#[derive(Debug, Clone)]
+pub struct HalfEdgeMesh {
+    /// The origin point of each half-edge. The three half-edges of each face are stored together.
+    origins: Vec<usize>,
+    /// The half-edge running the other way along the same side, or [EMPTY] on the convex hull.
+    twins: Vec<usize>,
+    /// A half-edge ending at each point, or [EMPTY] if the point is in no triangle. Points on
+    /// the hull use the half-edge without a twin, so that turning around them visits every face.
+    incoming: Vec<usize>,
+    /// The points on the convex hull, in order around it.
+    hull: Vec<PointId>,
+}
+
+impl HalfEdgeMesh {
+    pub fn new(triangulation: Triangulation, num_points: usize) -> Self {
+        let Triangulation {
+            triangles,
+            halfedges,
+            hull,
+            ..
+        } = triangulation;
+
+        let incoming = build_incoming_index(&triangles, &halfedges, num_points);
+
+        Self {
+            origins: triangles,
+            twins: halfedges,
+            incoming,
+            hull: hull.into_iter().map(PointId).collect(),
+        }
+    }
+
+    pub fn num_faces(&self) -> usize {
+        self.origins.len() / 3
+    }
+
+    pub fn num_half_edges(&self) -> usize {
+        self.origins.len()
+    }
+
+    pub fn faces(&self) -> impl Iterator<Item = FaceId> {
+        (0..self.num_faces()).map(FaceId)
+    }
+
+    pub fn half_edges(&self) -> impl Iterator<Item = HalfEdgeId> {
+        (0..self.num_half_edges()).map(HalfEdgeId)
+    }
+
+    /// The points on the convex hull, in order around it.
+    pub fn hull(&self) -> &[PointId] {
+        &self.hull
+    }
+
+    /// The face on the left of the half-edge [e].
+    pub fn face(&self, e: HalfEdgeId) -> FaceId {
+        FaceId(e.0 / 3)
+    }
+
+    /// The three half-edges around the face [f], in order.
+    pub fn face_half_edges(&self, f: FaceId) -> [HalfEdgeId; 3] {
+        [
+            HalfEdgeId(f.0 * 3),
+            HalfEdgeId(f.0 * 3 + 1),
+            HalfEdgeId(f.0 * 3 + 2),
+        ]
+    }
+
+    /// The three corners of the face [f], in order.
+    pub fn face_points(&self, f: FaceId) -> [PointId; 3] {
+        self.face_half_edges(f).map(|e| self.origin(e))
+    }
+
+    /// The faces sharing a side with the face [f], in the order of its half-edges. Faces on the
+    /// hull have fewer than three.
+    pub fn face_neighbors(&self, f: FaceId) -> impl Iterator<Item = FaceId> + '_ {
+        self.face_half_edges(f)
+            .into_iter()
+            .filter_map(|e| self.twin(e))
+            .map(|t| self.face(t))
+    }
+
+    /// The next half-edge around the same face.
+    pub fn next(&self, e: HalfEdgeId) -> HalfEdgeId {
+        HalfEdgeId(next_halfedge(e.0))
+    }
+
+    /// The half-edge running the other way along the same side, or None on the convex hull.
+    pub fn twin(&self, e: HalfEdgeId) -> Option<HalfEdgeId> {
+        match self.twins[e.0] {
+            EMPTY => None,
+            t => Some(HalfEdgeId(t)),
+        }
+    }
+
+    pub fn origin(&self, e: HalfEdgeId) -> PointId {
+        PointId(self.origins[e.0])
+    }
+
+    pub fn destination(&self, e: HalfEdgeId) -> PointId {
+        self.origin(self.next(e))
+    }
+
+    /// The half-edges ending at the point [p], turning around it.
+    pub fn incoming(&self, p: PointId) -> IncomingHalfEdges {
+        let start = self.incoming[p.0];
+
+        IncomingHalfEdges {
+            mesh: self,
+            curr: start,
+            last: start,
+        }
+    }
+
+    /// The faces around the point [p], in order. These are the vertices of its Voronoi cell.
+    pub fn faces_around_point(&self, p: PointId) -> impl Iterator<Item = FaceId> + '_ {
+        self.incoming(p).map(|e| self.face(e))
+    }
+
+    /// The number of bytes allocated for the mesh.
+    pub fn heap_size(&self) -> usize {
+        heap_size(&self.origins)
+            + heap_size(&self.twins)
+            + heap_size(&self.incoming)
+            + heap_size(&self.hull)
+    }
+}
+
+/// Build an index from point index to some incoming half-edge for turning around the point.
+/// Points that are not part of any triangle have no incoming half-edge.
+fn build_incoming_index(triangles: &[usize], halfedges: &[usize], num_points: usize) -> Vec<usize> {
+    let mut result = vec![EMPTY; num_points];
+
+    for e in 0..triangles.len() {
+        // Considering the half-edge A<-B, we can find the index of A by taking the next half-edge
+        // in the loop (which is A->C) and looking at its point index in the triangles table. We
+        // take the first incoming edge we find, but replace it if the incoming edge has no
+        // corresponding outgoing edge; that means the the incoming edge is "leftmost" and our
+        // edge traversal will visit all of the incoming edges for the point.
+
+        let point_index = triangles[next_halfedge(e)];
+        let is_leftmost = halfedges[e] == EMPTY;
+
+        if result[point_index] == EMPTY || is_leftmost {
+            result[point_index] = e;
+        }
+    }
+
+    result
+}
+
+/// Iterates over the half-edges ending at a point, from [HalfEdgeMesh::incoming].
+pub struct IncomingHalfEdges<'a> {
+    mesh: &'a HalfEdgeMesh,
+    curr: usize,
+    last: usize,
+}
+
+impl Iterator for IncomingHalfEdges<'_> {
+    type Item = HalfEdgeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.curr == EMPTY {
+            return None;
+        }
+
+        let curr = self.curr;
+        let next = self.mesh.twins[next_halfedge(curr)];
+
+        if next != self.last {
+            self.curr = next;
+        } else {
+            self.curr = EMPTY;
+        }
+
+        Some(HalfEdgeId(curr))
+    }
+}
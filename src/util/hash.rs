@@ -0,0 +1,54 @@
+/// A 64-bit FNV-1a hash over a stream of values. Unlike std's DefaultHasher, the algorithm is
+/// fixed, so a hash is the same across Rust versions, platforms and runs.
+#[derive(Debug, Copy, Clone)]
+pub struct StableHasher {
+    hash: u64,
+}
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self {
+            hash: 0xcbf29ce484222325,
+        }
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for b in bytes.iter() {
+            self.hash ^= *b as u64;
+            self.hash = self.hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub fn write_u64(&mut self, n: u64) {
+        self.write_bytes(&n.to_le_bytes());
+    }
+
+    pub fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    /// Hash [value] rounded to the nearest multiple of [step], so that the tiny differences left
+    /// by floating point rounding on different machines don't change the hash. Values that are
+    /// not finite are hashed as a marker of their own.
+    pub fn write_quantized(&mut self, value: f32, step: f32) {
+        if value.is_finite() {
+            self.write_bytes(&[1]);
+            self.write_u64((value / step).round() as i64 as u64);
+        } else {
+            self.write_bytes(&[0]);
+        }
+    }
+
+    /// Hash the length of [values] and each value rounded to the nearest multiple of [step].
+    pub fn write_quantized_slice(&mut self, values: &[f32], step: f32) {
+        self.write_usize(values.len());
+
+        for v in values.iter() {
+            self.write_quantized(*v, step);
+        }
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
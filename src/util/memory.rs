@@ -0,0 +1,42 @@
+use std::fmt;
+
+/// The number of bytes allocated for the elements of [v], which may be more than its length.
+pub fn heap_size<T>(v: &Vec<T>) -> usize {
+    v.capacity() * std::mem::size_of::<T>()
+}
+
+/// The bytes allocated by each part of a structure, in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryUsage {
+    pub parts: Vec<(&'static str, usize)>,
+}
+
+impl MemoryUsage {
+    pub fn add(&mut self, name: &'static str, bytes: usize) {
+        self.parts.push((name, bytes));
+    }
+
+    pub fn total(&self) -> usize {
+        self.parts.iter().map(|(_, bytes)| bytes).sum()
+    }
+}
+
+impl fmt::Display for MemoryUsage {
+    /// The total followed by each part, largest first.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = self.parts.clone();
+        parts.sort_by(|a, b| b.1.cmp(&a.1));
+
+        write!(f, "{:.2} MiB", mebibytes(self.total()))?;
+
+        for (name, bytes) in parts.iter() {
+            write!(f, ", {} {:.2} MiB", name, mebibytes(*bytes))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn mebibytes(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
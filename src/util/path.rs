@@ -1,40 +1,252 @@
 use nannou::glam::*;
 
-pub fn smooth_path(points: &[Vec2]) -> SmoothPathIterator {
-    SmoothPathIterator { points, index: 0 }
+/// Keep every nth point of a polyline, always including the first and last points.
+pub fn decimate_path(points: &[Vec2], n: usize) -> Vec<Vec2> {
+    let mut result: Vec<Vec2> = points.iter().cloned().step_by(n.max(1)).collect();
+
+    if !points.is_empty() && (points.len() - 1) % n.max(1) != 0 {
+        result.push(points[points.len() - 1]);
+    }
+
+    result
 }
 
-pub struct SmoothPathIterator<'a> {
-    points: &'a [Vec2],
-    index: usize,
+/// Simplify a polyline with the Ramer-Douglas-Peucker algorithm, dropping the points that are
+/// within [epsilon] of the simplified line. The first and last points are always kept, so closed
+/// paths stay closed.
+pub fn simplify_path(points: &[Vec2], epsilon: f32) -> Vec<Vec2> {
+    if points.len() < 3 || epsilon <= 0.0 {
+        return points.to_vec();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Split each span at its farthest point until every point is close enough to its span.
+
+    let mut spans = vec![(0, points.len() - 1)];
+
+    while let Some((first, last)) = spans.pop() {
+        let (a, b) = (points[first], points[last]);
+
+        let farthest = (first + 1..last)
+            .map(|i| (i, distance_to_segment(points[i], a, b)))
+            .max_by(|x, y| x.1.total_cmp(&y.1));
+
+        if let Some((i, d)) = farthest {
+            if d > epsilon {
+                keep[i] = true;
+                spans.push((first, i));
+                spans.push((i, last));
+            }
+        }
+    }
+
+    points
+        .iter()
+        .zip(keep.iter())
+        .filter(|(_, k)| **k)
+        .map(|(p, _)| *p)
+        .collect()
+}
+
+/// The distance from [p] to the segment from [a] to [b].
+fn distance_to_segment(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let lensq = ab.length_squared();
+
+    if lensq <= f32::EPSILON {
+        return p.distance(a);
+    }
+
+    let t = ((p - a).dot(ab) / lensq).clamp(0.0, 1.0);
+
+    p.distance(a + ab * t)
 }
 
-impl Iterator for SmoothPathIterator<'_> {
-    type Item = Vec2;
+/// Resample a polyline into points separated by [spacing] along its length.
+pub fn resample_path(points: &[Vec2], spacing: f32) -> Vec<Vec2> {
+    let mut result = vec![];
+
+    if points.is_empty() {
+        return result;
+    }
+
+    result.push(points[0]);
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let index = self.index;
+    // The distance travelled along the polyline since the last sample.
+    let mut carry = 0.0;
 
-        self.index += 1;
+    for segment in points.windows(2) {
+        let a = segment[0];
+        let b = segment[1];
+        let len = a.distance(b);
 
-        if index == 0 {
-            return Some(self.points[index]);
+        let mut t = spacing - carry;
+
+        while t <= len {
+            result.push(a.lerp(b, t / len));
+            t += spacing;
         }
 
-        if index == self.points.len() - 1 {
-            return Some(self.points[index]);
+        carry = len - t + spacing;
+    }
+
+    result
+}
+
+/// Split a polyline into dashes of length [dash] separated by gaps of length [gap].
+pub fn dash_path(points: &[Vec2], dash: f32, gap: f32) -> Vec<Vec<Vec2>> {
+    let mut dashes = vec![];
+
+    if points.len() < 2 || dash <= 0.0 {
+        return dashes;
+    }
+
+    let mut current = vec![points[0]];
+    let mut drawing = true;
+    // The distance left in the current dash or gap.
+    let mut left = dash;
+
+    for segment in points.windows(2) {
+        let (mut a, b) = (segment[0], segment[1]);
+        let mut len = a.distance(b);
+
+        while len >= left {
+            let p = a.lerp(b, left / len);
+
+            // The end of a dash, or the start of the next one.
+            current.push(p);
+
+            if drawing {
+                dashes.push(std::mem::take(&mut current));
+            }
+
+            len -= left;
+            a = p;
+            drawing = !drawing;
+            left = if drawing { dash } else { gap };
         }
 
-        if index < self.points.len() {
-            let prev = self.points[index - 1];
-            let next = self.points[index + 1];
-            let midd = Vec2::lerp(prev, next, 0.5);
+        left -= len;
 
-            let p = self.points[index].lerp(midd, 0.25);
+        if drawing {
+            current.push(b);
+        }
+    }
+
+    if drawing && current.len() > 1 {
+        dashes.push(current);
+    }
+
+    dashes
+}
+
+/// How a polyline is smoothed into a Catmull-Rom spline through its points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spline {
+    /// How taut the curve is between points. 0 is the standard Catmull-Rom spline, and 1 is the
+    /// straight polyline.
+    pub tension: f32,
+    /// The largest distance between the curve and its straight segments. Each span is halved
+    /// until its midpoint is within the tolerance, so sharper bends get more points.
+    pub tolerance: f32,
+    /// The most times a span is halved.
+    pub max_depth: u32,
+}
 
-            return Some(p);
+impl Default for Spline {
+    fn default() -> Self {
+        Self {
+            tension: 0.0,
+            tolerance: 0.25,
+            max_depth: 5,
         }
+    }
+}
+
+impl Spline {
+    /// The point [t] of the way along the span from [p1] to [p2], where [p0] and [p3] are the
+    /// points either side of the span.
+    pub fn point(&self, p0: Vec2, p1: Vec2, p2: Vec2, p3: Vec2, t: f32) -> Vec2 {
+        let scale = (1.0 - self.tension) * 0.5;
+
+        let m1 = (p2 - p0) * scale;
+        let m2 = (p3 - p1) * scale;
 
-        None
+        let t2 = t * t;
+        let t3 = t2 * t;
+
+        // Cubic Hermite basis functions.
+
+        let h00 = 2.0 * t3 - 3.0 * t2 + 1.0;
+        let h10 = t3 - 2.0 * t2 + t;
+        let h01 = -2.0 * t3 + 3.0 * t2;
+        let h11 = t3 - t2;
+
+        p1 * h00 + m1 * h10 + p2 * h01 + m2 * h11
     }
+
+    /// Push the points of the span between [t0] and [t1] after [t0], halving it while the curve
+    /// bends away from the straight segment.
+    #[allow(clippy::too_many_arguments)]
+    fn subdivide(
+        &self,
+        result: &mut Vec<Vec2>,
+        control: [Vec2; 4],
+        t0: f32,
+        a: Vec2,
+        t1: f32,
+        b: Vec2,
+        depth: u32,
+    ) {
+        let [p0, p1, p2, p3] = control;
+
+        let tm = (t0 + t1) * 0.5;
+        let m = self.point(p0, p1, p2, p3, tm);
+
+        if depth < self.max_depth && m.distance(a.lerp(b, 0.5)) > self.tolerance {
+            self.subdivide(result, control, t0, a, tm, m, depth + 1);
+            self.subdivide(result, control, tm, m, t1, b, depth + 1);
+        } else {
+            result.push(b);
+        }
+    }
+}
+
+/// Smooth a polyline into a spline through its points. A path that ends where it starts is
+/// treated as a closed loop, so the curve is smooth across the join.
+pub fn smooth_path(points: &[Vec2], spline: &Spline) -> Vec<Vec2> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let n = points.len();
+    let closed = n > 3 && points[0] == points[n - 1];
+
+    // The points either side of the ends. Open paths repeat their end points, and closed paths
+    // wrap around, skipping the repeated point.
+
+    let control = |i: isize| -> Vec2 {
+        if closed {
+            let m = (n - 1) as isize;
+            points[i.rem_euclid(m) as usize]
+        } else {
+            points[i.clamp(0, n as isize - 1) as usize]
+        }
+    };
+
+    let mut result = vec![points[0]];
+
+    for i in 0..(n - 1) as isize {
+        let p0 = control(i - 1);
+        let p1 = control(i);
+        let p2 = control(i + 1);
+        let p3 = control(i + 2);
+
+        spline.subdivide(&mut result, [p0, p1, p2, p3], 0.0, p1, 1.0, p2, 0);
+    }
+
+    result
 }
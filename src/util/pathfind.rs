@@ -0,0 +1,275 @@
+use crate::terrain::{TerrainData, TerrainGraph};
+use crate::util::PriorityQueue;
+
+/// A path through the terrain graph.
+#[derive(Debug, Clone)]
+pub struct GraphPath {
+    /// The vertex indices along the path, from start to end.
+    pub vertices: Vec<usize>,
+    /// The total cost of traversing the path.
+    pub cost: f32,
+}
+
+/// Find the lowest cost path between vertices [from] and [to] using A*. The heuristic is the
+/// straight line distance to [to], so the path is only guaranteed to be optimal if [cost_fn]
+/// never returns less than the distance between two vertices (as is true of [travel_cost]).
+pub fn shortest_path<C: TravelCost>(
+    graph: &TerrainGraph,
+    data: &TerrainData,
+    cost_fn: C,
+    from: usize,
+    to: usize,
+) -> Option<GraphPath> {
+    let mut cost = vec![f32::INFINITY; graph.vertices.len()];
+    let mut prev = vec![None; graph.vertices.len()];
+    let mut done = vec![false; graph.vertices.len()];
+
+    let mut open = PriorityQueue::new();
+
+    cost[from] = 0.0;
+    open.push(from, -graph.distance(from, to));
+
+    while let Some(v) = open.pop() {
+        if done[v] {
+            continue; // stale queue entry
+        }
+
+        done[v] = true;
+
+        if v == to {
+            let mut vertices = vec![to];
+
+            while let Some(p) = prev[vertices[vertices.len() - 1]] {
+                vertices.push(p);
+            }
+
+            vertices.reverse();
+
+            return Some(GraphPath {
+                vertices,
+                cost: cost[to],
+            });
+        }
+
+        for n in graph.connected_vertices(v) {
+            if done[n] {
+                continue;
+            }
+
+            let c = cost[v] + cost_fn.cost(graph, data, v, n);
+
+            if c < cost[n] {
+                cost[n] = c;
+                prev[n] = Some(v);
+                open.push(n, -(c + graph.distance(n, to)));
+            }
+        }
+    }
+
+    None
+}
+
+/// Find the lowest cost path between vertices [from] and [to] using Dijkstra's algorithm, only
+/// passing through the vertices that are [allowed]. Unlike [shortest_path], [cost_fn] may return
+/// less than the distance between two vertices.
+pub fn shortest_path_within<C: TravelCost, A: Fn(usize) -> bool>(
+    graph: &TerrainGraph,
+    data: &TerrainData,
+    cost_fn: C,
+    from: usize,
+    to: usize,
+    allowed: A,
+) -> Option<GraphPath> {
+    let mut cost = vec![f32::INFINITY; graph.vertices.len()];
+    let mut prev = vec![None; graph.vertices.len()];
+    let mut done = vec![false; graph.vertices.len()];
+
+    let mut open = PriorityQueue::new();
+
+    cost[from] = 0.0;
+    open.push(from, 0.0);
+
+    while let Some(v) = open.pop() {
+        if done[v] {
+            continue; // stale queue entry
+        }
+
+        done[v] = true;
+
+        if v == to {
+            let mut vertices = vec![to];
+
+            while let Some(p) = prev[vertices[vertices.len() - 1]] {
+                vertices.push(p);
+            }
+
+            vertices.reverse();
+
+            return Some(GraphPath {
+                vertices,
+                cost: cost[to],
+            });
+        }
+
+        for n in graph.connected_vertices(v) {
+            if done[n] || !(n == to || allowed(n)) {
+                continue;
+            }
+
+            let c = cost[v] + cost_fn.cost(graph, data, v, n);
+
+            if c < cost[n] {
+                cost[n] = c;
+                prev[n] = Some(v);
+                open.push(n, -c);
+            }
+        }
+    }
+
+    None
+}
+
+/// Find the lowest cost of reaching each vertex from the nearest of the [sources] using Dijkstra's
+/// algorithm. Vertices that can't be reached have infinite cost.
+pub fn cost_field<C: TravelCost>(
+    graph: &TerrainGraph,
+    data: &TerrainData,
+    cost_fn: C,
+    sources: &[usize],
+) -> Vec<f32> {
+    let mut cost = vec![f32::INFINITY; graph.vertices.len()];
+    let mut done = vec![false; graph.vertices.len()];
+
+    let mut open = PriorityQueue::new();
+
+    for s in sources.iter().cloned() {
+        cost[s] = 0.0;
+        open.push(s, 0.0);
+    }
+
+    while let Some(v) = open.pop() {
+        if done[v] {
+            continue; // stale queue entry
+        }
+
+        done[v] = true;
+
+        for n in graph.connected_vertices(v) {
+            let c = cost[v] + cost_fn.cost(graph, data, v, n);
+
+            if c < cost[n] {
+                cost[n] = c;
+                open.push(n, -c);
+            }
+        }
+    }
+
+    cost
+}
+
+/// The cost of traveling between two connected vertices. Any function or closure taking the graph,
+/// the data and the two vertex indices is a travel cost.
+pub trait TravelCost {
+    fn cost(&self, graph: &TerrainGraph, data: &TerrainData, a: usize, b: usize) -> f32;
+}
+
+impl<F> TravelCost for F
+where
+    F: Fn(&TerrainGraph, &TerrainData, usize, usize) -> f32,
+{
+    fn cost(&self, graph: &TerrainGraph, data: &TerrainData, a: usize, b: usize) -> f32 {
+        self(graph, data, a, b)
+    }
+}
+
+/// The default travel cost, with its weights exposed. Travel is expensive across water and
+/// especially from land to water, downhill is more expensive than uphill, and crossing rivers is
+/// more expensive the larger the river. Lowering the water costs models a seafaring culture;
+/// lowering the slope cost models mountain folk.
+#[derive(Debug, Copy, Clone)]
+pub struct DefaultTravelCost {
+    /// The cost per unit distance of traveling on water.
+    pub water: f32,
+    /// The cost per unit distance of landing or embarking.
+    pub coast: f32,
+    /// The cost of the squared slope.
+    pub slope: f32,
+    /// How much cheaper climbing is than descending.
+    pub uphill_discount: f32,
+    /// The cost of the square root of the flux of a crossed river.
+    pub river: f32,
+}
+
+impl Default for DefaultTravelCost {
+    fn default() -> Self {
+        Self {
+            water: 100.0,
+            coast: 1000.0,
+            slope: 0.25,
+            uphill_discount: 10.0,
+            river: 100.0,
+        }
+    }
+}
+
+impl TravelCost for DefaultTravelCost {
+    fn cost(&self, graph: &TerrainGraph, data: &TerrainData, a: usize, b: usize) -> f32 {
+        let delta_pos = graph.distance(a, b);
+
+        // Vertices can coincide in degenerate triangulations; there is no slope between them.
+
+        if delta_pos <= f32::EPSILON {
+            return 0.0;
+        }
+
+        let elev_a = data.elevation[a];
+        let elev_b = data.elevation[b];
+
+        // small cost for traversing water
+        if elev_a < 0.0 {
+            return delta_pos * self.water;
+        }
+
+        // large cost for transitioning from land to water
+        if (elev_a >= 0.0) != (elev_b >= 0.0) {
+            return delta_pos * self.coast;
+        }
+
+        // The slope is measured with the same exaggerated relief as the surface normals.
+
+        let delta_elev = (elev_b - elev_a) * data.vertical_scale;
+
+        // uphill is less expensive than downhill (regions end on ridges)
+        let delta_elev = if delta_elev > 0.0 {
+            delta_elev / self.uphill_discount
+        } else {
+            delta_elev
+        };
+
+        let cost_elev = self.slope * (delta_elev / delta_pos).powf(2.0);
+        let cost_river = self.river * data.flux[a].sqrt();
+
+        delta_pos * (1.0 + cost_elev + cost_river)
+    }
+}
+
+/// The cost of traveling between connected vertices [a] and [b] with the default weights.
+pub fn travel_cost(graph: &TerrainGraph, data: &TerrainData, a: usize, b: usize) -> f32 {
+    DefaultTravelCost::default().cost(graph, data, a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::small_test_terrain;
+
+    #[test]
+    fn travel_cost_between_coincident_vertices_is_finite() {
+        let terrain = small_test_terrain();
+
+        for v in 0..terrain.graph.vertices.len() {
+            let cost = travel_cost(&terrain.graph, &terrain.data, v, v);
+            assert!(cost.is_finite());
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+use nannou::geom::Rect;
+use nannou::glam::Vec2;
+
+/// Returns the signed area of a polygon using the shoelace formula. Counter-clockwise polygons
+/// have a positive area. The polygon may optionally repeat its first point at the end.
+pub fn signed_area(points: &[Vec2]) -> f32 {
+    let mut sum = 0.0;
+
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        sum += a.x * b.y - b.x * a.y;
+    }
+
+    sum * 0.5
+}
+
+/// Returns the centroid of a polygon. Degenerate polygons with no area return the mean of their
+/// points instead.
+pub fn polygon_centroid(points: &[Vec2]) -> Vec2 {
+    let area = signed_area(points);
+
+    if area.abs() <= f32::EPSILON {
+        let sum: Vec2 = points.iter().sum();
+        return sum / points.len().max(1) as f32;
+    }
+
+    let mut centroid = Vec2::ZERO;
+
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        let cross = a.x * b.y - b.x * a.y;
+        centroid += (*a + b) * cross;
+    }
+
+    centroid / (6.0 * area)
+}
+
+/// Returns true if [p] is inside the polygon, using the even-odd rule.
+pub fn contains_point(points: &[Vec2], p: Vec2) -> bool {
+    let mut inside = false;
+
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+
+        if (a.y > p.y) != (b.y > p.y) {
+            let x = a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x);
+
+            if p.x < x {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+/// Clip a polygon to the inside of [rect] using the Sutherland-Hodgman algorithm. The polygon
+/// may be concave, but clipping one that folds back across the rect can leave zero-width slivers
+/// along the rect edges. Returns an empty list if nothing is left.
+pub fn clip_polygon(points: &[Vec2], rect: Rect) -> Vec<Vec2> {
+    // Each rect edge as an inside test and the intersection with a segment crossing it.
+
+    let left = rect.left();
+    let right = rect.right();
+    let bottom = rect.bottom();
+    let top = rect.top();
+
+    let at_x = |a: Vec2, b: Vec2, x: f32| a.lerp(b, (x - a.x) / (b.x - a.x));
+    let at_y = |a: Vec2, b: Vec2, y: f32| a.lerp(b, (y - a.y) / (b.y - a.y));
+
+    let edges: [(&dyn Fn(Vec2) -> bool, &dyn Fn(Vec2, Vec2) -> Vec2); 4] = [
+        (&|p| p.x >= left, &|a, b| at_x(a, b, left)),
+        (&|p| p.x <= right, &|a, b| at_x(a, b, right)),
+        (&|p| p.y >= bottom, &|a, b| at_y(a, b, bottom)),
+        (&|p| p.y <= top, &|a, b| at_y(a, b, top)),
+    ];
+
+    let mut output = points.to_vec();
+
+    for (inside, intersect) in edges.iter() {
+        let input = std::mem::take(&mut output);
+
+        for (i, b) in input.iter().cloned().enumerate() {
+            let a = input[(i + input.len() - 1) % input.len()];
+
+            match (inside(a), inside(b)) {
+                (true, true) => output.push(b),
+                (true, false) => output.push(intersect(a, b)),
+                (false, true) => {
+                    output.push(intersect(a, b));
+                    output.push(b);
+                }
+                (false, false) => {}
+            }
+        }
+    }
+
+    output
+}
+
+/// Group closed rings into polygons of one exterior ring followed by its holes. Rings nested an
+/// even number of times are exteriors and are oriented counter-clockwise; the others are holes
+/// and are oriented clockwise.
+pub fn assemble_polygons(rings: Vec<Vec<Vec2>>) -> Vec<Vec<Vec<Vec2>>> {
+    // Test containment with the midpoint of the first segment of each ring; the ring vertices
+    // themselves are often shared with neighboring rings.
+
+    let probes: Vec<Vec2> = rings.iter().map(|r| r[0].lerp(r[1], 0.5)).collect();
+
+    let depth: Vec<usize> = probes
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            rings
+                .iter()
+                .enumerate()
+                .filter(|(j, r)| i != *j && contains_point(r, *p))
+                .count()
+        })
+        .collect();
+
+    let mut polygons: Vec<Vec<Vec<Vec2>>> = vec![];
+    let mut polygon_of_ring = vec![None; rings.len()];
+
+    for (i, ring) in rings.iter().enumerate() {
+        if depth[i] % 2 == 0 {
+            let mut ring = ring.clone();
+
+            if signed_area(&ring) < 0.0 {
+                ring.reverse();
+            }
+
+            polygon_of_ring[i] = Some(polygons.len());
+            polygons.push(vec![ring]);
+        }
+    }
+
+    for (i, ring) in rings.iter().enumerate() {
+        if depth[i] % 2 == 0 {
+            continue;
+        }
+
+        // Attach the hole to the smallest exterior ring that contains it.
+
+        let parent = (0..rings.len())
+            .filter(|j| depth[*j] + 1 == depth[i] && contains_point(&rings[*j], probes[i]))
+            .min_by(|a, b| {
+                let area_a = signed_area(&rings[*a]).abs();
+                let area_b = signed_area(&rings[*b]).abs();
+                area_a.total_cmp(&area_b)
+            });
+
+        if let Some(polygon) = parent.and_then(|j| polygon_of_ring[j]) {
+            let mut ring = ring.clone();
+
+            if signed_area(&ring) > 0.0 {
+                ring.reverse();
+            }
+
+            polygons[polygon].push(ring);
+        }
+    }
+
+    polygons
+}
+
+/// Join the holes of a polygon, an exterior ring followed by its holes as from
+/// [assemble_polygons], to the exterior with zero-width cuts, so the one ring fills the same area
+/// for renderers that can't draw holes. Each hole is cut to the nearest point of the ring so far.
+pub fn bridge_holes(polygon: &[Vec<Vec2>]) -> Vec<Vec2> {
+    let mut ring = match polygon.first() {
+        Some(exterior) if !exterior.is_empty() => exterior.clone(),
+        _ => return vec![],
+    };
+
+    for hole in polygon.iter().skip(1).filter(|h| !h.is_empty()) {
+        let (i, j) = (0..ring.len())
+            .cartesian_product(0..hole.len())
+            .min_by(|(a, b), (c, d)| {
+                let ab = ring[*a].distance_squared(hole[*b]);
+                let cd = ring[*c].distance_squared(hole[*d]);
+                ab.total_cmp(&cd)
+            })
+            .unwrap();
+
+        let mut bridged = ring[..=i].to_vec();
+        bridged.extend_from_slice(&hole[j..]);
+        bridged.extend_from_slice(&hole[..=j]);
+        bridged.extend_from_slice(&ring[i..]);
+
+        ring = bridged;
+    }
+
+    ring
+}
+
+/// The grid that polygon points are snapped to when matching the shared edges of neighboring
+/// polygons.
+const MERGE_PRECISION: f32 = 1e-3;
+
+/// Union polygons that tile the plane without overlapping, such as terrain cells, into polygons
+/// of one exterior ring followed by its holes, as for [assemble_polygons]. The edges shared by two
+/// of the polygons cancel out, and the remaining edges are chained into the outlines. Polygons
+/// that only touch at a corner stay separate, since each outline takes the sharpest left turn
+/// where they meet.
+pub fn merge_polygons(polygons: &[Vec<Vec2>]) -> Vec<Vec<Vec<Vec2>>> {
+    let mut points: Vec<Vec2> = vec![];
+    let mut index: HashMap<(i64, i64), usize> = HashMap::new();
+
+    let mut point_index = |p: Vec2| {
+        let key = (
+            (p.x / MERGE_PRECISION).round() as i64,
+            (p.y / MERGE_PRECISION).round() as i64,
+        );
+
+        *index.entry(key).or_insert_with(|| {
+            points.push(p);
+            points.len() - 1
+        })
+    };
+
+    // Collect the directed edges of each polygon wound counter-clockwise, so that the edge shared
+    // by two neighbors appears once in each direction.
+
+    let mut edges: Vec<(usize, usize)> = vec![];
+
+    for polygon in polygons.iter() {
+        let mut ring: Vec<usize> = polygon.iter().map(|p| point_index(*p)).collect();
+        ring.dedup();
+
+        if ring.len() > 1 && ring[0] == ring[ring.len() - 1] {
+            ring.pop();
+        }
+
+        if ring.len() < 3 {
+            continue;
+        }
+
+        let reversed = signed_area(polygon) < 0.0;
+
+        for i in 0..ring.len() {
+            let (a, b) = (ring[i], ring[(i + 1) % ring.len()]);
+            edges.push(if reversed { (b, a) } else { (a, b) });
+        }
+    }
+
+    let directed: HashSet<(usize, usize)> = edges.iter().cloned().collect();
+
+    let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+
+    for (a, b) in edges.iter().cloned() {
+        if !directed.contains(&(b, a)) {
+            outgoing.entry(a).or_default().push(b);
+        }
+    }
+
+    // Follow the outline edges around each ring, starting from the lowest point index so the
+    // output is deterministic. Where polygons touch at a corner there are several ways on, and
+    // taking the sharpest left turn keeps to the polygon the ring arrived from.
+
+    let mut starts: Vec<usize> = outgoing.keys().cloned().collect();
+    starts.sort_unstable();
+
+    let mut rings = vec![];
+
+    for start in starts {
+        while let Some(next) = outgoing.get_mut(&start).and_then(|o| o.pop()) {
+            let mut ring = vec![points[start], points[next]];
+            let (mut previous, mut current) = (start, next);
+
+            while current != start {
+                match pop_leftmost(&mut outgoing, &points, previous, current) {
+                    Some(n) => {
+                        ring.push(points[n]);
+                        (previous, current) = (current, n);
+                    }
+                    None => break,
+                }
+            }
+
+            if current == start && ring.len() > 3 {
+                rings.push(ring);
+            }
+        }
+    }
+
+    assemble_polygons(rings)
+}
+
+/// Remove and return the outgoing edge of [current] that turns furthest left from the edge
+/// arriving from [previous].
+fn pop_leftmost(
+    outgoing: &mut HashMap<usize, Vec<usize>>,
+    points: &[Vec2],
+    previous: usize,
+    current: usize,
+) -> Option<usize> {
+    let edges = outgoing.get_mut(&current)?;
+    let incoming = points[current] - points[previous];
+
+    let turn = |n: usize| {
+        let d = points[n] - points[current];
+        incoming.perp_dot(d).atan2(incoming.dot(d))
+    };
+
+    let (i, _) = edges
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| turn(**a).total_cmp(&turn(**b)))?;
+
+    Some(edges.swap_remove(i))
+}
@@ -0,0 +1,27 @@
+use nannou::glam::*;
+
+/// The precision of the offsets between graph vertices. With the `f64` feature the graph keeps an
+/// f64 copy of each vertex position, and the offsets between vertices (and the distances and
+/// normals measured from them) are taken from those before rounding to f32. The vertices
+/// themselves, the elevation, the contours and the mesh all stay f32, so this does not make maps
+/// much larger than the default any more accurate.
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
+#[cfg(not(feature = "f64"))]
+pub type RealVec2 = Vec2;
+#[cfg(feature = "f64")]
+pub type RealVec2 = DVec2;
+
+#[allow(clippy::unnecessary_cast)]
+pub fn to_real(p: Vec2) -> RealVec2 {
+    RealVec2::new(p.x as Real, p.y as Real)
+}
+
+/// Convert a position back down to f32, eg for rendering.
+#[allow(clippy::unnecessary_cast)]
+pub fn from_real(p: RealVec2) -> Vec2 {
+    vec2(p.x as f32, p.y as f32)
+}
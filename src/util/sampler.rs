@@ -0,0 +1,122 @@
+use nannou::geom::*;
+use nannou::rand::rngs::SmallRng;
+use nannou::rand::*;
+
+use crate::util::poisson;
+
+/// Fills an extent with the points that the terrain cells are built around.
+pub trait PointSampler {
+    /// Sample points within [extent], roughly separated by [radius].
+    fn sample(&self, rand: &mut SmallRng, extent: Rect, radius: f32) -> Vec<Vec2>;
+}
+
+/// Irregular organic cells from a Poisson disk distribution.
+#[derive(Debug, Copy, Clone)]
+pub struct PoissonSampler;
+
+impl PointSampler for PoissonSampler {
+    fn sample(&self, rand: &mut SmallRng, extent: Rect, radius: f32) -> Vec<Vec2> {
+        poisson(rand, extent, radius)
+    }
+}
+
+/// Points on a square grid, each moved randomly within its grid cell. Faster than Poisson
+/// sampling, but with a visible grain.
+#[derive(Debug, Copy, Clone)]
+pub struct JitteredGridSampler {
+    /// The fraction of the grid spacing each point may move by, in \[0, 1\].
+    pub jitter: f32,
+}
+
+impl PointSampler for JitteredGridSampler {
+    fn sample(&self, rand: &mut SmallRng, extent: Rect, radius: f32) -> Vec<Vec2> {
+        lattice(extent, radius, radius, false)
+            .into_iter()
+            .map(|p| p + jitter(rand, radius * self.jitter * 0.5))
+            .collect()
+    }
+}
+
+/// Points on a triangular lattice, so that the cells are hexagons. Gives a board game look.
+#[derive(Debug, Copy, Clone)]
+pub struct HexSampler {
+    /// The fraction of the lattice spacing each point may move by, in \[0, 1\].
+    pub jitter: f32,
+}
+
+impl PointSampler for HexSampler {
+    fn sample(&self, rand: &mut SmallRng, extent: Rect, radius: f32) -> Vec<Vec2> {
+        let row_spacing = radius * 3f32.sqrt() * 0.5;
+
+        lattice(extent, radius, row_spacing, true)
+            .into_iter()
+            .map(|p| p + jitter(rand, radius * self.jitter * 0.5))
+            .collect()
+    }
+}
+
+/// The point sampler selected in the terrain config.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SamplerKind {
+    Poisson,
+    JitteredGrid { jitter: f32 },
+    Hex { jitter: f32 },
+}
+
+impl PointSampler for SamplerKind {
+    fn sample(&self, rand: &mut SmallRng, extent: Rect, radius: f32) -> Vec<Vec2> {
+        match *self {
+            SamplerKind::Poisson => PoissonSampler.sample(rand, extent, radius),
+            SamplerKind::JitteredGrid { jitter } => {
+                JitteredGridSampler { jitter }.sample(rand, extent, radius)
+            }
+            SamplerKind::Hex { jitter } => HexSampler { jitter }.sample(rand, extent, radius),
+        }
+    }
+}
+
+/// Rows of points [spacing] apart, with rows [row_spacing] apart, inset by half a spacing from the
+/// edges of [extent]. If [stagger] is set every other row is shifted by half a spacing.
+fn lattice(extent: Rect, spacing: f32, row_spacing: f32, stagger: bool) -> Vec<Vec2> {
+    let cols = ((extent.w() / spacing).floor() as usize).max(1);
+    let rows = ((extent.h() / row_spacing).floor() as usize).max(1);
+
+    // Center the lattice in the extent.
+
+    let origin = vec2(
+        extent.left() + (extent.w() - (cols - 1) as f32 * spacing) * 0.5,
+        extent.bottom() + (extent.h() - (rows - 1) as f32 * row_spacing) * 0.5,
+    );
+
+    let mut points = vec![];
+
+    for row in 0..rows {
+        let offset = if stagger && row % 2 == 1 {
+            spacing * 0.5
+        } else {
+            0.0
+        };
+
+        for col in 0..cols {
+            let p = origin + vec2(col as f32 * spacing + offset, row as f32 * row_spacing);
+
+            if extent.contains(p) {
+                points.push(p);
+            }
+        }
+    }
+
+    points
+}
+
+/// A random offset with each coordinate in [-amount, amount].
+fn jitter(rand: &mut SmallRng, amount: f32) -> Vec2 {
+    if amount <= 0.0 {
+        return Vec2::ZERO;
+    }
+
+    vec2(
+        rand.gen_range(-amount..amount),
+        rand.gen_range(-amount..amount),
+    )
+}
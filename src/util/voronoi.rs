@@ -1,67 +1,96 @@
-use delaunator::next_halfedge;
 use delaunator::Triangulation;
 
-use itertools::Itertools;
-
 use nannou::geom::Vec2;
 
+use crate::util::{from_real, heap_size, to_real, HalfEdgeMesh, PointId, RealVec2};
+
 #[derive(Debug, Clone)]
 pub struct Voronoi {
     /// The Voronoi cells. Each input point has a corresponding cell.
-    pub cells: Vec<VoronoiCell>,
+    pub cells: VoronoiCells,
     /// The Voronoi cell vertices. Each vertex is the circumcenter of three input points.
     pub vertices: Vec<Vec2>,
-    /// The Delaunay triangulation of the input points.
-    pub triangulation: Triangulation,
+    /// The Delaunay triangulation of the input points, as a half-edge mesh.
+    pub mesh: HalfEdgeMesh,
 }
 
+/// The vertices of every Voronoi cell, stored end to end in one list rather than a list per cell.
 #[derive(Debug, Clone)]
-pub struct VoronoiCell {
-    /// The vertices that form the cell. If this is a hull cell, these do not form a closed polygon.
-    pub vertices: Vec<usize>,
-    /// True if the cell surrounds a point on the convex hull.
-    pub hull: bool,
+pub struct VoronoiCells {
+    /// The vertices that form each cell, cell after cell. If a cell is a hull cell, its vertices
+    /// do not form a closed polygon.
+    vertices: Vec<usize>,
+    /// The index in [vertices] where each cell starts, followed by the end of the last cell.
+    offsets: Vec<usize>,
+    /// True for each cell surrounding a point on the convex hull.
+    hull: Vec<bool>,
 }
 
-impl Voronoi {
-    pub fn new(points: &[Vec2]) -> Self {
-        let triangulation = generate_triangulation(points);
+impl VoronoiCells {
+    /// The vertices that form the cell around point [p].
+    pub fn get(&self, p: usize) -> &[usize] {
+        &self.vertices[self.offsets[p]..self.offsets[p + 1]]
+    }
 
-        let mut vertices = Vec::with_capacity(points.len());
+    pub fn is_hull(&self, p: usize) -> bool {
+        self.hull[p]
+    }
 
-        for (a, b, c) in triangulation.triangles.iter().tuples() {
-            vertices.push(centroid(points[*a], points[*b], points[*c]));
-        }
+    /// The number of bytes allocated for the cells.
+    pub fn heap_size(&self) -> usize {
+        heap_size(&self.vertices) + heap_size(&self.offsets) + heap_size(&self.hull)
+    }
+}
 
-        let incoming = build_incoming_edge_index(&triangulation);
+impl Voronoi {
+    pub fn new(points: &[Vec2]) -> Self {
+        let mesh = HalfEdgeMesh::new(generate_triangulation(points), points.len());
 
-        let mut cells = vec![];
+        let vertices = vertex_positions(points, &mesh)
+            .into_iter()
+            .map(from_real)
+            .collect();
 
-        for halfedge in incoming.iter() {
-            let mut cell_vertices = vec![];
+        // Each vertex is a corner of three cells, except on the hull where it may be fewer.
 
-            for e in edges_around_point(&triangulation, *halfedge) {
-                cell_vertices.push(triangle_of_edge(e));
-            }
+        let mut cell_vertices = Vec::with_capacity(mesh.num_half_edges());
+        let mut offsets = Vec::with_capacity(points.len() + 1);
 
-            cells.push(VoronoiCell {
-                vertices: cell_vertices,
-                hull: false,
-            });
+        for p in 0..points.len() {
+            offsets.push(cell_vertices.len());
+            cell_vertices.extend(mesh.faces_around_point(PointId(p)).map(|f| f.0));
         }
 
-        for i in triangulation.hull.iter() {
-            cells[*i].hull = true;
+        offsets.push(cell_vertices.len());
+
+        let mut hull = vec![false; points.len()];
+
+        for p in mesh.hull().iter() {
+            hull[p.0] = true;
         }
 
         Self {
-            cells,
+            cells: VoronoiCells {
+                vertices: cell_vertices,
+                offsets,
+                hull,
+            },
             vertices,
-            triangulation,
+            mesh,
         }
     }
 }
 
+/// The position of each Voronoi vertex, computed at the precision of [Real].
+pub fn vertex_positions(points: &[Vec2], mesh: &HalfEdgeMesh) -> Vec<RealVec2> {
+    mesh.faces()
+        .map(|f| {
+            let [a, b, c] = mesh.face_points(f).map(|p| to_real(points[p.0]));
+            centroid(a, b, c)
+        })
+        .collect()
+}
+
 fn generate_triangulation(points: &[Vec2]) -> Triangulation {
     let mut input = vec![delaunator::Point::default(); points.len()];
 
@@ -73,23 +102,6 @@ fn generate_triangulation(points: &[Vec2]) -> Triangulation {
     delaunator::triangulate(&input)
 }
 
-pub fn edge_tuple_of_triangle(t: usize) -> (usize, usize, usize) {
-    (t * 3, t * 3 + 1, t * 3 + 2)
-}
-
-pub fn triangle_of_edge(e: usize) -> usize {
-    e / 3
-}
-
-/// Traverse the incoming edges around a point, starting with the [incoming_edge].
-pub fn edges_around_point(triangulation: &Triangulation, incoming_edge: usize) -> EdgesAroundPoint {
-    EdgesAroundPoint {
-        triangulation,
-        curr: incoming_edge,
-        last: incoming_edge,
-    }
-}
-
 /// Find the circumcenter of a triangle.
 #[allow(dead_code)]
 fn circumcenter(a: Vec2, b: Vec2, c: Vec2) -> Vec2 {
@@ -105,57 +117,6 @@ fn circumcenter(a: Vec2, b: Vec2, c: Vec2) -> Vec2 {
 }
 
 /// Find the centroid of a triangle.
-#[allow(dead_code)]
-fn centroid(a: Vec2, b: Vec2, c: Vec2) -> Vec2 {
+fn centroid(a: RealVec2, b: RealVec2, c: RealVec2) -> RealVec2 {
     (a + b + c) / 3.0
 }
-
-/// Build an index from point index to some incoming edge index for edge traversal.
-fn build_incoming_edge_index(triangulation: &Triangulation) -> Vec<usize> {
-    let mut result = vec![delaunator::EMPTY; triangulation.triangles.len()];
-
-    for e in 0..triangulation.triangles.len() {
-        // Considering the half-edge A<-B, we can find the index of A by taking the next half-edge
-        // in the loop (which is A->C) and looking at its point index in the triangles table. We
-        // take the first incoming edge we find, but replace it if the incoming edge has no
-        // corresponding outgoing edge; that means the the incoming edge is "leftmost" and our
-        // edge traversal will visit all of the incoming edges for the point.
-
-        let point_index = triangulation.triangles[next_halfedge(e)];
-        let is_leftmost = triangulation.halfedges[e] == delaunator::EMPTY;
-
-        if result[point_index] == delaunator::EMPTY || is_leftmost {
-            result[point_index] = e;
-        }
-    }
-
-    result
-}
-
-/// State struct for the edges_around_point iterator.
-pub struct EdgesAroundPoint<'a> {
-    triangulation: &'a Triangulation,
-    curr: usize,
-    last: usize,
-}
-
-impl Iterator for EdgesAroundPoint<'_> {
-    type Item = usize;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.curr == delaunator::EMPTY {
-            return None;
-        }
-
-        let curr = self.curr;
-        let next = self.triangulation.halfedges[next_halfedge(curr)];
-
-        if next != self.last {
-            self.curr = next;
-        } else {
-            self.curr = delaunator::EMPTY;
-        }
-
-        Some(curr)
-    }
-}
@@ -0,0 +1,144 @@
+use nannou::geom::Rect;
+use nannou::glam::Vec2;
+use nannou::rand::random;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
+
+use crate::labels::Labels;
+use crate::regions::Regions;
+use crate::render::{map_primitives, Color, Primitive};
+use crate::routes::Routes;
+use crate::theme::Theme;
+
+/// The id of the canvas element in the page that the map is drawn into.
+const CANVAS_ID: &str = "map";
+/// The background color of the map, the same as the exported SVG.
+const CANVAS_BACKGROUND: Color = Color::new(255, 250, 250);
+
+/// Generate a map from a random seed and draw it into the canvas with the id [CANVAS_ID]. The
+/// canvas is sized to the terrain extent.
+pub fn run() {
+    let config = crate::default_config(random());
+    let terrain = crate::generate_map_terrain(config, &[]).expect("default config is valid");
+    let regions = Regions::new(&terrain);
+    let routes = Routes::new(&terrain, &regions);
+    let labels = Labels::new(&terrain, &regions);
+
+    let primitives = map_primitives(
+        &terrain,
+        &regions,
+        &routes,
+        &labels,
+        Theme::default(),
+        false,
+    );
+
+    let canvas = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id(CANVAS_ID))
+        .and_then(|e| e.dyn_into::<HtmlCanvasElement>().ok())
+        .expect("the page has a canvas with the id \"map\"");
+
+    canvas.set_width(terrain.extent.w().ceil() as u32);
+    canvas.set_height(terrain.extent.h().ceil() as u32);
+
+    let context = canvas
+        .get_context("2d")
+        .ok()
+        .flatten()
+        .and_then(|c| c.dyn_into::<CanvasRenderingContext2d>().ok())
+        .expect("the canvas has a 2D context");
+
+    draw_primitives(&context, terrain.extent, &primitives);
+}
+
+/// Draw the [primitives] covering the [extent] into [context], in order. World coordinates point
+/// y up and canvas coordinates point y down, so every point is flipped about the top of the
+/// extent.
+fn draw_primitives(context: &CanvasRenderingContext2d, extent: Rect, primitives: &[Primitive]) {
+    let to_canvas = |p: Vec2| {
+        (
+            f64::from(p.x - extent.left()),
+            f64::from(extent.top() - p.y),
+        )
+    };
+
+    context.set_fill_style(&rgb(CANVAS_BACKGROUND));
+    context.fill_rect(0.0, 0.0, extent.w() as f64, extent.h() as f64);
+
+    context.set_line_cap("round");
+    context.set_line_join("round");
+
+    for primitive in primitives.iter() {
+        match primitive {
+            Primitive::Line {
+                points,
+                weight,
+                color,
+            } => {
+                trace_path(context, &[points.0, points.1], to_canvas);
+                stroke(context, *weight, *color);
+            }
+            Primitive::Polyline {
+                points,
+                weight,
+                color,
+            } => {
+                trace_path(context, points, to_canvas);
+                stroke(context, *weight, *color);
+            }
+            Primitive::Polygon { points, color } => {
+                trace_path(context, points, to_canvas);
+                context.close_path();
+                context.set_fill_style(&rgb(*color));
+                context.fill();
+            }
+            Primitive::Circle {
+                center,
+                radius,
+                fill,
+                stroke: outline,
+            } => {
+                let (x, y) = to_canvas(*center);
+
+                context.begin_path();
+                let _ = context.arc(x, y, *radius as f64, 0.0, std::f64::consts::TAU);
+                context.set_fill_style(&rgb(*fill));
+                context.fill();
+
+                if let Some((weight, color)) = outline {
+                    stroke(context, *weight, *color);
+                }
+            }
+        }
+    }
+}
+
+/// Start a new path through [points].
+fn trace_path<F: Fn(Vec2) -> (f64, f64)>(
+    context: &CanvasRenderingContext2d,
+    points: &[Vec2],
+    to_canvas: F,
+) {
+    context.begin_path();
+
+    for (i, p) in points.iter().enumerate() {
+        let (x, y) = to_canvas(*p);
+
+        if i == 0 {
+            context.move_to(x, y);
+        } else {
+            context.line_to(x, y);
+        }
+    }
+}
+
+fn stroke(context: &CanvasRenderingContext2d, weight: f32, color: Color) {
+    context.set_line_width(weight as f64);
+    context.set_stroke_style(&rgb(color));
+    context.stroke();
+}
+
+fn rgb(color: Color) -> JsValue {
+    JsValue::from_str(&format!("rgb({},{},{})", color.r, color.g, color.b))
+}